@@ -0,0 +1,88 @@
+// Generates the standard tables when the `baked-tables` feature is enabled
+//
+// The field elements 0..256 are written out in their internal (Montgomery) form, so that they are constants
+// of the crate rather than values which are converted at startup, and the rows of each table are written
+// as indices into them. See `lookup::table::baked`.
+//
+// The Montgomery form is computed with plain integers, so that building the crate does not need `algebra` as a
+// build dependency. `lookup::table::baked` tests that every constant equals `Fr::from(x)`.
+use std::{env, fmt::Write, fs, path::Path};
+
+// The modulus of the scalar field of BLS12-381, as little-endian limbs
+const MODULUS: [u64; 4] = [
+    0xffffffff00000001,
+    0x53bda402fffe5bfe,
+    0x3339d80809a1d805,
+    0x73eda753299d7d48,
+];
+// 2^256 mod MODULUS, the Montgomery form of one
+const R: [u64; 4] = [
+    0x00000001fffffffe,
+    0x5884b7fa00034802,
+    0x998c4fefecbc4ff5,
+    0x1824b159acc5056f,
+];
+
+fn main() {
+    println!("cargo:rerun-if-changed=build.rs");
+    if env::var_os("CARGO_FEATURE_BAKED_TABLES").is_none() {
+        return;
+    }
+
+    let mut out = String::new();
+    writeln!(out, "// Generated by build.rs, do not edit").unwrap();
+
+    writeln!(out, "const SMALL_VALUES: [Fr; 256] = [").unwrap();
+    let mut limbs = [0u64; 4];
+    for _ in 0..256 {
+        writeln!(
+            out,
+            "    field_new!(Fr, BigInteger256([{:#x}, {:#x}, {:#x}, {:#x}])),",
+            limbs[0], limbs[1], limbs[2], limbs[3]
+        )
+        .unwrap();
+        limbs = add_mod(&limbs, &R);
+    }
+    writeln!(out, "];").unwrap();
+
+    write_outputs(&mut out, "XOR_4_OUTPUTS", 4, |a, b| a ^ b);
+    write_outputs(&mut out, "XOR_8_OUTPUTS", 8, |a, b| a ^ b);
+
+    let path = Path::new(&env::var("OUT_DIR").unwrap()).join("baked_tables.rs");
+    fs::write(path, out).unwrap();
+}
+
+// Returns a + b mod MODULUS, for a and b less than MODULUS
+fn add_mod(a: &[u64; 4], b: &[u64; 4]) -> [u64; 4] {
+    let mut sum = [0u64; 4];
+    let mut carry = false;
+    for i in 0..4 {
+        let (limb, carry_1) = a[i].overflowing_add(b[i]);
+        let (limb, carry_2) = limb.overflowing_add(carry as u64);
+        sum[i] = limb;
+        carry = carry_1 || carry_2;
+    }
+    // The modulus is less than 2^255, so the sum does not overflow 256 bits
+    assert!(!carry);
+    if sum.iter().rev().ge(MODULUS.iter().rev()) {
+        let mut borrow = false;
+        for i in 0..4 {
+            let (limb, borrow_1) = sum[i].overflowing_sub(MODULUS[i]);
+            let (limb, borrow_2) = limb.overflowing_sub(borrow as u64);
+            sum[i] = limb;
+            borrow = borrow_1 || borrow_2;
+        }
+    }
+    sum
+}
+
+// Writes the output of the row (a, b) at index (a << bits) + b
+fn write_outputs(out: &mut String, name: &str, bits: usize, f: impl Fn(usize, usize) -> usize) {
+    let range = 1 << bits;
+    writeln!(out, "const {}: [u8; {}] = [", name, range * range).unwrap();
+    for a in 0..range {
+        let row: Vec<_> = (0..range).map(|b| f(a, b).to_string()).collect();
+        writeln!(out, "    {},", row.join(", ")).unwrap();
+    }
+    writeln!(out, "];").unwrap();
+}
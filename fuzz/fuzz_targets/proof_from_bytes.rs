@@ -0,0 +1,15 @@
+// Decodes arbitrary bytes as a proof, in each of the encodings that a verifier accepts
+// Malformed input must be rejected with an error rather than a panic
+#![no_main]
+use libfuzzer_sys::fuzz_target;
+use plookup::lookup::proof::LookUpProof;
+
+fuzz_target!(|data: &[u8]| {
+    let _ = LookUpProof::from_compressed_bytes(data);
+    let _ = LookUpProof::from_uncompressed_bytes(data);
+
+    if let Ok(encoded) = std::str::from_utf8(data) {
+        let _ = LookUpProof::from_hex(encoded);
+        let _ = LookUpProof::from_base64(encoded);
+    }
+});
@@ -0,0 +1,10 @@
+// Decodes arbitrary bytes as a proving key and as a verifier key
+// Malformed input must be rejected with an error rather than a panic
+#![no_main]
+use libfuzzer_sys::fuzz_target;
+use plookup::kzg10;
+
+fuzz_target!(|data: &[u8]| {
+    let _ = kzg10::read_powers(&mut &data[..]);
+    let _ = kzg10::read_verifier_key(&mut &data[..]);
+});
@@ -0,0 +1,155 @@
+#ifndef PLOOKUP_H
+#define PLOOKUP_H
+
+/* Generated by cbindgen from src/ffi.rs, do not edit by hand */
+
+#include <stdarg.h>
+#include <stdbool.h>
+#include <stdint.h>
+#include <stdlib.h>
+
+/*
+ The call succeeded
+ */
+#define PLOOKUP_OK 0
+
+/*
+ A pointer was null, or a field element was not canonically encoded
+ */
+#define PLOOKUP_INVALID_ARGUMENT 1
+
+/*
+ The proof could not be decoded, or it did not verify
+ */
+#define PLOOKUP_INVALID_PROOF 2
+
+/*
+ The library panicked, for example because the witness does not fit in the table
+ */
+#define PLOOKUP_INTERNAL_ERROR 3
+
+/*
+ The proving and verifier keys
+ */
+typedef struct PlookupKeys PlookupKeys;
+
+/*
+ A table which has been preprocessed
+ */
+typedef struct PlookupPreprocessedTable PlookupPreprocessedTable;
+
+/*
+ A table of rows (a, b) -> c which is built row by row
+ */
+typedef struct PlookupTable PlookupTable;
+
+/*
+ The rows (a, b, c) that the prover shows are in the table
+ */
+typedef struct PlookupWitness PlookupWitness;
+
+/*
+ A byte buffer which is owned by the library, and must be freed with `plookup_buffer_free`
+ */
+typedef struct PlookupBuffer {
+  uint8_t *data;
+  uintptr_t len;
+} PlookupBuffer;
+
+/*
+ Frees a buffer returned by the library
+ */
+void plookup_buffer_free(PlookupBuffer buffer);
+
+/*
+ Frees keys created by `plookup_keys_insecure_setup` or `plookup_keys_load`
+ */
+void plookup_keys_free(PlookupKeys *keys);
+
+/*
+ Creates keys for polynomials of degree up to `max_degree` from an insecure seed
+ This is for testing only, as the seed determines the trapdoor of the setup.
+ */
+PlookupKeys *plookup_keys_insecure_setup(uintptr_t max_degree,
+                                         const uint8_t *seed,
+                                         uintptr_t seed_len);
+
+/*
+ Reads keys which were written by `kzg10::write_powers` and `kzg10::write_verifier_key`
+ Returns null if either key could not be decoded.
+ */
+PlookupKeys *plookup_keys_load(const uint8_t *proving_key,
+                               uintptr_t proving_key_len,
+                               const uint8_t *verifier_key,
+                               uintptr_t verifier_key_len);
+
+/*
+ Frees a table created by `plookup_table_preprocess`
+ */
+void plookup_preprocessed_table_free(PlookupPreprocessedTable *table);
+
+/*
+ Proves that every row of the witness is in the table
+ The transcript is initialised with `label`, which the verifier must also use.
+ On success, the compressed proof is written to `proof`, which must then be freed with `plookup_buffer_free`.
+ */
+int plookup_prove(const PlookupKeys *keys,
+                  const PlookupPreprocessedTable *table,
+                  const PlookupWitness *witness,
+                  const uint8_t *label,
+                  uintptr_t label_len,
+                  PlookupBuffer *proof);
+
+/*
+ Frees a table created by `plookup_table_new`
+ */
+void plookup_table_free(PlookupTable *table);
+
+/*
+ Adds the row (a, b) -> c to the table, if there is no row with input (a, b) yet
+ */
+int plookup_table_insert(PlookupTable *table, const uint8_t *a, const uint8_t *b, const uint8_t *c);
+
+/*
+ Creates an empty table
+ */
+PlookupTable *plookup_table_new(void);
+
+/*
+ Preprocesses the table over a domain of size `n`, which must be a power of two
+ Returns null if the table could not be preprocessed.
+ */
+PlookupPreprocessedTable *plookup_table_preprocess(const PlookupTable *table,
+                                                   const PlookupKeys *keys,
+                                                   uintptr_t n);
+
+/*
+ Verifies a compressed proof against the table
+ Returns `PLOOKUP_OK` if the proof is valid, and `PLOOKUP_INVALID_PROOF` if it is not.
+ */
+int plookup_verify(const PlookupKeys *keys,
+                   const PlookupPreprocessedTable *table,
+                   const uint8_t *proof,
+                   uintptr_t proof_len,
+                   const uint8_t *label,
+                   uintptr_t label_len);
+
+/*
+ Frees a witness created by `plookup_witness_new`
+ */
+void plookup_witness_free(PlookupWitness *witness);
+
+/*
+ Creates an empty witness
+ */
+PlookupWitness *plookup_witness_new(void);
+
+/*
+ Adds the row (a, b, c) to the witness
+ */
+int plookup_witness_push(PlookupWitness *witness,
+                         const uint8_t *a,
+                         const uint8_t *b,
+                         const uint8_t *c);
+
+#endif /* PLOOKUP_H */
@@ -0,0 +1,234 @@
+// Encoding integers as field elements
+//
+// Unsigned integers are encoded as the field element with the same value.
+// Signed integers are encoded using two's complement in a fixed number of bits, so an n-bit value -x is
+// encoded as 2^n - x. The encoding of a signed value therefore depends on the width it is encoded in:
+// -1 is 0xff as an i8 and 0xffff as an i16. Tables of signed values must be built with the same widths
+// that the application encodes its values with, see `lookup::table::signed`.
+//
+// A field element is encoded in bytes as the 32 byte little-endian encoding of its canonical integer.
+// This is the encoding used by the transcript, by proof and table serialisation, and by the C API,
+// so other implementations can reproduce every byte that is hashed or stored.
+use algebra::{bls12_381::Fr, CanonicalDeserialize, CanonicalSerialize, PrimeField};
+
+#[cfg(feature = "parallel")]
+use rayon::prelude::*;
+
+/// Encodes `value` using two's complement in `bits` bits
+/// The value must be in the range -2^{bits-1}..2^{bits-1}
+pub fn to_twos_complement(value: i64, bits: usize) -> u64 {
+    assert!(bits > 0 && bits <= 64);
+    if bits < 64 {
+        let bound = 1i64 << (bits - 1);
+        assert!(value >= -bound && value < bound);
+        (value as u64) & ((1u64 << bits) - 1)
+    } else {
+        value as u64
+    }
+}
+
+/// Decodes a `bits`-bit two's complement encoding
+pub fn from_twos_complement(x: u64, bits: usize) -> i64 {
+    assert!(bits > 0 && bits <= 64);
+    let shift = 64 - bits;
+    ((x << shift) as i64) >> shift
+}
+
+/// Encodes an i8 using two's complement in 8 bits
+pub fn encode_i8(value: i8) -> Fr {
+    Fr::from(value as u8)
+}
+
+/// Encodes an i16 using two's complement in 16 bits
+pub fn encode_i16(value: i16) -> Fr {
+    Fr::from(value as u16)
+}
+
+/// Encodes an i32 using two's complement in 32 bits
+pub fn encode_i32(value: i32) -> Fr {
+    Fr::from(value as u32)
+}
+
+/// Encodes an i64 using two's complement in 64 bits
+pub fn encode_i64(value: i64) -> Fr {
+    Fr::from(value as u64)
+}
+
+/// Encodes `value` using two's complement in `bits` bits
+/// The value must be in the range -2^{bits-1}..2^{bits-1}
+pub fn encode_signed(value: i64, bits: usize) -> Fr {
+    Fr::from(to_twos_complement(value, bits))
+}
+
+/// Decodes a field element which encodes a signed value in `bits` bits
+/// Returns None if the field element is not less than 2^bits
+pub fn decode_signed(value: &Fr, bits: usize) -> Option<i64> {
+    let x = fr_to_u64(value)?;
+    if bits < 64 && x >> bits != 0 {
+        return None;
+    }
+    Some(from_twos_complement(x, bits))
+}
+
+/// Returns the integer that a field element encodes, if it is less than 2^64
+pub fn fr_to_u64(value: &Fr) -> Option<u64> {
+    let repr = value.into_repr();
+    let limbs = repr.as_ref();
+    if limbs[1..].iter().all(|limb| *limb == 0) {
+        Some(limbs[0])
+    } else {
+        None
+    }
+}
+
+/// Encodes a field element as `0x` followed by the 64 digit big-endian hex encoding of its canonical integer
+pub fn fr_to_hex(value: &Fr) -> String {
+    let mut bytes = fr_to_bytes_le(value);
+    bytes.reverse();
+    format!("0x{}", hex::encode(bytes))
+}
+
+/// Encodes a field element as the 32 byte little-endian encoding of its canonical integer
+pub fn fr_to_bytes_le(value: &Fr) -> [u8; 32] {
+    let mut bytes = [0u8; 32];
+    value
+        .serialize(&mut &mut bytes[..])
+        .expect("a field element is 32 bytes");
+    bytes
+}
+
+/// Decodes a field element from the 32 byte little-endian encoding of its canonical integer
+/// Returns None if there are not exactly 32 bytes, or if the integer is not less than the modulus.
+pub fn fr_from_bytes_le(bytes: &[u8]) -> Option<Fr> {
+    if bytes.len() != 32 {
+        return None;
+    }
+    Fr::deserialize(&mut &bytes[..]).ok()
+}
+
+/// Converts each value into a field element
+/// With the `parallel` feature enabled, the values are converted across all available threads
+pub fn u64s_to_frs(values: &[u64]) -> Vec<Fr> {
+    #[cfg(feature = "parallel")]
+    let iter = values.par_iter();
+    #[cfg(not(feature = "parallel"))]
+    let iter = values.iter();
+
+    iter.map(|value| Fr::from(*value)).collect()
+}
+
+/// Converts each byte into a field element
+/// With the `parallel` feature enabled, the bytes are converted across all available threads
+pub fn bytes_to_frs(bytes: &[u8]) -> Vec<Fr> {
+    #[cfg(feature = "parallel")]
+    let iter = bytes.par_iter();
+    #[cfg(not(feature = "parallel"))]
+    let iter = bytes.iter();
+
+    iter.map(|byte| Fr::from(*byte)).collect()
+}
+
+/// Splits a byte stream into little-endian words of `word_size` bytes, and converts each word into a field element
+/// Panics if `word_size` is not between 1 and 8, or does not divide the length of the stream.
+pub fn le_words_to_frs(bytes: &[u8], word_size: usize) -> Vec<Fr> {
+    assert!(word_size > 0 && word_size <= 8);
+    assert_eq!(bytes.len() % word_size, 0);
+
+    let to_fr = |word: &[u8]| {
+        let mut buf = [0u8; 8];
+        buf[..word.len()].copy_from_slice(word);
+        Fr::from(u64::from_le_bytes(buf))
+    };
+
+    #[cfg(feature = "parallel")]
+    let iter = bytes.par_chunks(word_size);
+    #[cfg(not(feature = "parallel"))]
+    let iter = bytes.chunks(word_size);
+
+    iter.map(to_fr).collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_twos_complement() {
+        assert_eq!(to_twos_complement(-1, 8), 0xff);
+        assert_eq!(to_twos_complement(-128, 8), 0x80);
+        assert_eq!(to_twos_complement(127, 8), 0x7f);
+        assert_eq!(to_twos_complement(-1, 64), u64::max_value());
+
+        for value in -128..128 {
+            assert_eq!(from_twos_complement(to_twos_complement(value, 8), 8), value);
+        }
+    }
+
+    #[test]
+    fn test_signed_encodings() {
+        assert_eq!(encode_i8(-1), Fr::from(0xffu8));
+        assert_eq!(encode_i16(-1), Fr::from(0xffffu16));
+        assert_eq!(encode_i32(-2), Fr::from(0xffff_fffeu32));
+        assert_eq!(encode_i64(i64::min_value()), Fr::from(1u64 << 63));
+        assert_eq!(encode_i8(-5), encode_signed(-5, 8));
+        assert_eq!(encode_i32(7), Fr::from(7u8));
+
+        assert_eq!(decode_signed(&encode_i16(-300), 16), Some(-300));
+        assert_eq!(decode_signed(&encode_i64(-3), 64), Some(-3));
+        // The encoding of an i16 is too wide for 8 bits
+        assert_eq!(decode_signed(&encode_i16(-1), 8), None);
+        assert_eq!(decode_signed(&-Fr::from(1u8), 64), None);
+    }
+
+    #[test]
+    fn test_bulk_conversions() {
+        let values: Vec<u64> = (0..5000).map(|i| i * 0x1_0000_0001).collect();
+        let frs = u64s_to_frs(&values);
+        assert_eq!(frs.len(), values.len());
+        for (fr, value) in frs.iter().zip(values.iter()) {
+            assert_eq!(fr_to_u64(fr), Some(*value));
+        }
+
+        assert_eq!(
+            bytes_to_frs(&[0, 7, 255]),
+            vec![Fr::from(0u8), Fr::from(7u8), Fr::from(255u8)]
+        );
+
+        let words = le_words_to_frs(&[0x01, 0x02, 0x03, 0x04, 0xff, 0xff], 2);
+        assert_eq!(
+            words,
+            vec![
+                Fr::from(0x0201u16),
+                Fr::from(0x0403u16),
+                Fr::from(0xffffu16)
+            ]
+        );
+        let words = le_words_to_frs(&[1, 0, 0, 0, 0, 0, 0, 0x80], 8);
+        assert_eq!(words, vec![Fr::from(0x8000_0000_0000_0001u64)]);
+    }
+
+    #[test]
+    fn test_bytes_le() {
+        use algebra::{to_bytes, ToBytes};
+
+        let value = Fr::from(0x0102_0304u64);
+        let bytes = fr_to_bytes_le(&value);
+        assert_eq!(&bytes[..5], &[0x04, 0x03, 0x02, 0x01, 0x00]);
+        assert_eq!(bytes.to_vec(), to_bytes![value].unwrap());
+        assert_eq!(fr_from_bytes_le(&bytes), Some(value));
+        assert_eq!(fr_to_hex(&value), format!("0x{}01020304", "0".repeat(56)));
+
+        let minus_one = -Fr::from(1u8);
+        assert_eq!(
+            fr_from_bytes_le(&fr_to_bytes_le(&minus_one)),
+            Some(minus_one)
+        );
+
+        // The modulus itself is not a canonical encoding
+        let mut modulus = fr_to_bytes_le(&minus_one);
+        modulus[0] += 1;
+        assert_eq!(fr_from_bytes_le(&modulus), None);
+        assert_eq!(fr_from_bytes_le(&[0xff; 32]), None);
+        assert_eq!(fr_from_bytes_le(&bytes[..31]), None);
+    }
+}
@@ -0,0 +1,129 @@
+use crate::multiset::ProverStage;
+use algebra::SerializationError;
+use std::fmt;
+
+/// Errors which can be returned by the library
+#[derive(Debug)]
+pub enum Error {
+    /// Reading or writing a file failed
+    Io(std::io::Error),
+    /// Stored data could not be decoded
+    Serialization(SerializationError),
+    /// Stored data was produced for a different table than the one requested,
+    /// or the table does not have the size of the domain it is proven over
+    TableMismatch,
+    /// The witness contains rows which are not in the table, given by their indices
+    MissingRows(Vec<usize>),
+    /// The values at the given indices are out of the range that was requested
+    OutOfRange(Vec<usize>),
+    /// The keys at the given indices are in the table, so they cannot be shown not to be
+    ContainedKeys(Vec<usize>),
+    /// The rows at the given indices have the same input as an earlier row, but a different output
+    ConflictingRows(Vec<usize>),
+    /// The queries at the given indices use a row more times than the table allows
+    Overused(Vec<usize>),
+    /// A hex string could not be decoded
+    Hex(hex::FromHexError),
+    /// A base64 string could not be decoded
+    Base64(base64::DecodeError),
+    /// The prover was cancelled before the given stage
+    Cancelled(ProverStage),
+    /// The prover's audit found that the plookup identity does not hold
+    AuditFailed,
+    /// The evaluations of a committed polynomial do not match the witness
+    WitnessMismatch,
+    /// A saved prover state was computed for a different witness or table
+    StateMismatch,
+    /// The SRS supports polynomials up to degree `available`, but degree `required` is needed
+    SrsTooSmall { required: usize, available: usize },
+    /// A thread pool with the requested number of threads could not be created
+    ThreadPool(String),
+    /// A witness of `rows` rows does not fit in a proof which holds at most `capacity` rows
+    CapacityExceeded { rows: usize, capacity: usize },
+    /// An MSM window of this many bits is not between 1 and `kzg10::MAX_WINDOW_BITS`
+    InvalidWindow(usize),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::Io(err) => write!(f, "io error: {}", err),
+            Error::Serialization(err) => write!(f, "serialization error: {}", err),
+            Error::TableMismatch => write!(f, "the table does not match the one expected"),
+            Error::MissingRows(indices) => {
+                write!(f, "witness rows {:?} are not in the table", indices)
+            }
+            Error::OutOfRange(indices) => write!(f, "values {:?} are out of range", indices),
+            Error::ContainedKeys(indices) => write!(f, "keys {:?} are in the table", indices),
+            Error::ConflictingRows(indices) => write!(
+                f,
+                "rows {:?} give a different output for an input already in the table",
+                indices
+            ),
+            Error::Overused(indices) => {
+                write!(f, "queries {:?} use a row more times than allowed", indices)
+            }
+            Error::Hex(err) => write!(f, "hex decoding error: {}", err),
+            Error::Base64(err) => write!(f, "base64 decoding error: {}", err),
+            Error::Cancelled(stage) => write!(f, "prover was cancelled before stage {:?}", stage),
+            Error::AuditFailed => write!(f, "the plookup identity does not hold for the proof"),
+            Error::WitnessMismatch => {
+                write!(f, "the committed polynomial does not match the witness")
+            }
+            Error::StateMismatch => {
+                write!(
+                    f,
+                    "the prover state was saved for a different witness or table"
+                )
+            }
+            Error::SrsTooSmall {
+                required,
+                available,
+            } => write!(
+                f,
+                "the SRS supports degree {}, but degree {} is required: load an SRS with at least {} powers",
+                available,
+                required,
+                required + 1
+            ),
+            Error::ThreadPool(err) => write!(f, "could not create the thread pool: {}", err),
+            Error::CapacityExceeded { rows, capacity } => write!(
+                f,
+                "{} rows do not fit in a proof which holds at most {} rows",
+                rows, capacity
+            ),
+            Error::InvalidWindow(window_bits) => write!(
+                f,
+                "a window of {} bits is not between 1 and {} bits",
+                window_bits,
+                crate::kzg10::MAX_WINDOW_BITS
+            ),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl From<std::io::Error> for Error {
+    fn from(err: std::io::Error) -> Self {
+        Error::Io(err)
+    }
+}
+
+impl From<SerializationError> for Error {
+    fn from(err: SerializationError) -> Self {
+        Error::Serialization(err)
+    }
+}
+
+impl From<hex::FromHexError> for Error {
+    fn from(err: hex::FromHexError) -> Self {
+        Error::Hex(err)
+    }
+}
+
+impl From<base64::DecodeError> for Error {
+    fn from(err: base64::DecodeError) -> Self {
+        Error::Base64(err)
+    }
+}
@@ -0,0 +1,490 @@
+// A C ABI for the prover and the verifier, enabled with the `ffi` feature
+//
+// Keys, tables and witnesses are passed across the boundary as opaque handles, which are created
+// and freed by the functions below. Field elements are passed as pointers to their 32 byte canonical
+// (little-endian) encoding, and proofs are passed as their compressed byte encoding.
+//
+// Functions which can fail return one of the `PLOOKUP_*` status codes. Panics are caught at the boundary
+// and reported as `PLOOKUP_INTERNAL_ERROR`.
+//
+// The header `include/plookup.h` is generated with `cbindgen --config cbindgen.toml --output include/plookup.h`
+// The crate is built as an rlib. A C library is built with
+// `cargo rustc --release --features ffi -- --crate-type cdylib`, or `staticlib` for a static library.
+use crate::{
+    encoding::fr_from_bytes_le,
+    kzg10,
+    lookup::{
+        proof::LookUpProof,
+        table::{DynamicTable, Generic, LookUpTable, PreProcessedTable},
+    },
+    multiset::MultiSet,
+};
+use algebra::{bls12_381::Fr, Bls12_381};
+use merlin::Transcript;
+use poly_commit::kzg10::{Powers, VerifierKey};
+use std::{
+    collections::HashMap,
+    os::raw::c_int,
+    panic::{catch_unwind, AssertUnwindSafe},
+    ptr, slice,
+};
+
+/// The call succeeded
+pub const PLOOKUP_OK: c_int = 0;
+/// A pointer was null, or a field element was not canonically encoded
+pub const PLOOKUP_INVALID_ARGUMENT: c_int = 1;
+/// The proof could not be decoded, or it did not verify
+pub const PLOOKUP_INVALID_PROOF: c_int = 2;
+/// The library panicked, for example because the witness does not fit in the table
+pub const PLOOKUP_INTERNAL_ERROR: c_int = 3;
+
+/// The proving and verifier keys
+pub struct PlookupKeys {
+    proving_key: Powers<'static, Bls12_381>,
+    verifier_key: VerifierKey<Bls12_381>,
+}
+
+/// A table of rows (a, b) -> c which is built row by row
+pub struct PlookupTable(Generic);
+
+/// A table which has been preprocessed
+pub struct PlookupPreprocessedTable(PreProcessedTable);
+
+/// The rows (a, b, c) that the prover shows are in the table
+pub struct PlookupWitness {
+    f_1: MultiSet,
+    f_2: MultiSet,
+    f_3: MultiSet,
+}
+
+/// A byte buffer which is owned by the library, and must be freed with `plookup_buffer_free`
+#[repr(C)]
+pub struct PlookupBuffer {
+    pub data: *mut u8,
+    pub len: usize,
+}
+
+/// Creates keys for polynomials of degree up to `max_degree` from an insecure seed
+/// This is for testing only, as the seed determines the trapdoor of the setup.
+#[no_mangle]
+pub unsafe extern "C" fn plookup_keys_insecure_setup(
+    max_degree: usize,
+    seed: *const u8,
+    seed_len: usize,
+) -> *mut PlookupKeys {
+    if seed.is_null() {
+        return ptr::null_mut();
+    }
+    let seed = slice::from_raw_parts(seed, seed_len);
+    match catch_unwind(|| kzg10::trusted_setup(max_degree, seed)) {
+        Ok((proving_key, verifier_key)) => Box::into_raw(Box::new(PlookupKeys {
+            proving_key,
+            verifier_key,
+        })),
+        Err(_) => ptr::null_mut(),
+    }
+}
+
+/// Reads keys which were written by `kzg10::write_powers` and `kzg10::write_verifier_key`
+/// Returns null if either key could not be decoded.
+#[no_mangle]
+pub unsafe extern "C" fn plookup_keys_load(
+    proving_key: *const u8,
+    proving_key_len: usize,
+    verifier_key: *const u8,
+    verifier_key_len: usize,
+) -> *mut PlookupKeys {
+    if proving_key.is_null() || verifier_key.is_null() {
+        return ptr::null_mut();
+    }
+    let mut proving_key = slice::from_raw_parts(proving_key, proving_key_len);
+    let mut verifier_key = slice::from_raw_parts(verifier_key, verifier_key_len);
+    let result = catch_unwind(AssertUnwindSafe(|| {
+        Some((
+            kzg10::read_powers(&mut proving_key).ok()?,
+            kzg10::read_verifier_key(&mut verifier_key).ok()?,
+        ))
+    }));
+    match result {
+        Ok(Some((proving_key, verifier_key))) => Box::into_raw(Box::new(PlookupKeys {
+            proving_key,
+            verifier_key,
+        })),
+        _ => ptr::null_mut(),
+    }
+}
+
+/// Frees keys created by `plookup_keys_insecure_setup` or `plookup_keys_load`
+#[no_mangle]
+pub unsafe extern "C" fn plookup_keys_free(keys: *mut PlookupKeys) {
+    if !keys.is_null() {
+        drop(Box::from_raw(keys));
+    }
+}
+
+/// Creates an empty table
+#[no_mangle]
+pub extern "C" fn plookup_table_new() -> *mut PlookupTable {
+    Box::into_raw(Box::new(PlookupTable(
+        Generic::with_hashmap(HashMap::new()),
+    )))
+}
+
+/// Adds the row (a, b) -> c to the table, if there is no row with input (a, b) yet
+#[no_mangle]
+pub unsafe extern "C" fn plookup_table_insert(
+    table: *mut PlookupTable,
+    a: *const u8,
+    b: *const u8,
+    c: *const u8,
+) -> c_int {
+    let table = match table.as_mut() {
+        Some(table) => table,
+        None => return PLOOKUP_INVALID_ARGUMENT,
+    };
+    match (read_scalar(a), read_scalar(b), read_scalar(c)) {
+        (Some(a), Some(b), Some(c)) => {
+            table.0.insert((a, b), c);
+            PLOOKUP_OK
+        }
+        _ => PLOOKUP_INVALID_ARGUMENT,
+    }
+}
+
+/// Preprocesses the table over a domain of size `n`, which must be a power of two
+/// Returns null if the table could not be preprocessed.
+#[no_mangle]
+pub unsafe extern "C" fn plookup_table_preprocess(
+    table: *const PlookupTable,
+    keys: *const PlookupKeys,
+    n: usize,
+) -> *mut PlookupPreprocessedTable {
+    let (table, keys) = match (table.as_ref(), keys.as_ref()) {
+        (Some(table), Some(keys)) => (table, keys),
+        _ => return ptr::null_mut(),
+    };
+    match catch_unwind(AssertUnwindSafe(|| {
+        table.0.preprocess(&keys.proving_key, n)
+    })) {
+        Ok(preprocessed) => Box::into_raw(Box::new(PlookupPreprocessedTable(preprocessed))),
+        Err(_) => ptr::null_mut(),
+    }
+}
+
+/// Frees a table created by `plookup_table_new`
+#[no_mangle]
+pub unsafe extern "C" fn plookup_table_free(table: *mut PlookupTable) {
+    if !table.is_null() {
+        drop(Box::from_raw(table));
+    }
+}
+
+/// Frees a table created by `plookup_table_preprocess`
+#[no_mangle]
+pub unsafe extern "C" fn plookup_preprocessed_table_free(table: *mut PlookupPreprocessedTable) {
+    if !table.is_null() {
+        drop(Box::from_raw(table));
+    }
+}
+
+/// Creates an empty witness
+#[no_mangle]
+pub extern "C" fn plookup_witness_new() -> *mut PlookupWitness {
+    Box::into_raw(Box::new(PlookupWitness {
+        f_1: MultiSet::new(),
+        f_2: MultiSet::new(),
+        f_3: MultiSet::new(),
+    }))
+}
+
+/// Adds the row (a, b, c) to the witness
+#[no_mangle]
+pub unsafe extern "C" fn plookup_witness_push(
+    witness: *mut PlookupWitness,
+    a: *const u8,
+    b: *const u8,
+    c: *const u8,
+) -> c_int {
+    let witness = match witness.as_mut() {
+        Some(witness) => witness,
+        None => return PLOOKUP_INVALID_ARGUMENT,
+    };
+    match (read_scalar(a), read_scalar(b), read_scalar(c)) {
+        (Some(a), Some(b), Some(c)) => {
+            witness.f_1.push(a);
+            witness.f_2.push(b);
+            witness.f_3.push(c);
+            PLOOKUP_OK
+        }
+        _ => PLOOKUP_INVALID_ARGUMENT,
+    }
+}
+
+/// Frees a witness created by `plookup_witness_new`
+#[no_mangle]
+pub unsafe extern "C" fn plookup_witness_free(witness: *mut PlookupWitness) {
+    if !witness.is_null() {
+        drop(Box::from_raw(witness));
+    }
+}
+
+/// Proves that every row of the witness is in the table
+/// The transcript is initialised with `label`, which the verifier must also use.
+/// On success, the compressed proof is written to `proof`, which must then be freed with `plookup_buffer_free`.
+#[no_mangle]
+pub unsafe extern "C" fn plookup_prove(
+    keys: *const PlookupKeys,
+    table: *const PlookupPreprocessedTable,
+    witness: *const PlookupWitness,
+    label: *const u8,
+    label_len: usize,
+    proof: *mut PlookupBuffer,
+) -> c_int {
+    let (keys, table, witness) = match (keys.as_ref(), table.as_ref(), witness.as_ref()) {
+        (Some(keys), Some(table), Some(witness)) => (keys, table, witness),
+        _ => return PLOOKUP_INVALID_ARGUMENT,
+    };
+    let label = match read_label(label, label_len) {
+        Some(label) => label,
+        None => return PLOOKUP_INVALID_ARGUMENT,
+    };
+    if proof.is_null() {
+        return PLOOKUP_INVALID_ARGUMENT;
+    }
+
+    let result = catch_unwind(AssertUnwindSafe(|| {
+        let mut transcript = new_transcript(label);
+        LookUpProof::prove(
+            &witness.f_1,
+            &witness.f_2,
+            &witness.f_3,
+            &keys.proving_key,
+            &table.0,
+            &mut transcript,
+        )
+        .to_compressed_bytes()
+    }));
+    match result {
+        Ok(bytes) => {
+            *proof = into_buffer(bytes);
+            PLOOKUP_OK
+        }
+        Err(_) => PLOOKUP_INTERNAL_ERROR,
+    }
+}
+
+/// Verifies a compressed proof against the table
+/// Returns `PLOOKUP_OK` if the proof is valid, and `PLOOKUP_INVALID_PROOF` if it is not.
+#[no_mangle]
+pub unsafe extern "C" fn plookup_verify(
+    keys: *const PlookupKeys,
+    table: *const PlookupPreprocessedTable,
+    proof: *const u8,
+    proof_len: usize,
+    label: *const u8,
+    label_len: usize,
+) -> c_int {
+    let (keys, table) = match (keys.as_ref(), table.as_ref()) {
+        (Some(keys), Some(table)) => (keys, table),
+        _ => return PLOOKUP_INVALID_ARGUMENT,
+    };
+    let label = match read_label(label, label_len) {
+        Some(label) => label,
+        None => return PLOOKUP_INVALID_ARGUMENT,
+    };
+    if proof.is_null() {
+        return PLOOKUP_INVALID_ARGUMENT;
+    }
+    let proof = match LookUpProof::from_compressed_bytes(slice::from_raw_parts(proof, proof_len)) {
+        Ok(proof) => proof,
+        Err(_) => return PLOOKUP_INVALID_PROOF,
+    };
+
+    let result = catch_unwind(AssertUnwindSafe(|| {
+        let mut transcript = new_transcript(label);
+        proof.verify(&keys.verifier_key, &table.0, &mut transcript)
+    }));
+    match result {
+        Ok(true) => PLOOKUP_OK,
+        Ok(false) => PLOOKUP_INVALID_PROOF,
+        Err(_) => PLOOKUP_INTERNAL_ERROR,
+    }
+}
+
+/// Frees a buffer returned by the library
+#[no_mangle]
+pub unsafe extern "C" fn plookup_buffer_free(buffer: PlookupBuffer) {
+    if !buffer.data.is_null() {
+        drop(Vec::from_raw_parts(buffer.data, buffer.len, buffer.len));
+    }
+}
+
+// Reads a field element from its 32 byte canonical encoding
+unsafe fn read_scalar(bytes: *const u8) -> Option<Fr> {
+    if bytes.is_null() {
+        return None;
+    }
+    fr_from_bytes_le(slice::from_raw_parts(bytes, 32))
+}
+
+unsafe fn read_label<'a>(label: *const u8, label_len: usize) -> Option<&'a [u8]> {
+    if label.is_null() {
+        return None;
+    }
+    Some(slice::from_raw_parts(label, label_len))
+}
+
+// Merlin transcripts are labelled with a static string, so the caller's label is appended to the transcript instead
+fn new_transcript(label: &[u8]) -> Transcript {
+    let mut transcript = Transcript::new(b"plookup_ffi");
+    transcript.append_message(b"label", label);
+    transcript
+}
+
+fn into_buffer(bytes: Vec<u8>) -> PlookupBuffer {
+    let mut bytes = bytes.into_boxed_slice();
+    let buffer = PlookupBuffer {
+        data: bytes.as_mut_ptr(),
+        len: bytes.len(),
+    };
+    std::mem::forget(bytes);
+    buffer
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::encoding::fr_to_bytes_le;
+
+    fn encode(x: u64) -> Vec<u8> {
+        fr_to_bytes_le(&Fr::from(x)).to_vec()
+    }
+
+    #[test]
+    fn test_prove_and_verify() {
+        unsafe {
+            let seed = b"insecure_seed";
+            let keys = plookup_keys_insecure_setup(2usize.pow(10), seed.as_ptr(), seed.len());
+            assert!(!keys.is_null());
+
+            // A table of (a, b) -> a + b for 4 bit values
+            let table = plookup_table_new();
+            for a in 0..16 {
+                for b in 0..16 {
+                    let (a_bytes, b_bytes, c_bytes) = (encode(a), encode(b), encode(a + b));
+                    let status = plookup_table_insert(
+                        table,
+                        a_bytes.as_ptr(),
+                        b_bytes.as_ptr(),
+                        c_bytes.as_ptr(),
+                    );
+                    assert_eq!(status, PLOOKUP_OK);
+                }
+            }
+            let preprocessed = plookup_table_preprocess(table, keys, 2usize.pow(8));
+            assert!(!preprocessed.is_null());
+
+            let witness = plookup_witness_new();
+            let (a, b, c) = (encode(3), encode(4), encode(7));
+            let status = plookup_witness_push(witness, a.as_ptr(), b.as_ptr(), c.as_ptr());
+            assert_eq!(status, PLOOKUP_OK);
+
+            // A non-canonical field element is rejected
+            let invalid = [0xffu8; 32];
+            let status = plookup_witness_push(witness, invalid.as_ptr(), b.as_ptr(), c.as_ptr());
+            assert_eq!(status, PLOOKUP_INVALID_ARGUMENT);
+
+            let label = b"ffi_test";
+            let mut proof = PlookupBuffer {
+                data: ptr::null_mut(),
+                len: 0,
+            };
+            let status = plookup_prove(
+                keys,
+                preprocessed,
+                witness,
+                label.as_ptr(),
+                label.len(),
+                &mut proof,
+            );
+            assert_eq!(status, PLOOKUP_OK);
+
+            let status = plookup_verify(
+                keys,
+                preprocessed,
+                proof.data,
+                proof.len,
+                label.as_ptr(),
+                label.len(),
+            );
+            assert_eq!(status, PLOOKUP_OK);
+
+            // The verifier must use the same label
+            let other_label = b"other";
+            let status = plookup_verify(
+                keys,
+                preprocessed,
+                proof.data,
+                proof.len,
+                other_label.as_ptr(),
+                other_label.len(),
+            );
+            assert_eq!(status, PLOOKUP_INVALID_PROOF);
+
+            // Null handles are rejected
+            let status = plookup_verify(
+                ptr::null(),
+                preprocessed,
+                proof.data,
+                proof.len,
+                label.as_ptr(),
+                label.len(),
+            );
+            assert_eq!(status, PLOOKUP_INVALID_ARGUMENT);
+
+            plookup_buffer_free(proof);
+            plookup_witness_free(witness);
+            plookup_preprocessed_table_free(preprocessed);
+            plookup_table_free(table);
+            plookup_keys_free(keys);
+        }
+    }
+
+    #[test]
+    fn test_keys_load() {
+        let (proving_key, verifier_key) = kzg10::trusted_setup(2usize.pow(6), b"insecure_seed");
+        let mut proving_key_bytes = Vec::new();
+        kzg10::write_powers(&proving_key, &mut proving_key_bytes).unwrap();
+        let mut verifier_key_bytes = Vec::new();
+        kzg10::write_verifier_key(&verifier_key, &mut verifier_key_bytes).unwrap();
+
+        unsafe {
+            let keys = plookup_keys_load(
+                proving_key_bytes.as_ptr(),
+                proving_key_bytes.len(),
+                verifier_key_bytes.as_ptr(),
+                verifier_key_bytes.len(),
+            );
+            assert!(!keys.is_null());
+            assert_eq!((*keys).proving_key.powers_of_g, proving_key.powers_of_g);
+            assert_eq!((*keys).verifier_key.beta_h, verifier_key.beta_h);
+            plookup_keys_free(keys);
+
+            // Truncated keys are rejected
+            let keys = plookup_keys_load(
+                proving_key_bytes.as_ptr(),
+                proving_key_bytes.len() - 1,
+                verifier_key_bytes.as_ptr(),
+                verifier_key_bytes.len(),
+            );
+            assert!(keys.is_null());
+            let keys = plookup_keys_load(
+                proving_key_bytes.as_ptr(),
+                proving_key_bytes.len(),
+                ptr::null(),
+                0,
+            );
+            assert!(keys.is_null());
+        }
+    }
+}
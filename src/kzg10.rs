@@ -1,23 +1,104 @@
-use algebra::bls12_381::{Fr, G1Projective};
-use algebra::Bls12_381;
-use algebra_core::curves::AffineCurve;
-use ff_fft::DensePolynomial as Polynomial;
-use num_traits::identities::Zero;
+use crate::{error::Error, lookup::cost, multiset::ProverConfig};
+use algebra::bls12_381::{Fr, G1Affine, G1Projective, G2Affine};
+use algebra::{
+    io::{Read, Write},
+    to_bytes, Bls12_381, CanonicalDeserialize, CanonicalSerialize, Field, PairingEngine,
+    PrimeField, SerializationError, ToBytes, UniformRand,
+};
+use algebra_core::curves::{AffineCurve, ProjectiveCurve};
+use algebra_core::msm::{FixedBaseMSM, VariableBaseMSM};
+use ff_fft::{DensePolynomial as Polynomial, EvaluationDomain};
+use merlin::Transcript;
+use num_traits::identities::{One, Zero};
 use poly_commit::kzg10::{Commitment, Powers, Proof, UniversalParams, VerifierKey, KZG10};
 use rand_chacha::ChaChaRng;
-use rand_core::SeedableRng;
+use rand_core::{CryptoRng, RngCore, SeedableRng};
+
+#[cfg(feature = "parallel")]
+use rayon::prelude::*;
 // Modification of https://github.com/scipr-lab/poly-commit/blob/master/src/kzg10/mod.rs
 type KzgBls12_381 = KZG10<Bls12_381>;
 
+/// Generates an SRS for polynomials of degree up to `max_deg`, with the secret derived from the first 32 bytes of `seed`
+/// Anyone who knows the seed can forge proofs, so this is only suitable for testing
 pub fn trusted_setup<'a>(
     max_deg: usize,
     seed: &[u8],
 ) -> (Powers<'a, Bls12_381>, VerifierKey<Bls12_381>) {
     let mut rng = ChaChaRng::from_seed(to_32_bytes(seed));
-    let pp = KzgBls12_381::setup(max_deg, false, &mut rng).unwrap();
+    setup_with_rng(max_deg, &mut rng)
+}
+
+/// Generates an SRS for polynomials of degree up to `max_deg`, with the secret sampled from `rng`
+pub fn setup_with_rng<'a, R: RngCore + CryptoRng>(
+    max_deg: usize,
+    rng: &mut R,
+) -> (Powers<'a, Bls12_381>, VerifierKey<Bls12_381>) {
+    let pp = universal_setup(max_deg, rng);
     trim(&pp, max_deg)
 }
 
+/// Generates universal parameters for polynomials of degree up to `max_deg`, which can be trimmed to a smaller degree
+pub fn universal_setup<R: RngCore + CryptoRng>(
+    max_deg: usize,
+    rng: &mut R,
+) -> UniversalParams<Bls12_381> {
+    KzgBls12_381::setup(max_deg, false, rng).unwrap()
+}
+
+/// Returns the largest degree of a polynomial that can be committed to with `powers`
+pub fn max_degree(powers: &Powers<Bls12_381>) -> usize {
+    powers.size() - 1
+}
+
+/// Trims the universal parameters to exactly the degree needed to prove lookups into a table padded to `n`
+/// Returns an error if the parameters do not support that degree
+pub fn trim_for_table<'a>(
+    pp: &UniversalParams<Bls12_381>,
+    n: usize,
+) -> Result<(Powers<'a, Bls12_381>, VerifierKey<Bls12_381>), Error> {
+    let required = cost::srs_size(n) - 1;
+    let available = pp.powers_of_g.len() - 1;
+    if required > available {
+        return Err(Error::SrsTooSmall {
+            required,
+            available,
+        });
+    }
+    Ok(trim(pp, required))
+}
+
+/// Checks that `powers` can commit to every polynomial in a proof for a table padded to `n`,
+/// when the proof is made with the default `ProverConfig`
+pub fn check_capacity(powers: &Powers<Bls12_381>, n: usize) -> Result<(), Error> {
+    check_capacity_with_config(powers, n, &ProverConfig::default())
+}
+
+/// Checks that `powers` can commit to every polynomial in a proof for a table padded to `n`,
+/// when the proof is made with `config`
+/// Returns `Error::SrsTooSmall` with the degree that the SRS must support if it cannot.
+pub fn check_capacity_with_config<K: CommitKey + ?Sized>(
+    powers: &K,
+    n: usize,
+    config: &ProverConfig,
+) -> Result<(), Error> {
+    let required = cost::srs_size_with_config(n, config) - 1;
+    let available = powers.size() - 1;
+    if required > available {
+        return Err(Error::SrsTooSmall {
+            required,
+            available,
+        });
+    }
+    Ok(())
+}
+
+/// Generates the same SRS on every call, for tests and benchmarks which must be deterministic
+/// The secret is derived from a fixed, public seed, so the SRS must never be used outside of tests
+pub fn test_setup<'a>(max_deg: usize) -> (Powers<'a, Bls12_381>, VerifierKey<Bls12_381>) {
+    trusted_setup(max_deg, b"plookup_insecure_test_srs")
+}
+
 fn to_32_bytes(bytes: &[u8]) -> [u8; 32] {
     let mut array: [u8; 32] = [0; 32];
     for (a, b) in bytes.iter().zip(array.iter_mut()) {
@@ -51,12 +132,596 @@ fn trim<'a>(
     (powers, vk)
 }
 
+/// Constructs the keys from the points of an SRS made by an external ceremony, such as the Ethereum KZG ceremony:
+/// the powers [tau^i]G of the G1 generator, the G2 generator h, and [tau]h
+/// Commitments in this crate are never hiding, so the hiding powers are left empty and `gamma_g` is set to `g`.
+pub fn keys_from_points<'a>(
+    powers_of_g: Vec<G1Affine>,
+    h: G2Affine,
+    beta_h: G2Affine,
+) -> (Powers<'a, Bls12_381>, VerifierKey<Bls12_381>) {
+    assert!(!powers_of_g.is_empty());
+    let g = powers_of_g[0];
+
+    let powers = Powers {
+        powers_of_g: std::borrow::Cow::Owned(powers_of_g),
+        powers_of_gamma_g: std::borrow::Cow::Owned(Vec::new()),
+    };
+    let vk = VerifierKey {
+        g,
+        gamma_g: g,
+        h,
+        beta_h,
+        prepared_h: h.into(),
+        prepared_beta_h: beta_h.into(),
+    };
+    (powers, vk)
+}
+
+/// Returns a digest of the first `n` powers in the SRS, which are all that is used
+/// to commit to a polynomial with `n` coefficients
+pub fn srs_digest(powers: &Powers<Bls12_381>, n: usize) -> [u8; 32] {
+    assert!(n <= powers.size());
+
+    let mut transcript = Transcript::new(b"plookup_srs");
+    transcript.append_u64(b"n", n as u64);
+    for g in powers.powers_of_g[..n].iter() {
+        transcript.append_message(b"g", &to_bytes![g].unwrap());
+    }
+
+    let mut digest = [0u8; 32];
+    transcript.challenge_bytes(b"digest", &mut digest);
+    digest
+}
+
+// The proving key is serialised as the number of `powers_of_g` and the number of `powers_of_gamma_g`, each as a u64,
+// followed by the powers themselves. The hiding powers are counted separately as keys from `keys_from_points` have none.
+// The verifier key is serialised as `g`, `gamma_g`, `h` and `beta_h`. The prepared elements are recomputed when it is read.
+
+/// Writes the proving key with each group element in its compressed form
+pub fn write_powers<W: Write>(
+    powers: &Powers<Bls12_381>,
+    writer: &mut W,
+) -> Result<(), SerializationError> {
+    (powers.powers_of_g.len() as u64).serialize(writer)?;
+    (powers.powers_of_gamma_g.len() as u64).serialize(writer)?;
+    for g in powers.powers_of_g.iter() {
+        g.serialize(writer)?;
+    }
+    for gamma_g in powers.powers_of_gamma_g.iter() {
+        gamma_g.serialize(writer)?;
+    }
+    Ok(())
+}
+
+/// Reads a proving key which was written by `write_powers`
+pub fn read_powers<'a, R: Read>(
+    reader: &mut R,
+) -> Result<Powers<'a, Bls12_381>, SerializationError> {
+    let num_powers = u64::deserialize(reader)? as usize;
+    let num_gamma_powers = u64::deserialize(reader)? as usize;
+    let powers_of_g = (0..num_powers)
+        .map(|_| G1Affine::deserialize(reader))
+        .collect::<Result<Vec<_>, _>>()?;
+    let powers_of_gamma_g = (0..num_gamma_powers)
+        .map(|_| G1Affine::deserialize(reader))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    Ok(Powers {
+        powers_of_g: std::borrow::Cow::Owned(powers_of_g),
+        powers_of_gamma_g: std::borrow::Cow::Owned(powers_of_gamma_g),
+    })
+}
+
+/// Writes the verifier key with each group element in its compressed form
+pub fn write_verifier_key<W: Write>(
+    vk: &VerifierKey<Bls12_381>,
+    writer: &mut W,
+) -> Result<(), SerializationError> {
+    vk.g.serialize(writer)?;
+    vk.gamma_g.serialize(writer)?;
+    vk.h.serialize(writer)?;
+    vk.beta_h.serialize(writer)
+}
+
+/// Reads a verifier key which was written by `write_verifier_key`
+pub fn read_verifier_key<R: Read>(
+    reader: &mut R,
+) -> Result<VerifierKey<Bls12_381>, SerializationError> {
+    let g = G1Affine::deserialize(reader)?;
+    let gamma_g = G1Affine::deserialize(reader)?;
+    let h = G2Affine::deserialize(reader)?;
+    let beta_h = G2Affine::deserialize(reader)?;
+
+    Ok(VerifierKey {
+        g,
+        gamma_g,
+        h,
+        beta_h,
+        prepared_h: h.into(),
+        prepared_beta_h: beta_h.into(),
+    })
+}
+
 pub fn commit(powers: &Powers<Bls12_381>, p: &Polynomial<Fr>) -> Commitment<Bls12_381> {
     let hiding_bound = None;
     let (comm, _) = KZG10::commit(&powers, &p, hiding_bound, None).unwrap();
     comm
 }
 
+// Commits to a list of polynomials
+// Every commitment is a multi-scalar multiplication over the same SRS bases.
+// With the `parallel` feature enabled, the MSMs are computed concurrently,
+// and all of the results are converted to affine form with a single batch inversion
+pub fn commit_many(
+    powers: &Powers<Bls12_381>,
+    polynomials: Vec<&Polynomial<Fr>>,
+) -> Vec<Commitment<Bls12_381>> {
+    #[cfg(feature = "parallel")]
+    let points: Vec<_> = polynomials
+        .par_iter()
+        .map(|p| commit_projective(powers, p))
+        .collect();
+    #[cfg(not(feature = "parallel"))]
+    let points: Vec<_> = polynomials
+        .iter()
+        .map(|p| commit_projective(powers, p))
+        .collect();
+
+    batch_normalize(&points)
+}
+
+// Computes the (non-hiding) KZG commitment to `p` without converting it to affine form
+fn commit_projective(powers: &Powers<Bls12_381>, p: &Polynomial<Fr>) -> G1Projective {
+    assert!(
+        p.coeffs.len() <= powers.size(),
+        "polynomial of degree {} is too large for an SRS of size {}",
+        p.degree(),
+        powers.size()
+    );
+    let scalars: Vec<_> = p.coeffs.iter().map(|coeff| coeff.into_repr()).collect();
+    VariableBaseMSM::multi_scalar_mul(&powers.powers_of_g[..scalars.len()], &scalars)
+}
+
+/// A key which the prover commits to polynomials with
+/// `Powers` holds the SRS bases in memory. `MappedPowers`, with the `mmap` feature,
+/// reads them from a file a chunk at a time, for SRS which are too large to hold in memory.
+pub trait CommitKey: Sync {
+    /// Returns the number of SRS bases, which is one more than the largest degree that can be committed to
+    fn size(&self) -> usize;
+    /// Commits to a list of polynomials, with the MSMs tuned by `config`
+    /// Returns `Error::InvalidWindow` if `config.window_bits` is out of range,
+    /// or `Error::ThreadPool` if `config.threads` is set and the thread pool could not be created.
+    fn commit_many_with_config(
+        &self,
+        polynomials: Vec<&Polynomial<Fr>>,
+        config: &MsmConfig,
+    ) -> Result<Vec<Commitment<Bls12_381>>, Error>;
+    /// Commits to `p`, with the MSM tuned by `config`
+    fn commit_with_config(
+        &self,
+        p: &Polynomial<Fr>,
+        config: &MsmConfig,
+    ) -> Result<Commitment<Bls12_381>, Error> {
+        Ok(self.commit_many_with_config(vec![p], config)?[0])
+    }
+}
+
+impl<'a> CommitKey for Powers<'a, Bls12_381> {
+    fn size(&self) -> usize {
+        Powers::size(self)
+    }
+    fn commit_many_with_config(
+        &self,
+        polynomials: Vec<&Polynomial<Fr>>,
+        config: &MsmConfig,
+    ) -> Result<Vec<Commitment<Bls12_381>>, Error> {
+        commit_many_with_config(self, polynomials, config)
+    }
+}
+
+/// Tuning for the multi-scalar multiplications which compute commitments
+///
+/// The defaults leave every choice to the backend's MSM, which picks the window size from the number of bases
+/// and runs on rayon's global thread pool. The best settings depend on the machine: larger windows need fewer
+/// additions but more memory per bucket set, and a server with many cores may want more chunks than a laptop.
+/// Any setting which is not `None` switches to this crate's bucket method, with the others at their defaults.
+/// Every setting gives the same commitments.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct MsmConfig {
+    /// The number of bits of each scalar handled by one set of buckets, between 1 and `MAX_WINDOW_BITS`
+    pub window_bits: Option<usize>,
+    /// The number of threads to commit with, in a thread pool of their own
+    /// Only used with the `parallel` feature.
+    pub threads: Option<usize>,
+    /// The number of coefficients in each chunk, whose buckets are summed on one thread
+    /// Defaults to an equal share of the coefficients for each thread.
+    /// For `MappedPowers`, this is the number of bases which are decoded from the file at once.
+    pub chunk_size: Option<usize>,
+}
+
+impl MsmConfig {
+    /// Returns `Error::InvalidWindow` if `window_bits` is set and is not between 1 and `MAX_WINDOW_BITS`
+    pub fn check(&self) -> Result<(), Error> {
+        match self.window_bits {
+            Some(window_bits) => check_window(window_bits),
+            None => Ok(()),
+        }
+    }
+}
+
+/// Commits to `p` as `commit` does, with the MSM tuned by `config`
+/// Returns `Error::InvalidWindow` if `config.window_bits` is out of range,
+/// or `Error::ThreadPool` if `config.threads` is set and the thread pool could not be created.
+pub fn commit_with_config(
+    powers: &Powers<Bls12_381>,
+    p: &Polynomial<Fr>,
+    config: &MsmConfig,
+) -> Result<Commitment<Bls12_381>, Error> {
+    Ok(commit_many_with_config(powers, vec![p], config)?[0])
+}
+
+/// Commits to a list of polynomials as `commit_many` does, with the MSMs tuned by `config`
+/// Setting `config.threads` builds a thread pool for this call. A prover which commits many times builds it
+/// once with `MsmPool`.
+/// Returns `Error::InvalidWindow` if `config.window_bits` is out of range,
+/// or `Error::ThreadPool` if `config.threads` is set and the thread pool could not be created.
+pub fn commit_many_with_config(
+    powers: &Powers<Bls12_381>,
+    polynomials: Vec<&Polynomial<Fr>>,
+    config: &MsmConfig,
+) -> Result<Vec<Commitment<Bls12_381>>, Error> {
+    config.check()?;
+    if *config == MsmConfig::default() {
+        return Ok(commit_many(powers, polynomials));
+    }
+
+    #[cfg(feature = "parallel")]
+    let points: Vec<_> = {
+        let commit_all = || {
+            polynomials
+                .par_iter()
+                .map(|p| tuned_msm(powers, p, config))
+                .collect()
+        };
+        match config.threads {
+            Some(threads) => crate::threads::with_thread_budget(threads, commit_all)?,
+            None => commit_all(),
+        }
+    };
+    #[cfg(not(feature = "parallel"))]
+    let points: Vec<_> = polynomials
+        .iter()
+        .map(|p| tuned_msm(powers, p, config))
+        .collect();
+
+    Ok(batch_normalize(&points))
+}
+
+/// The thread pool of `MsmConfig::threads`, built once so that a prover can reuse it for every commitment
+/// Without the `parallel` feature, or without `threads`, the commitments are computed as the key computes them.
+pub struct MsmPool {
+    config: MsmConfig,
+    #[cfg(feature = "parallel")]
+    pool: Option<rayon::ThreadPool>,
+}
+
+impl MsmPool {
+    /// Builds the thread pool for `config`
+    /// Returns `Error::InvalidWindow` if `config.window_bits` is out of range,
+    /// or `Error::ThreadPool` if `config.threads` is zero or the pool could not be created.
+    pub fn new(config: &MsmConfig) -> Result<MsmPool, Error> {
+        config.check()?;
+        if let Some(threads) = config.threads {
+            crate::threads::check_budget(threads)?;
+        }
+        #[cfg(feature = "parallel")]
+        let pool = match config.threads {
+            Some(threads) => Some(crate::threads::build_pool(threads)?),
+            None => None,
+        };
+        Ok(MsmPool {
+            // The commitments run on the pool, so the key does not build one of its own
+            config: MsmConfig {
+                threads: None,
+                ..*config
+            },
+            #[cfg(feature = "parallel")]
+            pool,
+        })
+    }
+    /// Commits to a list of polynomials with `key`, on the pool
+    pub fn commit_many<K: CommitKey + ?Sized>(
+        &self,
+        key: &K,
+        polynomials: Vec<&Polynomial<Fr>>,
+    ) -> Result<Vec<Commitment<Bls12_381>>, Error> {
+        #[cfg(feature = "parallel")]
+        {
+            if let Some(pool) = &self.pool {
+                return pool.install(|| key.commit_many_with_config(polynomials, &self.config));
+            }
+        }
+        key.commit_many_with_config(polynomials, &self.config)
+    }
+    /// Commits to `p` with `key`, on the pool
+    pub fn commit<K: CommitKey + ?Sized>(
+        &self,
+        key: &K,
+        p: &Polynomial<Fr>,
+    ) -> Result<Commitment<Bls12_381>, Error> {
+        Ok(self.commit_many(key, vec![p])?[0])
+    }
+}
+
+// Computes the commitment to `p` with the bucket method, splitting the coefficients into chunks
+fn tuned_msm(powers: &Powers<Bls12_381>, p: &Polynomial<Fr>, config: &MsmConfig) -> G1Projective {
+    assert!(
+        p.coeffs.len() <= powers.size(),
+        "polynomial of degree {} is too large for an SRS of size {}",
+        p.degree(),
+        powers.size()
+    );
+    let scalars: Vec<_> = p.coeffs.iter().map(|coeff| coeff.into_repr()).collect();
+    if scalars.is_empty() {
+        return G1Projective::zero();
+    }
+    let bases = &powers.powers_of_g[..scalars.len()];
+
+    // The same default as the backend's MSM, up to the largest window
+    let window_bits = config.window_bits.unwrap_or_else(|| {
+        if scalars.len() < 32 {
+            3
+        } else {
+            ((scalars.len() as f64).ln().ceil() as usize + 2).min(MAX_WINDOW_BITS)
+        }
+    });
+    // A window set in the config was checked by the caller, and the default is always in range
+    assert!(
+        check_window(window_bits).is_ok(),
+        "a window of {} bits is not between 1 and {} bits",
+        window_bits,
+        MAX_WINDOW_BITS
+    );
+
+    #[cfg(feature = "parallel")]
+    let threads = rayon::current_num_threads();
+    #[cfg(not(feature = "parallel"))]
+    let threads = 1;
+    let chunk_size = config
+        .chunk_size
+        .unwrap_or_else(|| (scalars.len() + threads - 1) / threads)
+        .max(1);
+
+    // The partial sums of the chunks are few, so they are added on one thread
+    #[cfg(feature = "parallel")]
+    let partial_sums: Vec<_> = bases
+        .par_chunks(chunk_size)
+        .zip(scalars.par_chunks(chunk_size))
+        .map(|(bases, scalars)| bucket_msm(bases, scalars, window_bits))
+        .collect();
+    #[cfg(not(feature = "parallel"))]
+    let partial_sums: Vec<_> = bases
+        .chunks(chunk_size)
+        .zip(scalars.chunks(chunk_size))
+        .map(|(bases, scalars)| bucket_msm(bases, scalars, window_bits))
+        .collect();
+    partial_sums
+        .into_iter()
+        .fold(G1Projective::zero(), |sum, partial_sum| sum + &partial_sum)
+}
+
+/// The largest window that the bucket methods accept
+/// Each set of buckets holds `2^window_bits - 1` points, so a window of 20 bits already needs about 150MB of buckets.
+pub const MAX_WINDOW_BITS: usize = 20;
+
+// Returns an error unless `window_bits` is between 1 and `MAX_WINDOW_BITS`
+pub(crate) fn check_window(window_bits: usize) -> Result<(), Error> {
+    if window_bits == 0 || window_bits > MAX_WINDOW_BITS {
+        return Err(Error::InvalidWindow(window_bits));
+    }
+    Ok(())
+}
+
+// Computes sum_i scalars[i] * bases[i], one window of `window_bits` bits at a time from the most significant
+pub(crate) fn bucket_msm(
+    bases: &[G1Affine],
+    scalars: &[<Fr as PrimeField>::BigInt],
+    window_bits: usize,
+) -> G1Projective {
+    let num_windows = (Fr::size_in_bits() + window_bits - 1) / window_bits;
+
+    let mut result = G1Projective::zero();
+    for j in (0..num_windows).rev() {
+        for _ in 0..window_bits {
+            result.double_in_place();
+        }
+
+        // bucket[d - 1] holds the sum of all bases whose window digit is `d`
+        let mut buckets = vec![G1Projective::zero(); (1 << window_bits) - 1];
+        for (base, scalar) in bases.iter().zip(scalars.iter()) {
+            let digit = window_digit(scalar.as_ref(), j * window_bits, window_bits);
+            if digit != 0 {
+                buckets[digit - 1].add_assign_mixed(base);
+            }
+        }
+
+        // Add sum_d d * bucket_d using a running sum
+        let mut running_sum = G1Projective::zero();
+        for bucket in buckets.into_iter().rev() {
+            running_sum += &bucket;
+            result += &running_sum;
+        }
+    }
+    result
+}
+
+/// Returns the commitments [L_i(tau)]G to the lagrange polynomials of the domain of size `n`
+/// A polynomial with evaluations v_i over the domain is committed to as sum_i v_i [L_i(tau)]G,
+/// so changing one evaluation changes the commitment by a multiple of one base.
+///
+/// The bases are the inverse FFT of the powers [tau^j]G, which costs n log n scalar multiplications,
+/// several times more than one commitment, so it pays off for tables which are updated many times.
+pub fn lagrange_basis(powers: &Powers<Bls12_381>, n: usize) -> Vec<G1Affine> {
+    assert!(n <= powers.size());
+    let domain: EvaluationDomain<Fr> = EvaluationDomain::new(n).unwrap();
+    assert_eq!(domain.size(), n);
+
+    let mut points: Vec<_> = powers.powers_of_g[..n]
+        .iter()
+        .map(|g| g.into_projective())
+        .collect();
+    group_fft(&mut points, domain.group_gen_inv);
+    for point in points.iter_mut() {
+        *point = point.mul(domain.size_inv);
+    }
+    G1Projective::batch_normalization_into_affine(&points)
+}
+
+// Computes the radix-2 FFT of `points` in place, where `omega` is a root of unity of order `points.len()`
+fn group_fft(points: &mut [G1Projective], omega: Fr) {
+    let n = points.len();
+    if n <= 1 {
+        return;
+    }
+    let log_n = n.trailing_zeros();
+    let usize_bits = (std::mem::size_of::<usize>() * 8) as u32;
+    for k in 0..n {
+        let rk = k.reverse_bits() >> (usize_bits - log_n);
+        if k < rk {
+            points.swap(k, rk);
+        }
+    }
+
+    let mut m = 1;
+    while m < n {
+        let w_m = omega.pow(&[(n / (2 * m)) as u64]);
+        for k in (0..n).step_by(2 * m) {
+            let mut w = Fr::from(1u8);
+            for j in 0..m {
+                let t = points[k + j + m].mul(w);
+                points[k + j + m] = points[k + j] - &t;
+                points[k + j] += &t;
+                w *= &w_m;
+            }
+        }
+        m *= 2;
+    }
+}
+
+/// Precomputed multiples of the SRS bases, for provers which commit many times against the same SRS
+///
+/// For each base `g_i` and each window `j`, we store `2^{w * j} * g_i` where `w` is the window size in bits.
+/// A commitment then needs no doublings, as every window of every coefficient is added into one shared set of buckets.
+///
+/// The window size is the memory/speed trade-off: each base is stored `ceil(255 / w)` times,
+/// and each commitment costs roughly `ceil(255 / w)` additions per coefficient plus `2^{w+1}` additions for the buckets.
+pub struct PrecomputedPowers {
+    window_bits: usize,
+    num_windows: usize,
+    // The shifted bases for `g_i` are stored at indices `i * num_windows..(i + 1) * num_windows`
+    bases: Vec<G1Affine>,
+}
+
+impl PrecomputedPowers {
+    /// Returns `Error::InvalidWindow` unless `window_bits` is between 1 and `MAX_WINDOW_BITS`
+    pub fn new(powers: &Powers<Bls12_381>, window_bits: usize) -> Result<PrecomputedPowers, Error> {
+        check_window(window_bits)?;
+        let num_windows = (Fr::size_in_bits() + window_bits - 1) / window_bits;
+
+        let mut bases = Vec::with_capacity(powers.size() * num_windows);
+        for base in powers.powers_of_g.iter() {
+            let mut shifted_base = base.into_projective();
+            for _ in 0..num_windows {
+                bases.push(shifted_base);
+                for _ in 0..window_bits {
+                    shifted_base.double_in_place();
+                }
+            }
+        }
+
+        Ok(PrecomputedPowers {
+            window_bits,
+            num_windows,
+            bases: G1Projective::batch_normalization_into_affine(&bases),
+        })
+    }
+    /// Returns the number of SRS bases that were precomputed
+    pub fn size(&self) -> usize {
+        self.bases.len() / self.num_windows
+    }
+    /// Commits to `p` using the precomputed bases
+    /// The result is the same as `kzg10::commit`
+    pub fn commit(&self, p: &Polynomial<Fr>) -> Commitment<Bls12_381> {
+        assert!(
+            p.coeffs.len() <= self.size(),
+            "polynomial of degree {} is too large for an SRS of size {}",
+            p.degree(),
+            self.size()
+        );
+        let scalars: Vec<_> = p.coeffs.iter().map(|coeff| coeff.into_repr()).collect();
+
+        // Split the coefficients into chunks, each with their own buckets
+        #[cfg(feature = "parallel")]
+        let result = {
+            let chunk_size = std::cmp::max(1, scalars.len() / rayon::current_num_threads());
+            scalars
+                .par_chunks(chunk_size)
+                .enumerate()
+                .map(|(i, chunk)| self.bucket_sum(i * chunk_size, chunk))
+                .reduce(G1Projective::zero, |a, b| a + &b)
+        };
+        #[cfg(not(feature = "parallel"))]
+        let result = self.bucket_sum(0, &scalars);
+
+        Commitment(result.into())
+    }
+
+    // Computes sum_i scalars[i] * g_{offset + i}
+    fn bucket_sum(
+        &self,
+        offset: usize,
+        scalars: &[<Fr as PrimeField>::BigInt],
+    ) -> G1Projective {
+        // bucket[d - 1] holds the sum of all bases whose window digit is `d`
+        let mut buckets = vec![G1Projective::zero(); (1 << self.window_bits) - 1];
+
+        for (i, scalar) in scalars.iter().enumerate() {
+            let shifted_bases = &self.bases[(offset + i) * self.num_windows..];
+            for j in 0..self.num_windows {
+                let digit = window_digit(scalar.as_ref(), j * self.window_bits, self.window_bits);
+                if digit != 0 {
+                    buckets[digit - 1].add_assign_mixed(&shifted_bases[j]);
+                }
+            }
+        }
+
+        // Compute sum_d d * bucket_d using a running sum
+        let mut running_sum = G1Projective::zero();
+        let mut result = G1Projective::zero();
+        for bucket in buckets.into_iter().rev() {
+            running_sum += &bucket;
+            result += &running_sum;
+        }
+        result
+    }
+}
+
+// Returns the `window_bits` bits of a little-endian integer, starting at bit `start`
+pub(crate) fn window_digit(limbs: &[u64], start: usize, window_bits: usize) -> usize {
+    let limb = start / 64;
+    let offset = start % 64;
+    if limb >= limbs.len() {
+        return 0;
+    }
+
+    let mut digit = limbs[limb] >> offset;
+    if offset + window_bits > 64 && limb + 1 < limbs.len() {
+        digit |= limbs[limb + 1] << (64 - offset);
+    }
+    (digit & ((1u64 << window_bits) - 1)) as usize
+}
+
 pub fn commit_vec(powers: &Powers<Bls12_381>, p_vec: &Vec<Fr>) -> Commitment<Bls12_381> {
     let p = Polynomial::from_coefficients_slice(p_vec);
     commit(powers, &p)
@@ -91,10 +756,54 @@ pub fn compute_aggregate_witness(
     &result / &divisor
 }
 
+// Opens a polynomial at `point`
+// Returns the evaluation of the polynomial at `point` and the commitment to its witness
+pub fn open(
+    powers: &Powers<Bls12_381>,
+    polynomial: &Polynomial<Fr>,
+    point: Fr,
+) -> (Fr, Commitment<Bls12_381>) {
+    let value = polynomial.evaluate(point);
+    let witness = compute_witness(polynomial, point);
+    (value, commit(powers, &witness))
+}
+// Opens a list of polynomials at the same `point` using one witness
+// Returns the commitment to the aggregate witness, see `compute_aggregate_witness`
+// The verifier checks the opening against `aggregate_commitments` and `aggregate_values`
+pub fn open_aggregate(
+    powers: &Powers<Bls12_381>,
+    polynomials: Vec<&Polynomial<Fr>>,
+    point: Fr,
+    aggregation_challenge: Fr,
+) -> Commitment<Bls12_381> {
+    let witness = compute_aggregate_witness(polynomials, point, aggregation_challenge);
+    commit(powers, &witness)
+}
+
 pub fn aggregate_commitments(
     commitments: Vec<&Commitment<Bls12_381>>,
     aggregation_challenge: Fr,
 ) -> Commitment<Bls12_381> {
+    Commitment(aggregate_commitments_projective(commitments, aggregation_challenge).into())
+}
+// Computes the sum of each commitment multiplied by its coefficient
+pub fn linear_combination(
+    commitments: Vec<&Commitment<Bls12_381>>,
+    coefficients: &[Fr],
+) -> Commitment<Bls12_381> {
+    assert_eq!(commitments.len(), coefficients.len());
+    let mut result = G1Projective::zero();
+    for (commitment, coefficient) in commitments.iter().zip(coefficients.iter()) {
+        result += &commitment.0.mul(*coefficient);
+    }
+    Commitment(result.into())
+}
+// Aggregates the commitments without converting the result to affine form
+// This allows the caller to normalise many aggregated commitments with one batch inversion
+pub fn aggregate_commitments_projective(
+    commitments: Vec<&Commitment<Bls12_381>>,
+    aggregation_challenge: Fr,
+) -> G1Projective {
     let mut powers = Fr::from(1u8);
     let mut result = G1Projective::zero();
 
@@ -104,7 +813,15 @@ pub fn aggregate_commitments(
         powers = powers * aggregation_challenge;
     }
 
-    Commitment(result.into())
+    result
+}
+// Converts a list of projective points into commitments
+// All points share a single field inversion, instead of one inversion per point
+pub fn batch_normalize(points: &[G1Projective]) -> Vec<Commitment<Bls12_381>> {
+    G1Projective::batch_normalization_into_affine(points)
+        .into_iter()
+        .map(Commitment)
+        .collect()
 }
 pub fn aggregate_values(values: Vec<&Fr>, aggregation_challenge: Fr) -> Fr {
     let mut powers = Fr::from(1u8);
@@ -118,7 +835,8 @@ pub fn aggregate_values(values: Vec<&Fr>, aggregation_challenge: Fr) -> Fr {
 
     result
 }
-pub fn verify(
+// Checks that the polynomial committed to in `commitment_to_poly` evaluates to `value` at `evaluation_point`
+pub fn check(
     vk: &VerifierKey<Bls12_381>,
     commitment_to_poly: &Commitment<Bls12_381>,
     commitment_to_witness: &Commitment<Bls12_381>,
@@ -133,7 +851,94 @@ pub fn verify(
     KzgBls12_381::check(vk, commitment_to_poly, evaluation_point, value, &proof).unwrap()
 }
 
-pub fn batch_verify(
+#[deprecated(note = "renamed to `check`")]
+pub fn verify(
+    vk: &VerifierKey<Bls12_381>,
+    commitment_to_poly: &Commitment<Bls12_381>,
+    commitment_to_witness: &Commitment<Bls12_381>,
+    evaluation_point: Fr,
+    value: Fr,
+) -> bool {
+    check(
+        vk,
+        commitment_to_poly,
+        commitment_to_witness,
+        evaluation_point,
+        value,
+    )
+}
+
+/// Claimed openings of committed polynomials, which are checked together
+/// Checking the openings of several proofs in one batch costs two pairings, rather than two per proof.
+#[derive(Clone, Debug, Default)]
+pub struct OpeningBatch {
+    commitments: Vec<Commitment<Bls12_381>>,
+    witnesses: Vec<Commitment<Bls12_381>>,
+    points: Vec<Fr>,
+    values: Vec<Fr>,
+}
+
+impl OpeningBatch {
+    pub fn new() -> OpeningBatch {
+        OpeningBatch {
+            commitments: Vec::new(),
+            witnesses: Vec::new(),
+            points: Vec::new(),
+            values: Vec::new(),
+        }
+    }
+    /// Adds the claim that the polynomial committed to in `commitment` evaluates to `value` at `point`,
+    /// with `witness` the commitment to the opening witness
+    pub fn push(
+        &mut self,
+        commitment: Commitment<Bls12_381>,
+        witness: Commitment<Bls12_381>,
+        point: Fr,
+        value: Fr,
+    ) {
+        self.commitments.push(commitment);
+        self.witnesses.push(witness);
+        self.points.push(point);
+        self.values.push(value);
+    }
+    /// Adds every claim in `other` to the batch
+    pub fn extend(&mut self, other: OpeningBatch) {
+        self.commitments.extend(other.commitments);
+        self.witnesses.extend(other.witnesses);
+        self.points.extend(other.points);
+        self.values.extend(other.values);
+    }
+    /// Returns the number of claims in the batch
+    pub fn len(&self) -> usize {
+        self.points.len()
+    }
+    /// Returns true if the batch has no claims
+    pub fn is_empty(&self) -> bool {
+        self.points.is_empty()
+    }
+    /// Checks every claim in the batch
+    pub fn check(self, vk: &VerifierKey<Bls12_381>) -> bool {
+        batch_check(
+            vk,
+            self.commitments,
+            self.witnesses,
+            self.points,
+            self.values,
+        )
+    }
+    /// Checks every claim in the batch, as `check` does, with the precomputed values of `pvk`
+    pub fn check_prepared(self, pvk: &PreparedVerifierKey) -> bool {
+        pvk.batch_check(
+            &self.commitments,
+            &self.witnesses,
+            &self.points,
+            &self.values,
+        )
+    }
+}
+
+// Checks a list of openings with a single pairing check
+pub fn batch_check(
     vk: &VerifierKey<Bls12_381>,
     commitment_to_polynomials: Vec<Commitment<Bls12_381>>,
     commitment_to_witnesses: Vec<Commitment<Bls12_381>>,
@@ -159,3 +964,456 @@ pub fn batch_verify(
     )
     .unwrap()
 }
+
+#[deprecated(note = "renamed to `batch_check`")]
+pub fn batch_verify(
+    vk: &VerifierKey<Bls12_381>,
+    commitment_to_polynomials: Vec<Commitment<Bls12_381>>,
+    commitment_to_witnesses: Vec<Commitment<Bls12_381>>,
+    evaluation_points: Vec<Fr>,
+    values: Vec<Fr>,
+) -> bool {
+    batch_check(
+        vk,
+        commitment_to_polynomials,
+        commitment_to_witnesses,
+        evaluation_points,
+        values,
+    )
+}
+
+/// Precomputed multiples of one point of G1, which multiply it by any scalar with additions alone
+/// The point is stored `2^FIXED_BASE_WINDOW_BITS` times for each window of that many bits of a scalar,
+/// which is about 800KB for 8 bit windows.
+#[derive(Clone, Debug)]
+pub struct FixedBaseTable {
+    num_windows: usize,
+    multiples: Vec<Vec<G1Affine>>,
+}
+
+/// The number of bits of a scalar handled by each window of a `FixedBaseTable`
+pub const FIXED_BASE_WINDOW_BITS: usize = 8;
+
+impl FixedBaseTable {
+    pub fn new(base: G1Affine) -> FixedBaseTable {
+        let scalar_bits = Fr::size_in_bits();
+        FixedBaseTable {
+            num_windows: (scalar_bits + FIXED_BASE_WINDOW_BITS - 1) / FIXED_BASE_WINDOW_BITS,
+            multiples: FixedBaseMSM::get_window_table(
+                scalar_bits,
+                FIXED_BASE_WINDOW_BITS,
+                base.into_projective(),
+            ),
+        }
+    }
+    /// Returns `scalar` times the base
+    pub fn mul(&self, scalar: &Fr) -> G1Projective {
+        FixedBaseMSM::windowed_mul::<G1Projective>(
+            self.num_windows,
+            FIXED_BASE_WINDOW_BITS,
+            &self.multiples,
+            scalar,
+        )
+    }
+}
+
+/// A verifier key with precomputed multiples of `g`, for verifiers which check many proofs
+///
+/// The G2 elements of the key are prepared for the Miller loop once, when the key is created or read,
+/// and every pairing check uses them as they are. Each batch of openings then costs two Miller loops,
+/// one final exponentiation and a scalar multiplication of `g` by additions alone.
+#[derive(Clone, Debug)]
+pub struct PreparedVerifierKey {
+    pub vk: VerifierKey<Bls12_381>,
+    g: FixedBaseTable,
+}
+
+impl PreparedVerifierKey {
+    pub fn new(vk: VerifierKey<Bls12_381>) -> PreparedVerifierKey {
+        PreparedVerifierKey {
+            g: FixedBaseTable::new(vk.g),
+            vk,
+        }
+    }
+    /// Checks that each polynomial committed to in `commitments[i]` evaluates to `values[i]` at `points[i]`,
+    /// with `witnesses[i]` the commitment to its opening witness
+    /// The claims are combined with random coefficients into one check of e(C, h) = e(W, beta_h).
+    pub fn batch_check(
+        &self,
+        commitments: &[Commitment<Bls12_381>],
+        witnesses: &[Commitment<Bls12_381>],
+        points: &[Fr],
+        values: &[Fr],
+    ) -> bool {
+        assert_eq!(commitments.len(), witnesses.len());
+        assert_eq!(commitments.len(), points.len());
+        assert_eq!(commitments.len(), values.len());
+
+        // Each claim is e(C_i - v_i * g + z_i * W_i, h) = e(W_i, beta_h)
+        let mut rng = rand::thread_rng();
+        let mut total_c = G1Projective::zero();
+        let mut total_w = G1Projective::zero();
+        let mut total_value = Fr::zero();
+        let mut randomizer = Fr::from(1u8);
+        for i in 0..points.len() {
+            total_c += &commitments[i].0.mul(randomizer);
+            total_c += &witnesses[i].0.mul(randomizer * points[i]);
+            total_w += &witnesses[i].0.mul(randomizer);
+            total_value += &(randomizer * values[i]);
+            randomizer = Fr::rand(&mut rng);
+        }
+        total_c -= &self.g.mul(&total_value);
+
+        let affine = G1Projective::batch_normalization_into_affine(&[total_c, -total_w]);
+        Bls12_381::product_of_pairings(&[
+            (affine[0].into(), self.vk.prepared_h.clone()),
+            (affine[1].into(), self.vk.prepared_beta_h.clone()),
+        ])
+        .is_one()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_key_serialization() {
+        let (proving_key, verifier_key) = trusted_setup(2usize.pow(6), b"insecure_seed");
+
+        let mut bytes = Vec::new();
+        write_powers(&proving_key, &mut bytes).unwrap();
+        let decoded_powers = read_powers(&mut &bytes[..]).unwrap();
+        assert_eq!(decoded_powers.powers_of_g, proving_key.powers_of_g);
+        assert_eq!(
+            decoded_powers.powers_of_gamma_g,
+            proving_key.powers_of_gamma_g
+        );
+
+        let mut bytes = Vec::new();
+        write_verifier_key(&verifier_key, &mut bytes).unwrap();
+        assert_eq!(bytes.len(), 2 * 48 + 2 * 96);
+        let decoded_vk = read_verifier_key(&mut &bytes[..]).unwrap();
+        assert_eq!(decoded_vk.g, verifier_key.g);
+        assert_eq!(decoded_vk.beta_h, verifier_key.beta_h);
+
+        // The decoded keys can be used to open and check polynomials
+        let mut rng = ChaChaRng::from_seed([0u8; 32]);
+        let poly = Polynomial::rand(10, &mut rng);
+        let point = Fr::rand(&mut rng);
+        let (value, witness) = open(&decoded_powers, &poly, point);
+        let commitment = commit(&decoded_powers, &poly);
+        assert!(check(&decoded_vk, &commitment, &witness, point, value));
+    }
+
+    #[test]
+    fn test_keys_from_points() {
+        let mut rng = ChaChaRng::from_seed([0u8; 32]);
+        let pp = universal_setup(2usize.pow(4), &mut rng);
+        let (powers, vk) = keys_from_points(pp.powers_of_g.clone(), pp.h, pp.beta_h);
+        assert_eq!(max_degree(&powers), 2usize.pow(4));
+
+        let poly = Polynomial::rand(10, &mut rng);
+        let point = Fr::rand(&mut rng);
+        let (value, witness) = open(&powers, &poly, point);
+        let commitment = commit(&powers, &poly);
+        assert!(check(&vk, &commitment, &witness, point, value));
+        assert!(batch_check(
+            &vk,
+            vec![commitment],
+            vec![witness],
+            vec![point],
+            vec![value]
+        ));
+
+        // The keys have no hiding powers, and still round-trip through the serialised form
+        let mut bytes = Vec::new();
+        write_powers(&powers, &mut bytes).unwrap();
+        let decoded_powers = read_powers(&mut &bytes[..]).unwrap();
+        assert_eq!(decoded_powers.powers_of_g, powers.powers_of_g);
+        assert!(decoded_powers.powers_of_gamma_g.is_empty());
+        assert_eq!(commit(&decoded_powers, &poly), commitment);
+    }
+
+    #[test]
+    fn test_seeded_setup() {
+        // The test SRS is the same on every call
+        let (powers_a, vk_a) = test_setup(2usize.pow(4));
+        let (powers_b, vk_b) = test_setup(2usize.pow(4));
+        assert_eq!(powers_a.powers_of_g, powers_b.powers_of_g);
+        assert_eq!(vk_a.beta_h, vk_b.beta_h);
+
+        // Seeding an rng directly gives the same SRS as seeding `trusted_setup`
+        let (powers_c, _) = trusted_setup(2usize.pow(4), b"insecure_seed");
+        let mut rng = ChaChaRng::from_seed(to_32_bytes(b"insecure_seed"));
+        let (powers_d, _) = setup_with_rng(2usize.pow(4), &mut rng);
+        assert_eq!(powers_c.powers_of_g, powers_d.powers_of_g);
+        assert_ne!(powers_a.powers_of_g, powers_c.powers_of_g);
+    }
+
+    #[test]
+    fn test_trim_for_table() {
+        let mut rng = ChaChaRng::from_seed([0u8; 32]);
+        let pp = universal_setup(2usize.pow(7), &mut rng);
+
+        let (powers, _) = trim_for_table(&pp, 2usize.pow(5)).unwrap();
+        assert_eq!(powers.size(), cost::srs_size(2usize.pow(5)));
+        assert_eq!(max_degree(&powers), cost::srs_size(2usize.pow(5)) - 1);
+        assert!(check_capacity(&powers, 2usize.pow(5)).is_ok());
+        // Extra blinding factors raise the degree of the polynomials
+        assert!(check_capacity_with_config(
+            &powers,
+            2usize.pow(5),
+            &ProverConfig::with_extra_openings(1)
+        )
+        .is_err());
+
+        match check_capacity(&powers, 2usize.pow(6)) {
+            Err(Error::SrsTooSmall { available, .. }) => assert_eq!(available, max_degree(&powers)),
+            _ => panic!("expected the SRS to be too small"),
+        }
+        match trim_for_table(&pp, 2usize.pow(7)) {
+            Err(Error::SrsTooSmall {
+                required,
+                available,
+            }) => {
+                assert_eq!(available, 2usize.pow(7));
+                assert_eq!(required, cost::srs_size(2usize.pow(7)) - 1);
+            }
+            _ => panic!("expected the SRS to be too small"),
+        }
+    }
+
+    #[test]
+    fn test_commit_many() {
+        let (proving_key, _) = trusted_setup(2usize.pow(6), b"insecure_seed");
+        let mut rng = ChaChaRng::from_seed([0u8; 32]);
+
+        let polys: Vec<_> = (0..4)
+            .map(|i| Polynomial::rand(10 * i, &mut rng))
+            .collect();
+        let zero_poly = Polynomial::zero();
+
+        let mut to_commit: Vec<_> = polys.iter().collect();
+        to_commit.push(&zero_poly);
+        let commitments = commit_many(&proving_key, to_commit);
+
+        assert_eq!(commitments.len(), 5);
+        for (poly, commitment) in polys.iter().zip(commitments.iter()) {
+            assert_eq!(&commit(&proving_key, poly), commitment);
+        }
+        assert_eq!(commit(&proving_key, &zero_poly), commitments[4]);
+    }
+
+    #[test]
+    fn test_precomputed_powers() {
+        let (proving_key, _) = trusted_setup(2usize.pow(6), b"insecure_seed");
+        let mut rng = ChaChaRng::from_seed([0u8; 32]);
+        let poly = Polynomial::rand(50, &mut rng);
+        let expected = commit(&proving_key, &poly);
+
+        // Window sizes which do and do not divide the size of the scalar
+        for window_bits in vec![1, 4, 5, 8] {
+            let precomputed = PrecomputedPowers::new(&proving_key, window_bits).unwrap();
+            assert_eq!(precomputed.size(), proving_key.size());
+            assert_eq!(precomputed.commit(&poly), expected);
+        }
+
+        // Windows which are empty or need too many buckets are rejected
+        for &window_bits in &[0, MAX_WINDOW_BITS + 1, 31] {
+            assert!(matches!(
+                PrecomputedPowers::new(&proving_key, window_bits),
+                Err(Error::InvalidWindow(bits)) if bits == window_bits
+            ));
+        }
+    }
+
+    #[test]
+    fn test_commit_with_config() {
+        let (proving_key, _) = trusted_setup(2usize.pow(8), b"insecure_seed");
+        let mut rng = rand::thread_rng();
+        let polys: Vec<_> = vec![1, 33, 200]
+            .into_iter()
+            .map(|size| {
+                Polynomial::from_coefficients_vec((0..size).map(|_| Fr::rand(&mut rng)).collect())
+            })
+            .collect();
+        let expected = commit_many(&proving_key, polys.iter().collect());
+
+        let configs = vec![
+            MsmConfig {
+                window_bits: Some(1),
+                ..MsmConfig::default()
+            },
+            MsmConfig {
+                window_bits: Some(13),
+                chunk_size: Some(7),
+                ..MsmConfig::default()
+            },
+            MsmConfig {
+                threads: Some(2),
+                chunk_size: Some(1000),
+                ..MsmConfig::default()
+            },
+        ];
+        for config in configs {
+            assert_eq!(
+                commit_many_with_config(&proving_key, polys.iter().collect(), &config).unwrap(),
+                expected
+            );
+            assert_eq!(
+                commit_with_config(&proving_key, &polys[2], &config).unwrap(),
+                expected[2]
+            );
+            // A pool built once commits as each call does
+            let pool = MsmPool::new(&config).unwrap();
+            assert_eq!(
+                pool.commit_many(&proving_key, polys.iter().collect())
+                    .unwrap(),
+                expected
+            );
+            assert_eq!(pool.commit(&proving_key, &polys[1]).unwrap(), expected[1]);
+        }
+
+        // Windows which are empty or need too many buckets are rejected rather than committed with
+        for &window_bits in &[0, MAX_WINDOW_BITS + 1, 64] {
+            let config = MsmConfig {
+                window_bits: Some(window_bits),
+                ..MsmConfig::default()
+            };
+            assert!(matches!(
+                commit_with_config(&proving_key, &polys[0], &config),
+                Err(Error::InvalidWindow(bits)) if bits == window_bits
+            ));
+            assert!(matches!(
+                MsmPool::new(&config),
+                Err(Error::InvalidWindow(bits)) if bits == window_bits
+            ));
+        }
+    }
+
+    #[test]
+    fn test_lagrange_basis() {
+        let (proving_key, _) = trusted_setup(2usize.pow(6), b"insecure_seed");
+        let mut rng = ChaChaRng::from_seed([0u8; 32]);
+        let n = 16;
+        let domain: EvaluationDomain<Fr> = EvaluationDomain::new(n).unwrap();
+        let evaluations: Vec<_> = (0..n).map(|_| Fr::rand(&mut rng)).collect();
+        let poly = Polynomial::from_coefficients_vec(domain.ifft(&evaluations));
+
+        // Committing to the evaluations in the lagrange basis is the same as committing to the coefficients
+        let bases = lagrange_basis(&proving_key, n);
+        let scalars: Vec<_> = evaluations.iter().map(|v| v.into_repr()).collect();
+        let commitment = VariableBaseMSM::multi_scalar_mul(&bases, &scalars);
+        assert_eq!(Commitment(commitment.into()), commit(&proving_key, &poly));
+    }
+
+    #[test]
+    fn test_open_and_check() {
+        let (proving_key, verifier_key) = trusted_setup(2usize.pow(6), b"insecure_seed");
+        let mut rng = ChaChaRng::from_seed([0u8; 32]);
+
+        let poly = Polynomial::rand(20, &mut rng);
+        let commitment = commit(&proving_key, &poly);
+        let point = Fr::rand(&mut rng);
+
+        let (value, witness) = open(&proving_key, &poly, point);
+        assert_eq!(value, poly.evaluate(point));
+        assert!(check(&verifier_key, &commitment, &witness, point, value));
+
+        // Wrong value should fail
+        assert!(!check(
+            &verifier_key,
+            &commitment,
+            &witness,
+            point,
+            value + Fr::from(1u8)
+        ));
+    }
+
+    #[test]
+    fn test_open_aggregate_and_batch_check() {
+        let (proving_key, verifier_key) = trusted_setup(2usize.pow(6), b"insecure_seed");
+        let mut rng = ChaChaRng::from_seed([0u8; 32]);
+
+        let poly_a = Polynomial::rand(20, &mut rng);
+        let poly_b = Polynomial::rand(30, &mut rng);
+        let poly_c = Polynomial::rand(10, &mut rng);
+        let comm_a = commit(&proving_key, &poly_a);
+        let comm_b = commit(&proving_key, &poly_b);
+        let comm_c = commit(&proving_key, &poly_c);
+
+        let point = Fr::rand(&mut rng);
+        let shifted_point = Fr::rand(&mut rng);
+        let aggregation_challenge = Fr::rand(&mut rng);
+
+        // Open `a` and `b` at `point` and `c` at `shifted_point`
+        let agg_witness = open_aggregate(
+            &proving_key,
+            vec![&poly_a, &poly_b],
+            point,
+            aggregation_challenge,
+        );
+        let agg_commitment = aggregate_commitments(vec![&comm_a, &comm_b], aggregation_challenge);
+        let agg_value = aggregate_values(
+            vec![&poly_a.evaluate(point), &poly_b.evaluate(point)],
+            aggregation_challenge,
+        );
+        let (c_value, c_witness) = open(&proving_key, &poly_c, shifted_point);
+
+        assert!(check(
+            &verifier_key,
+            &agg_commitment,
+            &agg_witness,
+            point,
+            agg_value
+        ));
+        assert!(batch_check(
+            &verifier_key,
+            vec![agg_commitment, comm_c],
+            vec![agg_witness, c_witness],
+            vec![point, shifted_point],
+            vec![agg_value, c_value],
+        ));
+        assert!(!batch_check(
+            &verifier_key,
+            vec![agg_commitment, comm_c],
+            vec![agg_witness, c_witness],
+            vec![point, shifted_point],
+            vec![agg_value, agg_value],
+        ));
+    }
+
+    #[test]
+    fn test_prepared_verifier_key() {
+        let (proving_key, verifier_key) = trusted_setup(2usize.pow(6), b"insecure_seed");
+        let mut rng = ChaChaRng::from_seed([0u8; 32]);
+
+        let scalar = Fr::rand(&mut rng);
+        let table = FixedBaseTable::new(verifier_key.g);
+        assert_eq!(table.mul(&scalar), verifier_key.g.mul(scalar));
+
+        let polys: Vec<_> = (0..3)
+            .map(|i| Polynomial::rand(10 * i + 5, &mut rng))
+            .collect();
+        let points: Vec<_> = (0..3).map(|_| Fr::rand(&mut rng)).collect();
+        let commitments: Vec<_> = polys.iter().map(|p| commit(&proving_key, p)).collect();
+        let (values, witnesses): (Vec<_>, Vec<_>) = polys
+            .iter()
+            .zip(points.iter())
+            .map(|(p, point)| open(&proving_key, p, *point))
+            .unzip();
+
+        let pvk = PreparedVerifierKey::new(verifier_key);
+        assert!(pvk.batch_check(&commitments, &witnesses, &points, &values));
+        assert!(pvk.batch_check(&[], &[], &[], &[]));
+
+        // A wrong value or a wrong point fails the batch
+        let mut wrong_values = values.clone();
+        wrong_values[1] += Fr::from(1u8);
+        assert!(!pvk.batch_check(&commitments, &witnesses, &points, &wrong_values));
+        let mut wrong_points = points.clone();
+        wrong_points.swap(0, 2);
+        assert!(!pvk.batch_check(&commitments, &witnesses, &wrong_points, &values));
+    }
+}
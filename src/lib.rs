@@ -1,4 +1,13 @@
+pub mod encoding;
+pub mod error;
+#[cfg(feature = "ffi")]
+pub mod ffi;
 pub mod kzg10;
 pub mod lookup;
+#[cfg(feature = "mmap")]
+pub mod mapped_powers;
 pub mod multiset;
+#[cfg(feature = "test-utils")]
+pub mod test_utils;
+pub mod threads;
 pub mod transcript;
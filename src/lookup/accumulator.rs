@@ -0,0 +1,218 @@
+// Accumulating the lookups of many program steps into one proof
+//
+// An incrementally verifiable computation proves one step of a program at a time, and folds each step into a
+// running instance so that only the final instance is proven. Plookup's grand product cannot be folded as a relaxed
+// instance in that way: h_1, h_2 and the accumulator Z(X) depend on the sorted order of the whole witness, so they
+// are not linear in the queries, and a random linear combination of rows in the table is not a row in the table.
+//
+// The lookup relation is instead closed under union: if the queries of each step are rows of the table, so are the
+// queries of every step together. `LookUpAccumulator` folds each step by appending its queries to a running witness,
+// after checking them against the step's table, and a single plookup proof is made over the running witness at the end.
+// As the steps' tables need not be the table that is proven against, the whole witness is checked again before proving.
+// The size of the witness grows with the number of steps, up to `n - 1` queries for a table preprocessed over a
+// domain of size `n`.
+use super::{
+    lookup::LookUp,
+    proof::{LookUpProof, ProveOptions},
+    table::{LookUpTable, PreProcessedTable},
+};
+use crate::{error::Error, multiset::MultiSet, transcript::TranscriptProtocol};
+use algebra::Bls12_381;
+use poly_commit::kzg10::Powers;
+use std::{collections::HashSet, ops::Range};
+
+/// The queries of many steps, folded into one witness for a table of size `n`
+#[derive(Clone, Debug)]
+pub struct LookUpAccumulator {
+    n: usize,
+    left_wires: MultiSet,
+    right_wires: MultiSet,
+    output_wires: MultiSet,
+    // The index in the witness after the last query of each step
+    step_ends: Vec<usize>,
+}
+
+impl LookUpAccumulator {
+    /// Creates an empty accumulator for a table preprocessed over a domain of size `n`
+    pub fn new(n: usize) -> LookUpAccumulator {
+        assert!(n.is_power_of_two());
+        LookUpAccumulator {
+            n,
+            left_wires: MultiSet::new(),
+            right_wires: MultiSet::new(),
+            output_wires: MultiSet::new(),
+            step_ends: Vec::new(),
+        }
+    }
+    /// Folds the queries of one step into the accumulator
+    /// The queries are checked against the step's table, so that a bad query is reported with the step that made it.
+    /// Returns the index of the step, or an error if a query is not in the table or the accumulator is full,
+    /// in which case the accumulator is unchanged.
+    /// The rows which the step still holds as integers are converted first.
+    pub fn fold<T: LookUpTable>(&mut self, step: &mut LookUp<T>) -> Result<usize, Error> {
+        step.validate()?;
+        let (left_wires, right_wires, output_wires) = step.wires();
+
+        let rows = self.len() + left_wires.len();
+        if rows > self.capacity() {
+            return Err(Error::CapacityExceeded {
+                rows,
+                capacity: self.capacity(),
+            });
+        }
+
+        self.left_wires = self.left_wires.concatenate(left_wires);
+        self.right_wires = self.right_wires.concatenate(right_wires);
+        self.output_wires = self.output_wires.concatenate(output_wires);
+        self.step_ends.push(rows);
+        Ok(self.step_ends.len() - 1)
+    }
+    /// Returns the number of steps that have been folded
+    pub fn num_steps(&self) -> usize {
+        self.step_ends.len()
+    }
+    /// Returns the number of queries that have been folded
+    pub fn len(&self) -> usize {
+        self.left_wires.len()
+    }
+    /// Returns true if no queries have been folded
+    pub fn is_empty(&self) -> bool {
+        self.left_wires.is_empty()
+    }
+    /// Returns the number of queries that the accumulator can hold
+    pub fn capacity(&self) -> usize {
+        self.n - 1
+    }
+    /// Returns the positions in the witness of the queries of step `index`
+    pub fn step_rows(&self, index: usize) -> Range<usize> {
+        let start = if index == 0 {
+            0
+        } else {
+            self.step_ends[index - 1]
+        };
+        start..self.step_ends[index]
+    }
+    /// Proves that every query of every step is a row of the table
+    /// Returns an error if the table was preprocessed over a different domain than the accumulator's,
+    /// `Error::MissingRows` with the positions in the witness of the queries which are not rows of the table,
+    /// or an error from the prover.
+    pub fn prove<Tr: TranscriptProtocol + ?Sized>(
+        &self,
+        proving_key: &Powers<Bls12_381>,
+        preprocessed_table: &PreProcessedTable,
+        transcript: &mut Tr,
+    ) -> Result<LookUpProof, Error> {
+        if preprocessed_table.n != self.n {
+            return Err(Error::TableMismatch);
+        }
+
+        // The steps were checked against their own tables, so check every query against the table being proven
+        let (t_1, t_2, t_3) = (
+            &preprocessed_table.t_1.0,
+            &preprocessed_table.t_2.0,
+            &preprocessed_table.t_3.0,
+        );
+        let table_rows: HashSet<_> = (0..t_1.len())
+            .map(|i| (t_1.0[i], t_2.0[i], t_3.0[i]))
+            .collect();
+        let missing: Vec<_> = (0..self.len())
+            .filter(|&i| {
+                let row = (
+                    self.left_wires.0[i],
+                    self.right_wires.0[i],
+                    self.output_wires.0[i],
+                );
+                !table_rows.contains(&row)
+            })
+            .collect();
+        if !missing.is_empty() {
+            return Err(Error::MissingRows(missing));
+        }
+
+        LookUpProof::prove_with_options(
+            &self.left_wires,
+            &self.right_wires,
+            &self.output_wires,
+            proving_key,
+            preprocessed_table,
+            ProveOptions::new(),
+            transcript,
+        )
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::kzg10;
+    use crate::lookup::table::four_bits::XOR4Bit;
+    use merlin::Transcript;
+
+    #[test]
+    fn test_accumulator() {
+        let (proving_key, verifier_key) = kzg10::trusted_setup(2usize.pow(10), b"insecure_seed");
+        let n = 2usize.pow(8);
+        let preprocessed_table = XOR4Bit::new().preprocess(&proving_key, n);
+
+        let mut accumulator = LookUpAccumulator::new(n);
+        for i in 0..4u8 {
+            let mut step = LookUp::new(XOR4Bit::new());
+            step.read((i, i + 1));
+            step.read((i + 2, 7u8));
+            assert_eq!(accumulator.fold(&mut step).unwrap(), i as usize);
+        }
+        assert_eq!(accumulator.num_steps(), 4);
+        assert_eq!(accumulator.len(), 8);
+        assert_eq!(accumulator.step_rows(2), 4..6);
+
+        // A step with a query outside of the table is rejected without changing the accumulator
+        let mut bad_step =
+            LookUp::from_rows(vec![(1u8.into(), 2u8.into(), 4u8.into())], XOR4Bit::new());
+        assert!(accumulator.fold(&mut bad_step).is_err());
+        assert_eq!(accumulator.len(), 8);
+
+        let mut prover_transcript = Transcript::new(b"lookup");
+        let proof = accumulator
+            .prove(&proving_key, &preprocessed_table, &mut prover_transcript)
+            .unwrap();
+        let mut verifier_transcript = Transcript::new(b"lookup");
+        assert!(proof.verify(&verifier_key, &preprocessed_table, &mut verifier_transcript));
+    }
+
+    #[test]
+    fn test_accumulator_other_table() {
+        use crate::lookup::table::four_bits::Add4Bit;
+
+        let (proving_key, _) = kzg10::trusted_setup(2usize.pow(10), b"insecure_seed");
+        let n = 2usize.pow(8);
+        let preprocessed_table = XOR4Bit::new().preprocess(&proving_key, n);
+
+        // The steps' queries are rows of their own table, but not all of them are rows of the table being proven
+        let mut accumulator = LookUpAccumulator::new(n);
+        let mut step = LookUp::new(Add4Bit::new());
+        step.read((2u8, 4u8));
+        step.read((1u8, 1u8));
+        accumulator.fold(&mut step).unwrap();
+
+        let mut prover_transcript = Transcript::new(b"lookup");
+        match accumulator.prove(&proving_key, &preprocessed_table, &mut prover_transcript) {
+            Err(Error::MissingRows(rows)) => assert_eq!(rows, vec![1]),
+            _ => panic!("expected a query to be missing from the table"),
+        }
+    }
+
+    #[test]
+    fn test_accumulator_capacity() {
+        let mut accumulator = LookUpAccumulator::new(4);
+        let mut step = LookUp::new(XOR4Bit::new());
+        step.read((1u8, 2u8));
+        step.read((3u8, 4u8));
+
+        assert!(accumulator.fold(&mut step).is_ok());
+        match accumulator.fold(&mut step) {
+            Err(Error::CapacityExceeded { rows, capacity }) => assert_eq!((rows, capacity), (4, 3)),
+            _ => panic!("expected the accumulator to be full"),
+        }
+        assert_eq!(accumulator.num_steps(), 1);
+    }
+}
@@ -0,0 +1,100 @@
+// Binding proofs to the application and the session they were made for
+//
+// A proof only shows that a witness is contained in a table. If a relayer passes a proof from one application
+// or session on to another, it would verify there too, as a statement about the same table. `ProofContext` names
+// the application and the session, and is absorbed into the transcript before anything else, so every challenge
+// depends on it. The proof carries its context, and the verifier only accepts it for the context it expects.
+use algebra::io::{Read, Write};
+use algebra::{CanonicalDeserialize, CanonicalSerialize, SerializationError};
+
+/// The maximum length in bytes of the application identifier and of the session
+pub const MAX_CONTEXT_BYTES: usize = 256;
+
+/// The application and the session that a proof is made for
+/// The default context has an empty application identifier and session, and is used by proofs made without one.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct ProofContext {
+    /// Identifies the application, for example a protocol name and version
+    pub app_id: Vec<u8>,
+    /// Identifies the session within the application, for example a nonce agreed with the verifier
+    pub session: Vec<u8>,
+}
+
+impl ProofContext {
+    /// Panics if `app_id` or `session` is longer than `MAX_CONTEXT_BYTES`
+    pub fn new(app_id: &[u8], session: &[u8]) -> ProofContext {
+        assert!(app_id.len() <= MAX_CONTEXT_BYTES);
+        assert!(session.len() <= MAX_CONTEXT_BYTES);
+        ProofContext {
+            app_id: app_id.to_vec(),
+            session: session.to_vec(),
+        }
+    }
+    /// Returns the canonical encoding of the context, which is absorbed into the transcript
+    /// Each field is prefixed with its length as a u64, so that no two contexts have the same encoding.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(self.serialized_size());
+        self.serialize(&mut bytes)
+            .expect("serialising into a vector cannot fail");
+        bytes
+    }
+}
+
+impl CanonicalSerialize for ProofContext {
+    fn serialize<W: Write>(&self, writer: &mut W) -> Result<(), SerializationError> {
+        for field in &[&self.app_id, &self.session] {
+            (field.len() as u64).serialize(writer)?;
+            writer.write_all(field)?;
+        }
+        Ok(())
+    }
+    fn serialized_size(&self) -> usize {
+        8 + self.app_id.len() + 8 + self.session.len()
+    }
+}
+
+impl CanonicalDeserialize for ProofContext {
+    fn deserialize<R: Read>(reader: &mut R) -> Result<Self, SerializationError> {
+        Ok(ProofContext {
+            app_id: read_field(reader)?,
+            session: read_field(reader)?,
+        })
+    }
+}
+
+// Reads a field prefixed with its length, rejecting a length over the maximum before allocating
+fn read_field<R: Read>(reader: &mut R) -> Result<Vec<u8>, SerializationError> {
+    let len = u64::deserialize(reader)?;
+    if len > MAX_CONTEXT_BYTES as u64 {
+        return Err(SerializationError::InvalidData);
+    }
+    let mut field = vec![0u8; len as usize];
+    reader.read_exact(&mut field)?;
+    Ok(field)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_context_encoding() {
+        let context = ProofContext::new(b"app", b"session");
+        let bytes = context.to_bytes();
+        assert_eq!(bytes.len(), context.serialized_size());
+        assert_eq!(ProofContext::deserialize(&mut &bytes[..]).unwrap(), context);
+
+        // Moving bytes between the fields changes the encoding
+        assert_ne!(ProofContext::new(b"apps", b"ession").to_bytes(), bytes);
+        assert_eq!(ProofContext::default().to_bytes(), vec![0u8; 16]);
+
+        // Fields longer than the maximum are rejected
+        let mut long = vec![];
+        ((MAX_CONTEXT_BYTES + 1) as u64)
+            .serialize(&mut long)
+            .unwrap();
+        long.extend(vec![0u8; MAX_CONTEXT_BYTES + 1]);
+        long.extend(vec![0u8; 8]);
+        assert!(ProofContext::deserialize(&mut &long[..]).is_err());
+    }
+}
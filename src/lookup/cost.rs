@@ -0,0 +1,217 @@
+// Estimating the cost of a proof without running the prover
+//
+// The estimates follow the prover with the default `ProverConfig`. They count the work done for one proof,
+// and exclude preprocessing the table, which is done once per table.
+use super::{context::ProofContext, domain::min_domain_size};
+use crate::multiset::ProverConfig;
+use algebra::CanonicalSerialize;
+use std::cmp::max;
+
+// The compressed size of a G1 element and of a field element
+const G1_SIZE: usize = 48;
+const FR_SIZE: usize = 32;
+// The size of the public parameters of the equality proof: the version, the domain size,
+// the two numbers of blinding factors and whether f(X) was committed to
+const PARAMETERS_SIZE: usize = 4 * 8 + 1;
+
+/// The expected cost of proving `num_queries` lookups into a table with `table_size` rows
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CostReport {
+    /// The size `n` of the domain that the table is padded to
+    pub domain_size: usize,
+    /// The size of the extended domain that the quotient polynomial is computed over
+    pub quotient_domain_size: usize,
+    /// The number of FFTs over the domain of size `n`
+    pub domain_ffts: usize,
+    /// The number of FFTs over the extended domain
+    pub quotient_domain_ffts: usize,
+    /// The number of bases in each multi-scalar multiplication
+    pub msm_sizes: Vec<usize>,
+    /// The number of SRS powers needed to commit to the largest polynomial
+    pub srs_size: usize,
+    /// A rough estimate of the peak memory used by the prover, in bytes
+    pub peak_memory: usize,
+    /// The size of a slim proof in its compressed form, including its context, in bytes
+    pub proof_size: usize,
+    /// The size of a self-contained proof in its compressed form, including its context, in bytes
+    pub self_contained_proof_size: usize,
+}
+
+/// Estimates the cost of proving `num_queries` lookups into a table with `table_size` rows, without a context
+pub fn estimate(table_size: usize, num_queries: usize) -> CostReport {
+    estimate_in_context(table_size, num_queries, &ProofContext::default())
+}
+
+/// Estimates the cost of proving `num_queries` lookups into a table with `table_size` rows, for `context`
+/// The proof carries its context, so the proof sizes grow with the application identifier and the session.
+pub fn estimate_in_context(
+    table_size: usize,
+    num_queries: usize,
+    context: &ProofContext,
+) -> CostReport {
+    let n = min_domain_size(table_size, num_queries);
+    let degrees = Degrees::new(n, &ProverConfig::default());
+    let quotient_domain_size = degrees.quotient_domain_size(n);
+    let msm_sizes = degrees.msm_sizes();
+    let srs_size = *msm_sizes.iter().max().unwrap();
+
+    // The prover holds the evaluations of six polynomials over the extended domain while computing the quotient,
+    // on top of the multisets and polynomials over the domain of size `n`
+    let peak_memory = FR_SIZE * (7 * quotient_domain_size + 12 * n);
+
+    // The mode, the table identifier, 7 G1 elements, 9 field elements, the public parameters and the context
+    let proof_size =
+        1 + 32 + 7 * G1_SIZE + 9 * FR_SIZE + PARAMETERS_SIZE + context.serialized_size();
+    // The size of the table, the digest of the SRS and the three table commitments
+    let self_contained_proof_size = proof_size + 8 + 32 + 3 * G1_SIZE;
+
+    CostReport {
+        domain_size: n,
+        quotient_domain_size,
+        // f(X), t(X), h_1(X), h_2(X) and Z(X) are interpolated over the domain
+        domain_ffts: 5,
+        // The same polynomials are evaluated over the extended domain, and the quotient is interpolated from it
+        quotient_domain_ffts: 6,
+        msm_sizes,
+        srs_size,
+        peak_memory,
+        proof_size,
+        self_contained_proof_size,
+    }
+}
+
+/// Returns the number of SRS powers needed to prove lookups into a table padded to `n`
+pub fn srs_size(n: usize) -> usize {
+    srs_size_with_config(n, &ProverConfig::default())
+}
+
+/// Returns the degree that the SRS must support to prove `num_queries` lookups into a table with `table_len` rows
+/// This accounts for padding the table to a power of two, the blinding factors and the degree of the quotient.
+/// The ceremony output must hold one more power of G1 than this degree, see `kzg10::trim_for_table`.
+pub fn required_srs_degree(table_len: usize, num_queries: usize) -> usize {
+    srs_size(min_domain_size(table_len, num_queries)) - 1
+}
+
+/// Returns the number of SRS powers needed to prove lookups into a table padded to `n`, blinding as set in `config`
+pub fn srs_size_with_config(n: usize, config: &ProverConfig) -> usize {
+    *Degrees::new(n, config).msm_sizes().iter().max().unwrap()
+}
+
+// The degrees of the polynomials that the prover commits to, over a domain of size `n`
+struct Degrees {
+    f: usize,
+    shifted: usize,
+    numerator: usize,
+    quotient: usize,
+}
+
+impl Degrees {
+    fn new(n: usize, config: &ProverConfig) -> Degrees {
+        // Blinding with k factors adds a multiple of the vanishing polynomial of degree n + k - 1
+        let f = n + config.f_blinding_factors - 1;
+        let t = n - 1;
+        let shifted = n + config.shifted_blinding_factors - 1;
+
+        // The numerator of the quotient is dominated by the term check, see `quotient_poly::compute`
+        let numerator = 1 + shifted + max(f + t, 2 * shifted);
+
+        Degrees {
+            f,
+            shifted,
+            numerator,
+            quotient: numerator - n,
+        }
+    }
+    fn quotient_domain_size(&self, n: usize) -> usize {
+        max(4 * n, (self.numerator + 1).next_power_of_two())
+    }
+    // Commitments to f(X), h_1(X), h_2(X), Z(X) and the quotient,
+    // then the opening witnesses at `z` and at `z * omega`, whose degrees are one less than the largest polynomial they open
+    fn msm_sizes(&self) -> Vec<usize> {
+        vec![
+            self.f + 1,
+            self.shifted + 1,
+            self.shifted + 1,
+            self.shifted + 1,
+            self.quotient + 1,
+            self.quotient,
+            self.shifted,
+        ]
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        kzg10,
+        lookup::{
+            lookup::LookUp,
+            proof::{ProofMode, ProveOptions},
+            table::{four_bits::XOR4Bit, LookUpTable},
+        },
+    };
+    use merlin::Transcript;
+
+    #[test]
+    fn test_estimate() {
+        let report = estimate(256, 3);
+        assert_eq!(report.domain_size, 256);
+        assert_eq!(report.quotient_domain_size, 1024);
+        assert_eq!(report.msm_sizes.len(), 7);
+        // The quotient has degree 2n + 7
+        assert_eq!(report.srs_size, 2 * 256 + 8);
+
+        // Queries which do not fit in the table's domain double it
+        let report = estimate(256, 256);
+        assert_eq!(report.domain_size, 512);
+        assert_eq!(report.quotient_domain_size, 2048);
+
+        // Small domains need a larger extension for the blinding factors
+        let report = estimate(4, 1);
+        assert_eq!(report.domain_size, 4);
+        assert_eq!(report.quotient_domain_size, 32);
+        assert_eq!(report.srs_size, srs_size(4));
+    }
+
+    #[test]
+    fn test_estimated_proof_size() {
+        let (proving_key, _) = kzg10::trusted_setup(2usize.pow(10), b"insecure_seed");
+        let preprocessed_table = XOR4Bit::new().preprocess(&proving_key, 256);
+
+        let mut lookup = LookUp::new(XOR4Bit::new());
+        for i in 0..3u8 {
+            lookup.read_u8(i, i + 1);
+        }
+        for context in &[
+            ProofContext::default(),
+            ProofContext::new(b"app", b"session 42"),
+        ] {
+            let report = estimate_in_context(256, 3, context);
+            for &(mode, size) in &[
+                (ProofMode::Slim, report.proof_size),
+                (ProofMode::SelfContained, report.self_contained_proof_size),
+            ] {
+                let mut transcript = Transcript::new(b"lookup");
+                let proof = lookup
+                    .prove_with_options(
+                        &proving_key,
+                        &preprocessed_table,
+                        ProveOptions::new().mode(mode).context(context),
+                        &mut transcript,
+                    )
+                    .unwrap();
+                assert_eq!(proof.to_compressed_bytes().len(), size);
+            }
+        }
+    }
+
+    #[test]
+    fn test_required_srs_degree() {
+        assert_eq!(required_srs_degree(256, 3), 2 * 256 + 7);
+        // The table is padded to a power of two, and must hold one more element than the number of queries
+        assert_eq!(required_srs_degree(300, 3), 2 * 512 + 7);
+        assert_eq!(required_srs_degree(256, 256), 2 * 512 + 7);
+        assert_eq!(required_srs_degree(256, 3), estimate(256, 3).srs_size - 1);
+    }
+}
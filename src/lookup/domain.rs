@@ -0,0 +1,94 @@
+// Choosing the size of the domain that a table is preprocessed over
+//
+// The evaluation domains used by the prover are radix-2, so a table is always padded to a power of two `n`,
+// and a proof can hold at most `n - 1` queries. The scalar field of BLS12-381 does have subgroups of other
+// smooth orders, such as 3 * 2^k, but `ff-fft` only supports radix-2 domains, so other sizes are not used here.
+//
+// Padding to the next power of two can nearly double the domain: 2^17 + 1 queries need a domain of size 2^18.
+// When the table is small, the queries can instead be split over several proofs which each use a smaller domain.
+// `DomainPlan` computes both choices, so that the cheaper one can be picked.
+use std::cmp::max;
+
+/// The size of the domain, and how many queries go into each proof over that domain
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DomainPlan {
+    /// The size of the domain that the table is preprocessed over
+    pub n: usize,
+    /// The number of queries in each proof
+    pub chunks: Vec<usize>,
+}
+
+impl DomainPlan {
+    /// Returns the smallest domain which holds all of the queries in one proof
+    pub fn single(table_len: usize, num_queries: usize) -> DomainPlan {
+        DomainPlan {
+            n: min_domain_size(table_len, num_queries),
+            chunks: vec![num_queries],
+        }
+    }
+    /// Returns a plan which splits the queries over as many proofs as needed,
+    /// each over a domain of size `n`
+    pub fn split(table_len: usize, num_queries: usize, n: usize) -> DomainPlan {
+        assert!(n.is_power_of_two());
+        assert!(n >= min_domain_size(table_len, 1));
+
+        let capacity = n - 1;
+        let mut chunks = vec![capacity; num_queries / capacity];
+        if num_queries % capacity != 0 {
+            chunks.push(num_queries % capacity);
+        }
+
+        DomainPlan { n, chunks }
+    }
+    /// Returns a plan which splits the queries over proofs using the smallest domain that holds the table
+    pub fn split_smallest(table_len: usize, num_queries: usize) -> DomainPlan {
+        DomainPlan::split(table_len, num_queries, min_domain_size(table_len, 1))
+    }
+    /// Returns the number of proofs in the plan
+    pub fn num_proofs(&self) -> usize {
+        self.chunks.len()
+    }
+    /// Returns the total number of padding rows that are added to the witnesses
+    pub fn padding(&self) -> usize {
+        let queries: usize = self.chunks.iter().sum();
+        self.chunks.len() * (self.n - 1) - queries
+    }
+}
+
+/// Returns the smallest admissible domain size for a table with `table_len` rows and `num_queries` queries
+/// The domain must hold every row of the table, and one more element than the number of queries.
+pub fn min_domain_size(table_len: usize, num_queries: usize) -> usize {
+    max(table_len, num_queries + 1).next_power_of_two()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_min_domain_size() {
+        assert_eq!(min_domain_size(256, 3), 256);
+        assert_eq!(min_domain_size(256, 255), 256);
+        assert_eq!(min_domain_size(256, 256), 512);
+        assert_eq!(min_domain_size(300, 3), 512);
+    }
+
+    #[test]
+    fn test_domain_plans() {
+        let num_queries = 2usize.pow(17) + 1;
+
+        let single = DomainPlan::single(256, num_queries);
+        assert_eq!(single.n, 2usize.pow(18));
+        assert_eq!(single.num_proofs(), 1);
+        assert_eq!(single.padding(), 2usize.pow(17) - 2);
+
+        let split = DomainPlan::split(256, num_queries, 2usize.pow(17));
+        assert_eq!(split.chunks, vec![2usize.pow(17) - 1, 2]);
+        assert_eq!(split.padding(), 2usize.pow(17) - 3);
+
+        let smallest = DomainPlan::split_smallest(256, 510);
+        assert_eq!(smallest.n, 256);
+        assert_eq!(smallest.chunks, vec![255, 255]);
+        assert_eq!(smallest.padding(), 0);
+    }
+}
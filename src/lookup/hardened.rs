@@ -0,0 +1,288 @@
+// Lookups with an independent challenge for each column, drawn after the columns are committed to
+//
+// `LookUpProof` folds the columns of the witness with the powers of one challenge `alpha`, drawn before the
+// prover has committed to anything but the table. Here the prover first commits to each column of the witness,
+// then draws one challenge per column and folds the witness and the table with them. The folded witness is
+// committed to as the same combination of the column commitments, which the verifier recomputes, so the
+// multiset equality proof is made with `EqualityProof::prove_with_committed_f`.
+use super::table::{PreProcessedTable, TableCommitments, TableId};
+use crate::{
+    error::Error,
+    kzg10,
+    multiset::{proof::blind, EqualityProof, MultiSet, ProverConfig, PublicParameters},
+    transcript::TranscriptProtocol,
+};
+use algebra::{bls12_381::Fr, to_bytes, Bls12_381, ToBytes};
+use ff_fft::{DensePolynomial as Polynomial, EvaluationDomain};
+use num_traits::identities::Zero;
+use poly_commit::kzg10::{Commitment, Powers, VerifierKey};
+
+const CHALLENGE_LABELS: [&[u8]; 3] = [
+    b"column_challenge_1",
+    b"column_challenge_2",
+    b"column_challenge_3",
+];
+
+/// A proof that a witness is contained in a table, with a challenge for each column
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct HardenedLookUpProof {
+    /// The identifier of the table that the proof was made against
+    pub table_id: TableId,
+    /// The commitments to the three columns of the witness
+    pub column_commitments: [Commitment<Bls12_381>; 3],
+    pub multiset_equality_proof: EqualityProof,
+}
+
+impl HardenedLookUpProof {
+    /// Proves that every row (f_1, f_2, f_3) of the witness is a row of the table
+    ///
+    /// The prover absorbs the public parameters of the equality proof, the identifier of the table
+    /// and the commitment to each column of the witness,
+    /// then draws a challenge for each column. The rest of the transcript is documented on `EqualityProof::prove`.
+    pub fn prove<T: TranscriptProtocol + ?Sized>(
+        f_1: &MultiSet,
+        f_2: &MultiSet,
+        f_3: &MultiSet,
+        proving_key: &Powers<Bls12_381>,
+        preprocessed_table: &PreProcessedTable,
+        config: &ProverConfig,
+        transcript: &mut T,
+    ) -> Result<HardenedLookUpProof, Error> {
+        kzg10::check_capacity_with_config(proving_key, preprocessed_table.n, config)?;
+        let n = preprocessed_table.n;
+        let domain: EvaluationDomain<Fr> = EvaluationDomain::new(n).unwrap();
+
+        PublicParameters::new(n, config, true).absorb(transcript);
+        transcript.append_table_id(b"table_id", &preprocessed_table.id);
+        transcript.append_message(b"srs_digest", &preprocessed_table.srs_digest);
+
+        // Pad each column as the folded witness is padded, and commit to each blinded column
+        let mut rng = transcript.build_prover_rng(&to_bytes![f_1.0, f_2.0, f_3.0].unwrap());
+        let columns: Vec<_> = vec![f_1, f_2, f_3]
+            .into_iter()
+            .map(|column| pad_column(column, n))
+            .collect();
+        let column_polys: Vec<_> = columns
+            .iter()
+            .map(|column| {
+                blind(
+                    column.to_polynomial(&domain),
+                    config.f_blinding_factors,
+                    &domain,
+                    &mut rng,
+                )
+            })
+            .collect();
+        let commitments = kzg10::commit_many(proving_key, column_polys.iter().collect());
+        let column_commitments = [commitments[0], commitments[1], commitments[2]];
+
+        let challenges = column_challenges(&column_commitments, transcript);
+
+        // Fold the witness, its polynomial and its commitment, and the table, with the same challenges
+        let f = fold_columns(columns.iter().collect(), &challenges);
+        let f_poly = fold_polynomials(column_polys.iter().collect(), &challenges);
+        let f_commit = kzg10::linear_combination(commitments.iter().collect(), &challenges);
+        let t = fold_columns(
+            vec![
+                &preprocessed_table.t_1.0,
+                &preprocessed_table.t_2.0,
+                &preprocessed_table.t_3.0,
+            ],
+            &challenges,
+        );
+
+        let multiset_equality_proof = EqualityProof::prove_committed(
+            f,
+            f_poly,
+            f_commit,
+            t,
+            proving_key,
+            &preprocessed_table.quotient_key,
+            config,
+            transcript,
+        )?;
+
+        Ok(HardenedLookUpProof {
+            table_id: preprocessed_table.id,
+            column_commitments,
+            multiset_equality_proof,
+        })
+    }
+    /// Verifies the proof against a table that the verifier holds
+    /// `config` is the configuration the prover used.
+    pub fn verify<T: TranscriptProtocol + ?Sized>(
+        &self,
+        verification_key: &VerifierKey<Bls12_381>,
+        preprocessed_table: &PreProcessedTable,
+        config: &ProverConfig,
+        transcript: &mut T,
+    ) -> bool {
+        self.verify_against(
+            verification_key,
+            &preprocessed_table.commitments(),
+            config,
+            transcript,
+        )
+    }
+    /// Verifies the proof against the commitments to a table
+    pub fn verify_against<T: TranscriptProtocol + ?Sized>(
+        &self,
+        verification_key: &VerifierKey<Bls12_381>,
+        table_commitments: &TableCommitments,
+        config: &ProverConfig,
+        transcript: &mut T,
+    ) -> bool {
+        // The proof must have been made against the same table
+        if self.table_id != table_commitments.id {
+            return false;
+        }
+        let parameters = PublicParameters::new(table_commitments.n, config, true);
+        if !self
+            .multiset_equality_proof
+            .absorb_parameters(&parameters, transcript)
+        {
+            return false;
+        }
+        transcript.append_table_id(b"table_id", &table_commitments.id);
+        transcript.append_message(b"srs_digest", &table_commitments.srs_digest);
+
+        let challenges = column_challenges(&self.column_commitments, transcript);
+        let f_commit =
+            kzg10::linear_combination(self.column_commitments.iter().collect(), &challenges);
+        let t_commit = kzg10::linear_combination(
+            vec![
+                &table_commitments.t_1,
+                &table_commitments.t_2,
+                &table_commitments.t_3,
+            ],
+            &challenges,
+        );
+
+        self.multiset_equality_proof.commitments.f == f_commit
+            && self
+                .multiset_equality_proof
+                .openings(table_commitments.n, t_commit, transcript)
+                .check(verification_key)
+    }
+}
+
+// Absorbs the commitments to the columns, then draws a challenge for each column
+fn column_challenges<T: TranscriptProtocol + ?Sized>(
+    column_commitments: &[Commitment<Bls12_381>; 3],
+    transcript: &mut T,
+) -> Vec<Fr> {
+    transcript.append_commitment(b"f_1", &column_commitments[0]);
+    transcript.append_commitment(b"f_2", &column_commitments[1]);
+    transcript.append_commitment(b"f_3", &column_commitments[2]);
+
+    CHALLENGE_LABELS
+        .iter()
+        .map(|label| {
+            let challenge = transcript.challenge_scalar(*label);
+            transcript.append_scalar(*label, &challenge);
+            challenge
+        })
+        .collect()
+}
+
+// Pads a column to one less than `n` by repeating its last value, as `merge_witness` pads the folded witness
+fn pad_column(column: &MultiSet, n: usize) -> MultiSet {
+    assert!(column.len() < n);
+    let mut padded = column.clone();
+    padded.extend(n - 1 - column.len(), column.last());
+    padded
+}
+
+fn fold_columns(columns: Vec<&MultiSet>, challenges: &[Fr]) -> MultiSet {
+    let mut folded = vec![Fr::zero(); columns[0].len()];
+    for (column, challenge) in columns.iter().zip(challenges.iter()) {
+        for (folded, value) in folded.iter_mut().zip(column.0.iter()) {
+            *folded += &(*value * challenge);
+        }
+    }
+    MultiSet(folded)
+}
+
+fn fold_polynomials(polys: Vec<&Polynomial<Fr>>, challenges: &[Fr]) -> Polynomial<Fr> {
+    let len = polys.iter().map(|poly| poly.coeffs.len()).max().unwrap();
+    let mut coeffs = vec![Fr::zero(); len];
+    for (poly, challenge) in polys.iter().zip(challenges.iter()) {
+        for (folded, coeff) in coeffs.iter_mut().zip(poly.coeffs.iter()) {
+            *folded += &(*coeff * challenge);
+        }
+    }
+    Polynomial::from_coefficients_vec(coeffs)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::lookup::{
+        proof::LookUpProof,
+        table::{four_bits::XOR4Bit, LookUpTable},
+    };
+    use merlin::Transcript;
+
+    #[test]
+    fn test_hardened_lookup() {
+        let (proving_key, verifier_key) = kzg10::trusted_setup(2usize.pow(10), b"insecure_seed");
+        let preprocessed_table = XOR4Bit::new().preprocess(&proving_key, 2usize.pow(8));
+
+        let f_1: MultiSet = vec![1u8, 5, 15].into_iter().map(Fr::from).collect();
+        let f_2: MultiSet = vec![2u8, 9, 15].into_iter().map(Fr::from).collect();
+        let f_3: MultiSet = vec![3u8, 12, 0].into_iter().map(Fr::from).collect();
+
+        let mut prover_transcript = Transcript::new(b"hardened");
+        let proof = HardenedLookUpProof::prove(
+            &f_1,
+            &f_2,
+            &f_3,
+            &proving_key,
+            &preprocessed_table,
+            &ProverConfig::default(),
+            &mut prover_transcript,
+        )
+        .unwrap();
+
+        let mut verifier_transcript = Transcript::new(b"hardened");
+        assert!(proof.verify(
+            &verifier_key,
+            &preprocessed_table,
+            &ProverConfig::default(),
+            &mut verifier_transcript
+        ));
+
+        // Swapping the column commitments changes the challenges and the folded commitment
+        let mut swapped = proof.clone();
+        swapped.column_commitments.swap(0, 1);
+        let mut verifier_transcript = Transcript::new(b"hardened");
+        assert!(!swapped.verify(
+            &verifier_key,
+            &preprocessed_table,
+            &ProverConfig::default(),
+            &mut verifier_transcript
+        ));
+
+        // The transcript differs from that of a `LookUpProof`
+        let mut prover_transcript = Transcript::new(b"hardened");
+        let lookup_proof = LookUpProof::prove(
+            &f_1,
+            &f_2,
+            &f_3,
+            &proving_key,
+            &preprocessed_table,
+            &mut prover_transcript,
+        );
+        let mut verifier_transcript = Transcript::new(b"hardened");
+        let mixed = HardenedLookUpProof {
+            multiset_equality_proof: lookup_proof.multiset_equality_proof,
+            ..proof
+        };
+        assert!(!mixed.verify(
+            &verifier_key,
+            &preprocessed_table,
+            &ProverConfig::default(),
+            &mut verifier_transcript
+        ));
+    }
+}
@@ -1,11 +1,16 @@
 use super::{
-    proof::LookUpProof,
-    table::{LookUpTable, PreProcessedTable},
+    multiplicity,
+    proof::{merge_witness, LookUpProof, ProofMode, ProveOptions},
+    table::{DynamicTable, LookUpTable, PreProcessedTable},
 };
-use crate::{multiset::MultiSet, transcript::TranscriptProtocol};
+use crate::{error::Error, kzg10::CommitKey, multiset::MultiSet, transcript::TranscriptProtocol};
 use algebra::{bls12_381::Fr, Bls12_381};
 use poly_commit::kzg10::Powers;
 
+#[cfg(feature = "parallel")]
+use rayon::prelude::*;
+
+#[derive(Clone, Debug)]
 pub struct LookUp<T: LookUpTable> {
     table: T,
     // This is the set of values which we want to prove is a subset of the
@@ -13,8 +18,50 @@ pub struct LookUp<T: LookUpTable> {
     left_wires: MultiSet,
     right_wires: MultiSet,
     output_wires: MultiSet,
+    // Rows read as integers which have not been converted into field elements yet, see `with_integer_wires`
+    // They follow the rows in the wires.
+    pending: Vec<(u64, u64, u64)>,
+    integer_wires: bool,
+}
+
+/// The number of rows that had been read when a snapshot was taken, see `LookUp::snapshot`
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Snapshot {
+    num_reads: usize,
+}
+
+/// Converts the inputs of a row into field elements,
+/// so that rows can be read with native integers as well as field elements
+pub trait IntoKey {
+    fn into_key(self) -> (Fr, Fr);
+}
+
+impl IntoKey for (Fr, Fr) {
+    fn into_key(self) -> (Fr, Fr) {
+        self
+    }
+}
+
+impl IntoKey for &(Fr, Fr) {
+    fn into_key(self) -> (Fr, Fr) {
+        *self
+    }
+}
+
+macro_rules! impl_into_key {
+    ($($int:ty),*) => {
+        $(
+            impl IntoKey for ($int, $int) {
+                fn into_key(self) -> (Fr, Fr) {
+                    (Fr::from(self.0), Fr::from(self.1))
+                }
+            }
+        )*
+    };
 }
 
+impl_into_key!(u8, u16, u32, u64);
+
 impl<T: LookUpTable> LookUp<T> {
     pub fn new(table: T) -> LookUp<T> {
         LookUp {
@@ -22,33 +69,268 @@ impl<T: LookUpTable> LookUp<T> {
             left_wires: MultiSet::new(),
             right_wires: MultiSet::new(),
             output_wires: MultiSet::new(),
+            pending: Vec::new(),
+            integer_wires: false,
+        }
+    }
+    /// Creates a lookup which keeps the rows read with `read_u8`, `read_u16` and `read_u64` as integers
+    /// They are converted into field elements in one batched pass, in parallel with the `parallel` feature,
+    /// when the lookup is proved or `flush` is called. This takes the conversions off the path of witness collection,
+    /// which dominates it for long traces such as those of a zkVM.
+    pub fn with_integer_wires(table: T) -> LookUp<T> {
+        let mut lookup = LookUp::new(table);
+        lookup.integer_wires = true;
+        lookup
+    }
+    /// Converts the rows which are still held as integers into field elements, appending them to the wires
+    /// This is done by every method which proves the lookup or reads the wires.
+    pub fn flush(&mut self) {
+        if self.pending.is_empty() {
+            return;
+        }
+        let pending = std::mem::take(&mut self.pending);
+        let column = |select: fn(&(u64, u64, u64)) -> u64| -> MultiSet {
+            #[cfg(feature = "parallel")]
+            let values = pending
+                .par_iter()
+                .map(|row| Fr::from(select(row)))
+                .collect();
+            #[cfg(not(feature = "parallel"))]
+            let values = pending.iter().map(|row| Fr::from(select(row))).collect();
+            MultiSet(values)
+        };
+        self.left_wires.push_all(column(|row| row.0).0);
+        self.right_wires.push_all(column(|row| row.1).0);
+        self.output_wires.push_all(column(|row| row.2).0);
+    }
+    /// Creates a lookup from wires which have already been assigned, for example from a circuit trace
+    /// The rows are not checked against the table until `validate` is called.
+    pub fn from_wires(
+        left_wires: MultiSet,
+        right_wires: MultiSet,
+        output_wires: MultiSet,
+        table: T,
+    ) -> LookUp<T> {
+        assert_eq!(left_wires.len(), right_wires.len());
+        assert_eq!(left_wires.len(), output_wires.len());
+
+        LookUp {
+            table,
+            left_wires,
+            right_wires,
+            output_wires,
+            pending: Vec::new(),
+            integer_wires: false,
+        }
+    }
+    /// Creates a lookup from a stream of rows (left, right, output), for example read from a trace file
+    /// The rows are not checked against the table until `validate` is called.
+    pub fn from_rows<I: IntoIterator<Item = (Fr, Fr, Fr)>>(rows: I, table: T) -> LookUp<T> {
+        let mut lookup = LookUp::new(table);
+        for (left, right, output) in rows {
+            lookup.left_wires.push(left);
+            lookup.right_wires.push(right);
+            lookup.output_wires.push(output);
         }
+        lookup
+    }
+    /// Creates a lookup from a full trace of wires, where only the rows with `selector[i]` set are looked up
+    /// Every inactive row is replaced by the row of the table with input `padding_key`,
+    /// so the wires keep their positions in the trace without adding rows which are not in the table.
+    pub fn from_selected_wires(
+        left_wires: &MultiSet,
+        right_wires: &MultiSet,
+        output_wires: &MultiSet,
+        selector: &[bool],
+        padding_key: (Fr, Fr),
+        table: T,
+    ) -> LookUp<T> {
+        assert_eq!(left_wires.len(), selector.len());
+        let padding_output = table
+            .read(&padding_key)
+            .expect("the padding row must be in the table");
+
+        let select = |wires: &MultiSet, padding: Fr| -> MultiSet {
+            MultiSet(
+                wires
+                    .0
+                    .iter()
+                    .zip(selector.iter())
+                    .map(|(value, active)| if *active { *value } else { padding })
+                    .collect(),
+            )
+        };
+
+        LookUp::from_wires(
+            select(left_wires, padding_key.0),
+            select(right_wires, padding_key.1),
+            select(output_wires, padding_output),
+            table,
+        )
+    }
+    /// Returns the table that values are read from
+    pub fn table(&self) -> &T {
+        &self.table
+    }
+    /// Returns the left inputs of the rows that were read
+    /// The wire accessors and `merged_witness` first convert the rows which are still held as integers, see `flush`.
+    pub fn left_wires(&mut self) -> &MultiSet {
+        self.flush();
+        &self.left_wires
+    }
+    /// Returns the right inputs of the rows that were read
+    pub fn right_wires(&mut self) -> &MultiSet {
+        self.flush();
+        &self.right_wires
+    }
+    /// Returns the outputs of the rows that were read
+    pub fn output_wires(&mut self) -> &MultiSet {
+        self.flush();
+        &self.output_wires
+    }
+    /// Returns the left inputs, the right inputs and the outputs of the rows that were read, borrowed together
+    pub fn wires(&mut self) -> (&MultiSet, &MultiSet, &MultiSet) {
+        self.flush();
+        (&self.left_wires, &self.right_wires, &self.output_wires)
+    }
+    /// Records the current position in the witness, so that the rows read after it can be discarded with `restore`
+    /// This lets a prover collect the witness for a branch that may be rolled back, without copying the table.
+    pub fn snapshot(&self) -> Snapshot {
+        Snapshot {
+            num_reads: self.left_wires.len() + self.pending.len(),
+        }
+    }
+    /// Discards every row read since `snapshot` was taken
+    /// Rows inserted into a `DynamicTable` since then are kept, as extra rows in the table do not affect the proof.
+    /// Panics if rows read before the snapshot have already been discarded.
+    pub fn restore(&mut self, snapshot: Snapshot) {
+        self.flush();
+        assert!(snapshot.num_reads <= self.left_wires.len());
+        self.left_wires.0.truncate(snapshot.num_reads);
+        self.right_wires.0.truncate(snapshot.num_reads);
+        self.output_wires.0.truncate(snapshot.num_reads);
+    }
+    /// Returns the witness aggregated into one multiset using `alpha` and padded for a table of size `n`
+    /// This is the multiset `f` in the multiset equality argument
+    pub fn merged_witness(&mut self, alpha: Fr, n: usize) -> MultiSet {
+        self.flush();
+        merge_witness(
+            &self.left_wires,
+            &self.right_wires,
+            &self.output_wires,
+            alpha,
+            n,
+        )
     }
     // First reads a value from the underlying table
     // Then we add the key and value to their respective multisets
-    // Returns true if the value existed in the table
-    pub fn read(&mut self, key: &(Fr, Fr)) -> bool {
-        let option_output = self.table.read(key);
-        if option_output.is_none() {
-            return false;
-        }
-        let output = *option_output.unwrap();
+    // Returns the output of the row, or None if the key is not in the table
+    // The key can be given as field elements or as a pair of native integers, see `IntoKey`
+    pub fn read<K: IntoKey>(&mut self, key: K) -> Option<Fr> {
+        let key = key.into_key();
+        let output = self.table.read(&key)?;
+        // Keep the rows in the order they were read
+        self.flush();
 
         // Add (input, output) combination into the corresponding multisets
         self.left_wires.push(key.0);
         self.right_wires.push(key.1);
         self.output_wires.push(output);
 
-        return true;
+        Some(output)
+    }
+    /// Reads a row with 8-bit inputs, returning the output as an 8-bit integer
+    /// Returns None if an input is wider than the table's inputs, if the row is not in the table,
+    /// or if the output does not fit in 8 bits.
+    pub fn read_u8(&mut self, a: u8, b: u8) -> Option<u8> {
+        self.read_integer(a as u64, b as u64, 8)
+            .map(|output| output as u8)
+    }
+    /// Reads a row with 16-bit inputs, returning the output as a 16-bit integer
+    /// Returns None in the same cases as `read_u8`.
+    pub fn read_u16(&mut self, a: u16, b: u16) -> Option<u16> {
+        self.read_integer(a as u64, b as u64, 16)
+            .map(|output| output as u16)
+    }
+    /// Reads a row with 64-bit inputs, returning the output as a 64-bit integer
+    /// Returns None in the same cases as `read_u8`.
+    pub fn read_u64(&mut self, a: u64, b: u64) -> Option<u64> {
+        self.read_integer(a, b, 64)
+    }
+    // Reads a row with integer inputs, whose output must fit in `output_bits` bits
+    // Nothing is added to the witness unless the inputs fit the table, the row is in the table and the output fits.
+    fn read_integer(&mut self, a: u64, b: u64, output_bits: usize) -> Option<u64> {
+        if let Some(bits) = self.table.input_bits() {
+            if bits < 64 && (a >> bits != 0 || b >> bits != 0) {
+                return None;
+            }
+        }
+        // Check the output before the row is added to the witness
+        let output = self.table.read_u64(a, b)?;
+        if output_bits < 64 && output >> output_bits != 0 {
+            return None;
+        }
+        if self.integer_wires {
+            self.pending.push((a, b, output));
+        } else {
+            self.left_wires.push_u64(a);
+            self.right_wires.push_u64(b);
+            self.output_wires.push_u64(output);
+        }
+        Some(output)
+    }
+
+    /// Checks that every row (left, right, output) of the witness is a row of the table
+    /// Returns the indices of the rows which are not, as a proof for them would not verify
+    /// Rows still held as integers were read from the table, so they are not checked again.
+    pub fn validate(&self) -> Result<(), Error> {
+        let missing: Vec<_> = (0..self.left_wires.len())
+            .filter(|&i| {
+                let key = (self.left_wires.0[i], self.right_wires.0[i]);
+                self.table.read(&key) != Some(self.output_wires.0[i])
+            })
+            .collect();
+
+        if missing.is_empty() {
+            Ok(())
+        } else {
+            Err(Error::MissingRows(missing))
+        }
+    }
+
+    /// Returns the number of times the witness reads each row of the table, as the vector m of a logUp argument
+    /// The counts are in the order that `LookUpTable::to_multiset` puts the rows in, so they line up with its columns.
+    /// Returns `Error::MissingRows` if the witness has rows which are not in the table, as `validate` does.
+    pub fn multiplicities(&self) -> Result<MultiSet, Error> {
+        let rows: Vec<_> = (0..self.left_wires.len())
+            .map(|i| {
+                (
+                    self.left_wires.0[i],
+                    self.right_wires.0[i],
+                    self.output_wires.0[i],
+                )
+            })
+            .chain(
+                self.pending
+                    .iter()
+                    .map(|(a, b, c)| (Fr::from(*a), Fr::from(*b), Fr::from(*c))),
+            )
+            .collect();
+        let counts = multiplicity::multiplicities(&rows, &self.table)?;
+        Ok(counts
+            .into_iter()
+            .map(|count| Fr::from(count as u64))
+            .collect())
     }
 
     /// Creates a proof that the values (f_1, f_2, f_3)  are within the table of values (t_1, t_2,t_3)
-    pub fn prove(
+    pub fn prove<Tr: TranscriptProtocol + ?Sized>(
         &mut self,
         proving_key: &Powers<Bls12_381>,
         preprocessed_table: &PreProcessedTable,
-        transcript: &mut dyn TranscriptProtocol,
+        transcript: &mut Tr,
     ) -> LookUpProof {
+        self.flush();
         LookUpProof::prove(
             &self.left_wires,
             &self.right_wires,
@@ -58,6 +340,136 @@ impl<T: LookUpTable> LookUp<T> {
             transcript,
         )
     }
+
+    /// Creates a proof that the values (f_1, f_2, f_3) are within the table of values (t_1, t_2, t_3),
+    /// which either carries the table commitments or not, depending on `mode`
+    pub fn prove_with_mode<Tr: TranscriptProtocol + ?Sized>(
+        &mut self,
+        proving_key: &Powers<Bls12_381>,
+        preprocessed_table: &PreProcessedTable,
+        mode: ProofMode,
+        transcript: &mut Tr,
+    ) -> LookUpProof {
+        self.flush();
+        LookUpProof::prove_with_mode(
+            &self.left_wires,
+            &self.right_wires,
+            &self.output_wires,
+            proving_key,
+            preprocessed_table,
+            mode,
+            transcript,
+        )
+    }
+
+    /// Creates a proof that the values (f_1, f_2, f_3) are within the table of values (t_1, t_2, t_3),
+    /// as set in `options`, see `LookUpProof::prove_with_options`
+    pub fn prove_with_options<K: CommitKey + ?Sized, Tr: TranscriptProtocol + ?Sized>(
+        &mut self,
+        proving_key: &K,
+        preprocessed_table: &PreProcessedTable,
+        options: ProveOptions,
+        transcript: &mut Tr,
+    ) -> Result<LookUpProof, Error> {
+        self.flush();
+        LookUpProof::prove_with_options(
+            &self.left_wires,
+            &self.right_wires,
+            &self.output_wires,
+            proving_key,
+            preprocessed_table,
+            options,
+            transcript,
+        )
+    }
+}
+
+/// Creates one proof that the witnesses of several lookups into the same table are within the table
+/// This costs one accumulator, one quotient and one set of openings, instead of one per lookup.
+///
+/// The witnesses are concatenated rather than combined with a challenge:
+/// a random linear combination of rows in the table is not a row in the table, whereas the union of
+/// several subsets of the table is a subset of the table. The total number of rows must be less than `n`.
+/// The rows which the lookups still hold as integers are converted first.
+pub fn prove_many<T: LookUpTable, Tr: TranscriptProtocol + ?Sized>(
+    lookups: &mut [&mut LookUp<T>],
+    proving_key: &Powers<Bls12_381>,
+    preprocessed_table: &PreProcessedTable,
+    transcript: &mut Tr,
+) -> LookUpProof {
+    let mut left_wires = MultiSet::new();
+    let mut right_wires = MultiSet::new();
+    let mut output_wires = MultiSet::new();
+    for lookup in lookups.iter_mut() {
+        lookup.flush();
+        left_wires = left_wires.concatenate(&lookup.left_wires);
+        right_wires = right_wires.concatenate(&lookup.right_wires);
+        output_wires = output_wires.concatenate(&lookup.output_wires);
+    }
+
+    LookUpProof::prove(
+        &left_wires,
+        &right_wires,
+        &output_wires,
+        proving_key,
+        preprocessed_table,
+        transcript,
+    )
+}
+
+/// Proves each lookup on its own, with at most `max_concurrent` proofs in progress at once
+/// Each proof is made with its own clone of `transcript`, so each is verified with its own clone of the verifier's transcript.
+/// The proofs are returned in the order of the lookups.
+/// The rows which the lookups still hold as integers are converted first.
+///
+/// With the `parallel` feature, the proofs in progress run concurrently on rayon's thread pool.
+/// Every proof in progress holds its polynomials and quotient computation in memory,
+/// so `max_concurrent` bounds the memory used by a batch of many small instances.
+pub fn prove_all<T, Tr>(
+    lookups: &mut [LookUp<T>],
+    proving_key: &Powers<Bls12_381>,
+    preprocessed_table: &PreProcessedTable,
+    transcript: &Tr,
+    max_concurrent: usize,
+) -> Vec<LookUpProof>
+where
+    T: LookUpTable + Sync,
+    Tr: TranscriptProtocol + Clone + Send + Sync,
+{
+    assert!(max_concurrent > 0);
+    for lookup in lookups.iter_mut() {
+        lookup.flush();
+    }
+
+    let prove = |lookup: &LookUp<T>| {
+        let mut transcript = transcript.clone();
+        LookUpProof::prove(
+            &lookup.left_wires,
+            &lookup.right_wires,
+            &lookup.output_wires,
+            proving_key,
+            preprocessed_table,
+            &mut transcript,
+        )
+    };
+
+    let mut proofs = Vec::with_capacity(lookups.len());
+    for chunk in lookups.chunks(max_concurrent) {
+        #[cfg(feature = "parallel")]
+        proofs.par_extend(chunk.par_iter().map(prove));
+        #[cfg(not(feature = "parallel"))]
+        proofs.extend(chunk.iter().map(prove));
+    }
+    proofs
+}
+
+impl<T: DynamicTable> LookUp<T> {
+    /// Reads the row with the given key, first adding the row (key, value) to the table if the key is not in it
+    /// Returns the output of the row, which is `value` unless the key was already in the table
+    pub fn read_or_insert(&mut self, key: &(Fr, Fr), value: Fr) -> Fr {
+        self.table.insert(*key, value);
+        self.read(key).expect("the key was inserted into the table")
+    }
 }
 
 #[cfg(test)]
@@ -65,7 +477,9 @@ mod test {
     use super::*;
     use crate::kzg10;
     use crate::lookup::table::four_bits::XOR4Bit;
+    use crate::multiset::ProverScratch;
     use merlin::Transcript;
+    use num_traits::identities::Zero;
 
     #[test]
     fn test_proof() {
@@ -92,4 +506,342 @@ mod test {
         let ok = proof.verify(&verifier_key, &preprocessed_table, &mut verifier_transcript);
         assert!(ok);
     }
+
+    #[test]
+    fn test_prove_with_scratch() {
+        let (proving_key, verifier_key) = kzg10::trusted_setup(2usize.pow(10), b"insecure_seed");
+        let preprocessed_table = XOR4Bit::new().preprocess(&proving_key, 2usize.pow(8));
+        let mut scratch = ProverScratch::new();
+
+        let mut allocations = Vec::new();
+        for &(a, b) in &[(1, 2), (3, 5)] {
+            let mut lookup = LookUp::new(XOR4Bit::new());
+            lookup.read_u8(a, b);
+            let mut prover_transcript = Transcript::new(b"lookup");
+            let proof = lookup
+                .prove_with_options(
+                    &proving_key,
+                    &preprocessed_table,
+                    ProveOptions::new().scratch(&mut scratch),
+                    &mut prover_transcript,
+                )
+                .unwrap();
+            allocations.push(scratch.scratch().allocations());
+
+            let mut verifier_transcript = Transcript::new(b"lookup");
+            assert!(proof.verify(&verifier_key, &preprocessed_table, &mut verifier_transcript));
+        }
+        // The second proof reuses the buffers of the first
+        assert!(allocations[0] > 0);
+        assert_eq!(allocations[0], allocations[1]);
+    }
+
+    #[test]
+    fn test_multiplicities() {
+        let mut lookup = LookUp::new(XOR4Bit::new());
+        lookup.read_u8(1, 2);
+        lookup.read_u8(3, 5);
+        lookup.read_u8(1, 2);
+
+        // The rows of the table are sorted by their inputs, so the row (a, b) is at 16a + b
+        let m = lookup.multiplicities().unwrap();
+        assert_eq!(m.len(), 256);
+        assert_eq!(m.0[18], Fr::from(2u8));
+        assert_eq!(m.0[53], Fr::from(1u8));
+        assert_eq!(m.0.iter().filter(|count| !count.is_zero()).count(), 2);
+
+        let rows = vec![(Fr::from(3u8), Fr::from(5u8), Fr::from(7u8))];
+        let lookup = LookUp::from_rows(rows, XOR4Bit::new());
+        match lookup.multiplicities() {
+            Err(Error::MissingRows(rows)) => assert_eq!(rows, vec![0]),
+            _ => panic!("expected the row to be missing from the table"),
+        }
+    }
+
+    #[test]
+    fn test_integer_wires() {
+        let (proving_key, verifier_key) = kzg10::trusted_setup(2usize.pow(10), b"insecure_seed");
+        let preprocessed_table = XOR4Bit::new().preprocess(&proving_key, 2usize.pow(8));
+
+        let mut lookup = LookUp::with_integer_wires(XOR4Bit::new());
+        let mut expected = LookUp::new(XOR4Bit::new());
+        for lookup in vec![&mut lookup, &mut expected] {
+            lookup.read_u8(1, 2);
+            lookup.read(&(Fr::from(2u8), Fr::from(4u8)));
+            lookup.read_u8(3, 5);
+            lookup.read_u8(3, 16);
+        }
+        assert_eq!(lookup.snapshot(), expected.snapshot());
+        assert_eq!(
+            lookup.multiplicities().unwrap(),
+            expected.multiplicities().unwrap()
+        );
+
+        // Reading the wires converts the rows, in the order they were read
+        assert_eq!(lookup.left_wires(), expected.left_wires());
+        assert_eq!(lookup.output_wires(), expected.output_wires());
+
+        lookup.read_u8(7, 7);
+        let mut prover_transcript = Transcript::new(b"lookup");
+        let proof = lookup.prove(&proving_key, &preprocessed_table, &mut prover_transcript);
+        let mut verifier_transcript = Transcript::new(b"lookup");
+        assert!(proof.verify(&verifier_key, &preprocessed_table, &mut verifier_transcript));
+        assert_eq!(lookup.left_wires().len(), 4);
+    }
+
+    #[test]
+    fn test_read() {
+        let mut lookup = LookUp::new(XOR4Bit::new());
+
+        assert_eq!(
+            lookup.read(&(Fr::from(3u8), Fr::from(5u8))),
+            Some(Fr::from(6u8))
+        );
+        assert_eq!(lookup.read(&(Fr::from(16u8), Fr::from(5u8))), None);
+
+        // Only rows in the table are recorded
+        assert_eq!(lookup.left_wires.len(), 1);
+        assert_eq!(lookup.output_wires.0[0], Fr::from(6u8));
+    }
+
+    #[test]
+    fn test_read_integers() {
+        let mut lookup = LookUp::new(XOR4Bit::new());
+
+        assert_eq!(lookup.read((3u8, 5u8)), Some(Fr::from(6u8)));
+        assert_eq!(lookup.read((9u64, 9u64)), Some(Fr::from(0u8)));
+        assert_eq!(lookup.read_u8(12, 10), Some(6));
+        assert_eq!(lookup.read_u16(1, 15), Some(14));
+
+        // Inputs wider than the table's 4-bit inputs are rejected without reading the table
+        assert_eq!(lookup.read_u8(16, 5), None);
+        assert_eq!(lookup.read_u64(1 << 40, 5), None);
+        assert_eq!(lookup.left_wires.len(), 4);
+        assert_eq!(lookup.left_wires.0[2], Fr::from(12u8));
+    }
+
+    #[test]
+    fn test_read_narrow_output() {
+        use crate::lookup::table::IntegerTable;
+        use std::collections::HashMap;
+
+        let mut map = HashMap::new();
+        map.insert((1, 1), 300);
+        map.insert((2, 2), 70_000);
+        let table = IntegerTable::with_hashmap(map);
+
+        for mut lookup in vec![
+            LookUp::new(table.clone()),
+            LookUp::with_integer_wires(table),
+        ] {
+            let snapshot = lookup.snapshot();
+
+            // An output which does not fit the narrow type leaves the witness unchanged
+            assert_eq!(lookup.read_u8(1, 1), None);
+            assert_eq!(lookup.read_u16(2, 2), None);
+            assert_eq!(lookup.snapshot(), snapshot);
+
+            assert_eq!(lookup.read_u16(1, 1), Some(300));
+            assert_eq!(lookup.read_u64(2, 2), Some(70_000));
+            assert_eq!(lookup.left_wires().len(), 2);
+        }
+    }
+
+    #[test]
+    fn test_read_or_insert() {
+        use crate::lookup::table::Generic;
+        use std::collections::HashMap;
+
+        // A dictionary of observed values, (value, 0) -> index
+        let mut lookup = LookUp::new(Generic::with_hashmap(HashMap::new()));
+        let words = [7u8, 3, 7, 9, 3, 7];
+        let mut next_index = 0u8;
+        let mut indices = Vec::new();
+        for word in words.iter() {
+            let key = (Fr::from(*word), Fr::zero());
+            let index = lookup.read_or_insert(&key, Fr::from(next_index));
+            if index == Fr::from(next_index) {
+                next_index += 1;
+            }
+            indices.push(index);
+        }
+
+        let expected: Vec<_> = [0u8, 1, 0, 2, 1, 0].iter().map(|i| Fr::from(*i)).collect();
+        assert_eq!(indices, expected);
+        assert_eq!(lookup.table().len(), 3);
+        assert_eq!(lookup.left_wires.len(), words.len());
+        assert!(lookup.validate().is_ok());
+    }
+
+    #[test]
+    fn test_snapshot() {
+        let mut lookup = LookUp::new(XOR4Bit::new());
+        lookup.read((1u8, 2u8));
+        let snapshot = lookup.snapshot();
+
+        // Speculatively read from a branch, then roll it back
+        let mut branch = lookup.clone();
+        branch.read((3u8, 4u8));
+        assert_eq!(branch.left_wires().len(), 2);
+        assert_eq!(lookup.left_wires().len(), 1);
+
+        lookup.read((5u8, 6u8));
+        lookup.read((7u8, 8u8));
+        lookup.restore(snapshot);
+        assert_eq!(lookup.left_wires().0, vec![Fr::from(1u8)]);
+        assert_eq!(lookup.output_wires().0, vec![Fr::from(3u8)]);
+        assert_eq!(lookup.snapshot(), snapshot);
+        assert!(lookup.validate().is_ok());
+    }
+
+    #[test]
+    fn test_from_wires() {
+        let left = MultiSet(vec![Fr::from(1u8), Fr::from(2u8), Fr::from(3u8)]);
+        let right = MultiSet(vec![Fr::from(2u8), Fr::from(4u8), Fr::from(5u8)]);
+        let output = MultiSet(vec![Fr::from(3u8), Fr::from(6u8), Fr::from(6u8)]);
+
+        let lookup = LookUp::from_wires(left.clone(), right.clone(), output, XOR4Bit::new());
+        assert!(lookup.validate().is_ok());
+
+        let wrong_output = MultiSet(vec![Fr::from(3u8), Fr::from(6u8), Fr::from(7u8)]);
+        let lookup = LookUp::from_wires(left, right, wrong_output, XOR4Bit::new());
+        match lookup.validate() {
+            Err(Error::MissingRows(indices)) => assert_eq!(indices, vec![2]),
+            _ => panic!("expected row 2 to be missing"),
+        }
+    }
+
+    #[test]
+    fn test_merged_multisets() {
+        let (proving_key, _) = kzg10::trusted_setup(2usize.pow(9), b"insecure_seed");
+        let table = XOR4Bit::new();
+        let preprocessed_table = table.preprocess(&proving_key, 2usize.pow(8));
+
+        let mut lookup = LookUp::new(table);
+        lookup.read(&(Fr::from(1u8), Fr::from(2u8)));
+        lookup.read(&(Fr::from(7u8), Fr::from(9u8)));
+        assert_eq!(lookup.left_wires().0, vec![Fr::from(1u8), Fr::from(7u8)]);
+        assert_eq!(lookup.right_wires().0, vec![Fr::from(2u8), Fr::from(9u8)]);
+        assert_eq!(lookup.output_wires().0, vec![Fr::from(3u8), Fr::from(14u8)]);
+
+        let alpha = Fr::from(5u8);
+        let f = lookup.merged_witness(alpha, preprocessed_table.n);
+        let t = preprocessed_table.merged(alpha);
+        assert_eq!(f.len(), preprocessed_table.n - 1);
+        assert_eq!(t.len(), preprocessed_table.n);
+        // 1 + 2 * 5 + 3 * 25
+        assert_eq!(f.0[0], Fr::from(86u8));
+        assert!(f.is_subset_of(&t));
+    }
+
+    #[test]
+    fn test_from_rows() {
+        let rows = (0..16u64).map(|i| (Fr::from(i), Fr::from(i), Fr::zero()));
+        let mut lookup = LookUp::from_rows(rows, XOR4Bit::new());
+        assert_eq!(lookup.left_wires().len(), 16);
+        assert!(lookup.validate().is_ok());
+    }
+
+    #[test]
+    fn test_from_selected_wires() {
+        // The second row is an arithmetic gate, which does not use the XOR table
+        let left = MultiSet(vec![Fr::from(1u8), Fr::from(100u8), Fr::from(3u8)]);
+        let right = MultiSet(vec![Fr::from(2u8), Fr::from(200u8), Fr::from(5u8)]);
+        let output = MultiSet(vec![Fr::from(3u8), Fr::from(300u16), Fr::from(6u8)]);
+        let selector = [true, false, true];
+
+        let padding_key = (Fr::zero(), Fr::zero());
+        let mut lookup = LookUp::from_selected_wires(
+            &left,
+            &right,
+            &output,
+            &selector,
+            padding_key,
+            XOR4Bit::new(),
+        );
+        assert!(lookup.validate().is_ok());
+        assert_eq!(lookup.left_wires().len(), 3);
+        assert_eq!(lookup.left_wires().0[1], Fr::zero());
+        assert_eq!(lookup.output_wires().0[1], Fr::zero());
+        assert_eq!(lookup.output_wires().0[2], Fr::from(6u8));
+    }
+
+    #[test]
+    fn test_prove_many() {
+        let (proving_key, verifier_key) = kzg10::trusted_setup(2usize.pow(12), b"insecure_seed");
+
+        let table = XOR4Bit::new();
+        let preprocessed_table = table.preprocess(&proving_key, 2usize.pow(8));
+
+        let mut first = LookUp::new(XOR4Bit::new());
+        first.read(&(Fr::from(1u8), Fr::from(2u8)));
+        first.read(&(Fr::from(2u8), Fr::from(4u8)));
+        // Rows held as integers are converted before the witnesses are concatenated
+        let mut second = LookUp::with_integer_wires(XOR4Bit::new());
+        second.read_u8(15, 15);
+
+        let mut prover_transcript = Transcript::new(b"lookup");
+        let proof = prove_many(
+            &mut [&mut first, &mut second],
+            &proving_key,
+            &preprocessed_table,
+            &mut prover_transcript,
+        );
+
+        let mut verifier_transcript = Transcript::new(b"lookup");
+        let ok = proof.verify(&verifier_key, &preprocessed_table, &mut verifier_transcript);
+        assert!(ok);
+    }
+
+    #[test]
+    fn test_prove_all() {
+        let (proving_key, verifier_key) = kzg10::trusted_setup(2usize.pow(10), b"insecure_seed");
+        let preprocessed_table = XOR4Bit::new().preprocess(&proving_key, 2usize.pow(8));
+
+        let mut lookups: Vec<_> = (0..5u8)
+            .map(|i| {
+                let mut lookup = LookUp::with_integer_wires(XOR4Bit::new());
+                for j in 0..=i {
+                    lookup.read_u8(i, j);
+                }
+                lookup
+            })
+            .collect();
+
+        let transcript = Transcript::new(b"lookup");
+        let proofs = prove_all(
+            &mut lookups,
+            &proving_key,
+            &preprocessed_table,
+            &transcript,
+            2,
+        );
+        assert_eq!(proofs.len(), lookups.len());
+        for proof in proofs.iter() {
+            let mut verifier_transcript = Transcript::new(b"lookup");
+            assert!(proof.verify(&verifier_key, &preprocessed_table, &mut verifier_transcript));
+        }
+    }
+
+    #[test]
+    fn test_validate() {
+        let mut lookup = LookUp::new(XOR4Bit::new());
+        lookup.read(&(Fr::from(1u8), Fr::from(2u8)));
+        lookup.read(&(Fr::from(2u8), Fr::from(4u8)));
+        assert!(lookup.validate().is_ok());
+
+        // Record rows which are not in the table
+        lookup.left_wires.push(Fr::from(1u8));
+        lookup.right_wires.push(Fr::from(2u8));
+        lookup.output_wires.push(Fr::from(4u8));
+        lookup.read(&(Fr::from(3u8), Fr::from(5u8)));
+        lookup.left_wires.push(Fr::from(16u8));
+        lookup.right_wires.push(Fr::from(0u8));
+        lookup.output_wires.push(Fr::from(16u8));
+
+        match lookup.validate() {
+            Err(Error::MissingRows(indices)) => assert_eq!(indices, vec![2, 4]),
+            _ => panic!("expected rows 2 and 4 to be missing"),
+        }
+    }
 }
@@ -1,4 +1,16 @@
+pub mod accumulator;
+pub mod context;
+pub mod cost;
+pub mod domain;
+pub mod hardened;
 pub mod lookup;
+pub mod multi;
+pub mod multiplicity;
+pub mod non_membership;
 
 pub mod proof;
+pub mod range;
+pub mod subtable;
 pub mod table;
+#[cfg(feature = "tokio")]
+pub mod task;
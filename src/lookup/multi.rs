@@ -0,0 +1,138 @@
+// Proving lookups into several tables together
+//
+// A circuit often reads from more than one table, for example a range table, an XOR table and an S-box table.
+// `MultiLookup` proves one lookup per table under a single transcript, so every proof is bound to the others,
+// and the verifier checks the openings of all of the proofs in one batch.
+use super::{context::ProofContext, proof::LookUpProof, table::PreProcessedTable};
+use crate::{
+    kzg10::OpeningBatch,
+    multiset::{MultiSet, ProverConfig},
+    transcript::TranscriptProtocol,
+};
+use algebra::{bls12_381::Fr, Bls12_381};
+use poly_commit::kzg10::{Powers, VerifierKey};
+
+/// The proofs for each table, in the order in which the tables were given
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct MultiLookupProof {
+    pub proofs: Vec<LookUpProof>,
+}
+
+/// A set of preprocessed tables which are looked up in together
+pub struct MultiLookup<'a> {
+    tables: Vec<&'a PreProcessedTable>,
+}
+
+impl<'a> MultiLookup<'a> {
+    pub fn new(tables: Vec<&'a PreProcessedTable>) -> MultiLookup<'a> {
+        MultiLookup { tables }
+    }
+    /// Proves that each witness (f_1, f_2, f_3) is contained in the table at the same position
+    pub fn prove<T: TranscriptProtocol + ?Sized>(
+        &self,
+        witnesses: &[(MultiSet, MultiSet, MultiSet)],
+        proving_key: &Powers<Bls12_381>,
+        transcript: &mut T,
+    ) -> MultiLookupProof {
+        assert_eq!(witnesses.len(), self.tables.len());
+        transcript.append_scalar(b"num_lookups", &Fr::from(self.tables.len() as u64));
+
+        let proofs = self
+            .tables
+            .iter()
+            .zip(witnesses.iter())
+            .map(|(table, (f_1, f_2, f_3))| {
+                LookUpProof::prove(f_1, f_2, f_3, proving_key, table, transcript)
+            })
+            .collect();
+
+        MultiLookupProof { proofs }
+    }
+    /// Verifies the proofs for every table, checking all of their openings together
+    pub fn verify<T: TranscriptProtocol + ?Sized>(
+        &self,
+        proof: &MultiLookupProof,
+        verification_key: &VerifierKey<Bls12_381>,
+        transcript: &mut T,
+    ) -> bool {
+        if proof.proofs.len() != self.tables.len() {
+            return false;
+        }
+        transcript.append_scalar(b"num_lookups", &Fr::from(self.tables.len() as u64));
+
+        // The proofs are made without a context, as they are bound to each other by the transcript
+        let context = ProofContext::default();
+        let config = ProverConfig::default();
+        let mut openings = OpeningBatch::new();
+        for (table, lookup_proof) in self.tables.iter().zip(proof.proofs.iter()) {
+            let table_openings =
+                lookup_proof.openings_against(&table.commitments(), &context, &config, transcript);
+            match table_openings {
+                Some(table_openings) => openings.extend(table_openings),
+                None => return false,
+            }
+        }
+        openings.check(verification_key)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        kzg10,
+        lookup::table::{
+            four_bits::{Add4Bit, XOR4Bit},
+            LookUpTable,
+        },
+    };
+    use merlin::Transcript;
+
+    fn witness(rows: &[(u8, u8, u8)]) -> (MultiSet, MultiSet, MultiSet) {
+        (
+            rows.iter().map(|row| Fr::from(row.0)).collect(),
+            rows.iter().map(|row| Fr::from(row.1)).collect(),
+            rows.iter().map(|row| Fr::from(row.2)).collect(),
+        )
+    }
+
+    #[test]
+    fn test_multi_lookup() {
+        let (proving_key, verifier_key) = kzg10::trusted_setup(2usize.pow(10), b"insecure_seed");
+        let xor_table = XOR4Bit::new().preprocess(&proving_key, 2usize.pow(8));
+        let add_table = Add4Bit::new().preprocess(&proving_key, 2usize.pow(8));
+        let multi_lookup = MultiLookup::new(vec![&xor_table, &add_table]);
+
+        let witnesses = vec![
+            witness(&[(1, 2, 3), (5, 9, 12)]),
+            witness(&[(1, 2, 3), (7, 8, 15)]),
+        ];
+        let mut prover_transcript = Transcript::new(b"multi_lookup");
+        let proof = multi_lookup.prove(&witnesses, &proving_key, &mut prover_transcript);
+        assert_eq!(proof.proofs.len(), 2);
+
+        let mut verifier_transcript = Transcript::new(b"multi_lookup");
+        assert!(multi_lookup.verify(&proof, &verifier_key, &mut verifier_transcript));
+
+        // The proofs are bound to the order of the tables
+        let swapped = MultiLookup::new(vec![&add_table, &xor_table]);
+        let mut verifier_transcript = Transcript::new(b"multi_lookup");
+        assert!(!swapped.verify(&proof, &verifier_key, &mut verifier_transcript));
+
+        // A proof which was made on its own does not verify as part of the batch
+        let mut prover_transcript = Transcript::new(b"multi_lookup");
+        let (f_1, f_2, f_3) = &witnesses[0];
+        let single = LookUpProof::prove(
+            f_1,
+            f_2,
+            f_3,
+            &proving_key,
+            &xor_table,
+            &mut prover_transcript,
+        );
+        let mut mixed = proof.clone();
+        mixed.proofs[0] = single;
+        let mut verifier_transcript = Transcript::new(b"multi_lookup");
+        assert!(!multi_lookup.verify(&mixed, &verifier_key, &mut verifier_transcript));
+    }
+}
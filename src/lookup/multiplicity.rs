@@ -0,0 +1,698 @@
+// Proving that no row of a table is used more than k times
+//
+// Plookup shows that every query is a row of the table, but a row may be queried any number of times. For one-time
+// tickets or nullifier-like tables each row may only be used a bounded number of times, which needs multiset
+// inclusion rather than set inclusion.
+//
+// `bounded_table` repeats every row of a table k times, and pads the result with a row whose key is not in the
+// table. A fourth column, the indicator, is one on the rows of the table and zero on the padding, and every column is
+// folded with the powers of `alpha`. The prover arranges the queries, followed by the copies they did not use, into a
+// witness w of the same size, and shows with a grand product that w is a rearrangement of the bounded table:
+//
+//   Z(1) = 1,  Z(g^{i+1}) = Z(g^i) (gamma + w_i) / (gamma + t_i)
+//
+// Z returns to 1 after the last point exactly when the multisets of w and t are equal. As w then holds at most k
+// copies of each row, no row is used by the queries more than k times. The multiplicity of each row is the number of
+// its copies among the queries, which `multiplicities` returns.
+//
+// The verifier holds commitments to the columns of the m queries, padded with zeros, which `query_commitments`
+// computes. They are folded as the rows are into f, and w must agree with f on the first m points of the domain,
+// with an indicator of one, so the proof is bound to the queries rather than to any rearrangement of the bounded
+// table. A query of the padding row would have an indicator of one, so it does not match the padding in t.
+//
+// The quotient encodes three checks, separated by the powers of `separation_challenge`:
+// L_1(X) (Z(X) - 1) = 0, Z(Xg) (gamma + t(X)) - Z(X) (gamma + w(X)) = 0 and S(X) (w(X) - f(X) - alpha^3) = 0
+// over the domain, where S(X) = L_1(X) + ... + L_m(X) selects the first m points.
+use super::table::{LookUpTable, PreProcessedTable, TableCommitments, TableId};
+use crate::{
+    error::Error,
+    kzg10,
+    multiset::{proof::blind, quotient_poly, MultiSet, ProverConfig},
+    transcript::TranscriptProtocol,
+};
+use algebra::{bls12_381::Fr, to_bytes, Bls12_381, ToBytes};
+use ff_fft::{DensePolynomial as Polynomial, EvaluationDomain};
+use merlin::Transcript;
+use num_traits::identities::{One, Zero};
+use poly_commit::kzg10::{Commitment, Powers, VerifierKey};
+use std::collections::HashMap;
+
+/// A row of a table
+pub type Row = (Fr, Fr, Fr);
+
+/// A table which holds every row of a table `k` times, padded to size `n`, see `bounded_table`
+#[derive(Clone, Debug)]
+pub struct BoundedTable {
+    /// The rows of the table, each repeated `k` times, followed by the padding rows
+    pub table: PreProcessedTable,
+    /// The column which is one on the rows of the table and zero on the padding, with its commitment and polynomial
+    pub indicator: (MultiSet, Commitment<Bls12_381>, Polynomial<Fr>),
+}
+
+/// The commitments to the columns of a bounded table, which is all the verifier needs of it
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct BoundedTableCommitments {
+    pub table: TableCommitments,
+    pub indicator: Commitment<Bls12_381>,
+}
+
+impl BoundedTable {
+    /// Returns the commitments to the columns of the bounded table
+    pub fn commitments(&self) -> BoundedTableCommitments {
+        BoundedTableCommitments {
+            table: self.table.commitments(),
+            indicator: self.indicator.1,
+        }
+    }
+}
+
+/// Returns the table which holds every row of `table` `k` times, padded to size `n` and committed to
+/// The padding row has the key (a, a) for the least integer `a` such that the key is not in the table,
+/// and the indicator column tells it apart from the rows of the table for the verifier.
+/// Panics if the bounded table has more than `n` rows.
+pub fn bounded_table<T: LookUpTable + ?Sized>(
+    table: &T,
+    k: usize,
+    commit_key: &Powers<Bls12_381>,
+    n: usize,
+) -> BoundedTable {
+    assert!(n.is_power_of_two());
+    assert!(k > 0);
+    assert!(
+        table.len() * k <= n,
+        "the bounded table does not fit in {} rows",
+        n
+    );
+
+    let padding = (0u64..)
+        .map(Fr::from)
+        .find(|a| !table.contains(&(*a, *a)))
+        .unwrap();
+
+    let (t_1, t_2, t_3) = table.to_multiset();
+    let mut columns = vec![MultiSet::new(), MultiSet::new(), MultiSet::new()];
+    for i in 0..t_1.len() {
+        for (column, value) in columns.iter_mut().zip(vec![t_1.0[i], t_2.0[i], t_3.0[i]]) {
+            column.extend(k, value);
+        }
+    }
+    let mut indicator = MultiSet::new();
+    indicator.extend(columns[0].len(), Fr::one());
+    columns.push(indicator);
+    for (column, value) in columns
+        .iter_mut()
+        .zip(vec![padding, padding, Fr::zero(), Fr::zero()])
+    {
+        column.extend(n - column.len(), value);
+    }
+
+    let domain: EvaluationDomain<Fr> = EvaluationDomain::new(n).unwrap();
+    let polys: Vec<_> = columns
+        .iter()
+        .map(|column| column.to_polynomial(&domain))
+        .collect();
+    let commitments = kzg10::commit_many(commit_key, polys.iter().collect());
+
+    let mut parts = columns
+        .into_iter()
+        .zip(commitments.into_iter())
+        .zip(polys.into_iter())
+        .map(|((column, commitment), poly)| (column, commitment, poly));
+    BoundedTable {
+        table: PreProcessedTable {
+            id: bounded_table_id(&table.id(), k),
+            n,
+            srs_digest: kzg10::srs_digest(commit_key, n),
+            t_1: parts.next().unwrap(),
+            t_2: parts.next().unwrap(),
+            t_3: parts.next().unwrap(),
+            quotient_key: quotient_poly::QuotientKey::new(n),
+        },
+        indicator: parts.next().unwrap(),
+    }
+}
+
+// Derives the identifier of the bounded table from the identifier of the table and the bound
+fn bounded_table_id(table_id: &TableId, k: usize) -> TableId {
+    let mut transcript = Transcript::new(b"plookup_bounded_table");
+    transcript.append_message(b"table_id", &table_id.0);
+    transcript.append_u64(b"k", k as u64);
+
+    let mut digest = [0u8; 32];
+    transcript.challenge_bytes(b"digest", &mut digest);
+    TableId(digest)
+}
+
+/// Returns the number of times each row of the table is used by the queries,
+/// in the order that `LookUpTable::to_multiset` puts the rows in
+/// Returns `Error::MissingRows` with the indices of the queries which are not rows of the table, if there are any.
+pub fn multiplicities<T: LookUpTable + ?Sized>(
+    queries: &[Row],
+    table: &T,
+) -> Result<Vec<usize>, Error> {
+    let (t_1, t_2, t_3) = table.to_multiset();
+    let positions: HashMap<Row, usize> = (0..t_1.len())
+        .map(|i| ((t_1.0[i], t_2.0[i], t_3.0[i]), i))
+        .collect();
+
+    let mut multiplicities = vec![0; t_1.len()];
+    let mut missing = Vec::new();
+    for (i, query) in queries.iter().enumerate() {
+        match positions.get(query) {
+            Some(position) => multiplicities[*position] += 1,
+            None => missing.push(i),
+        }
+    }
+    if !missing.is_empty() {
+        return Err(Error::MissingRows(missing));
+    }
+    Ok(multiplicities)
+}
+
+/// Returns the commitments to the columns of the queries, which `BoundedUseProof::verify` checks a proof against
+/// Each column holds the queries at the first points of the domain of size `n`, followed by zeros.
+/// The commitments are not blinded, so anyone who knows the queries can recompute them.
+/// Panics if there are more than `n` queries.
+pub fn query_commitments(
+    queries: &[Row],
+    n: usize,
+    commit_key: &Powers<Bls12_381>,
+) -> [Commitment<Bls12_381>; 3] {
+    assert!(queries.len() <= n, "there are more than {} queries", n);
+    let domain: EvaluationDomain<Fr> = EvaluationDomain::new(n).unwrap();
+
+    let mut columns = vec![MultiSet::new(), MultiSet::new(), MultiSet::new()];
+    for (a, b, c) in queries.iter() {
+        columns[0].push(*a);
+        columns[1].push(*b);
+        columns[2].push(*c);
+    }
+    let polys: Vec<_> = columns
+        .into_iter()
+        .map(|mut column| {
+            column.extend(n - queries.len(), Fr::zero());
+            column.to_polynomial(&domain)
+        })
+        .collect();
+    let commitments = kzg10::commit_many(commit_key, polys.iter().collect());
+    [commitments[0], commitments[1], commitments[2]]
+}
+
+/// The evaluations of the polynomials in a `BoundedUseProof`
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct BoundedUseEvaluations {
+    pub w: Fr,
+    pub f: Fr,
+    pub t: Fr,
+    pub z: Fr,
+    pub q: Fr,
+    pub z_omega: Fr,
+}
+
+/// A proof that every query is a row of a table, and that no row is queried more times than the bounded table holds
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct BoundedUseProof {
+    /// The identifier of the bounded table that the proof was made against
+    pub table_id: TableId,
+    /// The commitment to the queries followed by the unused rows
+    pub w_commit: Commitment<Bls12_381>,
+    pub z_commit: Commitment<Bls12_381>,
+    pub q_commit: Commitment<Bls12_381>,
+    pub evaluations: BoundedUseEvaluations,
+    pub aggregate_witness_comm: Commitment<Bls12_381>,
+    pub shifted_witness_comm: Commitment<Bls12_381>,
+}
+
+impl BoundedUseProof {
+    /// Proves that every query is a row of `table`, and that no row is queried more than `k` times,
+    /// where `bounded` is `bounded_table(table, k, ..)`
+    ///
+    /// The proof is made against the commitments that `query_commitments` returns for the same queries and `n`.
+    ///
+    /// The prover absorbs the identifier of the bounded table, the digest of the SRS, the commitment to the indicator
+    /// column, the commitments to the queries and their number, draws `alpha` and absorbs it, then absorbs `w`,
+    /// draws `gamma`, absorbs `z`, draws `separation_challenge`, absorbs `q`, draws `evaluation_challenge` and
+    /// absorbs it, absorbs the evaluations in the order of `BoundedUseEvaluations` and draws `witness_aggregation`.
+    /// Returns `Error::MissingRows` for queries which are not rows of the table,
+    /// and `Error::Overused` for the queries which use a row after it has been used `k` times.
+    pub fn prove<T: LookUpTable + ?Sized, Tr: TranscriptProtocol + ?Sized>(
+        queries: &[Row],
+        table: &T,
+        bounded: &BoundedTable,
+        proving_key: &Powers<Bls12_381>,
+        transcript: &mut Tr,
+    ) -> Result<BoundedUseProof, Error> {
+        let indicator = &bounded.indicator;
+        let bounded_table = &bounded.table;
+        let n = bounded_table.n;
+        let config = ProverConfig::default();
+        kzg10::check_capacity_with_config(proving_key, n, &config)?;
+        multiplicities(queries, table)?;
+
+        let t_rows: Vec<Row> = (0..n)
+            .map(|i| {
+                (
+                    bounded_table.t_1.0 .0[i],
+                    bounded_table.t_2.0 .0[i],
+                    bounded_table.t_3.0 .0[i],
+                )
+            })
+            .collect();
+        let mut available: HashMap<Row, usize> = HashMap::new();
+        for row in t_rows.iter() {
+            *available.entry(*row).or_insert(0) += 1;
+        }
+        let overused: Vec<_> = queries
+            .iter()
+            .enumerate()
+            .filter_map(|(i, query)| {
+                let count = available.get_mut(query).unwrap();
+                if *count == 0 {
+                    Some(i)
+                } else {
+                    *count -= 1;
+                    None
+                }
+            })
+            .collect();
+        if !overused.is_empty() {
+            return Err(Error::Overused(overused));
+        }
+
+        // The queries, followed by the copies of each row which they did not use, with their indicators
+        let mut w_rows = queries.to_vec();
+        let mut w_indicator = vec![Fr::one(); queries.len()];
+        for (row, is_row) in t_rows.iter().zip(indicator.0 .0.iter()) {
+            let count = available.get_mut(row).unwrap();
+            if *count > 0 {
+                *count -= 1;
+                w_rows.push(*row);
+                w_indicator.push(*is_row);
+            }
+        }
+        assert_eq!(w_rows.len(), n);
+
+        let query_commits = query_commitments(queries, n, proving_key);
+        let alpha = draw_alpha(
+            &bounded.commitments(),
+            &query_commits,
+            queries.len(),
+            transcript,
+        );
+        let alpha_cubed = alpha * alpha * alpha;
+        let w = fold_indicated_rows(&w_rows, &w_indicator, alpha);
+        let t = fold_indicated_rows(&t_rows, &indicator.0 .0, alpha);
+        let domain: EvaluationDomain<Fr> = EvaluationDomain::new(n).unwrap();
+        // The folded queries, which are zero after the first m points as their columns are
+        let mut f = fold_rows(queries, alpha);
+        f.extend(n - queries.len(), Fr::zero());
+        let f_poly = f.to_polynomial(&domain);
+        let mut rng = transcript.build_prover_rng(&to_bytes![w.0].unwrap());
+
+        let w_poly = blind(
+            w.to_polynomial(&domain),
+            config.f_blinding_factors,
+            &domain,
+            &mut rng,
+        );
+        let t_poly = &*bounded_table.merged_interpolated(alpha).polynomial()
+            + &scale(&indicator.2, alpha_cubed);
+        let t_poly: &Polynomial<Fr> = &t_poly;
+        let w_commit = kzg10::commit(proving_key, &w_poly);
+        transcript.append_commitment(b"w", &w_commit);
+        let gamma = transcript.challenge_scalar(b"gamma");
+
+        // Z(g^{i+1}) = Z(g^i) (gamma + w_i) / (gamma + t_i)
+        let mut z_evaluations = Vec::with_capacity(n);
+        let mut z = Fr::one();
+        for i in 0..n {
+            z_evaluations.push(z);
+            z *= &((gamma + w.0[i]) / (gamma + t.0[i]));
+        }
+        assert_eq!(z, Fr::one());
+        let z_poly = blind(
+            Polynomial::from_coefficients_vec(domain.ifft(&z_evaluations)),
+            config.shifted_blinding_factors,
+            &domain,
+            &mut rng,
+        );
+        let z_commit = kzg10::commit(proving_key, &z_poly);
+        transcript.append_commitment(b"z", &z_commit);
+        let separation = transcript.challenge_scalar(b"separation_challenge");
+
+        let l_1 = quotient_poly::compute_n_lagrange_poly(&domain, 0);
+        let first_point = &l_1 * &add_constant(&z_poly, -Fr::one());
+        let z_shifted = shift(&z_poly, domain.group_gen);
+        let term = &(&z_shifted * &add_constant(t_poly, gamma))
+            - &(&z_poly * &add_constant(&w_poly, gamma));
+        let selector = first_points(&domain, queries.len());
+        let agreement = &selector * &add_constant(&(&w_poly - &f_poly), -alpha_cubed);
+        let numerator = &(&first_point + &scale(&term, separation))
+            + &scale(&agreement, separation * separation);
+        let (q_poly, remainder) = numerator.divide_by_vanishing_poly(domain).unwrap();
+        assert!(remainder.is_zero());
+        let q_commit = kzg10::commit(proving_key, &q_poly);
+        transcript.append_commitment(b"q", &q_commit);
+
+        let point = transcript.challenge_scalar(b"evaluation_challenge");
+        transcript.append_scalar(b"evaluation_challenge", &point);
+        let point_omega = point * domain.group_gen;
+        let evaluations = BoundedUseEvaluations {
+            w: w_poly.evaluate(point),
+            f: f_poly.evaluate(point),
+            t: t_poly.evaluate(point),
+            z: z_poly.evaluate(point),
+            q: q_poly.evaluate(point),
+            z_omega: z_poly.evaluate(point_omega),
+        };
+        absorb_evaluations(&evaluations, transcript);
+        let aggregation_challenge = transcript.challenge_scalar(b"witness_aggregation");
+
+        let aggregate_witness_comm = kzg10::open_aggregate(
+            proving_key,
+            vec![&w_poly, &f_poly, t_poly, &z_poly, &q_poly],
+            point,
+            aggregation_challenge,
+        );
+        let (_, shifted_witness_comm) = kzg10::open(proving_key, &z_poly, point_omega);
+
+        Ok(BoundedUseProof {
+            table_id: bounded_table.id,
+            w_commit,
+            z_commit,
+            q_commit,
+            evaluations,
+            aggregate_witness_comm,
+            shifted_witness_comm,
+        })
+    }
+    /// Verifies the proof against the commitments to a bounded table,
+    /// and the commitments to `num_queries` queries returned by `query_commitments`
+    pub fn verify<T: TranscriptProtocol + ?Sized>(
+        &self,
+        verification_key: &VerifierKey<Bls12_381>,
+        bounded: &BoundedTableCommitments,
+        query_commitments: &[Commitment<Bls12_381>; 3],
+        num_queries: usize,
+        transcript: &mut T,
+    ) -> bool {
+        let table = &bounded.table;
+        if self.table_id != table.id || num_queries > table.n {
+            return false;
+        }
+        let n = table.n;
+        let domain: EvaluationDomain<Fr> = EvaluationDomain::new(n).unwrap();
+
+        let alpha = draw_alpha(bounded, query_commitments, num_queries, transcript);
+        let t_commit = kzg10::aggregate_commitments(
+            vec![&table.t_1, &table.t_2, &table.t_3, &bounded.indicator],
+            alpha,
+        );
+        let f_commit = kzg10::aggregate_commitments(query_commitments.iter().collect(), alpha);
+        transcript.append_commitment(b"w", &self.w_commit);
+        let gamma = transcript.challenge_scalar(b"gamma");
+        transcript.append_commitment(b"z", &self.z_commit);
+        let separation = transcript.challenge_scalar(b"separation_challenge");
+        transcript.append_commitment(b"q", &self.q_commit);
+        let point = transcript.challenge_scalar(b"evaluation_challenge");
+        transcript.append_scalar(b"evaluation_challenge", &point);
+        absorb_evaluations(&self.evaluations, transcript);
+        let aggregation_challenge = transcript.challenge_scalar(b"witness_aggregation");
+
+        // Check the quotient identity at the evaluation challenge
+        let e = &self.evaluations;
+        let lagrange = domain.evaluate_all_lagrange_coefficients(point);
+        let first_point = lagrange[0] * (e.z - Fr::one());
+        let term = e.z_omega * (gamma + e.t) - e.z * (gamma + e.w);
+        let selector = lagrange[..num_queries]
+            .iter()
+            .fold(Fr::zero(), |sum, l| sum + l);
+        let agreement = selector * (e.w - e.f - alpha * alpha * alpha);
+        if first_point + separation * term + separation * separation * agreement
+            != e.q * domain.evaluate_vanishing_polynomial(point)
+        {
+            return false;
+        }
+
+        let commitments = vec![
+            &self.w_commit,
+            &f_commit,
+            &t_commit,
+            &self.z_commit,
+            &self.q_commit,
+        ];
+        let values = vec![&e.w, &e.f, &e.t, &e.z, &e.q];
+        let mut openings = kzg10::OpeningBatch::new();
+        openings.push(
+            kzg10::aggregate_commitments(commitments, aggregation_challenge),
+            self.aggregate_witness_comm,
+            point,
+            kzg10::aggregate_values(values, aggregation_challenge),
+        );
+        openings.push(
+            self.z_commit,
+            self.shifted_witness_comm,
+            point * domain.group_gen,
+            e.z_omega,
+        );
+        openings.check(verification_key)
+    }
+}
+
+fn draw_alpha<T: TranscriptProtocol + ?Sized>(
+    bounded: &BoundedTableCommitments,
+    query_commitments: &[Commitment<Bls12_381>; 3],
+    num_queries: usize,
+    transcript: &mut T,
+) -> Fr {
+    transcript.append_table_id(b"table_id", &bounded.table.id);
+    transcript.append_message(b"srs_digest", &bounded.table.srs_digest);
+    transcript.append_commitment(b"indicator", &bounded.indicator);
+    transcript.append_commitment(b"f_1", &query_commitments[0]);
+    transcript.append_commitment(b"f_2", &query_commitments[1]);
+    transcript.append_commitment(b"f_3", &query_commitments[2]);
+    transcript.append_scalar(b"num_queries", &Fr::from(num_queries as u64));
+    let alpha = transcript.challenge_scalar(b"alpha");
+    transcript.append_scalar(b"alpha", &alpha);
+    alpha
+}
+
+fn absorb_evaluations<T: TranscriptProtocol + ?Sized>(
+    evaluations: &BoundedUseEvaluations,
+    transcript: &mut T,
+) {
+    transcript.append_scalar(b"w_eval", &evaluations.w);
+    transcript.append_scalar(b"f_eval", &evaluations.f);
+    transcript.append_scalar(b"t_eval", &evaluations.t);
+    transcript.append_scalar(b"z_eval", &evaluations.z);
+    transcript.append_scalar(b"q_eval", &evaluations.q);
+    transcript.append_scalar(b"z_omega_eval", &evaluations.z_omega);
+}
+
+// Folds each row (a, b, c) into a + alpha * b + alpha^2 * c, as a lookup folds its table
+fn fold_rows(rows: &[Row], alpha: Fr) -> MultiSet {
+    let alpha_sq = alpha * alpha;
+    rows.iter()
+        .map(|(a, b, c)| *a + alpha * b + alpha_sq * c)
+        .collect()
+}
+
+// Folds each row with its indicator s into a + alpha * b + alpha^2 * c + alpha^3 * s, as the bounded table is folded
+fn fold_indicated_rows(rows: &[Row], indicator: &[Fr], alpha: Fr) -> MultiSet {
+    let alpha_cubed = alpha * alpha * alpha;
+    fold_rows(rows, alpha)
+        .0
+        .iter()
+        .zip(indicator.iter())
+        .map(|(folded, is_row)| *folded + alpha_cubed * is_row)
+        .collect()
+}
+
+// Returns the polynomial which is one on the first `m` points of the domain and zero on the others
+fn first_points(domain: &EvaluationDomain<Fr>, m: usize) -> Polynomial<Fr> {
+    let mut evaluations = vec![Fr::one(); m];
+    evaluations.resize(domain.size(), Fr::zero());
+    Polynomial::from_coefficients_vec(domain.ifft(&evaluations))
+}
+
+fn add_constant(poly: &Polynomial<Fr>, constant: Fr) -> Polynomial<Fr> {
+    let mut coeffs = poly.coeffs.clone();
+    if coeffs.is_empty() {
+        coeffs.push(Fr::zero());
+    }
+    coeffs[0] += &constant;
+    Polynomial::from_coefficients_vec(coeffs)
+}
+
+fn scale(poly: &Polynomial<Fr>, scalar: Fr) -> Polynomial<Fr> {
+    Polynomial::from_coefficients_vec(poly.coeffs.iter().map(|c| *c * scalar).collect())
+}
+
+// Returns p(Xg) for the generator g of the domain
+fn shift(poly: &Polynomial<Fr>, group_gen: Fr) -> Polynomial<Fr> {
+    let mut power = Fr::one();
+    let coeffs = poly
+        .coeffs
+        .iter()
+        .map(|c| {
+            let shifted = *c * power;
+            power *= &group_gen;
+            shifted
+        })
+        .collect();
+    Polynomial::from_coefficients_vec(coeffs)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::lookup::table::Generic;
+
+    #[test]
+    fn test_bounded_use() {
+        let (proving_key, verifier_key) = kzg10::trusted_setup(2usize.pow(8), b"insecure_seed");
+
+        // Four tickets, each of which may be spent twice
+        let tickets: HashMap<_, _> = (1..=4u8)
+            .map(|i| ((Fr::from(i), Fr::zero()), Fr::from(10 * i)))
+            .collect();
+        let table = Generic::with_hashmap(tickets);
+        let bounded = bounded_table(&table, 2, &proving_key, 16);
+        let ticket = |i: u8| (Fr::from(i), Fr::zero(), Fr::from(10 * i));
+
+        let queries = vec![ticket(1), ticket(3), ticket(1)];
+        assert_eq!(multiplicities(&queries, &table).unwrap(), vec![2, 0, 1, 0]);
+        let mut prover_transcript = Transcript::new(b"bounded");
+        let proof = BoundedUseProof::prove(
+            &queries,
+            &table,
+            &bounded,
+            &proving_key,
+            &mut prover_transcript,
+        )
+        .unwrap();
+        let query_commits = query_commitments(&queries, 16, &proving_key);
+        let mut verifier_transcript = Transcript::new(b"bounded");
+        assert!(proof.verify(
+            &verifier_key,
+            &bounded.commitments(),
+            &query_commits,
+            queries.len(),
+            &mut verifier_transcript
+        ));
+
+        // A table with a different bound has a different identifier
+        let loose = bounded_table(&table, 3, &proving_key, 16);
+        let mut verifier_transcript = Transcript::new(b"bounded");
+        assert!(!proof.verify(
+            &verifier_key,
+            &loose.commitments(),
+            &query_commits,
+            queries.len(),
+            &mut verifier_transcript
+        ));
+
+        // The proof does not verify against queries which spend a ticket three times,
+        // nor against fewer of the queries it was made for
+        let overused = vec![ticket(1), ticket(1), ticket(1)];
+        let mut verifier_transcript = Transcript::new(b"bounded");
+        assert!(!proof.verify(
+            &verifier_key,
+            &bounded.commitments(),
+            &query_commitments(&overused, 16, &proving_key),
+            overused.len(),
+            &mut verifier_transcript
+        ));
+        let mut verifier_transcript = Transcript::new(b"bounded");
+        assert!(!proof.verify(
+            &verifier_key,
+            &bounded.commitments(),
+            &query_commitments(&queries[..2], 16, &proving_key),
+            2,
+            &mut verifier_transcript
+        ));
+
+        // The third spend of a ticket is rejected, as is a ticket which is not in the table
+        let mut prover_transcript = Transcript::new(b"bounded");
+        let queries = vec![ticket(1), ticket(1), ticket(2), ticket(1)];
+        match BoundedUseProof::prove(
+            &queries,
+            &table,
+            &bounded,
+            &proving_key,
+            &mut prover_transcript,
+        ) {
+            Err(Error::Overused(indices)) => assert_eq!(indices, vec![3]),
+            _ => panic!("expected the fourth query to overuse its row"),
+        }
+        let mut prover_transcript = Transcript::new(b"bounded");
+        let queries = vec![ticket(1), ticket(5)];
+        match BoundedUseProof::prove(
+            &queries,
+            &table,
+            &bounded,
+            &proving_key,
+            &mut prover_transcript,
+        ) {
+            Err(Error::MissingRows(indices)) => assert_eq!(indices, vec![1]),
+            _ => panic!("expected the second query to be missing from the table"),
+        }
+    }
+
+    #[test]
+    fn test_padding_row_cannot_be_queried() {
+        let (proving_key, _) = kzg10::trusted_setup(2usize.pow(8), b"insecure_seed");
+        let tickets: HashMap<_, _> = (1..=4u8)
+            .map(|i| ((Fr::from(i), Fr::zero()), Fr::from(10 * i)))
+            .collect();
+        let table = Generic::with_hashmap(tickets);
+        let bounded = bounded_table(&table, 2, &proving_key, 16);
+        let t_rows: Vec<Row> = (0..16)
+            .map(|i| {
+                (
+                    bounded.table.t_1.0 .0[i],
+                    bounded.table.t_2.0 .0[i],
+                    bounded.table.t_3.0 .0[i],
+                )
+            })
+            .collect();
+        let indicator = &bounded.indicator.0 .0;
+        let padding = t_rows[15];
+        assert!(!table.contains(&(padding.0, padding.1)));
+        assert_eq!(indicator[7], Fr::one());
+        assert_eq!(indicator[8], Fr::zero());
+
+        // A prover which queries the padding row puts it among the queries, with the indicator of a query,
+        // in place of one of its copies among the unused rows
+        let queries = vec![t_rows[0], padding];
+        let mut w_rows = queries.clone();
+        w_rows.extend_from_slice(&t_rows[1..15]);
+        let mut w_indicator = vec![Fr::one(); queries.len()];
+        w_indicator.extend_from_slice(&indicator[1..15]);
+
+        // Without the indicator, w would be a rearrangement of the bounded table and the grand product would close
+        let alpha = Fr::from(7u8);
+        assert_eq!(
+            fold_rows(&w_rows, alpha).counts(),
+            fold_rows(&t_rows, alpha).counts()
+        );
+        // With it, the queried padding row does not match the padding of the table
+        assert_ne!(
+            fold_indicated_rows(&w_rows, &w_indicator, alpha).counts(),
+            fold_indicated_rows(&t_rows, indicator, alpha).counts()
+        );
+
+        // The prover rejects the query, as the padding row is not in the table
+        let mut prover_transcript = Transcript::new(b"bounded");
+        match BoundedUseProof::prove(
+            &queries,
+            &table,
+            &bounded,
+            &proving_key,
+            &mut prover_transcript,
+        ) {
+            Err(Error::MissingRows(indices)) => assert_eq!(indices, vec![1]),
+            _ => panic!("expected the padding row to be missing from the table"),
+        }
+    }
+}
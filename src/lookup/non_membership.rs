@@ -0,0 +1,363 @@
+// Proving that keys are not in a table
+//
+// A lookup can only show that rows are in a table. To show that a key is not, the prover shows that it falls
+// between two keys which are adjacent in the table. `gap_table` derives a table of the adjacent pairs of keys of a
+// table, with a row before its first key and a row after its last. The verifier holds the commitments to the gap
+// table, as it would hold those of any other table, and trusts them as it trusts the table they are derived from.
+//
+// The excluded keys and their neighbours are public. The verifier checks in the clear that each key falls strictly
+// between its neighbours, and the proof shows that each pair of neighbours is a row of the gap table.
+// The prover commits to the folded neighbours without blinding, and after the multiset equality proof opens the
+// commitment at a point `zeta`, where the verifier interpolates the neighbours itself to check the opening.
+//
+// Keys are pairs of inputs which are integers less than 2^64, ordered lexicographically.
+// In the gap table each key (a, b) is encoded as the field element a * 2^64 + b.
+use super::{
+    proof::merge_witness,
+    table::{Generic, LookUpTable, PreProcessedTable, TableCommitments, TableId},
+};
+use crate::{
+    encoding::fr_to_u64,
+    error::Error,
+    kzg10,
+    multiset::{EqualityProof, MultiSet, ProverConfig, PublicParameters},
+    transcript::TranscriptProtocol,
+};
+use algebra::{bls12_381::Fr, Bls12_381};
+use ff_fft::EvaluationDomain;
+use num_traits::identities::Zero;
+use poly_commit::kzg10::{Commitment, Powers, VerifierKey};
+use std::collections::HashMap;
+
+/// A key of a table, whose inputs are integers
+pub type Key = (u64, u64);
+
+/// The keys of a table on either side of a key which is not in it
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Neighbours {
+    /// The table has no rows
+    Empty,
+    /// The key is less than every key in the table, the least of which is `next`
+    Below { next: Key },
+    /// The key is between `prev` and `next`, which are adjacent in the table
+    Between { prev: Key, next: Key },
+    /// The key is greater than every key in the table, the greatest of which is `prev`
+    Above { prev: Key },
+}
+
+impl Neighbours {
+    /// Returns true if `key` falls strictly between the neighbours
+    pub fn excludes(&self, key: Key) -> bool {
+        match *self {
+            Neighbours::Empty => true,
+            Neighbours::Below { next } => key < next,
+            Neighbours::Between { prev, next } => prev < key && key < next,
+            Neighbours::Above { prev } => prev < key,
+        }
+    }
+    // Returns the row of the gap table for the neighbours
+    // The output tells the kinds of row apart, so that the sides of the table need no sentinel keys.
+    fn row(&self) -> (Fr, Fr, Fr) {
+        match *self {
+            Neighbours::Between { prev, next } => (encode(prev), encode(next), Fr::from(0u8)),
+            Neighbours::Below { next } => (Fr::zero(), encode(next), Fr::from(1u8)),
+            Neighbours::Above { prev } => (encode(prev), Fr::zero(), Fr::from(2u8)),
+            Neighbours::Empty => (Fr::zero(), Fr::zero(), Fr::from(3u8)),
+        }
+    }
+}
+
+// Encodes the key (a, b) as a * 2^64 + b
+fn encode(key: Key) -> Fr {
+    let shift = Fr::from(u64::max_value()) + Fr::from(1u8);
+    Fr::from(key.0) * shift + Fr::from(key.1)
+}
+
+/// Returns the keys of the table in order
+/// Returns the indices of the rows whose inputs are not both less than 2^64, if there are any.
+pub fn sorted_keys<T: LookUpTable + ?Sized>(table: &T) -> Result<Vec<Key>, Error> {
+    let mut keys = Vec::with_capacity(table.len());
+    let mut not_integers = Vec::new();
+    for (i, (key, _)) in table.iter().enumerate() {
+        match (fr_to_u64(&key.0), fr_to_u64(&key.1)) {
+            (Some(a), Some(b)) => keys.push((a, b)),
+            _ => not_integers.push(i),
+        }
+    }
+    if !not_integers.is_empty() {
+        return Err(Error::OutOfRange(not_integers));
+    }
+    keys.sort_unstable();
+    Ok(keys)
+}
+
+/// Returns the table of the pairs of adjacent keys in `table`, with a row before the first key and after the last
+/// The gap table has one more row than `table`.
+pub fn gap_table<T: LookUpTable + ?Sized>(table: &T) -> Result<Generic, Error> {
+    let keys = sorted_keys(table)?;
+    let rows: HashMap<_, _> = gaps(&keys)
+        .iter()
+        .map(|neighbours| {
+            let (prev, next, kind) = neighbours.row();
+            ((prev, next), kind)
+        })
+        .collect();
+    Ok(Generic::with_hashmap(rows))
+}
+
+// Returns the neighbours of every gap between the sorted keys
+fn gaps(keys: &[Key]) -> Vec<Neighbours> {
+    if keys.is_empty() {
+        return vec![Neighbours::Empty];
+    }
+    let mut gaps = Vec::with_capacity(keys.len() + 1);
+    gaps.push(Neighbours::Below { next: keys[0] });
+    for pair in keys.windows(2) {
+        gaps.push(Neighbours::Between {
+            prev: pair[0],
+            next: pair[1],
+        });
+    }
+    gaps.push(Neighbours::Above {
+        prev: keys[keys.len() - 1],
+    });
+    gaps
+}
+
+/// A proof that keys are not in a table
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct NonMembershipProof {
+    /// The identifier of the gap table that the proof was made against
+    pub gap_table_id: TableId,
+    /// The neighbours of each key, in the order of the keys
+    pub neighbours: Vec<Neighbours>,
+    /// The commitment to the folded neighbours
+    pub f_commit: Commitment<Bls12_381>,
+    pub multiset_equality_proof: EqualityProof,
+    /// The opening witness for the folded neighbours at zeta
+    pub f_witness: Commitment<Bls12_381>,
+}
+
+impl NonMembershipProof {
+    /// Proves that none of `keys` is in `table`, against the preprocessed `gap_table(table)`
+    ///
+    /// The prover absorbs the public parameters of the equality proof and the identifier of the gap table,
+    /// draws `alpha` and absorbs it. The rest of the transcript, from the commitment to the folded neighbours,
+    /// is documented on `EqualityProof::prove`, after which the challenge `non_membership_point` is drawn as zeta.
+    /// Returns `Error::ContainedKeys` with the indices of the keys which are in the table, if there are any.
+    /// Panics if `keys` is empty.
+    pub fn prove<T: LookUpTable + ?Sized, Tr: TranscriptProtocol + ?Sized>(
+        keys: &[Key],
+        table: &T,
+        preprocessed_gaps: &PreProcessedTable,
+        proving_key: &Powers<Bls12_381>,
+        transcript: &mut Tr,
+    ) -> Result<NonMembershipProof, Error> {
+        assert!(!keys.is_empty());
+        let sorted = sorted_keys(table)?;
+        let contained: Vec<_> = (0..keys.len())
+            .filter(|&i| sorted.binary_search(&keys[i]).is_ok())
+            .collect();
+        if !contained.is_empty() {
+            return Err(Error::ContainedKeys(contained));
+        }
+        let gaps = gaps(&sorted);
+        let neighbours: Vec<_> = keys
+            .iter()
+            .map(|key| gaps[sorted.binary_search(key).unwrap_err()])
+            .collect();
+
+        let n = preprocessed_gaps.n;
+        let config = ProverConfig::default();
+        kzg10::check_capacity_with_config(proving_key, n, &config)?;
+
+        PublicParameters::new(n, &config, true).absorb(transcript);
+        let alpha = draw_alpha(&preprocessed_gaps.commitments(), transcript);
+        let f = fold_neighbours(&neighbours, alpha, n);
+        let domain: EvaluationDomain<Fr> = EvaluationDomain::new(n).unwrap();
+        let f_poly = f.to_polynomial(&domain);
+        let f_commit = kzg10::commit(proving_key, &f_poly);
+
+        let multiset_equality_proof = EqualityProof::prove_committed(
+            f,
+            f_poly.clone(),
+            f_commit,
+            preprocessed_gaps.merged(alpha),
+            proving_key,
+            &preprocessed_gaps.quotient_key,
+            &config,
+            transcript,
+        )?;
+
+        let zeta = transcript.challenge_scalar(b"non_membership_point");
+        let (_, f_witness) = kzg10::open(proving_key, &f_poly, zeta);
+
+        Ok(NonMembershipProof {
+            gap_table_id: preprocessed_gaps.id,
+            neighbours,
+            f_commit,
+            multiset_equality_proof,
+            f_witness,
+        })
+    }
+    /// Verifies that none of `keys` is in the table whose gap table has the commitments `gap_commitments`
+    pub fn verify<T: TranscriptProtocol + ?Sized>(
+        &self,
+        keys: &[Key],
+        verification_key: &VerifierKey<Bls12_381>,
+        gap_commitments: &TableCommitments,
+        transcript: &mut T,
+    ) -> bool {
+        if self.gap_table_id != gap_commitments.id || keys.len() != self.neighbours.len() {
+            return false;
+        }
+        if !keys
+            .iter()
+            .zip(self.neighbours.iter())
+            .all(|(key, neighbours)| neighbours.excludes(*key))
+        {
+            return false;
+        }
+        // The equality proof must be for the committed neighbours
+        if self.multiset_equality_proof.commitments.f != self.f_commit {
+            return false;
+        }
+        let n = gap_commitments.n;
+        if self.neighbours.is_empty() || self.neighbours.len() >= n {
+            return false;
+        }
+
+        let parameters = PublicParameters::new(n, &ProverConfig::default(), true);
+        if !self
+            .multiset_equality_proof
+            .absorb_parameters(&parameters, transcript)
+        {
+            return false;
+        }
+        let alpha = draw_alpha(gap_commitments, transcript);
+        let t_commit = kzg10::aggregate_commitments(
+            vec![
+                &gap_commitments.t_1,
+                &gap_commitments.t_2,
+                &gap_commitments.t_3,
+            ],
+            alpha,
+        );
+        let mut openings = self
+            .multiset_equality_proof
+            .openings(n, t_commit, transcript);
+
+        // The verifier evaluates the folded neighbours at zeta itself
+        let zeta = transcript.challenge_scalar(b"non_membership_point");
+        let domain: EvaluationDomain<Fr> = EvaluationDomain::new(n).unwrap();
+        let f = fold_neighbours(&self.neighbours, alpha, n);
+        let f_eval =
+            f.0.iter()
+                .zip(domain.evaluate_all_lagrange_coefficients(zeta))
+                .map(|(value, lagrange)| *value * lagrange)
+                .fold(Fr::zero(), |sum, term| sum + term);
+        openings.push(self.f_commit, self.f_witness, zeta, f_eval);
+        openings.check(verification_key)
+    }
+}
+
+fn draw_alpha<T: TranscriptProtocol + ?Sized>(
+    gap_commitments: &TableCommitments,
+    transcript: &mut T,
+) -> Fr {
+    transcript.append_table_id(b"table_id", &gap_commitments.id);
+    transcript.append_message(b"srs_digest", &gap_commitments.srs_digest);
+    let alpha = transcript.challenge_scalar(b"alpha");
+    transcript.append_scalar(b"alpha", &alpha);
+    alpha
+}
+
+// Folds the rows of the gap table for the neighbours with `alpha`, padded as a lookup's witness is
+fn fold_neighbours(neighbours: &[Neighbours], alpha: Fr, n: usize) -> MultiSet {
+    let mut f_1 = MultiSet::new();
+    let mut f_2 = MultiSet::new();
+    let mut f_3 = MultiSet::new();
+    for (prev, next, kind) in neighbours.iter().map(Neighbours::row) {
+        f_1.push(prev);
+        f_2.push(next);
+        f_3.push(kind);
+    }
+    merge_witness(&f_1, &f_2, &f_3, alpha, n)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use merlin::Transcript;
+
+    #[test]
+    fn test_non_membership() {
+        let (proving_key, verifier_key) = kzg10::trusted_setup(2usize.pow(8), b"insecure_seed");
+
+        // A blacklist of values, each the key (value, 0)
+        let blacklist: HashMap<_, _> = [5u64, 10, 20]
+            .iter()
+            .map(|value| ((Fr::from(*value), Fr::zero()), Fr::from(1u8)))
+            .collect();
+        let table = Generic::with_hashmap(blacklist);
+        let gaps = gap_table(&table).unwrap();
+        assert_eq!(gaps.len(), 4);
+        let preprocessed_gaps = gaps.preprocess(&proving_key, 2usize.pow(4));
+
+        let keys = vec![(7, 0), (25, 0), (0, 0), (10, 1)];
+        let mut prover_transcript = Transcript::new(b"non_membership");
+        let proof = NonMembershipProof::prove(
+            &keys,
+            &table,
+            &preprocessed_gaps,
+            &proving_key,
+            &mut prover_transcript,
+        )
+        .unwrap();
+        assert_eq!(
+            proof.neighbours[0],
+            Neighbours::Between {
+                prev: (5, 0),
+                next: (10, 0)
+            }
+        );
+        assert_eq!(proof.neighbours[2], Neighbours::Below { next: (5, 0) });
+
+        let commitments = preprocessed_gaps.commitments();
+        let mut verifier_transcript = Transcript::new(b"non_membership");
+        assert!(proof.verify(&keys, &verifier_key, &commitments, &mut verifier_transcript));
+
+        // The proof does not show that other keys are excluded
+        let mut other_keys = keys.clone();
+        other_keys[0] = (5, 0);
+        let mut verifier_transcript = Transcript::new(b"non_membership");
+        assert!(!proof.verify(
+            &other_keys,
+            &verifier_key,
+            &commitments,
+            &mut verifier_transcript
+        ));
+
+        // Claiming neighbours which are not adjacent in the table fails the lookup
+        let mut forged = proof.clone();
+        forged.neighbours[0] = Neighbours::Between {
+            prev: (5, 0),
+            next: (20, 0),
+        };
+        let mut verifier_transcript = Transcript::new(b"non_membership");
+        assert!(!forged.verify(&keys, &verifier_key, &commitments, &mut verifier_transcript));
+
+        // A key in the table cannot be excluded
+        let mut prover_transcript = Transcript::new(b"non_membership");
+        match NonMembershipProof::prove(
+            &[(7, 0), (20, 0)],
+            &table,
+            &preprocessed_gaps,
+            &proving_key,
+            &mut prover_transcript,
+        ) {
+            Err(Error::ContainedKeys(indices)) => assert_eq!(indices, vec![1]),
+            _ => panic!("expected the second key to be in the table"),
+        }
+    }
+}
@@ -1,81 +1,772 @@
-use super::table::PreProcessedTable;
+use super::{
+    context::ProofContext,
+    table::{PreProcessedTable, PreparedTableCommitments, TableCommitments, TableId},
+};
 use crate::{
-    kzg10,
-    multiset::{EqualityProof, MultiSet},
+    error::Error,
+    kzg10::{self, CommitKey, OpeningBatch, PreparedVerifierKey},
+    multiset::{
+        quotient_poly::QuotientKey, EqualityProof, Interpolated, MultiSet, ProverConfig,
+        ProverScratch, ProverStage, ProverState, PublicParameters, ScratchBuffers,
+    },
     transcript::TranscriptProtocol,
 };
-use algebra::Bls12_381;
-use poly_commit::kzg10::{Powers, VerifierKey};
+use algebra::{
+    bls12_381::{Fr, G1Affine},
+    io::{Read, Write},
+    Bls12_381, CanonicalDeserialize, CanonicalSerialize, SerializationError,
+};
+use poly_commit::kzg10::{Commitment, Powers, VerifierKey};
+
+/// Whether a proof carries the commitments to the table it was made against
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ProofMode {
+    /// The verifier already holds the table commitments
+    Slim,
+    /// The proof includes the table commitments, which the verifier checks against a list of allowed tables
+    SelfContained,
+}
+
+/// The options of a lookup prover, see `LookUpProof::prove_with_options`
+/// Any of the options can be combined. The default options make a slim proof with the default `ProverConfig`
+/// and without a context, running every stage of the prover without stopping.
+pub struct ProveOptions<'a> {
+    mode: ProofMode,
+    config: ProverConfig,
+    context: ProofContext,
+    checkpoint: Option<&'a mut dyn FnMut(ProverStage) -> Result<(), Error>>,
+    scratch: Option<&'a mut ProverScratch>,
+    state: Option<(
+        &'a mut ProverState,
+        &'a mut dyn FnMut(&ProverState) -> Result<(), Error>,
+    )>,
+}
+
+impl<'a> Default for ProveOptions<'a> {
+    fn default() -> Self {
+        ProveOptions {
+            mode: ProofMode::Slim,
+            config: ProverConfig::default(),
+            context: ProofContext::default(),
+            checkpoint: None,
+            scratch: None,
+            state: None,
+        }
+    }
+}
+
+impl<'a> ProveOptions<'a> {
+    pub fn new() -> ProveOptions<'a> {
+        ProveOptions::default()
+    }
+    /// Sets whether the proof carries the table commitments
+    pub fn mode(mut self, mode: ProofMode) -> Self {
+        self.mode = mode;
+        self
+    }
+    /// Blinds the polynomials and runs the MSMs as set in `config`
+    /// The verifier must pass the same config to `LookUpProof::verify_with_config`.
+    pub fn config(mut self, config: &ProverConfig) -> Self {
+        self.config = *config;
+        self
+    }
+    /// Binds the proof to the application and the session in `context`
+    /// The context is absorbed before anything else and carried by the proof,
+    /// which only verifies with `verify_in_context` for the same context.
+    pub fn context(mut self, context: &ProofContext) -> Self {
+        self.context = context.clone();
+        self
+    }
+    /// Calls `checkpoint` before each stage of the prover
+    /// If `checkpoint` returns an error, the prover stops and returns that error.
+    /// Passing `|stage| token.check(stage)` for a `CancellationToken` lets the proof be cancelled.
+    pub fn checkpoint(
+        mut self,
+        checkpoint: &'a mut dyn FnMut(ProverStage) -> Result<(), Error>,
+    ) -> Self {
+        self.checkpoint = Some(checkpoint);
+        self
+    }
+    /// Reuses the memory that `scratch` kept from earlier proofs
+    /// Passing the same scratch to consecutive proofs over the same domain avoids allocating
+    /// the evaluations over the extended domain for each one.
+    pub fn scratch(mut self, scratch: &'a mut ProverScratch) -> Self {
+        self.scratch = Some(scratch);
+        self
+    }
+    /// Skips the stages of the multiset equality proof which have already completed in `state`,
+    /// and calls `save` with the state as each stage completes
+    /// See `EqualityProof::prove_resumable`. The witness and the preprocessed table are not part of the state,
+    /// and can be saved with `MultiSet` serialisation and `PreProcessedTable::save`.
+    pub fn resume(
+        mut self,
+        state: &'a mut ProverState,
+        save: &'a mut dyn FnMut(&ProverState) -> Result<(), Error>,
+    ) -> Self {
+        self.state = Some((state, save));
+        self
+    }
+}
+
+/// A proof that a witness is contained in a table
+/// Proofs only contain public values, so comparing them does not need to be constant time.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
 pub struct LookUpProof {
+    /// The identifier of the table that the proof was made against
+    pub table_id: TableId,
+    /// The commitments to the table, for self-contained proofs
+    pub table_commitments: Option<TableCommitments>,
     pub multiset_equality_proof: EqualityProof,
+    /// The application and the session that the proof was made for
+    pub context: ProofContext,
 }
 
 impl LookUpProof {
-    pub fn prove(
+    /// Proves that every row (f_1, f_2, f_3) of the witness is a row of the table
+    /// The proof does not carry the table commitments, see `prove_with_mode`
+    pub fn prove<T: TranscriptProtocol + ?Sized>(
         f_1: &MultiSet,
         f_2: &MultiSet,
         f_3: &MultiSet,
         proving_key: &Powers<Bls12_381>,
         preprocessed_table: &PreProcessedTable,
-        transcript: &mut dyn TranscriptProtocol,
+        transcript: &mut T,
     ) -> LookUpProof {
+        LookUpProof::prove_with_mode(
+            f_1,
+            f_2,
+            f_3,
+            proving_key,
+            preprocessed_table,
+            ProofMode::Slim,
+            transcript,
+        )
+    }
+    /// Proves that every row (f_1, f_2, f_3) of the witness is a row of the table
+    /// Panics if the proving key is too small for the table, see `prove_with_options` for a prover which returns
+    /// the error.
+    pub fn prove_with_mode<T: TranscriptProtocol + ?Sized>(
+        f_1: &MultiSet,
+        f_2: &MultiSet,
+        f_3: &MultiSet,
+        proving_key: &Powers<Bls12_381>,
+        preprocessed_table: &PreProcessedTable,
+        mode: ProofMode,
+        transcript: &mut T,
+    ) -> LookUpProof {
+        LookUpProof::prove_with_options(
+            f_1,
+            f_2,
+            f_3,
+            proving_key,
+            preprocessed_table,
+            ProveOptions::new().mode(mode),
+            transcript,
+        )
+        .unwrap_or_else(|err| panic!("prover failed: {}", err))
+    }
+    /// Proves that every row (f_1, f_2, f_3) of the witness is a row of the table, as set in `options`
+    ///
+    /// Before the multiset equality proof, the prover absorbs the context of the proof,
+    /// the public parameters of the equality proof, the identifier of the table and the digest of its SRS,
+    /// then draws the challenge `alpha` and absorbs it as a scalar.
+    /// `alpha` is used to fold the columns into one multiset.
+    /// The rest of the transcript is documented on `EqualityProof::prove`.
+    /// The transcript is the same in both modes, and does not depend on the checkpoint, the scratch or the state.
+    /// The proving key can be any `CommitKey`, such as a `MappedPowers` read from disk.
+    /// Returns `Error::SrsTooSmall` if the proving key is too small for the config, an error if the MSM settings
+    /// in the config are invalid, the error of the checkpoint or of the save callback if they fail,
+    /// or `Error::AuditFailed` if the proof fails the prover's own audit.
+    pub fn prove_with_options<K: CommitKey + ?Sized, T: TranscriptProtocol + ?Sized>(
+        f_1: &MultiSet,
+        f_2: &MultiSet,
+        f_3: &MultiSet,
+        proving_key: &K,
+        preprocessed_table: &PreProcessedTable,
+        options: ProveOptions,
+        transcript: &mut T,
+    ) -> Result<LookUpProof, Error> {
+        let ProveOptions {
+            mode,
+            config,
+            context,
+            checkpoint,
+            scratch,
+            state,
+        } = options;
+
+        // Options which were not set fall back to a prover which never stops, saves nothing and starts afresh
+        let mut no_checkpoint = |_: ProverStage| -> Result<(), Error> { Ok(()) };
+        let mut no_save = |_: &ProverState| -> Result<(), Error> { Ok(()) };
+        let mut new_state = ProverState::new();
+        let mut new_scratch = ScratchBuffers::new();
+        let checkpoint: &mut dyn FnMut(ProverStage) -> Result<(), Error> = match checkpoint {
+            Some(checkpoint) => checkpoint,
+            None => &mut no_checkpoint,
+        };
+        let (state, save): (
+            &mut ProverState,
+            &mut dyn FnMut(&ProverState) -> Result<(), Error>,
+        ) = match state {
+            Some((state, save)) => (state, save),
+            None => (&mut new_state, &mut no_save),
+        };
+        let scratch = match scratch {
+            Some(scratch) => &mut scratch.scratch,
+            None => &mut new_scratch,
+        };
+
+        kzg10::check_capacity_with_config(proving_key, preprocessed_table.n, &config)?;
+        checkpoint(ProverStage::Aggregation)?;
+
+        LookUpProof::prove_aggregated(
+            f_1,
+            f_2,
+            f_3,
+            preprocessed_table,
+            mode,
+            &config,
+            &context,
+            transcript,
+            |merged_witness, merged_table, transcript| {
+                EqualityProof::prove_inner(
+                    merged_witness,
+                    None,
+                    merged_table,
+                    proving_key,
+                    &preprocessed_table.quotient_key,
+                    &config,
+                    checkpoint,
+                    state,
+                    save,
+                    scratch,
+                    transcript,
+                )
+            },
+        )
+    }
+    // Folds the witness and the table into one multiset each, and proves the multiset equality with `prove`
+    fn prove_aggregated<T, F>(
+        f_1: &MultiSet,
+        f_2: &MultiSet,
+        f_3: &MultiSet,
+        preprocessed_table: &PreProcessedTable,
+        mode: ProofMode,
+        config: &ProverConfig,
+        context: &ProofContext,
+        transcript: &mut T,
+        prove: F,
+    ) -> Result<LookUpProof, Error>
+    where
+        T: TranscriptProtocol + ?Sized,
+        F: FnOnce(MultiSet, &Interpolated, &mut T) -> Result<EqualityProof, Error>,
+    {
+        // Bind the proof to the application and the session, then to the public parameters, before any challenge
+        transcript.append_context(b"context", context);
+        PublicParameters::new(preprocessed_table.n, config, false).absorb(transcript);
+
+        // Bind the proof to the table and to the SRS it was committed with
+        transcript.append_table_id(b"table_id", &preprocessed_table.id);
+        transcript.append_message(b"srs_digest", &preprocessed_table.srs_digest);
+
         // Generate alpha challenge
         let alpha = transcript.challenge_scalar(b"alpha");
         transcript.append_scalar(b"alpha", &alpha);
 
         // Aggregates the table and witness values into one multiset
         // and pads the witness to be the correct size
-        //
-        // Aggregate our table values into one multiset
-        let merged_table = MultiSet::aggregate(
-            vec![
-                &preprocessed_table.t_1.0,
-                &preprocessed_table.t_2.0,
-                &preprocessed_table.t_3.0,
-            ],
-            alpha,
-        );
-
-        // Aggregate witness values into one multiset
-        let mut merged_witness = MultiSet::aggregate(vec![f_1, f_2, f_3], alpha);
-
-        // Pad merged Witness to be one less than `n`
-        assert!(merged_witness.len() < preprocessed_table.n);
-        let pad_by = preprocessed_table.n - 1 - merged_witness.len();
-        merged_witness.extend(pad_by, merged_witness.last());
+        // t(X) is combined from the interpolated columns of the table, rather than interpolated for each proof
+        let merged_table = preprocessed_table.merged_interpolated(alpha);
+        let merged_witness = merge_witness(f_1, f_2, f_3, alpha, preprocessed_table.n);
 
         // Create a Multi-set equality proof
-        let multiset_equality_proof =
-            EqualityProof::prove(merged_witness, merged_table, proving_key, transcript);
+        let multiset_equality_proof = prove(merged_witness, &merged_table, transcript)?;
+
+        let table_commitments = match mode {
+            ProofMode::Slim => None,
+            ProofMode::SelfContained => Some(preprocessed_table.commitments()),
+        };
 
-        LookUpProof {
+        Ok(LookUpProof {
+            table_id: preprocessed_table.id,
+            table_commitments,
             multiset_equality_proof,
+            context: context.clone(),
+        })
+    }
+    /// Returns whether the proof carries the table commitments
+    pub fn mode(&self) -> ProofMode {
+        match self.table_commitments {
+            Some(_) => ProofMode::SelfContained,
+            None => ProofMode::Slim,
         }
     }
-    pub fn verify(
+    /// Verifies the proof against a table that the verifier holds
+    /// Proofs in either mode can be verified this way. The proof must have been made without a context.
+    pub fn verify<T: TranscriptProtocol + ?Sized>(
         &self,
         verification_key: &VerifierKey<Bls12_381>,
         preprocessed_table: &PreProcessedTable,
-        transcript: &mut dyn TranscriptProtocol,
+        transcript: &mut T,
+    ) -> bool {
+        self.verify_in_context(
+            verification_key,
+            preprocessed_table,
+            &ProofContext::default(),
+            transcript,
+        )
+    }
+    /// Verifies the proof against a table that the verifier holds, for the application and the session in `context`
+    /// A proof made for any other context is rejected. The proof must have been made with the default `ProverConfig`,
+    /// see `verify_with_config` otherwise.
+    pub fn verify_in_context<T: TranscriptProtocol + ?Sized>(
+        &self,
+        verification_key: &VerifierKey<Bls12_381>,
+        preprocessed_table: &PreProcessedTable,
+        context: &ProofContext,
+        transcript: &mut T,
+    ) -> bool {
+        self.verify_with_config(
+            verification_key,
+            preprocessed_table,
+            context,
+            &ProverConfig::default(),
+            transcript,
+        )
+    }
+    /// Verifies a proof made with `ProveOptions::config`, where `config` is the configuration the prover used
+    /// A proof made with another number of blinding factors, or for another context, is rejected.
+    pub fn verify_with_config<T: TranscriptProtocol + ?Sized>(
+        &self,
+        verification_key: &VerifierKey<Bls12_381>,
+        preprocessed_table: &PreProcessedTable,
+        context: &ProofContext,
+        config: &ProverConfig,
+        transcript: &mut T,
+    ) -> bool {
+        self.verify_against(
+            verification_key,
+            &preprocessed_table.commitments(),
+            context,
+            config,
+            transcript,
+        )
+    }
+    /// Verifies the proof against a table that the verifier holds, with a transcript made by `new_transcript`
+    /// The factory must absorb the same data as the transcript the prover passed to `prove_with_options`.
+    pub fn verify_with_transcript<T, F>(
+        &self,
+        verification_key: &VerifierKey<Bls12_381>,
+        preprocessed_table: &PreProcessedTable,
+        context: &ProofContext,
+        new_transcript: F,
+    ) -> bool
+    where
+        T: TranscriptProtocol,
+        F: FnOnce() -> T,
+    {
+        let mut transcript = new_transcript();
+        self.verify_in_context(
+            verification_key,
+            preprocessed_table,
+            context,
+            &mut transcript,
+        )
+    }
+    /// Verifies a self-contained proof, whose table commitments must be one of `allowed_tables`
+    /// The proof must have been made without a context.
+    pub fn verify_self_contained<T: TranscriptProtocol + ?Sized>(
+        &self,
+        verification_key: &VerifierKey<Bls12_381>,
+        allowed_tables: &[TableCommitments],
+        transcript: &mut T,
+    ) -> bool {
+        self.verify_self_contained_in_context(
+            verification_key,
+            allowed_tables,
+            &ProofContext::default(),
+            transcript,
+        )
+    }
+    /// Verifies a self-contained proof, whose table commitments must be one of `allowed_tables`,
+    /// for the application and the session in `context`
+    /// A proof made for any other context is rejected.
+    pub fn verify_self_contained_in_context<T: TranscriptProtocol + ?Sized>(
+        &self,
+        verification_key: &VerifierKey<Bls12_381>,
+        allowed_tables: &[TableCommitments],
+        context: &ProofContext,
+        transcript: &mut T,
+    ) -> bool {
+        let table_commitments = match &self.table_commitments {
+            Some(table_commitments) => table_commitments,
+            None => return false,
+        };
+        if !allowed_tables.contains(table_commitments) {
+            return false;
+        }
+
+        self.verify_against(
+            verification_key,
+            table_commitments,
+            context,
+            &ProverConfig::default(),
+            transcript,
+        )
+    }
+    fn verify_against<T: TranscriptProtocol + ?Sized>(
+        &self,
+        verification_key: &VerifierKey<Bls12_381>,
+        table_commitments: &TableCommitments,
+        context: &ProofContext,
+        config: &ProverConfig,
+        transcript: &mut T,
     ) -> bool {
+        self.openings_against(table_commitments, context, config, transcript)
+            .map_or(false, |openings| openings.check(verification_key))
+    }
+    /// Verifies the proof for the application and the session in `context`, as `verify_in_context` does,
+    /// with the precomputed values of the verifier key and of the table commitments
+    /// Verifiers which check many proofs against the same table prepare both once, and reuse them for every proof.
+    pub fn verify_prepared<T: TranscriptProtocol + ?Sized>(
+        &self,
+        verification_key: &PreparedVerifierKey,
+        table: &PreparedTableCommitments,
+        context: &ProofContext,
+        transcript: &mut T,
+    ) -> bool {
+        let config = ProverConfig::default();
+        self.openings_with(&table.commitments, context, &config, transcript, |alpha| {
+            table.merged(alpha)
+        })
+        .map_or(false, |openings| openings.check_prepared(verification_key))
+    }
+    /// Absorbs the proof into the transcript as `verify` does, and returns the openings that the proof must satisfy
+    /// Returns None if the proof was made against another table, for another context or with another `config`.
+    pub(crate) fn openings_against<T: TranscriptProtocol + ?Sized>(
+        &self,
+        table_commitments: &TableCommitments,
+        context: &ProofContext,
+        config: &ProverConfig,
+        transcript: &mut T,
+    ) -> Option<OpeningBatch> {
+        self.openings_with(table_commitments, context, config, transcript, |alpha| {
+            kzg10::aggregate_commitments(
+                vec![
+                    &table_commitments.t_1,
+                    &table_commitments.t_2,
+                    &table_commitments.t_3,
+                ],
+                alpha,
+            )
+        })
+    }
+    // Absorbs the proof as `openings_against` does, with `merge_table` folding the table commitments with `alpha`
+    fn openings_with<T, M>(
+        &self,
+        table_commitments: &TableCommitments,
+        context: &ProofContext,
+        config: &ProverConfig,
+        transcript: &mut T,
+        merge_table: M,
+    ) -> Option<OpeningBatch>
+    where
+        T: TranscriptProtocol + ?Sized,
+        M: FnOnce(Fr) -> Commitment<Bls12_381>,
+    {
+        // The proof must have been made against the same table, for the same application and session
+        if self.table_id != table_commitments.id || self.context != *context {
+            return None;
+        }
+        transcript.append_context(b"context", context);
+        let parameters = PublicParameters::new(table_commitments.n, config, false);
+        if !self
+            .multiset_equality_proof
+            .absorb_parameters(&parameters, transcript)
+        {
+            return None;
+        }
+        transcript.append_table_id(b"table_id", &table_commitments.id);
+        transcript.append_message(b"srs_digest", &table_commitments.srs_digest);
+
         // Merge preprocessed commitments to table using `alpha` challenge
         let alpha = transcript.challenge_scalar(b"alpha");
-        let merged_table_commit = kzg10::aggregate_commitments(
-            vec![
-                &preprocessed_table.t_1.1,
-                &preprocessed_table.t_2.1,
-                &preprocessed_table.t_3.1,
-            ],
-            alpha,
-        );
+        let merged_table_commit = merge_table(alpha);
         transcript.append_scalar(b"alpha", &alpha);
 
         // Call Multiset Equality Proof as a sub-routine
-        self.multiset_equality_proof.verify(
-            preprocessed_table.n,
-            verification_key,
+        Some(self.multiset_equality_proof.openings(
+            table_commitments.n,
             merged_table_commit,
             transcript,
+        ))
+    }
+}
+
+/// Aggregates the witness columns into one multiset using `alpha`,
+/// and pads it to be one less than the size of the table `n`
+pub fn merge_witness(
+    f_1: &MultiSet,
+    f_2: &MultiSet,
+    f_3: &MultiSet,
+    alpha: Fr,
+    n: usize,
+) -> MultiSet {
+    // Aggregate witness values into one multiset
+    let mut merged_witness = MultiSet::aggregate(vec![f_1, f_2, f_3], alpha);
+
+    // Pad merged Witness to be one less than `n`
+    assert!(merged_witness.len() < n);
+    let pad_by = n - 1 - merged_witness.len();
+    merged_witness.extend(pad_by, merged_witness.last());
+    merged_witness
+}
+
+/// Proves that the witness `f` is contained in the table `t`, where the caller has already folded
+/// the columns of each into one multiset
+/// This skips the table reads and the folding of `LookUpProof::prove`, for integrators whose own pipelines
+/// produce the merged witness and table. The caller must fold with a challenge drawn after the columns are fixed.
+/// The proof carries the prover's commitment to the padded witness, which is absorbed before any challenge
+/// of the equality proof, and the verifier checks the proof with `verify_from_multisets` against the commitment
+/// to the witness that its own protocol holds. Callers which commit to the witness before proving use
+/// `EqualityProof::prove_with_committed_f` and `verify_with_committed_f` instead.
+///
+/// `f` is padded with its last value to one row less than `t`, as `merge_witness` pads it.
+/// Returns `Error::TableMismatch` if `t` does not have as many rows as the domain of `quotient_key`,
+/// `Error::CapacityExceeded` if `f` does not fit, and `Error::MissingRows` with the indices of the values of `f`
+/// which are not in `t`.
+pub fn prove_from_multisets<K, T>(
+    mut f: MultiSet,
+    t: MultiSet,
+    proving_key: &K,
+    quotient_key: &QuotientKey,
+    config: &ProverConfig,
+    transcript: &mut T,
+) -> Result<EqualityProof, Error>
+where
+    K: CommitKey + ?Sized,
+    T: TranscriptProtocol + ?Sized,
+{
+    let n = quotient_key.domain().size();
+    if t.len() != n {
+        return Err(Error::TableMismatch);
+    }
+    if f.len() >= n {
+        return Err(Error::CapacityExceeded {
+            rows: f.len(),
+            capacity: n - 1,
+        });
+    }
+    let table = t.counts();
+    let missing: Vec<_> = (0..f.len())
+        .filter(|&i| !table.contains_key(&f.0[i]))
+        .collect();
+    if !missing.is_empty() {
+        return Err(Error::MissingRows(missing));
+    }
+
+    // An empty witness is padded with a row of the table
+    let pad_value = if f.len() > 0 { f.last() } else { t.0[0] };
+    let pad_by = n - 1 - f.len();
+    f.extend(pad_by, pad_value);
+
+    PublicParameters::new(n, config, false).absorb(transcript);
+    EqualityProof::prove_inner(
+        f,
+        None,
+        &Interpolated::new(t, *quotient_key.domain()),
+        proving_key,
+        quotient_key,
+        config,
+        &mut |_| Ok(()),
+        &mut ProverState::new(),
+        &mut |_| Ok(()),
+        &mut ScratchBuffers::new(),
+        transcript,
+    )
+}
+
+/// Verifies a proof made with `prove_from_multisets` over the domain of size `n`,
+/// where `f_commit` is the verifier's commitment to the witness and `t_commit` its commitment to the table
+/// The proof is rejected if it was made for another witness, or with another `config`.
+pub fn verify_from_multisets<T: TranscriptProtocol + ?Sized>(
+    proof: &EqualityProof,
+    n: usize,
+    verification_key: &VerifierKey<Bls12_381>,
+    f_commit: Commitment<Bls12_381>,
+    t_commit: Commitment<Bls12_381>,
+    config: &ProverConfig,
+    transcript: &mut T,
+) -> bool {
+    proof.commitments.f == f_commit
+        && proof.verify_with_config(n, verification_key, t_commit, config, transcript)
+}
+
+// The proof is serialised as a byte for the mode (0 for slim and 1 for self-contained),
+// followed by the 32 byte table identifier,
+// followed by the size of the table as a u64, the 32 byte digest of the SRS and the three table commitments,
+// for self-contained proofs,
+// followed by the multiset equality proof,
+// followed by the context, as the length of the application identifier as a u64 and its bytes,
+// then the length of the session as a u64 and its bytes
+impl LookUpProof {
+    /// Serialises the proof with each G1 element in its 48 byte compressed form
+    pub fn to_compressed_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(self.serialized_size());
+        self.serialize(&mut bytes)
+            .expect("serialising into a vector cannot fail");
+        bytes
+    }
+    /// Serialises the proof with each G1 element in its 96 byte uncompressed form
+    /// This avoids the square root needed to decompress points, for verifiers where that is expensive
+    pub fn to_uncompressed_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(self.uncompressed_size());
+        self.serialize_uncompressed(&mut bytes)
+            .expect("serialising into a vector cannot fail");
+        bytes
+    }
+    /// Deserialises a proof from its compressed form
+    pub fn from_compressed_bytes(mut bytes: &[u8]) -> Result<LookUpProof, SerializationError> {
+        LookUpProof::deserialize(&mut bytes)
+    }
+    /// Deserialises a proof from its uncompressed form
+    pub fn from_uncompressed_bytes(mut bytes: &[u8]) -> Result<LookUpProof, SerializationError> {
+        LookUpProof::deserialize_uncompressed(&mut bytes)
+    }
+    /// Encodes the compressed form of the proof as a hex string
+    pub fn to_hex(&self) -> String {
+        hex::encode(self.to_compressed_bytes())
+    }
+    /// Decodes a proof from the hex encoding of its compressed form
+    pub fn from_hex(encoded: &str) -> Result<LookUpProof, Error> {
+        let bytes = hex::decode(encoded)?;
+        Ok(LookUpProof::from_compressed_bytes(&bytes)?)
+    }
+    /// Encodes the compressed form of the proof as a base64 string
+    pub fn to_base64(&self) -> String {
+        base64::encode(self.to_compressed_bytes())
+    }
+    /// Decodes a proof from the base64 encoding of its compressed form
+    pub fn from_base64(encoded: &str) -> Result<LookUpProof, Error> {
+        let bytes = base64::decode(encoded)?;
+        Ok(LookUpProof::from_compressed_bytes(&bytes)?)
+    }
+
+    // Writes the mode and the table identifier, and the size of the table and the digest of the SRS for self-contained proofs
+    fn write_header<W: Write>(&self, writer: &mut W) -> Result<(), SerializationError> {
+        let mode: u8 = match self.mode() {
+            ProofMode::Slim => 0,
+            ProofMode::SelfContained => 1,
+        };
+        mode.serialize(writer)?;
+        writer.write_all(&self.table_id.0)?;
+        if let Some(table_commitments) = &self.table_commitments {
+            (table_commitments.n as u64).serialize(writer)?;
+            writer.write_all(&table_commitments.srs_digest)?;
+        }
+        Ok(())
+    }
+    fn header_size(&self) -> usize {
+        match self.table_commitments {
+            Some(_) => 1 + 32 + 8 + 32,
+            None => 1 + 32,
+        }
+    }
+    fn table_commitments_vec(&self) -> Vec<&Commitment<Bls12_381>> {
+        match &self.table_commitments {
+            Some(table_commitments) => vec![
+                &table_commitments.t_1,
+                &table_commitments.t_2,
+                &table_commitments.t_3,
+            ],
+            None => vec![],
+        }
+    }
+    fn read<R: Read>(
+        reader: &mut R,
+        read_point: fn(&mut R) -> Result<G1Affine, SerializationError>,
+        read_equality_proof: fn(&mut R) -> Result<EqualityProof, SerializationError>,
+    ) -> Result<Self, SerializationError> {
+        let mode = u8::deserialize(reader)?;
+        let mut id = [0u8; 32];
+        reader.read_exact(&mut id)?;
+        let table_id = TableId(id);
+
+        let table_commitments = match mode {
+            0 => None,
+            1 => Some(TableCommitments {
+                id: table_id,
+                n: u64::deserialize(reader)? as usize,
+                srs_digest: {
+                    let mut srs_digest = [0u8; 32];
+                    reader.read_exact(&mut srs_digest)?;
+                    srs_digest
+                },
+                t_1: Commitment(read_point(reader)?),
+                t_2: Commitment(read_point(reader)?),
+                t_3: Commitment(read_point(reader)?),
+            }),
+            _ => return Err(SerializationError::InvalidData),
+        };
+
+        Ok(LookUpProof {
+            table_id,
+            table_commitments,
+            multiset_equality_proof: read_equality_proof(reader)?,
+            context: ProofContext::deserialize(reader)?,
+        })
+    }
+}
+
+impl CanonicalSerialize for LookUpProof {
+    fn serialize<W: Write>(&self, writer: &mut W) -> Result<(), SerializationError> {
+        self.write_header(writer)?;
+        for comm in self.table_commitments_vec() {
+            comm.0.serialize(writer)?;
+        }
+        self.multiset_equality_proof.serialize(writer)?;
+        self.context.serialize(writer)
+    }
+    fn serialized_size(&self) -> usize {
+        self.header_size()
+            + self
+                .table_commitments_vec()
+                .iter()
+                .map(|comm| comm.0.serialized_size())
+                .sum::<usize>()
+            + self.multiset_equality_proof.serialized_size()
+            + self.context.serialized_size()
+    }
+    fn serialize_uncompressed<W: Write>(&self, writer: &mut W) -> Result<(), SerializationError> {
+        self.write_header(writer)?;
+        for comm in self.table_commitments_vec() {
+            comm.0.serialize_uncompressed(writer)?;
+        }
+        self.multiset_equality_proof
+            .serialize_uncompressed(writer)?;
+        self.context.serialize(writer)
+    }
+    fn uncompressed_size(&self) -> usize {
+        self.header_size()
+            + self
+                .table_commitments_vec()
+                .iter()
+                .map(|comm| comm.0.uncompressed_size())
+                .sum::<usize>()
+            + self.multiset_equality_proof.uncompressed_size()
+            + self.context.serialized_size()
+    }
+}
+
+impl CanonicalDeserialize for LookUpProof {
+    fn deserialize<R: Read>(reader: &mut R) -> Result<Self, SerializationError> {
+        LookUpProof::read(reader, G1Affine::deserialize, EqualityProof::deserialize)
+    }
+    fn deserialize_uncompressed<R: Read>(reader: &mut R) -> Result<Self, SerializationError> {
+        LookUpProof::read(
+            reader,
+            G1Affine::deserialize_uncompressed,
+            EqualityProof::deserialize_uncompressed,
         )
     }
 }
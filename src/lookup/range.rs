@@ -0,0 +1,252 @@
+// Range checks on their own, without building a table or a witness by hand
+//
+// Each value is split into limbs of `LIMB_BITS` bits, from the least significant, and each limb is read from
+// a table of (x, w) -> x for every width w up to `LIMB_BITS` and every x < 2^w. The top limb of a `bits`-bit
+// value is read with the width that remains, so one table serves every bit width.
+//
+// The rows (x, w, x) are folded into one value x + 2^8 w + 2^16 x, which is injective over the rows of the table,
+// so the folded witness does not depend on a challenge and the caller can commit to it before the proof is made.
+// The proof is made against that commitment with `EqualityProof::prove_with_committed_f`, and the verifier checks
+// it against the commitment it was given, so a proof only shows that the committed limbs are in range.
+// Constraining each committed value to be the sum of its limbs is left to the circuit which commits to them.
+use super::{
+    domain::min_domain_size,
+    proof::merge_witness,
+    table::{Generic, LookUpTable, TableCommitments},
+};
+use crate::{
+    error::Error,
+    kzg10,
+    multiset::{EqualityProof, MultiSet, ProverConfig},
+    transcript::TranscriptProtocol,
+};
+use algebra::{bls12_381::Fr, Bls12_381};
+use ff_fft::{DensePolynomial as Polynomial, EvaluationDomain};
+use merlin::Transcript;
+use poly_commit::kzg10::{Commitment, Powers, VerifierKey};
+use std::{cmp::min, collections::HashMap};
+
+/// The number of bits in each limb
+pub const LIMB_BITS: usize = 8;
+
+// The rows (x, w, x) of the witness and the table are folded with the powers of this value
+const FOLDING_BASE: u64 = 1 << LIMB_BITS;
+
+/// A proof that every committed value is less than 2^bits
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct RangeProof {
+    pub bits: usize,
+    pub proof: EqualityProof,
+}
+
+/// Constructs the table of (x, w) -> x, for every width w up to `LIMB_BITS` and every x < 2^w
+pub fn range_table() -> Generic {
+    let mut map = HashMap::new();
+    for width in 1..=LIMB_BITS {
+        for x in 0..(1u64 << width) {
+            map.insert((Fr::from(x), Fr::from(width as u64)), Fr::from(x));
+        }
+    }
+    Generic::with_hashmap(map)
+}
+
+/// Splits the first `bits` bits of `value` into limbs, from the least significant,
+/// and returns each limb with its width
+pub fn limbs(value: u64, bits: usize) -> Vec<(u64, usize)> {
+    assert!(bits > 0 && bits <= 64);
+    (0..bits)
+        .step_by(LIMB_BITS)
+        .map(|shift| {
+            let width = min(LIMB_BITS, bits - shift);
+            ((value >> shift) & ((1 << width) - 1), width)
+        })
+        .collect()
+}
+
+/// Returns the size of the domain that a range proof for `num_values` values of `bits` bits is made over
+pub fn range_domain_size(num_values: usize, bits: usize) -> usize {
+    let num_limbs = num_values * ((bits + LIMB_BITS - 1) / LIMB_BITS);
+    min_domain_size(range_table().len(), num_limbs)
+}
+
+/// Returns the commitments to the range table over a domain of size `n`, which the verifier checks proofs against
+pub fn range_table_commitments(proving_key: &Powers<Bls12_381>, n: usize) -> TableCommitments {
+    range_table().preprocess(proving_key, n).commitments()
+}
+
+/// Returns the commitment to the limbs of `values`, which `verify_in_range` checks a proof against
+/// The commitment is not blinded, so anyone who knows the values can recompute it.
+/// Returns the indices of the values which are not less than 2^bits, if there are any.
+/// Panics if `values` is empty.
+pub fn range_commitment(
+    values: &[u64],
+    bits: usize,
+    proving_key: &Powers<Bls12_381>,
+) -> Result<Commitment<Bls12_381>, Error> {
+    let (_, f_poly) = range_witness(values, bits)?;
+    Ok(kzg10::commit(proving_key, &f_poly))
+}
+
+/// Proves that every value is less than 2^bits
+/// The proof is made against the commitment returned by `range_commitment` for the same values.
+/// Returns the indices of the values which are not, if there are any.
+/// Panics if `values` is empty.
+pub fn prove_in_range(
+    values: &[u64],
+    bits: usize,
+    proving_key: &Powers<Bls12_381>,
+) -> Result<RangeProof, Error> {
+    let (f, f_poly) = range_witness(values, bits)?;
+    let f_commit = kzg10::commit(proving_key, &f_poly);
+
+    let n = range_domain_size(values.len(), bits);
+    let preprocessed_table = range_table().preprocess(proving_key, n);
+    let t = MultiSet::aggregate(
+        vec![
+            &preprocessed_table.t_1.0,
+            &preprocessed_table.t_2.0,
+            &preprocessed_table.t_3.0,
+        ],
+        Fr::from(FOLDING_BASE),
+    );
+
+    let mut transcript = range_transcript(bits, &preprocessed_table.commitments());
+    let proof = EqualityProof::prove_with_committed_f(
+        f,
+        f_poly,
+        f_commit,
+        t,
+        proving_key,
+        &preprocessed_table.quotient_key,
+        &ProverConfig::default(),
+        &mut transcript,
+    )?;
+
+    Ok(RangeProof { bits, proof })
+}
+
+/// Verifies that a proof shows the values committed to in `values_commitment` are less than 2^bits,
+/// where `table_commitments` are the commitments to the range table from `range_table_commitments`
+pub fn verify_in_range(
+    proof: &RangeProof,
+    bits: usize,
+    values_commitment: &Commitment<Bls12_381>,
+    verification_key: &VerifierKey<Bls12_381>,
+    table_commitments: &TableCommitments,
+) -> bool {
+    if proof.bits != bits || table_commitments.id != range_table().id() {
+        return false;
+    }
+    let t_commit = kzg10::aggregate_commitments(
+        vec![
+            &table_commitments.t_1,
+            &table_commitments.t_2,
+            &table_commitments.t_3,
+        ],
+        Fr::from(FOLDING_BASE),
+    );
+    proof.proof.verify_with_committed_f(
+        table_commitments.n,
+        verification_key,
+        *values_commitment,
+        t_commit,
+        &ProverConfig::default(),
+        &mut range_transcript(bits, table_commitments),
+    )
+}
+
+// Splits the values into limbs and folds each limb with its width into the witness,
+// padded to one less than the size of the domain, and interpolates it
+fn range_witness(values: &[u64], bits: usize) -> Result<(MultiSet, Polynomial<Fr>), Error> {
+    assert!(!values.is_empty());
+    assert!(bits > 0 && bits <= 64);
+
+    let out_of_range: Vec<_> = (0..values.len())
+        .filter(|&i| bits < 64 && values[i] >> bits != 0)
+        .collect();
+    if !out_of_range.is_empty() {
+        return Err(Error::OutOfRange(out_of_range));
+    }
+
+    let mut f_1 = MultiSet::new();
+    let mut f_2 = MultiSet::new();
+    for value in values {
+        for (limb, width) in limbs(*value, bits) {
+            f_1.push_u64(limb);
+            f_2.push_u64(width as u64);
+        }
+    }
+
+    let n = range_domain_size(values.len(), bits);
+    let f = merge_witness(&f_1, &f_2, &f_1, Fr::from(FOLDING_BASE), n);
+    let domain: EvaluationDomain<Fr> = EvaluationDomain::new(n).unwrap();
+    let f_poly = f.to_polynomial(&domain);
+    Ok((f, f_poly))
+}
+
+// The transcript holds the bit width and the table before the equality proof, which absorbs the commitment to the values
+fn range_transcript(bits: usize, table_commitments: &TableCommitments) -> Transcript {
+    let mut transcript = Transcript::new(b"plookup_range_proof");
+    transcript.append_u64(b"bits", bits as u64);
+    transcript.append_table_id(b"table_id", &table_commitments.id);
+    transcript.append_message(b"srs_digest", &table_commitments.srs_digest);
+    transcript
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_limbs() {
+        assert_eq!(range_table().len(), 510);
+        assert_eq!(limbs(0x3ff, 10), vec![(0xff, 8), (0x3, 2)]);
+        assert_eq!(limbs(0x1234, 16), vec![(0x34, 8), (0x12, 8)]);
+        assert_eq!(limbs(5, 3), vec![(5, 3)]);
+        assert_eq!(limbs(u64::max_value(), 64).len(), 8);
+    }
+
+    #[test]
+    fn test_range_proof() {
+        let (proving_key, verifier_key) = kzg10::trusted_setup(2usize.pow(11), b"insecure_seed");
+        let values = vec![0, 255, 1000, 1023];
+
+        let n = range_domain_size(values.len(), 10);
+        assert_eq!(n, 512);
+        let table_commitments = range_table_commitments(&proving_key, n);
+        let values_commitment = range_commitment(&values, 10, &proving_key).unwrap();
+
+        let proof = prove_in_range(&values, 10, &proving_key).unwrap();
+        assert_eq!(proof.proof.commitments.f, values_commitment);
+        assert!(verify_in_range(
+            &proof,
+            10,
+            &values_commitment,
+            &verifier_key,
+            &table_commitments
+        ));
+        // The proof is bound to the bit width
+        assert!(!verify_in_range(
+            &proof,
+            12,
+            &values_commitment,
+            &verifier_key,
+            &table_commitments
+        ));
+
+        // The proof is bound to the committed values, so it does not verify for other values
+        let other_commitment = range_commitment(&[0, 255, 1000, 1022], 10, &proving_key).unwrap();
+        assert!(!verify_in_range(
+            &proof,
+            10,
+            &other_commitment,
+            &verifier_key,
+            &table_commitments
+        ));
+
+        match prove_in_range(&[3, 1024, 7, 5000], 10, &proving_key) {
+            Err(Error::OutOfRange(indices)) => assert_eq!(indices, vec![1, 3]),
+            _ => panic!("expected the values to be out of range"),
+        }
+    }
+}
@@ -0,0 +1,294 @@
+// Proving that a small table is contained in a large master table
+//
+// A verifier may only trust the commitments to a master table, while proofs are cheaper against a subtable which
+// holds just the rows they need. `SubtableProof` shows that every row of a subtable, preprocessed over a domain of
+// size m, is a row of the master table, preprocessed over a domain of size n, from their commitments alone.
+// After it verifies, the subtable's commitments can be trusted as much as the master table's.
+//
+// The subtable is folded with `alpha` into s(X) over the domain of size m, as a lookup folds its table.
+// The witness of the multiset equality proof over the domain of size n is f(X) = s(X^r), with r = n / m:
+// X^r maps the domain of size n onto the domain of size m, so the evaluations of f(X) are those of s(X)
+// repeated r times, and every one of them is a row of the subtable. The prover commits to f(X) separately,
+// and after the equality proof the verifier draws a point zeta and checks that f(zeta) = s(zeta^r).
+//
+// The witness of the equality proof is only checked at the first n - 1 points of the domain. With r >= 2
+// the evaluation at the last point is the last row of the subtable, which also appears earlier, so the
+// subtable must be preprocessed over a smaller domain than the master table.
+use super::table::{PreProcessedTable, TableCommitments, TableId};
+use crate::{
+    error::Error,
+    kzg10,
+    multiset::{
+        EqualityProof, MultiSet, ProverConfig, ProverState, PublicParameters, ScratchBuffers,
+    },
+    transcript::TranscriptProtocol,
+};
+use algebra::{bls12_381::Fr, Bls12_381, Field};
+use ff_fft::DensePolynomial as Polynomial;
+use num_traits::identities::Zero;
+use poly_commit::kzg10::{Commitment, Powers, VerifierKey};
+use std::collections::HashSet;
+
+/// A proof that every row of a subtable is a row of a master table
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct SubtableProof {
+    /// The identifier of the master table
+    pub master_id: TableId,
+    /// The identifier of the subtable
+    pub subtable_id: TableId,
+    /// The commitment to f(X) = s(X^r), the folded subtable over the master table's domain
+    pub f_commit: Commitment<Bls12_381>,
+    pub multiset_equality_proof: EqualityProof,
+    /// The evaluation of f(X) at zeta, which is also the evaluation of s(X) at zeta^r
+    pub evaluation: Fr,
+    /// The opening witness for f(X) at zeta
+    pub f_witness: Commitment<Bls12_381>,
+    /// The opening witness for s(X) at zeta^r
+    pub s_witness: Commitment<Bls12_381>,
+}
+
+impl SubtableProof {
+    /// Proves that every row of `subtable` is a row of `master`
+    ///
+    /// The prover absorbs the public parameters of the equality proof, the identifiers of the master table
+    /// and the subtable and the commitments to the subtable,
+    /// draws `alpha` and absorbs it. The rest of the transcript, from the commitment to f(X), is documented
+    /// on `EqualityProof::prove`, after which the challenge `subtable_point` is drawn as zeta.
+    /// Panics if the subtable is not preprocessed over a smaller domain than the master table.
+    pub fn prove<T: TranscriptProtocol + ?Sized>(
+        subtable: &PreProcessedTable,
+        master: &PreProcessedTable,
+        proving_key: &Powers<Bls12_381>,
+        transcript: &mut T,
+    ) -> Result<SubtableProof, Error> {
+        assert!(
+            subtable.n < master.n,
+            "the subtable must be preprocessed over a smaller domain than the master table"
+        );
+        let (n, m) = (master.n, subtable.n);
+        let r = n / m;
+        let config = ProverConfig::default();
+        kzg10::check_capacity_with_config(proving_key, n, &config)?;
+
+        // Check the rows up front, as the multiset equality prover requires every row of f to be in t
+        let master_rows: HashSet<_> = rows(master).into_iter().collect();
+        let missing: Vec<_> = rows(subtable)
+            .iter()
+            .enumerate()
+            .filter(|(_, row)| !master_rows.contains(*row))
+            .map(|(i, _)| i)
+            .collect();
+        if !missing.is_empty() {
+            return Err(Error::MissingRows(missing));
+        }
+
+        PublicParameters::new(n, &config, true).absorb(transcript);
+        let alpha = absorb_tables(&subtable.commitments(), &master.commitments(), transcript);
+        let s = subtable.merged_interpolated(alpha);
+        let s_poly = s.polynomial();
+
+        // f(X) = s(X^r), whose evaluations are those of s(X) repeated r times
+        let mut f_coeffs = vec![Fr::zero(); n];
+        for (i, coeff) in s_poly.coeffs.iter().enumerate() {
+            f_coeffs[i * r] = *coeff;
+        }
+        let f_poly = Polynomial::from_coefficients_vec(f_coeffs);
+        let f = MultiSet((0..n - 1).map(|i| s.set().0[i % m]).collect());
+        let f_commit = kzg10::commit(proving_key, &f_poly);
+
+        let multiset_equality_proof = EqualityProof::prove_inner(
+            f,
+            Some((f_poly.clone(), f_commit)),
+            &master.merged_interpolated(alpha),
+            proving_key,
+            &master.quotient_key,
+            &config,
+            &mut |_| Ok(()),
+            &mut ProverState::new(),
+            &mut |_| Ok(()),
+            &mut ScratchBuffers::new(),
+            transcript,
+        )?;
+
+        let zeta = transcript.challenge_scalar(b"subtable_point");
+        let (evaluation, f_witness) = kzg10::open(proving_key, &f_poly, zeta);
+        let (_, s_witness) = kzg10::open(proving_key, &s_poly, zeta.pow(&[r as u64]));
+
+        Ok(SubtableProof {
+            master_id: master.id,
+            subtable_id: subtable.id,
+            f_commit,
+            multiset_equality_proof,
+            evaluation,
+            f_witness,
+            s_witness,
+        })
+    }
+    /// Verifies the proof against the commitments to the subtable and the master table
+    pub fn verify<T: TranscriptProtocol + ?Sized>(
+        &self,
+        verification_key: &VerifierKey<Bls12_381>,
+        subtable: &TableCommitments,
+        master: &TableCommitments,
+        transcript: &mut T,
+    ) -> bool {
+        if self.master_id != master.id || self.subtable_id != subtable.id {
+            return false;
+        }
+        if subtable.n >= master.n || master.n % subtable.n != 0 {
+            return false;
+        }
+        // The equality proof must be for the committed f(X)
+        if self.multiset_equality_proof.commitments.f != self.f_commit {
+            return false;
+        }
+        let r = master.n / subtable.n;
+
+        let parameters = PublicParameters::new(master.n, &ProverConfig::default(), true);
+        if !self
+            .multiset_equality_proof
+            .absorb_parameters(&parameters, transcript)
+        {
+            return false;
+        }
+        let alpha = absorb_tables(subtable, master, transcript);
+        let fold = |table: &TableCommitments| {
+            kzg10::aggregate_commitments(vec![&table.t_1, &table.t_2, &table.t_3], alpha)
+        };
+
+        let mut openings =
+            self.multiset_equality_proof
+                .openings(master.n, fold(master), transcript);
+
+        let zeta = transcript.challenge_scalar(b"subtable_point");
+        openings.push(self.f_commit, self.f_witness, zeta, self.evaluation);
+        openings.push(
+            fold(subtable),
+            self.s_witness,
+            zeta.pow(&[r as u64]),
+            self.evaluation,
+        );
+        openings.check(verification_key)
+    }
+}
+
+// Returns the rows of a preprocessed table, without the padding which repeats its last row
+fn rows(table: &PreProcessedTable) -> Vec<(Fr, Fr, Fr)> {
+    let (t_1, t_2, t_3) = (&table.t_1.0, &table.t_2.0, &table.t_3.0);
+    let mut rows: Vec<_> = (0..table.n)
+        .map(|i| (t_1.0[i], t_2.0[i], t_3.0[i]))
+        .collect();
+    while rows.len() > 1 && rows[rows.len() - 1] == rows[rows.len() - 2] {
+        rows.pop();
+    }
+    rows
+}
+
+// Absorbs the tables and draws the challenge `alpha` that their columns are folded with
+fn absorb_tables<T: TranscriptProtocol + ?Sized>(
+    subtable: &TableCommitments,
+    master: &TableCommitments,
+    transcript: &mut T,
+) -> Fr {
+    transcript.append_table_id(b"table_id", &master.id);
+    transcript.append_message(b"srs_digest", &master.srs_digest);
+    transcript.append_table_id(b"subtable_id", &subtable.id);
+    transcript.append_message(b"subtable_srs_digest", &subtable.srs_digest);
+    transcript.append_commitment(b"s_1", &subtable.t_1);
+    transcript.append_commitment(b"s_2", &subtable.t_2);
+    transcript.append_commitment(b"s_3", &subtable.t_3);
+
+    let alpha = transcript.challenge_scalar(b"alpha");
+    transcript.append_scalar(b"alpha", &alpha);
+    alpha
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::lookup::{
+        lookup::LookUp,
+        table::{four_bits::XOR4Bit, Generic, LookUpTable},
+    };
+    use merlin::Transcript;
+
+    #[test]
+    fn test_subtable_proof() {
+        let (proving_key, verifier_key) = kzg10::trusted_setup(2usize.pow(10), b"insecure_seed");
+        let master = XOR4Bit::new().preprocess(&proving_key, 2usize.pow(8));
+
+        // The rows of the master table with a left input of 3
+        let rows = (0..16u64).map(|b| ((Fr::from(3u8), Fr::from(b)), Fr::from(3 ^ b)));
+        let subtable = Generic::with_hashmap(rows.collect());
+        let preprocessed_subtable = subtable.preprocess(&proving_key, 2usize.pow(4));
+
+        let mut prover_transcript = Transcript::new(b"subtable");
+        let proof = SubtableProof::prove(
+            &preprocessed_subtable,
+            &master,
+            &proving_key,
+            &mut prover_transcript,
+        )
+        .unwrap();
+        let mut verifier_transcript = Transcript::new(b"subtable");
+        assert!(proof.verify(
+            &verifier_key,
+            &preprocessed_subtable.commitments(),
+            &master.commitments(),
+            &mut verifier_transcript
+        ));
+
+        // Proofs against the subtable are then made over its smaller domain
+        let mut lookup = LookUp::new(subtable);
+        assert_eq!(lookup.read_u8(3, 5), Some(6));
+        let mut prover_transcript = Transcript::new(b"lookup");
+        let lookup_proof =
+            lookup.prove(&proving_key, &preprocessed_subtable, &mut prover_transcript);
+        let mut verifier_transcript = Transcript::new(b"lookup");
+        assert!(lookup_proof.verify(
+            &verifier_key,
+            &preprocessed_subtable,
+            &mut verifier_transcript
+        ));
+    }
+
+    #[test]
+    fn test_subtable_not_in_master() {
+        let (proving_key, verifier_key) = kzg10::trusted_setup(2usize.pow(10), b"insecure_seed");
+        let master = XOR4Bit::new().preprocess(&proving_key, 2usize.pow(8));
+
+        // 3 XOR 5 is not 7
+        let rows = vec![
+            ((Fr::from(3u8), Fr::from(4u8)), Fr::from(7u8)),
+            ((Fr::from(3u8), Fr::from(5u8)), Fr::from(7u8)),
+        ];
+        let bad_subtable = Generic::with_hashmap(rows.into_iter().collect());
+        let bad_subtable = bad_subtable.preprocess(&proving_key, 2usize.pow(2));
+        let mut prover_transcript = Transcript::new(b"subtable");
+        match SubtableProof::prove(&bad_subtable, &master, &proving_key, &mut prover_transcript) {
+            Err(Error::MissingRows(rows)) => assert_eq!(rows, vec![1]),
+            _ => panic!("expected the second row to be missing from the master table"),
+        }
+
+        // A proof for one subtable does not verify against the commitments to another
+        let rows = vec![
+            ((Fr::from(3u8), Fr::from(4u8)), Fr::from(7u8)),
+            ((Fr::from(3u8), Fr::from(5u8)), Fr::from(6u8)),
+        ];
+        let subtable = Generic::with_hashmap(rows.into_iter().collect());
+        let subtable = subtable.preprocess(&proving_key, 2usize.pow(2));
+        let mut prover_transcript = Transcript::new(b"subtable");
+        let proof =
+            SubtableProof::prove(&subtable, &master, &proving_key, &mut prover_transcript).unwrap();
+
+        let mut forged = subtable.commitments();
+        forged.t_3 = bad_subtable.t_3.1;
+        let mut verifier_transcript = Transcript::new(b"subtable");
+        assert!(!proof.verify(
+            &verifier_key,
+            &forged,
+            &master.commitments(),
+            &mut verifier_transcript
+        ));
+    }
+}
@@ -0,0 +1,68 @@
+use super::{Generic, TableMap};
+use algebra::{biginteger::BigInteger256, bls12_381::Fr, field_new};
+
+// The standard tables, with their rows generated by `build.rs` and embedded as constants.
+// Constructing one of these tables does no field arithmetic: every input and output is one of the
+// constants 0..256, so an embedded prover only pays for building the map.
+include!(concat!(env!("OUT_DIR"), "/baked_tables.rs"));
+
+/// Returns the field element `x`, from the embedded constants
+pub fn small_value(x: u8) -> Fr {
+    SMALL_VALUES[x as usize]
+}
+
+/// The table of (a, b) -> a XOR b over 4-bit inputs, with the same rows as `XOR4Bit`
+pub fn xor_4bit() -> Generic {
+    from_outputs(&XOR_4_OUTPUTS, 4)
+}
+
+/// The table of (a, b) -> a XOR b over 8-bit inputs, with the same rows as `blake::Xor::new(8)`
+pub fn xor_8bit() -> Generic {
+    from_outputs(&XOR_8_OUTPUTS, 8)
+}
+
+/// The table of (x, 0) -> x for every byte x
+pub fn byte_range() -> Generic {
+    let map: TableMap = SMALL_VALUES
+        .iter()
+        .map(|x| ((*x, SMALL_VALUES[0]), *x))
+        .collect();
+    Generic::with_map(map)
+}
+
+fn from_outputs(outputs: &[u8], bits: usize) -> Generic {
+    let range = 1 << bits;
+    let mut map = TableMap::with_capacity_and_hasher(outputs.len(), Default::default());
+    for a in 0..range {
+        for b in 0..range {
+            let output = outputs[(a << bits) + b];
+            map.insert(
+                (SMALL_VALUES[a], SMALL_VALUES[b]),
+                SMALL_VALUES[output as usize],
+            );
+        }
+    }
+    Generic::with_map(map)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::lookup::table::{blake::Xor, four_bits::XOR4Bit, MapTable};
+
+    #[test]
+    fn test_baked_tables() {
+        for x in 0..=255u8 {
+            assert_eq!(small_value(x), Fr::from(x));
+        }
+        assert_eq!(xor_4bit().borrow_map(), XOR4Bit::new().borrow_map());
+        assert_eq!(xor_8bit().borrow_map(), Xor::new(8).borrow_map());
+
+        let bytes = byte_range();
+        assert_eq!(bytes.borrow_map().len(), 256);
+        assert_eq!(
+            bytes.borrow_map().get(&(Fr::from(200u8), Fr::from(0u8))),
+            Some(&Fr::from(200u8))
+        );
+    }
+}
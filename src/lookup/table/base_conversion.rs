@@ -0,0 +1,169 @@
+use super::{reinforced_concrete::decompose, Generic, MapTable, TableMap};
+use crate::lookup::lookup::LookUp;
+use algebra::bls12_381::Fr;
+
+// A number is in base-b (or spread) form when each of its bits has been placed into a separate base-b digit.
+// Adding numbers in spread form adds their bits digit-wise, and as long as the sum of each digit is less than b,
+// no carries will occur. The result can then be converted back by taking a function of each digit,
+// for example the parity of each digit is the XOR of the bits that were added.
+
+/// Constructs a table of (x, 0) -> x in base `base`, for every `bits`-bit chunk x
+pub struct ToBase {
+    bits: usize,
+    base: u64,
+    table: Generic,
+}
+
+impl ToBase {
+    pub fn new(bits: usize, base: u64) -> Self {
+        let func = |x: usize, _: usize| -> Fr { Fr::from(to_base(x as u64, bits, base)) };
+
+        ToBase {
+            bits,
+            base,
+            table: Generic::with_fn_ranges(func, 1 << bits, 1),
+        }
+    }
+}
+
+impl MapTable for ToBase {
+    fn borrow_map(&self) -> &TableMap {
+        &self.table.borrow_map()
+    }
+}
+
+/// Constructs a table of (x, 0) -> y, for every x with `digits` digits in base `base`,
+/// where bit i of y is the parity of digit i of x
+pub struct FromBase {
+    digits: usize,
+    base: u64,
+    table: Generic,
+}
+
+impl FromBase {
+    pub fn new(digits: usize, base: u64) -> Self {
+        let func = |x: usize, _: usize| -> Fr { Fr::from(parity_bits(x as u64, digits, base)) };
+
+        FromBase {
+            digits,
+            base,
+            table: Generic::with_fn_ranges(func, base.pow(digits as u32) as usize, 1),
+        }
+    }
+}
+
+impl MapTable for FromBase {
+    fn borrow_map(&self) -> &TableMap {
+        &self.table.borrow_map()
+    }
+}
+
+/// Converts the first `bits` bits of `x` into a number in base `base`, with one digit per bit
+pub fn to_base(x: u64, bits: usize, base: u64) -> u64 {
+    let mut result = 0u64;
+    for i in (0..bits).rev() {
+        result = result * base + ((x >> i) & 1);
+    }
+    result
+}
+
+/// Returns the number whose i'th bit is the parity of the i'th digit of `x` in base `base`
+pub fn parity_bits(x: u64, digits: usize, base: u64) -> u64 {
+    let mut x = x;
+    let mut result = 0u64;
+    for i in 0..digits {
+        result |= ((x % base) & 1) << i;
+        x /= base;
+    }
+    result
+}
+
+/// Converts a `word_bits`-bit word into base-b form, reading every chunk from the table
+/// Returns None if the word does not fit into `word_bits` bits
+pub fn to_base_word(lookup: &mut LookUp<ToBase>, x: u64, word_bits: usize) -> Option<Fr> {
+    let (bits, base) = (lookup.table().bits, lookup.table().base);
+    assert_eq!(word_bits % bits, 0);
+    if word_bits < 64 && x >> word_bits != 0 {
+        return None;
+    }
+
+    // Each chunk in base-b form is shifted by base^bits
+    let chunk_shift = Fr::from(base.pow(bits as u32));
+    let mask = (1u64 << bits) - 1;
+
+    let mut result = Fr::from(0u8);
+    for i in (0..word_bits).step_by(bits).rev() {
+        let chunk = (x >> i) & mask;
+        let output = lookup
+            .read(&(Fr::from(chunk), Fr::from(0u8)))
+            .expect("chunks are in range");
+        result = result * chunk_shift + output;
+    }
+    Some(result)
+}
+
+/// Converts a word with `word_digits` digits in base-b form back into binary, taking the parity of every digit
+/// and reading every chunk from the table
+/// Returns None if the word does not fit into `word_digits` digits
+pub fn from_base_word(lookup: &mut LookUp<FromBase>, x: &Fr, word_digits: usize) -> Option<u64> {
+    let (digits, base) = (lookup.table().digits, lookup.table().base);
+    assert_eq!(word_digits % digits, 0);
+    assert!(word_digits <= 64);
+
+    let chunk_radix = base.pow(digits as u32);
+    let chunks = decompose(x, &vec![chunk_radix; word_digits / digits])?;
+
+    let mut result = 0u64;
+    for chunk in chunks {
+        lookup
+            .read(&(Fr::from(chunk), Fr::from(0u8)))
+            .expect("chunks are in range");
+        result = (result << digits) | parity_bits(chunk, digits, base);
+    }
+    Some(result)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::lookup::table::LookUpTable;
+
+    #[test]
+    fn test_base_conversion_tables() {
+        let to_base_table = ToBase::new(3, 4);
+        assert_eq!(to_base_table.len(), 8);
+        assert_eq!(
+            to_base_table.read(&(Fr::from(0b101u8), Fr::from(0u8))),
+            Some(Fr::from(16u8 + 1))
+        );
+
+        let from_base_table = FromBase::new(2, 4);
+        assert_eq!(from_base_table.len(), 16);
+        // 3 * 4 + 2 has digits (3, 2)
+        assert_eq!(
+            from_base_table.read(&(Fr::from(14u8), Fr::from(0u8))),
+            Some(Fr::from(0b10u8))
+        );
+    }
+
+    #[test]
+    fn test_xor_in_base_form() {
+        let mut to_base_lookup = LookUp::new(ToBase::new(4, 4));
+        let mut from_base_lookup = LookUp::new(FromBase::new(4, 4));
+
+        let a = 0xbeefu64;
+        let b = 0x1234u64;
+        let c = 0xf00du64;
+
+        // Adding three words in base 4 never carries
+        let sum = to_base_word(&mut to_base_lookup, a, 16).unwrap()
+            + to_base_word(&mut to_base_lookup, b, 16).unwrap()
+            + to_base_word(&mut to_base_lookup, c, 16).unwrap();
+
+        assert_eq!(
+            from_base_word(&mut from_base_lookup, &sum, 16),
+            Some(a ^ b ^ c)
+        );
+        assert_eq!(to_base_word(&mut to_base_lookup, 1 << 16, 16), None);
+    }
+}
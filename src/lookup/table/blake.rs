@@ -0,0 +1,144 @@
+use super::{Generic, MapTable, TableMap};
+use crate::lookup::lookup::LookUp;
+use algebra::bls12_381::Fr;
+
+/// Rotation distances used by the G function of Blake2s and Blake3
+pub const BLAKE2S_ROTATIONS: [usize; 4] = [16, 12, 8, 7];
+/// Rotation distances used by the G function of Blake2b
+pub const BLAKE2B_ROTATIONS: [usize; 4] = [32, 24, 16, 63];
+
+// The G function mixes words using three operations: addition mod 2^k, XOR, and rotation.
+// Words are split into chunks of `bits` bits, and each operation is checked chunk-wise:
+// - XOR is checked directly with the `Xor` table
+// - Addition is computed in the field, and the carry out of each chunk is extracted with the `Carry` table
+// - Rotations by a multiple of the chunk size are a re-ordering of the chunks, and are free.
+//   Any other rotation splits the chunks at the rotation offset, using the `ShiftRight` table.
+
+/// Constructs a table of (a, b) -> a XOR b over `bits`-bit chunks
+pub struct Xor {
+    bits: usize,
+    table: Generic,
+}
+
+impl Xor {
+    pub fn new(bits: usize) -> Self {
+        let func = |a: usize, b: usize| -> Fr { Fr::from((a ^ b) as u64) };
+
+        Xor {
+            bits,
+            table: Generic::with_fn(func, 1 << bits),
+        }
+    }
+    /// Returns the size of a chunk in bits
+    pub fn bits(&self) -> usize {
+        self.bits
+    }
+}
+
+impl MapTable for Xor {
+    fn borrow_map(&self) -> &TableMap {
+        &self.table.borrow_map()
+    }
+}
+
+/// Constructs a table of (x, 0) -> x >> bits, for every x which is the sum of at most four `bits`-bit chunks
+/// The output is the carry out of a chunk, and the table also checks that the sum was in range.
+/// The remaining low bits of the chunk are x - (carry << bits)
+pub struct Carry(Generic);
+
+impl Carry {
+    pub fn new(bits: usize) -> Self {
+        let func = |x: usize, _: usize| -> Fr { Fr::from((x >> bits) as u64) };
+
+        Carry(Generic::with_fn_ranges(func, 1 << (bits + 2), 1))
+    }
+}
+
+impl MapTable for Carry {
+    fn borrow_map(&self) -> &TableMap {
+        &self.0.borrow_map()
+    }
+}
+
+/// Constructs a table of (x, s) -> x >> s, for every `bits`-bit chunk x and every 0 <= s < bits
+/// This splits a chunk at bit `s`, the low bits of the chunk are x - ((x >> s) << s)
+pub struct ShiftRight(Generic);
+
+impl ShiftRight {
+    pub fn new(bits: usize) -> Self {
+        let func = |x: usize, s: usize| -> Fr { Fr::from((x >> s) as u64) };
+
+        ShiftRight(Generic::with_fn_ranges(func, 1 << bits, bits))
+    }
+}
+
+impl MapTable for ShiftRight {
+    fn borrow_map(&self) -> &TableMap {
+        &self.0.borrow_map()
+    }
+}
+
+/// Computes a XOR b for two `word_bits`-bit words, reading every chunk from the XOR table
+/// Returns None if the words do not fit into `word_bits` bits
+pub fn xor_words(lookup: &mut LookUp<Xor>, a: u64, b: u64, word_bits: usize) -> Option<u64> {
+    let bits = lookup.table().bits();
+    assert_eq!(word_bits % bits, 0);
+    if word_bits < 64 && (a >> word_bits != 0 || b >> word_bits != 0) {
+        return None;
+    }
+
+    let mask = (1u64 << bits) - 1;
+    let mut result = 0u64;
+    for i in (0..word_bits).step_by(bits) {
+        let a_chunk = (a >> i) & mask;
+        let b_chunk = (b >> i) & mask;
+        lookup
+            .read(&(Fr::from(a_chunk), Fr::from(b_chunk)))
+            .expect("chunks are in range");
+        result |= (a_chunk ^ b_chunk) << i;
+    }
+    Some(result)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::lookup::table::LookUpTable;
+
+    #[test]
+    fn test_blake_tables() {
+        let xor = Xor::new(4);
+        assert_eq!(xor.len(), 256);
+        assert_eq!(
+            xor.read(&(Fr::from(0b1010u8), Fr::from(0b0110u8))),
+            Some(Fr::from(0b1100u8))
+        );
+
+        let carry = Carry::new(4);
+        assert_eq!(carry.len(), 64);
+        assert_eq!(
+            carry.read(&(Fr::from(15u8 + 15 + 15), Fr::from(0u8))),
+            Some(Fr::from(2u8))
+        );
+        assert!(carry.read(&(Fr::from(64u8), Fr::from(0u8))).is_none());
+
+        let shift = ShiftRight::new(4);
+        assert_eq!(shift.len(), 64);
+        assert_eq!(
+            shift.read(&(Fr::from(0b1011u8), Fr::from(1u8))),
+            Some(Fr::from(0b101u8))
+        );
+        assert!(shift.read(&(Fr::from(0b1011u8), Fr::from(4u8))).is_none());
+    }
+
+    #[test]
+    fn test_xor_words() {
+        let mut lookup = LookUp::new(Xor::new(4));
+
+        let a = 0xdead_beefu64;
+        let b = 0x0123_4567u64;
+        assert_eq!(xor_words(&mut lookup, a, b, 32), Some(a ^ b));
+
+        assert_eq!(xor_words(&mut lookup, 1 << 32, b, 32), None);
+    }
+}
@@ -0,0 +1,91 @@
+use super::TableId;
+use algebra::Bls12_381;
+use poly_commit::kzg10::Commitment;
+use std::{collections::HashMap, sync::RwLock};
+
+/// Identifies the commitments to the columns of a table
+/// The commitments only depend on the rows of the table, the SRS and the size the table was padded to.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct CacheKey {
+    /// The identifier of the table, see `LookUpTable::id`
+    pub table_id: TableId,
+    /// The digest of the powers used to commit to the table, see `kzg10::srs_digest`
+    pub srs_digest: [u8; 32],
+    /// The size that the table was padded to
+    pub n: usize,
+}
+
+/// A cache of table commitments, which may be shared between provers
+/// Implement this to back the cache with external storage.
+pub trait CommitmentCache {
+    /// Returns the commitments to the three columns of the table, if they are in the cache
+    fn get(&self, key: &CacheKey) -> Option<[Commitment<Bls12_381>; 3]>;
+    /// Adds the commitments to the three columns of the table to the cache
+    fn insert(&self, key: CacheKey, commitments: [Commitment<Bls12_381>; 3]);
+}
+
+/// An in-process cache of table commitments
+#[derive(Default)]
+pub struct InMemoryCache(RwLock<HashMap<CacheKey, [Commitment<Bls12_381>; 3]>>);
+
+impl InMemoryCache {
+    pub fn new() -> Self {
+        InMemoryCache::default()
+    }
+    /// Returns the number of tables in the cache
+    pub fn len(&self) -> usize {
+        self.0.read().unwrap().len()
+    }
+    /// Returns true if there are no tables in the cache
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl CommitmentCache for InMemoryCache {
+    fn get(&self, key: &CacheKey) -> Option<[Commitment<Bls12_381>; 3]> {
+        self.0.read().unwrap().get(key).copied()
+    }
+    fn insert(&self, key: CacheKey, commitments: [Commitment<Bls12_381>; 3]) {
+        self.0.write().unwrap().insert(key, commitments);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        kzg10,
+        lookup::table::{
+            four_bits::{Add4Bit, XOR4Bit},
+            LookUpTable,
+        },
+    };
+
+    #[test]
+    fn test_commitment_cache() {
+        let (proving_key, _) = kzg10::trusted_setup(2usize.pow(9), b"insecure_seed");
+        let cache = InMemoryCache::new();
+
+        let table = XOR4Bit::new();
+        let expected = table.preprocess(&proving_key, 2usize.pow(8));
+
+        let first = table.preprocess_with_cache(&proving_key, 2usize.pow(8), &cache);
+        assert_eq!(cache.len(), 1);
+        let second = table.preprocess_with_cache(&proving_key, 2usize.pow(8), &cache);
+        assert_eq!(cache.len(), 1);
+
+        for preprocessed_table in vec![first, second] {
+            assert_eq!(preprocessed_table.t_1.1, expected.t_1.1);
+            assert_eq!(preprocessed_table.t_2.1, expected.t_2.1);
+            assert_eq!(preprocessed_table.t_3.1, expected.t_3.1);
+        }
+
+        // A different table, domain size or SRS is a different entry
+        Add4Bit::new().preprocess_with_cache(&proving_key, 2usize.pow(8), &cache);
+        table.preprocess_with_cache(&proving_key, 2usize.pow(9), &cache);
+        let (other_key, _) = kzg10::trusted_setup(2usize.pow(9), b"other_seed");
+        table.preprocess_with_cache(&other_key, 2usize.pow(8), &cache);
+        assert_eq!(cache.len(), 4);
+    }
+}
@@ -0,0 +1,78 @@
+use super::{Generic, LookUpTable, MapTable, TableMap};
+use crate::lookup::lookup::LookUp;
+use algebra::bls12_381::Fr;
+
+/// Constructs a table of (c, 0) -> 1, for every byte c in a permitted character class
+/// Reading a byte from the table checks that it is in the class
+pub struct Charset(Generic);
+
+impl Charset {
+    /// Constructs a table from the bytes in the class
+    pub fn new<I: IntoIterator<Item = u8>>(chars: I) -> Self {
+        let map: TableMap = chars
+            .into_iter()
+            .map(|c| ((Fr::from(c), Fr::from(0u8)), Fr::from(1u8)))
+            .collect();
+
+        Charset(Generic::with_map(map))
+    }
+    /// The printable ASCII characters, from ' ' to '~'
+    pub fn printable_ascii() -> Self {
+        Charset::new(b' '..=b'~')
+    }
+    /// The hex digits, in lower and upper case
+    pub fn hex_digits() -> Self {
+        Charset::new((b'0'..=b'9').chain(b'a'..=b'f').chain(b'A'..=b'F'))
+    }
+    /// The decimal digits
+    pub fn decimal_digits() -> Self {
+        Charset::new(b'0'..=b'9')
+    }
+    /// The ASCII letters and decimal digits
+    pub fn alphanumeric() -> Self {
+        Charset::new((b'0'..=b'9').chain(b'a'..=b'z').chain(b'A'..=b'Z'))
+    }
+}
+
+impl MapTable for Charset {
+    fn borrow_map(&self) -> &TableMap {
+        &self.0.borrow_map()
+    }
+}
+
+/// Checks that every byte of `string` is in the character class, reading each byte from the table
+/// Returns false without reading any bytes if one of them is not in the class
+pub fn check_string(lookup: &mut LookUp<Charset>, string: &[u8]) -> bool {
+    let zero = Fr::from(0u8);
+    let in_class = string
+        .iter()
+        .all(|c| lookup.table().read(&(Fr::from(*c), zero)).is_some());
+    if !in_class {
+        return false;
+    }
+
+    for c in string {
+        lookup
+            .read(&(Fr::from(*c), zero))
+            .expect("bytes are in the class");
+    }
+    true
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_charset() {
+        assert_eq!(Charset::printable_ascii().len(), 95);
+        assert_eq!(Charset::hex_digits().len(), 22);
+        assert_eq!(Charset::decimal_digits().len(), 10);
+        assert_eq!(Charset::alphanumeric().len(), 62);
+
+        let mut lookup = LookUp::new(Charset::hex_digits());
+        assert!(check_string(&mut lookup, b"deadBEEF0123"));
+        assert!(!check_string(&mut lookup, b"0x1234"));
+        assert!(check_string(&mut lookup, b""));
+    }
+}
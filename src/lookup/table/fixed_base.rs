@@ -0,0 +1,160 @@
+use super::{Generic, MapTable, TableMap};
+use crate::{kzg10::window_digit, lookup::lookup::LookUp};
+use algebra::{
+    bls12_381::Fr,
+    ed_on_bls12_381::{EdwardsAffine, EdwardsProjective, Fr as EmbeddedScalar},
+    AffineCurve, PrimeField, ProjectiveCurve,
+};
+use num_traits::identities::Zero;
+
+// A fixed-base scalar multiplication k * B over the embedded curve (Jubjub) is split into windows
+// k * B = sum_i k_i * (2^{w * i} * B), where k_i are the `w`-bit windows of k
+// Every term k_i * (2^{w * i} * B) is precomputed, so each window only costs a read from the tables
+// (i, k_i) -> x-coordinate and (i, k_i) -> y-coordinate of the term.
+// Since the curve is a twisted Edwards curve, the terms can be added with the complete addition law,
+// including the identity (0, 1) for k_i = 0.
+
+/// The coordinate of the precomputed point which is the output of a table
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Coordinate {
+    X,
+    Y,
+}
+
+/// Constructs a table of (i, k_i) -> coordinate of k_i * 2^{window_bits * i} * B,
+/// for every window i and every `window_bits`-bit window value k_i
+pub struct FixedBase {
+    window_bits: usize,
+    num_windows: usize,
+    table: Generic,
+}
+
+impl FixedBase {
+    /// Constructs a table with enough windows for any scalar of the embedded curve
+    pub fn new(base: EdwardsProjective, window_bits: usize, coordinate: Coordinate) -> Self {
+        let scalar_bits = EmbeddedScalar::size_in_bits();
+        let num_windows = (scalar_bits + window_bits - 1) / window_bits;
+        FixedBase::with_windows(base, window_bits, num_windows, coordinate)
+    }
+    pub fn with_windows(
+        base: EdwardsProjective,
+        window_bits: usize,
+        num_windows: usize,
+        coordinate: Coordinate,
+    ) -> Self {
+        assert!(window_bits > 0 && window_bits < 64);
+
+        let mut map =
+            TableMap::with_capacity_and_hasher(num_windows << window_bits, Default::default());
+
+        // The base of the current window is 2^{window_bits * i} * B
+        let mut window_base = base;
+        for i in 0..num_windows {
+            let i_fr = Fr::from(i as u64);
+
+            let mut point = EdwardsProjective::zero();
+            for k_i in 0..(1u64 << window_bits) {
+                let affine = point.into_affine();
+                let output = match coordinate {
+                    Coordinate::X => affine.x,
+                    Coordinate::Y => affine.y,
+                };
+                map.insert((i_fr, Fr::from(k_i)), output);
+                point += &window_base;
+            }
+
+            for _ in 0..window_bits {
+                window_base.double_in_place();
+            }
+        }
+
+        FixedBase {
+            window_bits,
+            num_windows,
+            table: Generic::with_map(map),
+        }
+    }
+    /// Returns the size of a window in bits
+    pub fn window_bits(&self) -> usize {
+        self.window_bits
+    }
+    /// Returns the number of windows in the table
+    pub fn num_windows(&self) -> usize {
+        self.num_windows
+    }
+}
+
+impl MapTable for FixedBase {
+    fn borrow_map(&self) -> &TableMap {
+        &self.table.borrow_map()
+    }
+}
+
+/// Computes scalar * B, reading the coordinates of every window from the x and y tables
+/// Both tables must have been constructed with the same base point and window size
+pub fn fixed_base_mul(
+    x_lookup: &mut LookUp<FixedBase>,
+    y_lookup: &mut LookUp<FixedBase>,
+    scalar: &EmbeddedScalar,
+) -> EdwardsAffine {
+    let window_bits = x_lookup.table().window_bits();
+    let num_windows = x_lookup.table().num_windows();
+    assert_eq!(window_bits, y_lookup.table().window_bits());
+    assert_eq!(num_windows, y_lookup.table().num_windows());
+    assert!(window_bits * num_windows >= EmbeddedScalar::size_in_bits());
+
+    let repr = scalar.into_repr();
+    let limbs = repr.as_ref();
+
+    let mut result = EdwardsProjective::zero();
+    for i in 0..num_windows {
+        let k_i = window_digit(limbs, i * window_bits, window_bits);
+        let key = (Fr::from(i as u64), Fr::from(k_i as u64));
+
+        let x = x_lookup.read(&key).expect("windows are in range");
+        let y = y_lookup.read(&key).expect("windows are in range");
+
+        result.add_assign_mixed(&EdwardsAffine::new(x, y));
+    }
+    result.into_affine()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::lookup::table::LookUpTable;
+    use algebra::UniformRand;
+    use rand::thread_rng;
+
+    #[test]
+    fn test_fixed_base_tables() {
+        let base = EdwardsProjective::prime_subgroup_generator();
+        let table = FixedBase::with_windows(base, 2, 3, Coordinate::Y);
+        assert_eq!(table.len(), 12);
+
+        // The zero window maps to the identity
+        assert_eq!(
+            table.read(&(Fr::from(2u8), Fr::from(0u8))),
+            Some(Fr::from(1u8))
+        );
+        // Window 1 with value 3 maps to 3 * 4 * B
+        let expected = base.into_affine().mul(12u64);
+        assert_eq!(
+            table.read(&(Fr::from(1u8), Fr::from(3u8))),
+            Some(expected.into_affine().y)
+        );
+    }
+
+    #[test]
+    fn test_fixed_base_mul() {
+        let base = EdwardsProjective::prime_subgroup_generator();
+        let mut x_lookup = LookUp::new(FixedBase::new(base, 4, Coordinate::X));
+        let mut y_lookup = LookUp::new(FixedBase::new(base, 4, Coordinate::Y));
+
+        let scalar = EmbeddedScalar::rand(&mut thread_rng());
+        let result = fixed_base_mul(&mut x_lookup, &mut y_lookup, &scalar);
+
+        assert_eq!(result, base.into_affine().mul(scalar).into_affine());
+        assert!(!result.is_zero());
+    }
+}
@@ -1,16 +1,20 @@
-use super::{Generic, LookUpTable};
+use super::{Generic, MapTable, TableMap};
 use algebra::bls12_381::Fr;
-use std::collections::HashMap;
 
-const BIT_RANGE: usize = 16;
+const BITS: usize = 4;
+const BIT_RANGE: usize = 1 << BITS;
 
 /// Constructs a 4-bit Add table
+#[derive(Clone, Debug)]
 pub struct Add4Bit(Generic);
 
-impl LookUpTable for Add4Bit {
-    fn borrow_map(&self) -> &HashMap<(Fr, Fr), Fr> {
+impl MapTable for Add4Bit {
+    fn borrow_map(&self) -> &TableMap {
         &self.0.borrow_map()
     }
+    fn input_bits(&self) -> Option<usize> {
+        Some(BITS)
+    }
 }
 
 impl Add4Bit {
@@ -28,6 +32,7 @@ impl Add4Bit {
 }
 
 /// Constructs a 4-bit XOR table
+#[derive(Clone, Debug)]
 pub struct XOR4Bit(Generic);
 
 impl XOR4Bit {
@@ -43,8 +48,11 @@ impl XOR4Bit {
         XOR4Bit(xor_bit_table)
     }
 }
-impl LookUpTable for XOR4Bit {
-    fn borrow_map(&self) -> &HashMap<(Fr, Fr), Fr> {
+impl MapTable for XOR4Bit {
+    fn borrow_map(&self) -> &TableMap {
         &self.0.borrow_map()
     }
+    fn input_bits(&self) -> Option<usize> {
+        Some(BITS)
+    }
 }
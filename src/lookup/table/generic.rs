@@ -1,38 +1,169 @@
-use crate::lookup::table::LookUpTable;
+use crate::{
+    error::Error,
+    lookup::table::{DynamicTable, LookUpTable, TableHasher, TableMap},
+};
 use algebra::bls12_381::Fr;
-use std::collections::HashMap;
+use std::{
+    collections::{hash_map::Entry, HashMap},
+    hash::BuildHasher,
+};
+
+#[cfg(feature = "parallel")]
+use rayon::prelude::*;
 
 /// Construct a Generic lookup table over a bi-variate function
-pub struct Generic(HashMap<(Fr, Fr), Fr>);
+/// The rows are stored in a map hashed with `S`, which is the fast `TableHasher` unless the table is built with `with_map`
+/// The table implements `LookUpTable` for every hasher, so tables which wrap a `Generic` implement `MapTable`
+/// with its `borrow_map`.
+#[derive(Clone, Debug)]
+pub struct Generic<S = TableHasher>(HashMap<(Fr, Fr), Fr, S>);
 
 impl Generic {
     // Initialise a table using a bi-variate function over some bit-range
     pub fn with_fn<F>(f: F, n: usize) -> Self
     where
-        F: Fn(usize, usize) -> Fr,
+        F: Fn(usize, usize) -> Fr + Sync,
     {
-        let mut table = Generic(HashMap::new());
-
-        for i in 0..n {
-            let i_fr = Fr::from(i as u8);
+        Generic::with_fn_ranges(f, n, n)
+    }
+    // Initialise a table using a bi-variate function, where the left input ranges over 0..n_left
+    // and the right input ranges over 0..n_right
+    // With the `parallel` feature enabled, the rows are computed concurrently
+    pub fn with_fn_ranges<F>(f: F, n_left: usize, n_right: usize) -> Self
+    where
+        F: Fn(usize, usize) -> Fr + Sync,
+    {
+        let f = &f;
 
-            for k in 0..n {
-                let k_fr = Fr::from(k as u8);
+        #[cfg(feature = "parallel")]
+        let table = (0..n_left)
+            .into_par_iter()
+            .flat_map(|i| {
+                let i_fr = Fr::from(i as u64);
+                (0..n_right)
+                    .into_par_iter()
+                    .map(move |k| ((i_fr, Fr::from(k as u64)), f(i, k)))
+            })
+            .collect();
+        #[cfg(not(feature = "parallel"))]
+        let table = (0..n_left)
+            .flat_map(|i| {
+                let i_fr = Fr::from(i as u64);
+                (0..n_right).map(move |k| ((i_fr, Fr::from(k as u64)), f(i, k)))
+            })
+            .collect();
 
-                let result = f(i, k);
-                table.0.insert((i_fr, k_fr), result);
-            }
-        }
-        table
+        Generic(table)
     }
     // Initialise a table by passing all of its entries to the table
+    // The entries are moved into a map hashed with `TableHasher`, see `with_map` to keep the map as it is
     pub fn with_hashmap(map: HashMap<(Fr, Fr), Fr>) -> Self {
-        Generic(map)
+        Generic(map.into_iter().collect())
+    }
+    // Initialise a table from a list of rows, such as one read from a file
+    // Rows which repeat an earlier row are dropped, and their indices are returned so the caller can report them.
+    // The rows are kept by their input, so the order of the list does not change the table or its commitments,
+    // which are computed from the rows sorted by their inputs.
+    // Returns `Error::ConflictingRows` with the indices of the rows whose input has already been given a different output.
+    pub fn from_rows<I: IntoIterator<Item = ((Fr, Fr), Fr)>>(
+        rows: I,
+    ) -> Result<(Self, Vec<usize>), Error> {
+        let mut map = TableMap::default();
+        let mut duplicates = Vec::new();
+        let mut conflicts = Vec::new();
+        for (i, (key, value)) in rows.into_iter().enumerate() {
+            match map.entry(key) {
+                Entry::Vacant(entry) => {
+                    entry.insert(value);
+                }
+                Entry::Occupied(entry) if *entry.get() == value => duplicates.push(i),
+                Entry::Occupied(_) => conflicts.push(i),
+            }
+        }
+        if !conflicts.is_empty() {
+            return Err(Error::ConflictingRows(conflicts));
+        }
+        Ok((Generic(map), duplicates))
     }
 }
 
-impl LookUpTable for Generic {
-    fn borrow_map(&self) -> &HashMap<(Fr, Fr), Fr> {
+impl<S: BuildHasher> Generic<S> {
+    // Initialise a table from a map, which keeps the hasher it was built with
+    pub fn with_map(map: HashMap<(Fr, Fr), Fr, S>) -> Self {
+        Generic(map)
+    }
+    /// Returns the map that the rows are stored in
+    pub fn borrow_map(&self) -> &HashMap<(Fr, Fr), Fr, S> {
         &self.0
     }
 }
+
+impl<S: BuildHasher> DynamicTable for Generic<S> {
+    fn insert(&mut self, key: (Fr, Fr), value: Fr) -> Fr {
+        *self.0.entry(key).or_insert(value)
+    }
+}
+
+impl<S: BuildHasher> LookUpTable for Generic<S> {
+    fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    fn read(&self, key: &(Fr, Fr)) -> Option<Fr> {
+        self.0.get(key).copied()
+    }
+
+    fn iter(&self) -> Box<dyn Iterator<Item = ((Fr, Fr), Fr)> + '_> {
+        Box::new(self.0.iter().map(|(key, value)| (*key, *value)))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_with_fn_ranges() {
+        let func = |a: usize, b: usize| -> Fr { Fr::from((a * 1000 + b) as u64) };
+        let table = Generic::with_fn_ranges(func, 300, 200);
+
+        assert_eq!(table.len(), 300 * 200);
+        for (key, value) in table.iter() {
+            assert_eq!(value, key.0 * Fr::from(1000u64) + key.1);
+        }
+    }
+
+    #[test]
+    fn test_from_rows() {
+        let row = |a: u8, b: u8, c: u8| ((Fr::from(a), Fr::from(b)), Fr::from(c));
+        let rows = vec![row(2, 3, 5), row(1, 1, 2), row(2, 3, 5), row(1, 1, 2)];
+        let (table, duplicates) = Generic::from_rows(rows.clone()).unwrap();
+        assert_eq!(table.len(), 2);
+        assert_eq!(duplicates, vec![2, 3]);
+
+        // The order of the rows does not change the table
+        let (reversed, _) = Generic::from_rows(rows.into_iter().rev()).unwrap();
+        assert_eq!(table.to_multiset(), reversed.to_multiset());
+
+        match Generic::from_rows(vec![row(1, 1, 2), row(2, 3, 5), row(1, 1, 3)]) {
+            Err(Error::ConflictingRows(rows)) => assert_eq!(rows, vec![2]),
+            _ => panic!("expected the third row to conflict with the first"),
+        }
+    }
+
+    #[test]
+    fn test_with_map() {
+        // A table can be stored with any hasher, such as the standard library's SipHash
+        let map: HashMap<_, _> = (0..16u8)
+            .map(|i| ((Fr::from(i), Fr::from(0u8)), Fr::from(i)))
+            .collect();
+        let table = Generic::with_map(map.clone());
+        let fast = Generic::with_hashmap(map);
+        assert_eq!(table.len(), 16);
+        assert_eq!(
+            table.read(&(Fr::from(3u8), Fr::from(0u8))),
+            Some(Fr::from(3u8))
+        );
+        assert_eq!(table.to_multiset(), fast.to_multiset());
+    }
+}
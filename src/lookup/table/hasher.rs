@@ -0,0 +1,34 @@
+use algebra::bls12_381::Fr;
+use rustc_hash::FxHasher;
+use std::{collections::HashMap, hash::BuildHasherDefault};
+
+/// The hasher of the maps that tables are stored in, unless another is chosen with `Generic::with_map`
+///
+/// The standard library defaults to SipHash, which resists collisions chosen by an attacker but is slow for the
+/// four words of a field element. The rows of a table and the reads of a prover are chosen by the prover,
+/// so there is no attacker to resist, and the maps use the hash of `rustc-hash` instead.
+pub type TableHasher = BuildHasherDefault<FxHasher>;
+
+/// A map from the inputs of each row to its output
+pub type TableMap<S = TableHasher> = HashMap<(Fr, Fr), Fr, S>;
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::hash::{BuildHasher, Hash, Hasher};
+
+    #[test]
+    fn test_table_hasher() {
+        let hash = |key: &(Fr, Fr)| {
+            let mut hasher = TableHasher::default().build_hasher();
+            key.hash(&mut hasher);
+            hasher.finish()
+        };
+        let key = (Fr::from(3u8), Fr::from(5u8));
+        assert_eq!(hash(&key), hash(&(Fr::from(3u8), Fr::from(5u8))));
+        assert_ne!(hash(&key), hash(&(Fr::from(5u8), Fr::from(3u8))));
+
+        let map: TableMap = vec![(key, Fr::from(6u8))].into_iter().collect();
+        assert_eq!(map.get(&key), Some(&Fr::from(6u8)));
+    }
+}
@@ -0,0 +1,321 @@
+// A table whose commitments are updated in place as rows are appended or replaced
+//
+// `preprocess` sorts the rows, interpolates each column and commits to it, which costs an IFFT and an MSM of size `n`
+// per column. When a dynamic table grows by a few rows between proofs, most of that work is repeated.
+//
+// A column with evaluations v_i over the domain is committed to as sum_i v_i [L_i(tau)]G, where [L_i(tau)]G are the
+// commitments to the lagrange polynomials. An `IncrementalTable` keeps its rows in the order they were added, rather
+// than sorted, so that appending a row only changes the evaluations at the end of each column:
+// - the new row is written at position k, the number of rows before it,
+// - the padding after it repeats the last row, so positions k..n all change by the same amount,
+//   and the commitment changes by that amount times the sum of the bases from k onwards.
+// Replacing the output of a row changes one evaluation, or the last row and its padding.
+// Each update costs one scalar multiplication per column, against `LagrangeBasis`, which is computed once for `n`.
+use super::{DynamicTable, LookUpTable, PreProcessedTable, TableCommitments};
+use crate::{kzg10, multiset::MultiSet};
+use algebra::bls12_381::{Fr, G1Affine, G1Projective};
+use algebra::{Bls12_381, PrimeField};
+use algebra_core::{
+    curves::{AffineCurve, ProjectiveCurve},
+    msm::VariableBaseMSM,
+};
+use ff_fft::{DensePolynomial as Polynomial, EvaluationDomain};
+use num_traits::identities::Zero;
+use poly_commit::kzg10::{Commitment, Powers};
+use std::{collections::HashMap, sync::Arc};
+
+/// The commitments to the lagrange polynomials of the domain of size `n`, and their suffix sums
+/// It only depends on the SRS and `n`, so it can be shared between tables.
+#[derive(Clone, Debug)]
+pub struct LagrangeBasis {
+    bases: Vec<G1Affine>,
+    // suffix_sums[k] is the sum of bases[k..]
+    suffix_sums: Vec<G1Affine>,
+    // The digest of the powers the bases were computed from, which the table commitments carry
+    srs_digest: [u8; 32],
+}
+
+impl LagrangeBasis {
+    /// Computes the basis for tables padded to `n`, see `kzg10::lagrange_basis`
+    pub fn new(powers: &Powers<Bls12_381>, n: usize) -> LagrangeBasis {
+        let bases = kzg10::lagrange_basis(powers, n);
+
+        let mut suffix_sums = vec![G1Projective::zero(); n];
+        let mut sum = G1Projective::zero();
+        for (base, suffix_sum) in bases.iter().zip(suffix_sums.iter_mut()).rev() {
+            sum.add_assign_mixed(base);
+            *suffix_sum = sum;
+        }
+
+        LagrangeBasis {
+            bases,
+            suffix_sums: G1Projective::batch_normalization_into_affine(&suffix_sums),
+            srs_digest: kzg10::srs_digest(powers, n),
+        }
+    }
+    /// Returns the size of the domain
+    pub fn n(&self) -> usize {
+        self.bases.len()
+    }
+}
+
+/// A table of rows (a, b) -> c, with the commitments to its columns kept up to date as rows are added
+#[derive(Clone, Debug)]
+pub struct IncrementalTable {
+    basis: Arc<LagrangeBasis>,
+    // The columns without padding, in the order the rows were added
+    columns: Vec<MultiSet>,
+    positions: HashMap<(Fr, Fr), usize>,
+    commitments: Vec<G1Projective>,
+}
+
+impl IncrementalTable {
+    /// Creates an empty table, which can hold up to `basis.n()` rows
+    pub fn new(basis: Arc<LagrangeBasis>) -> IncrementalTable {
+        IncrementalTable {
+            basis,
+            columns: vec![MultiSet::new(), MultiSet::new(), MultiSet::new()],
+            positions: HashMap::new(),
+            commitments: vec![G1Projective::zero(); 3],
+        }
+    }
+    /// Creates a table holding the rows of `table`, in the order that `preprocess` puts them in
+    /// Until rows are added, the commitments are the same as those of `table.preprocess`.
+    pub fn from_table<T: LookUpTable + ?Sized>(
+        table: &T,
+        basis: Arc<LagrangeBasis>,
+    ) -> IncrementalTable {
+        let n = basis.n();
+        let (t_1, t_2, t_3) = table.to_multiset();
+        assert!(t_1.len() <= n, "the table has more than {} rows", n);
+
+        let positions = t_1
+            .0
+            .iter()
+            .zip(t_2.0.iter())
+            .enumerate()
+            .map(|(i, (a, b))| ((*a, *b), i))
+            .collect();
+        let columns = vec![t_1, t_2, t_3];
+
+        // Commit to the padded columns in the lagrange basis, which needs no interpolation
+        let commitments = columns
+            .iter()
+            .map(|column| {
+                if column.is_empty() {
+                    return G1Projective::zero();
+                }
+                let mut scalars: Vec<_> = column.0.iter().map(|v| v.into_repr()).collect();
+                scalars.resize(n, column.last().into_repr());
+                VariableBaseMSM::multi_scalar_mul(&basis.bases, &scalars)
+            })
+            .collect();
+
+        IncrementalTable {
+            basis,
+            columns,
+            positions,
+            commitments,
+        }
+    }
+    /// Returns the size of the domain that the table is padded to
+    pub fn n(&self) -> usize {
+        self.basis.n()
+    }
+    /// Replaces the output of the row with input `key`, updating the commitment to the output column
+    /// Returns the previous output, or None if the key is not in the table, in which case nothing is changed.
+    pub fn replace(&mut self, key: &(Fr, Fr), value: Fr) -> Option<Fr> {
+        let position = *self.positions.get(key)?;
+        let old = self.columns[2].0[position];
+        self.columns[2].0[position] = value;
+
+        // The padding repeats the last row, so it changes with it
+        let base = if position == self.columns[2].len() - 1 {
+            &self.basis.suffix_sums[position]
+        } else {
+            &self.basis.bases[position]
+        };
+        self.commitments[2] += &base.mul(value - old);
+        Some(old)
+    }
+    /// Returns the commitments to the columns of the table
+    pub fn commitments(&self) -> TableCommitments {
+        let commitments = kzg10::batch_normalize(&self.commitments);
+        TableCommitments {
+            id: self.id(),
+            n: self.n(),
+            srs_digest: self.basis.srs_digest,
+            t_1: commitments[0],
+            t_2: commitments[1],
+            t_3: commitments[2],
+        }
+    }
+    /// Returns the table padded and interpolated for the prover, with the commitments kept by the table
+    /// The columns are interpolated again, but not committed to again.
+    /// Panics if the table is empty.
+    pub fn preprocessed(&self) -> PreProcessedTable {
+        assert!(!self.columns[0].is_empty(), "the table has no rows");
+        let n = self.n();
+        let domain: EvaluationDomain<Fr> = EvaluationDomain::new(n).unwrap();
+
+        let columns: Vec<_> = self
+            .columns
+            .iter()
+            .map(|column| {
+                let mut column = column.clone();
+                column.extend(n - column.len(), column.last());
+                column
+            })
+            .collect();
+        let polys = columns
+            .iter()
+            .map(|column| Polynomial::from_coefficients_vec(domain.ifft(&column.0)))
+            .collect();
+        let commitments: Vec<Commitment<Bls12_381>> = kzg10::batch_normalize(&self.commitments);
+
+        PreProcessedTable::from_parts(
+            self.id(),
+            n,
+            self.basis.srs_digest,
+            columns,
+            commitments,
+            polys,
+        )
+    }
+}
+
+impl LookUpTable for IncrementalTable {
+    fn len(&self) -> usize {
+        self.columns[0].len()
+    }
+
+    fn read(&self, key: &(Fr, Fr)) -> Option<Fr> {
+        self.positions
+            .get(key)
+            .map(|position| self.columns[2].0[*position])
+    }
+
+    fn iter(&self) -> Box<dyn Iterator<Item = ((Fr, Fr), Fr)> + '_> {
+        Box::new((0..self.len()).map(move |i| {
+            (
+                (self.columns[0].0[i], self.columns[1].0[i]),
+                self.columns[2].0[i],
+            )
+        }))
+    }
+
+    /// Returns the columns in the order the rows were added, which is the order that the commitments are kept in
+    /// `preprocess` and `id` use this order too, so they agree with `preprocessed` and `commitments`.
+    /// Two incremental tables with the same rows added in a different order have different identifiers.
+    fn to_multiset(&self) -> (MultiSet, MultiSet, MultiSet) {
+        (
+            self.columns[0].clone(),
+            self.columns[1].clone(),
+            self.columns[2].clone(),
+        )
+    }
+}
+
+impl DynamicTable for IncrementalTable {
+    /// Appends the row (key, value) to the table, if the key is not already in the table,
+    /// updating the commitment to each column
+    /// Panics if the table already holds `n` rows.
+    fn insert(&mut self, key: (Fr, Fr), value: Fr) -> Fr {
+        if let Some(output) = self.read(&key) {
+            return output;
+        }
+        let position = self.len();
+        assert!(position < self.n(), "the table is full");
+
+        let row = [key.0, key.1, value];
+        for ((column, commitment), v) in self
+            .columns
+            .iter_mut()
+            .zip(self.commitments.iter_mut())
+            .zip(row.iter())
+        {
+            // Every position from this one onwards held the padding, which was the last row or zero
+            let padding = if column.is_empty() {
+                Fr::zero()
+            } else {
+                column.last()
+            };
+            *commitment += &self.basis.suffix_sums[position].mul(*v - padding);
+            column.push(*v);
+        }
+        self.positions.insert(key, position);
+        value
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::lookup::{lookup::LookUp, table::four_bits::XOR4Bit};
+    use merlin::Transcript;
+
+    #[test]
+    fn test_incremental_commitments() {
+        let (proving_key, verifier_key) = kzg10::trusted_setup(2usize.pow(10), b"insecure_seed");
+        let n = 2usize.pow(8);
+        let basis = Arc::new(LagrangeBasis::new(&proving_key, n));
+
+        let mut table = IncrementalTable::new(basis.clone());
+        for i in 0..10u64 {
+            table.insert((Fr::from(i), Fr::from(i + 1)), Fr::from(i * 7));
+        }
+        table.insert((Fr::from(3u8), Fr::from(4u8)), Fr::from(99u8));
+        assert_eq!(table.len(), 10);
+        assert_eq!(
+            table.replace(&(Fr::from(3u8), Fr::from(4u8)), Fr::from(5u8)),
+            Some(Fr::from(21u8))
+        );
+        // Replacing the last row also changes the padding
+        table.replace(&(Fr::from(9u8), Fr::from(10u8)), Fr::from(1u8));
+        assert_eq!(
+            table.replace(&(Fr::from(1u8), Fr::from(1u8)), Fr::from(1u8)),
+            None
+        );
+
+        // The updated commitments are the commitments to the interpolated columns
+        let preprocessed = table.preprocessed();
+        let polys = vec![
+            &preprocessed.t_1.2,
+            &preprocessed.t_2.2,
+            &preprocessed.t_3.2,
+        ];
+        assert_eq!(
+            kzg10::commit_many(&proving_key, polys),
+            vec![preprocessed.t_1.1, preprocessed.t_2.1, preprocessed.t_3.1]
+        );
+
+        let mut lookup = LookUp::new(table);
+        lookup.read((3u8, 4u8));
+        lookup.read_or_insert(&(Fr::from(20u8), Fr::from(21u8)), Fr::from(22u8));
+        let preprocessed = lookup.table().preprocessed();
+
+        let mut prover_transcript = Transcript::new(b"incremental");
+        let proof = lookup.prove(&proving_key, &preprocessed, &mut prover_transcript);
+        let mut verifier_transcript = Transcript::new(b"incremental");
+        assert!(proof.verify(&verifier_key, &preprocessed, &mut verifier_transcript));
+
+        // Preprocessing the table from scratch gives the same table as the one kept up to date
+        let table = lookup.table();
+        let from_scratch = table.preprocess(&proving_key, n);
+        assert_eq!(from_scratch.id, preprocessed.id);
+        assert_eq!(from_scratch.commitments(), preprocessed.commitments());
+        assert_eq!(from_scratch.t_3.0, preprocessed.t_3.0);
+        assert_eq!(
+            from_scratch.merged(Fr::from(5u8)),
+            preprocessed.merged(Fr::from(5u8))
+        );
+
+        // A table built from the same rows in sorted order has the same commitments as `preprocess`
+        let xor = XOR4Bit::new();
+        let table = IncrementalTable::from_table(&xor, basis);
+        assert_eq!(
+            table.commitments(),
+            xor.preprocess(&proving_key, n).commitments()
+        );
+    }
+}
@@ -0,0 +1,123 @@
+use crate::{
+    encoding::fr_to_u64,
+    lookup::table::{DynamicTable, LookUpTable},
+    multiset::MultiSet,
+};
+use algebra::bls12_381::Fr;
+use std::collections::HashMap;
+
+#[cfg(feature = "parallel")]
+use rayon::prelude::*;
+
+/// A table of rows (a, b) -> c whose inputs and outputs are integers, stored in a hash map of integers
+///
+/// Reads hash two integers rather than two field elements, and `read_u64` never converts to field elements at all.
+/// The rows are converted into field elements only when they are iterated or preprocessed.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct IntegerTable(HashMap<(u64, u64), u64>);
+
+impl IntegerTable {
+    // Initialise a table using a bi-variate function, where the left input ranges over 0..n_left
+    // and the right input ranges over 0..n_right
+    // With the `parallel` feature enabled, the rows are computed concurrently
+    pub fn with_fn_ranges<F>(f: F, n_left: u64, n_right: u64) -> Self
+    where
+        F: Fn(u64, u64) -> u64 + Sync,
+    {
+        let f = &f;
+
+        #[cfg(feature = "parallel")]
+        let table = (0..n_left)
+            .into_par_iter()
+            .flat_map(|i| (0..n_right).into_par_iter().map(move |k| ((i, k), f(i, k))))
+            .collect();
+        #[cfg(not(feature = "parallel"))]
+        let table = (0..n_left)
+            .flat_map(|i| (0..n_right).map(move |k| ((i, k), f(i, k))))
+            .collect();
+
+        IntegerTable(table)
+    }
+    // Initialise a table by passing all of its entries to the table
+    pub fn with_hashmap(map: HashMap<(u64, u64), u64>) -> Self {
+        IntegerTable(map)
+    }
+}
+
+impl LookUpTable for IntegerTable {
+    fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    fn read(&self, key: &(Fr, Fr)) -> Option<Fr> {
+        let output = self.0.get(&(fr_to_u64(&key.0)?, fr_to_u64(&key.1)?))?;
+        Some(Fr::from(*output))
+    }
+
+    fn read_u64(&self, a: u64, b: u64) -> Option<u64> {
+        self.0.get(&(a, b)).copied()
+    }
+
+    fn iter(&self) -> Box<dyn Iterator<Item = ((Fr, Fr), Fr)> + '_> {
+        Box::new(
+            self.0
+                .iter()
+                .map(|((a, b), c)| ((Fr::from(*a), Fr::from(*b)), Fr::from(*c))),
+        )
+    }
+
+    /// Sorts the rows as integers, which orders them as the default sorts field elements,
+    /// and converts them into field elements afterwards
+    fn to_multiset(&self) -> (MultiSet, MultiSet, MultiSet) {
+        let mut rows: Vec<_> = self.0.iter().map(|((a, b), c)| (*a, *b, *c)).collect();
+        rows.sort_unstable();
+
+        let mut columns = (MultiSet::new(), MultiSet::new(), MultiSet::new());
+        for (a, b, c) in rows {
+            columns.0.push_u64(a);
+            columns.1.push_u64(b);
+            columns.2.push_u64(c);
+        }
+        columns
+    }
+}
+
+impl DynamicTable for IntegerTable {
+    /// Panics if the key or the value does not fit in 64 bits
+    fn insert(&mut self, key: (Fr, Fr), value: Fr) -> Fr {
+        let key = (
+            fr_to_u64(&key.0).expect("the input does not fit in 64 bits"),
+            fr_to_u64(&key.1).expect("the input does not fit in 64 bits"),
+        );
+        let value = fr_to_u64(&value).expect("the output does not fit in 64 bits");
+        Fr::from(*self.0.entry(key).or_insert(value))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::lookup::{lookup::LookUp, table::four_bits::XOR4Bit};
+
+    #[test]
+    fn test_integer_table() {
+        let table = IntegerTable::with_fn_ranges(|a, b| a ^ b, 16, 16);
+        assert_eq!(table.len(), 256);
+        assert_eq!(table.read_u64(3, 5), Some(6));
+        assert_eq!(
+            table.read(&(Fr::from(3u8), Fr::from(5u8))),
+            Some(Fr::from(6u8))
+        );
+        assert!(table.read(&(-Fr::from(1u8), Fr::from(5u8))).is_none());
+        assert_eq!(table.to_multiset(), XOR4Bit::new().to_multiset());
+
+        let mut lookup = LookUp::new(table);
+        assert_eq!(lookup.read_u8(3, 5), Some(6));
+        assert_eq!(lookup.read_u64(16, 5), None);
+        assert_eq!(lookup.output_wires().0, vec![Fr::from(6u8)]);
+
+        let mut table = IntegerTable::default();
+        table.insert((Fr::from(1u8), Fr::from(2u8)), Fr::from(3u8));
+        assert_eq!(table.read_u64(1, 2), Some(3));
+    }
+}
@@ -0,0 +1,157 @@
+use super::{
+    base_conversion::{parity_bits, to_base},
+    Generic, MapTable, TableMap,
+};
+use algebra::bls12_381::Fr;
+
+/// The base used to represent lanes when computing the column parities in theta.
+/// Each column parity is the XOR of five bits, so a digit in base 6 can hold their sum without overflowing.
+pub const THETA_BASE: u64 = 6;
+
+/// Constructs a table for the chi step over `bits`-bit chunks of three lanes a, b, c
+/// (a, b + c * 2^bits) -> a XOR (NOT b AND c)
+///
+/// The table has 2^{3 * bits} rows, so `bits` will usually be small.
+pub struct Chi(Generic);
+
+impl Chi {
+    pub fn new(bits: usize) -> Self {
+        let mask = (1 << bits) - 1;
+        let func = |a: usize, bc: usize| -> Fr {
+            let b = bc & mask;
+            let c = bc >> bits;
+            Fr::from((a ^ (!b & c) & mask) as u64)
+        };
+
+        Chi(Generic::with_fn_ranges(func, 1 << bits, 1 << (2 * bits)))
+    }
+    /// Returns the right input to the table, which packs the chunks of `b` and `c`
+    pub fn pack(b: u64, c: u64, bits: usize) -> Fr {
+        Fr::from(b + (c << bits))
+    }
+}
+
+impl MapTable for Chi {
+    fn borrow_map(&self) -> &TableMap {
+        &self.0.borrow_map()
+    }
+}
+
+/// Constructs a table for the theta step over chunks of `digits` digits
+/// (x, 0) -> p, where x is a chunk in base `THETA_BASE`, and bit i of p is the parity of digit i of x
+///
+/// Lanes are converted to base `THETA_BASE` with `to_sparse`,
+/// so that the five lanes in a column can be added in the field instead of XOR-ed.
+pub struct Theta(Generic);
+
+impl Theta {
+    pub fn new(digits: usize) -> Self {
+        let func =
+            |x: usize, _: usize| -> Fr { Fr::from(parity_bits(x as u64, digits, THETA_BASE)) };
+
+        Theta(Generic::with_fn_ranges(
+            func,
+            THETA_BASE.pow(digits as u32) as usize,
+            1,
+        ))
+    }
+}
+
+impl MapTable for Theta {
+    fn borrow_map(&self) -> &TableMap {
+        &self.0.borrow_map()
+    }
+}
+
+/// Constructs a table of rotations for small lanes
+/// (x, r) -> x rotated left by r, for every `lane_bits`-bit lane x and every 0 <= r < lane_bits
+///
+/// This covers the rho step of Keccak-f[25], Keccak-f[50], Keccak-f[100] and Keccak-f[200],
+/// where lanes are 1, 2, 4 and 8 bits wide.
+pub struct Rotate(Generic);
+
+impl Rotate {
+    pub fn new(lane_bits: usize) -> Self {
+        let mask = (1 << lane_bits) - 1;
+        let func = |x: usize, r: usize| -> Fr {
+            let rotated = if r == 0 {
+                x
+            } else {
+                ((x << r) | (x >> (lane_bits - r))) & mask
+            };
+            Fr::from(rotated as u64)
+        };
+
+        Rotate(Generic::with_fn_ranges(func, 1 << lane_bits, lane_bits))
+    }
+}
+
+impl MapTable for Rotate {
+    fn borrow_map(&self) -> &TableMap {
+        &self.0.borrow_map()
+    }
+}
+
+/// Converts the first `bits` bits of `x` into a number in base `THETA_BASE`, with one digit per bit
+pub fn to_sparse(x: u64, bits: usize) -> u64 {
+    to_base(x, bits, THETA_BASE)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::lookup::table::LookUpTable;
+
+    #[test]
+    fn test_chi() {
+        let bits = 2;
+        let chi = Chi::new(bits);
+        assert_eq!(chi.len(), 64);
+
+        for a in 0..4u64 {
+            for b in 0..4u64 {
+                for c in 0..4u64 {
+                    let expected = a ^ (!b & c) & 3;
+                    assert_eq!(
+                        chi.read(&(Fr::from(a), Chi::pack(b, c, bits))),
+                        Some(Fr::from(expected))
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_theta() {
+        let digits = 3;
+        let theta = Theta::new(digits);
+        assert_eq!(theta.len(), 216);
+
+        // The column parity of five 3-bit lanes
+        let lanes = [0b101u64, 0b110, 0b011, 0b111, 0b001];
+        let sparse_sum: u64 = lanes.iter().map(|lane| to_sparse(*lane, digits)).sum();
+        let parity = lanes.iter().fold(0, |acc, lane| acc ^ lane);
+
+        assert_eq!(
+            theta.read(&(Fr::from(sparse_sum), Fr::from(0u8))),
+            Some(Fr::from(parity))
+        );
+    }
+
+    #[test]
+    fn test_rotate() {
+        let rotate = Rotate::new(4);
+        assert_eq!(rotate.len(), 64);
+        assert_eq!(
+            rotate.read(&(Fr::from(0b1001u8), Fr::from(1u8))),
+            Some(Fr::from(0b0011u8))
+        );
+        assert_eq!(
+            rotate.read(&(Fr::from(0b1001u8), Fr::from(0u8))),
+            Some(Fr::from(0b1001u8))
+        );
+        assert!(rotate
+            .read(&(Fr::from(0b1001u8), Fr::from(4u8)))
+            .is_none());
+    }
+}
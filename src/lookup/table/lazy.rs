@@ -0,0 +1,108 @@
+use crate::lookup::table::LookUpTable;
+use algebra::{bls12_381::Fr, PrimeField};
+
+/// A table over a bi-variate function, whose rows are computed on demand
+///
+/// Reads evaluate the function directly, so witnesses can be built against very large tables
+/// without materialising them. The rows are only enumerated when the table is preprocessed.
+pub struct Lazy<F>
+where
+    F: Fn(usize, usize) -> Fr,
+{
+    f: F,
+    n_left: usize,
+    n_right: usize,
+}
+
+impl<F> Lazy<F>
+where
+    F: Fn(usize, usize) -> Fr,
+{
+    // Initialise a table using a bi-variate function over some bit-range
+    pub fn with_fn(f: F, n: usize) -> Self {
+        Lazy::with_fn_ranges(f, n, n)
+    }
+    // Initialise a table using a bi-variate function, where the left input ranges over 0..n_left
+    // and the right input ranges over 0..n_right
+    pub fn with_fn_ranges(f: F, n_left: usize, n_right: usize) -> Self {
+        Lazy {
+            f,
+            n_left,
+            n_right,
+        }
+    }
+}
+
+impl<F> LookUpTable for Lazy<F>
+where
+    F: Fn(usize, usize) -> Fr,
+{
+    fn len(&self) -> usize {
+        self.n_left * self.n_right
+    }
+
+    fn read(&self, key: &(Fr, Fr)) -> Option<Fr> {
+        let left = to_index(&key.0, self.n_left)?;
+        let right = to_index(&key.1, self.n_right)?;
+        Some((self.f)(left, right))
+    }
+
+    fn iter(&self) -> Box<dyn Iterator<Item = ((Fr, Fr), Fr)> + '_> {
+        Box::new((0..self.n_left).flat_map(move |i| {
+            (0..self.n_right).map(move |k| {
+                (
+                    (Fr::from(i as u64), Fr::from(k as u64)),
+                    (self.f)(i, k),
+                )
+            })
+        }))
+    }
+}
+
+// Converts a field element into an index, if it is less than `n`
+fn to_index(x: &Fr, n: usize) -> Option<usize> {
+    let repr = x.into_repr();
+    let limbs = repr.as_ref();
+    if limbs[1..].iter().any(|limb| *limb != 0) || limbs[0] >= n as u64 {
+        return None;
+    }
+    Some(limbs[0] as usize)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::lookup::{lookup::LookUp, table::four_bits::XOR4Bit};
+
+    #[test]
+    fn test_lazy_table() {
+        let xor = |a: usize, b: usize| -> Fr { Fr::from((a ^ b) as u64) };
+        let table = Lazy::with_fn(xor, 16);
+
+        assert_eq!(table.len(), 256);
+        assert_eq!(
+            table.read(&(Fr::from(3u8), Fr::from(5u8))),
+            Some(Fr::from(6u8))
+        );
+        assert!(table.read(&(Fr::from(16u8), Fr::from(5u8))).is_none());
+        assert!(table.read(&(-Fr::from(1u8), Fr::from(5u8))).is_none());
+
+        // The rows are the same as the materialised table
+        assert_eq!(table.to_multiset(), XOR4Bit::new().to_multiset());
+    }
+
+    #[test]
+    fn test_lazy_witness() {
+        // A 16-bit XOR table has 2^32 rows, which we never materialise
+        let xor = |a: usize, b: usize| -> Fr { Fr::from((a ^ b) as u64) };
+        let mut lookup = LookUp::new(Lazy::with_fn(xor, 1 << 16));
+
+        assert_eq!(
+            lookup.read(&(Fr::from(0xbeefu64), Fr::from(0x1234u64))),
+            Some(Fr::from(0xbeefu64 ^ 0x1234))
+        );
+        assert!(lookup
+            .read(&(Fr::from(0x10000u64), Fr::from(0x1234u64)))
+            .is_none());
+    }
+}
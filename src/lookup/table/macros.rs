@@ -0,0 +1,73 @@
+/// Declares a lookup table from a bi-variate function and the domain of each input
+///
+/// The expression form evaluates to a `Generic` table:
+/// ```ignore
+/// let xor = table!(|a in 0..16, b in 0..16| a ^ b);
+/// ```
+///
+/// The item form declares a new table type, with a `new` constructor and a `MapTable` implementation:
+/// ```ignore
+/// table!(pub struct Xor4 = |a in 0..16, b in 0..16| a ^ b);
+/// let xor = Xor4::new();
+/// ```
+///
+/// The inputs and the output of the function are converted into field elements using `as u64`,
+/// so they must be non-negative integers.
+#[macro_export]
+macro_rules! table {
+    (|$a:ident in $a_range:expr, $b:ident in $b_range:expr| $body:expr) => {{
+        let mut map = $crate::lookup::table::TableMap::default();
+        for $a in $a_range {
+            for $b in $b_range {
+                let output = $body;
+                map.insert(
+                    (
+                        $crate::lookup::table::Fr::from($a as u64),
+                        $crate::lookup::table::Fr::from($b as u64),
+                    ),
+                    $crate::lookup::table::Fr::from(output as u64),
+                );
+            }
+        }
+        $crate::lookup::table::Generic::with_map(map)
+    }};
+    ($vis:vis struct $name:ident = |$a:ident in $a_range:expr, $b:ident in $b_range:expr| $body:expr) => {
+        $vis struct $name($crate::lookup::table::Generic);
+
+        impl $name {
+            pub fn new() -> Self {
+                $name($crate::table!(|$a in $a_range, $b in $b_range| $body))
+            }
+        }
+
+        impl $crate::lookup::table::MapTable for $name {
+            fn borrow_map(&self) -> &$crate::lookup::table::TableMap {
+                self.0.borrow_map()
+            }
+        }
+    };
+}
+
+#[cfg(test)]
+mod test {
+    use crate::lookup::table::{four_bits::XOR4Bit, LookUpTable, MapTable};
+    use algebra::bls12_381::Fr;
+
+    crate::table!(struct Xor4 = |a in 0..16, b in 0..16| a ^ b);
+
+    #[test]
+    fn test_table_macro() {
+        let xor = crate::table!(|a in 0..16, b in 0..16| a ^ b);
+        assert_eq!(xor.borrow_map(), XOR4Bit::new().borrow_map());
+        assert_eq!(Xor4::new().borrow_map(), XOR4Bit::new().borrow_map());
+
+        // Ranges do not have to start at zero
+        let shifted = crate::table!(|a in 4..8, b in 0..2| a << b);
+        assert_eq!(shifted.len(), 8);
+        assert_eq!(
+            shifted.read(&(Fr::from(5u8), Fr::from(1u8))),
+            Some(Fr::from(10u8))
+        );
+        assert!(shifted.read(&(Fr::from(3u8), Fr::from(1u8))).is_none());
+    }
+}
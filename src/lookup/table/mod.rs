@@ -1,34 +1,143 @@
-use crate::{kzg10, multiset::MultiSet};
-use algebra::{bls12_381::Fr, Bls12_381};
+use crate::{
+    encoding::fr_to_u64,
+    kzg10,
+    multiset::{quotient_poly::QuotientKey, Interpolated, MultiSet},
+    transcript::TranscriptProtocol,
+};
+pub use algebra::bls12_381::Fr;
+use algebra::{Bls12_381, PrimeField, ProjectiveCurve};
 use ff_fft::{DensePolynomial as Polynomial, EvaluationDomain};
+use merlin::Transcript;
+use num_traits::identities::{One, Zero};
 use poly_commit::kzg10::{Commitment, Powers};
-use std::collections::HashMap;
 
+#[cfg(feature = "baked-tables")]
+pub mod baked;
+pub mod base_conversion;
+pub mod blake;
+pub mod cache;
+pub mod charset;
+pub mod fixed_base;
 pub mod four_bits;
 pub mod generic;
+pub mod hasher;
+pub mod incremental;
+pub mod integer;
+pub mod keccak;
+pub mod lazy;
+mod macros;
+pub mod reinforced_concrete;
+pub mod signed;
+pub mod sorted;
+pub mod store;
+pub mod wildcard;
+pub use cache::{CacheKey, CommitmentCache, InMemoryCache};
 pub use generic::Generic;
+pub use hasher::{TableHasher, TableMap};
+pub use incremental::{IncrementalTable, LagrangeBasis};
+pub use integer::IntegerTable;
+pub use lazy::Lazy;
+pub use sorted::SortedTable;
 
+/// Identifies the rows of a table
+/// It is absorbed into the transcript and carried in every proof,
+/// so a proof made against one table is rejected when verified against another.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct TableId(pub [u8; 32]);
+
+/// The commitments to the columns of a preprocessed table, which is all the verifier needs of the table
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct TableCommitments {
+    pub id: TableId,
+    pub n: usize,
+    /// The digest of the powers the columns were committed with, see `kzg10::srs_digest`
+    /// It is absorbed with the identifier of the table, so a proof is bound to the SRS it was made with.
+    pub srs_digest: [u8; 32],
+    pub t_1: Commitment<Bls12_381>,
+    pub t_2: Commitment<Bls12_381>,
+    pub t_3: Commitment<Bls12_381>,
+}
+
+/// Table commitments with precomputed multiples of t_2 and t_3, for verifiers which check many proofs against one table
+/// Each proof folds the table as t_1 + alpha * t_2 + alpha^2 * t_3 with its own `alpha`,
+/// and the precomputed multiples replace the two scalar multiplications with additions.
+#[derive(Clone, Debug)]
+pub struct PreparedTableCommitments {
+    pub commitments: TableCommitments,
+    t_2: kzg10::FixedBaseTable,
+    t_3: kzg10::FixedBaseTable,
+}
+
+impl TableCommitments {
+    /// Precomputes the multiples of the commitments that folding the table needs, see `PreparedTableCommitments`
+    pub fn prepare(&self) -> PreparedTableCommitments {
+        PreparedTableCommitments {
+            commitments: *self,
+            t_2: kzg10::FixedBaseTable::new(self.t_2.0),
+            t_3: kzg10::FixedBaseTable::new(self.t_3.0),
+        }
+    }
+}
+
+impl PreparedTableCommitments {
+    /// Returns the commitment to the table folded with `alpha`, as `kzg10::aggregate_commitments` computes it
+    pub fn merged(&self, alpha: Fr) -> Commitment<Bls12_381> {
+        let mut merged = self.t_2.mul(&alpha) + &self.t_3.mul(&(alpha * alpha));
+        merged.add_assign_mixed(&self.commitments.t_1.0);
+        Commitment(merged.into())
+    }
+}
+
+#[derive(Clone, Debug)]
 pub struct PreProcessedTable {
+    pub id: TableId,
     pub n: usize,
+    /// The digest of the powers the columns were committed with, see `kzg10::srs_digest`
+    pub srs_digest: [u8; 32],
     pub t_1: (MultiSet, Commitment<Bls12_381>, Polynomial<Fr>),
     pub t_2: (MultiSet, Commitment<Bls12_381>, Polynomial<Fr>),
     pub t_3: (MultiSet, Commitment<Bls12_381>, Polynomial<Fr>),
+    /// The domains and inverses used by the prover to compute the quotient polynomial
+    pub quotient_key: QuotientKey,
 }
 
+/// A table of rows (a, b) -> c
+///
+/// Tables only need to be able to compute the output for an input, and to enumerate their rows.
+/// Reads are used while building the witness, so structured tables can compute them directly,
+/// while the rows are only enumerated when the table is preprocessed.
+/// Tables which are stored as a map only need to implement `MapTable`.
+/// The trait is object safe, and a table chosen at runtime can be used as a `Box<dyn LookUpTable>`.
 pub trait LookUpTable {
     /// Returns the number of entries in the lookup table
-    fn len(&self) -> usize {
-        self.borrow_map().keys().len()
-    }
-
-    /// We represent the lookup table as a map
-    /// Returns an immutable copy of the map
-    fn borrow_map(&self) -> &HashMap<(Fr, Fr), Fr>;
+    fn len(&self) -> usize;
 
     /// Fetches the appropriate lookup table value
     /// Given its input
-    fn read(&self, key: &(Fr, Fr)) -> Option<&Fr> {
-        self.borrow_map().get(key)
+    fn read(&self, key: &(Fr, Fr)) -> Option<Fr>;
+
+    /// Iterates over the rows of the table as (input, output) pairs
+    fn iter(&self) -> Box<dyn Iterator<Item = ((Fr, Fr), Fr)> + '_>;
+
+    /// Returns the output of the row with integer inputs (a, b), if the row is in the table and its output fits in 64 bits
+    /// Tables which store integers override this, so that reads do not convert to and from field elements.
+    fn read_u64(&self, a: u64, b: u64) -> Option<u64> {
+        fr_to_u64(&self.read(&(Fr::from(a), Fr::from(b)))?)
+    }
+
+    /// Returns true if the table contains a row with the given input
+    fn contains(&self, key: &(Fr, Fr)) -> bool {
+        self.read(key).is_some()
+    }
+
+    /// Returns true if the table has no rows
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns the number of bits in each input of the table, if the inputs have a fixed width
+    fn input_bits(&self) -> Option<usize> {
+        None
     }
 
     /// Given a lookup table where each row contains three entries (a,b,c)
@@ -36,15 +145,21 @@ pub trait LookUpTable {
     /// a = {a_0, a_1, a_2, a_3,...,a_n}
     /// b = {b_0, b_1, b_2, b_3,...,b_n}
     /// c = {c_0, c_1, c_2, c_3,...,c_n}
+    ///
+    /// The rows are sorted by their inputs, so the multisets (and therefore the table commitments)
+    /// do not depend on the order in which the table was iterated.
     fn to_multiset(&self) -> (MultiSet, MultiSet, MultiSet) {
         let mut table_multiset_left = MultiSet::new();
         let mut table_multiset_right = MultiSet::new();
         let mut table_multiset_out = MultiSet::new();
 
-        for (key, value) in self.borrow_map().iter() {
+        let mut rows: Vec<_> = self.iter().collect();
+        rows.sort_unstable_by_key(|(key, _)| (key.0.into_repr(), key.1.into_repr()));
+
+        for (key, value) in rows {
             let input_0 = key.0;
             let input_1 = key.1;
-            let output = *value;
+            let output = value;
 
             table_multiset_left.push(input_0);
             table_multiset_right.push(input_1);
@@ -57,39 +172,334 @@ pub trait LookUpTable {
             table_multiset_out,
         )
     }
+    /// Returns the identifier of the table, which is a digest of its rows
+    /// Two tables have the same identifier exactly when they have the same rows
+    fn id(&self) -> TableId {
+        let (t_1, t_2, t_3) = self.to_multiset();
+
+        let mut transcript = Transcript::new(b"plookup_table");
+        transcript.append_u64(b"len", t_1.len() as u64);
+        for column in vec![&t_1, &t_2, &t_3] {
+            for value in column.0.iter() {
+                transcript.append_scalar(b"value", value);
+            }
+        }
+
+        let mut digest = [0u8; 32];
+        transcript.challenge_bytes(b"digest", &mut digest);
+        TableId(digest)
+    }
     /// Pre-process a table by padding it to a size `n` commitment to each column in the table
     /// `n` will usually be equal to the size of your circuit, when padded.
     fn preprocess(&self, commit_key: &Powers<Bls12_381>, n: usize) -> PreProcessedTable {
-        assert!(n.is_power_of_two());
+        let (columns, polys) = pad_and_interpolate(self.to_multiset(), n);
+        let commitments = kzg10::commit_many(commit_key, polys.iter().collect());
+        let srs_digest = kzg10::srs_digest(commit_key, n);
+
+        PreProcessedTable::from_parts(self.id(), n, srs_digest, columns, commitments, polys)
+    }
+    /// Pre-process a table, reusing the commitments to its columns if they are in `cache`
+    /// Newly computed commitments are added to the cache.
+    fn preprocess_with_cache(
+        &self,
+        commit_key: &Powers<Bls12_381>,
+        n: usize,
+        cache: &dyn CommitmentCache,
+    ) -> PreProcessedTable {
+        let (columns, polys) = pad_and_interpolate(self.to_multiset(), n);
+
+        let id = self.id();
+        let key = CacheKey {
+            table_id: id,
+            srs_digest: kzg10::srs_digest(commit_key, n),
+            n,
+        };
+        let commitments = match cache.get(&key) {
+            Some(commitments) => commitments.to_vec(),
+            None => {
+                let commitments = kzg10::commit_many(commit_key, polys.iter().collect());
+                cache.insert(key, [commitments[0], commitments[1], commitments[2]]);
+                commitments
+            }
+        };
+
+        PreProcessedTable::from_parts(id, n, key.srs_digest, columns, commitments, polys)
+    }
+}
+
+// Pads each column of the table to size `n` by repeating its last value,
+// and interpolates each column over the domain of size `n`
+fn pad_and_interpolate(
+    columns: (MultiSet, MultiSet, MultiSet),
+    n: usize,
+) -> (Vec<MultiSet>, Vec<Polynomial<Fr>>) {
+    assert!(n.is_power_of_two());
 
-        let (mut t_1, mut t_2, mut t_3) = self.to_multiset();
+    let (t_1, t_2, t_3) = columns;
 
-        let k = t_1.len();
-        assert_eq!(t_1.len(), k);
-        assert_eq!(t_2.len(), k);
-        assert_eq!(t_3.len(), k);
+    let k = t_1.len();
+    assert_eq!(t_1.len(), k);
+    assert_eq!(t_2.len(), k);
+    assert_eq!(t_3.len(), k);
 
-        let domain: EvaluationDomain<Fr> = EvaluationDomain::new(n).unwrap();
+    let domain: EvaluationDomain<Fr> = EvaluationDomain::new(n).unwrap();
 
+    let mut columns = vec![t_1, t_2, t_3];
+    let mut polys = Vec::with_capacity(3);
+    for column in columns.iter_mut() {
         // Pad
-        let pad_by = n - t_1.len();
-        t_1.extend(pad_by, t_1.last());
-        t_2.extend(pad_by, t_2.last());
-        t_3.extend(pad_by, t_3.last());
+        let pad_by = n - column.len();
+        column.extend(pad_by, column.last());
 
-        let t_1_poly = Polynomial::from_coefficients_vec(domain.ifft(&t_1.0));
-        let t_2_poly = Polynomial::from_coefficients_vec(domain.ifft(&t_2.0));
-        let t_3_poly = Polynomial::from_coefficients_vec(domain.ifft(&t_3.0));
+        polys.push(Polynomial::from_coefficients_vec(domain.ifft(&column.0)));
+    }
+
+    (columns, polys)
+}
 
-        let t_1_commit = kzg10::commit(commit_key, &t_1_poly);
-        let t_2_commit = kzg10::commit(commit_key, &t_2_poly);
-        let t_3_commit = kzg10::commit(commit_key, &t_3_poly);
+impl PreProcessedTable {
+    /// Returns the commitments to the columns of the table
+    pub fn commitments(&self) -> TableCommitments {
+        TableCommitments {
+            id: self.id,
+            n: self.n,
+            srs_digest: self.srs_digest,
+            t_1: self.t_1.1,
+            t_2: self.t_2.1,
+            t_3: self.t_3.1,
+        }
+    }
+    /// Aggregates the padded table columns into one multiset using `alpha`
+    /// This is the multiset `t` in the multiset equality argument
+    pub fn merged(&self, alpha: Fr) -> MultiSet {
+        MultiSet::aggregate(vec![&self.t_1.0, &self.t_2.0, &self.t_3.0], alpha)
+    }
+    /// Aggregates the padded table columns as `merged` does, along with their polynomial
+    /// The polynomial is combined from the interpolated columns, so the merged table is not interpolated again.
+    pub fn merged_interpolated(&self, alpha: Fr) -> Interpolated {
+        let mut coeffs = vec![Fr::zero(); self.n];
+        let mut power = Fr::one();
+        for poly in vec![&self.t_1.2, &self.t_2.2, &self.t_3.2] {
+            for (merged, coeff) in coeffs.iter_mut().zip(poly.coeffs.iter()) {
+                *merged += &(*coeff * &power);
+            }
+            power *= &alpha;
+        }
+
+        Interpolated::with_polynomial(
+            self.merged(alpha),
+            *self.quotient_key.domain(),
+            Polynomial::from_coefficients_vec(coeffs),
+        )
+    }
+    fn from_parts(
+        id: TableId,
+        n: usize,
+        srs_digest: [u8; 32],
+        columns: Vec<MultiSet>,
+        commitments: Vec<Commitment<Bls12_381>>,
+        polys: Vec<Polynomial<Fr>>,
+    ) -> PreProcessedTable {
+        let mut parts = columns
+            .into_iter()
+            .zip(commitments.into_iter())
+            .zip(polys.into_iter())
+            .map(|((column, commitment), poly)| (column, commitment, poly));
 
         PreProcessedTable {
+            id,
             n: n,
-            t_1: (t_1, t_1_commit, t_1_poly),
-            t_2: (t_2, t_2_commit, t_2_poly),
-            t_3: (t_3, t_3_commit, t_3_poly),
+            srs_digest,
+            t_1: parts.next().unwrap(),
+            t_2: parts.next().unwrap(),
+            t_3: parts.next().unwrap(),
+            quotient_key: QuotientKey::new(n),
+        }
+    }
+}
+
+/// A table which the prover can add rows to while building the witness
+/// The table must only be preprocessed once all of its rows have been added.
+pub trait DynamicTable: LookUpTable {
+    /// Adds the row (key, value) to the table, if the key is not already in the table
+    /// Returns the output of the row with this key
+    fn insert(&mut self, key: (Fr, Fr), value: Fr) -> Fr;
+}
+
+/// A table which is fully materialised as a map from inputs to outputs
+/// The map is hashed with `TableHasher`. A table which is stored with another hasher implements
+/// `LookUpTable` directly, as `Generic` does.
+pub trait MapTable {
+    /// We represent the lookup table as a map
+    /// Returns an immutable copy of the map
+    fn borrow_map(&self) -> &TableMap;
+
+    /// Returns the number of bits in each input of the table, if the inputs have a fixed width
+    fn input_bits(&self) -> Option<usize> {
+        None
+    }
+}
+
+impl<T: MapTable> LookUpTable for T {
+    fn len(&self) -> usize {
+        self.borrow_map().keys().len()
+    }
+
+    fn read(&self, key: &(Fr, Fr)) -> Option<Fr> {
+        self.borrow_map().get(key).copied()
+    }
+
+    fn iter(&self) -> Box<dyn Iterator<Item = ((Fr, Fr), Fr)> + '_> {
+        Box::new(self.borrow_map().iter().map(|(key, value)| (*key, *value)))
+    }
+
+    fn input_bits(&self) -> Option<usize> {
+        MapTable::input_bits(self)
+    }
+}
+
+// Tables which are chosen at runtime are boxed, so that the prover is compiled once for every table type.
+// Every method is forwarded, so that the boxed table keeps any methods it overrides.
+macro_rules! impl_boxed_table {
+    ($boxed:ty) => {
+        impl LookUpTable for $boxed {
+            fn len(&self) -> usize {
+                (**self).len()
+            }
+            fn read(&self, key: &(Fr, Fr)) -> Option<Fr> {
+                (**self).read(key)
+            }
+            fn iter(&self) -> Box<dyn Iterator<Item = ((Fr, Fr), Fr)> + '_> {
+                (**self).iter()
+            }
+            fn read_u64(&self, a: u64, b: u64) -> Option<u64> {
+                (**self).read_u64(a, b)
+            }
+            fn contains(&self, key: &(Fr, Fr)) -> bool {
+                (**self).contains(key)
+            }
+            fn is_empty(&self) -> bool {
+                (**self).is_empty()
+            }
+            fn input_bits(&self) -> Option<usize> {
+                (**self).input_bits()
+            }
+            fn to_multiset(&self) -> (MultiSet, MultiSet, MultiSet) {
+                (**self).to_multiset()
+            }
+            fn id(&self) -> TableId {
+                (**self).id()
+            }
+            fn preprocess(&self, commit_key: &Powers<Bls12_381>, n: usize) -> PreProcessedTable {
+                (**self).preprocess(commit_key, n)
+            }
+            fn preprocess_with_cache(
+                &self,
+                commit_key: &Powers<Bls12_381>,
+                n: usize,
+                cache: &dyn CommitmentCache,
+            ) -> PreProcessedTable {
+                (**self).preprocess_with_cache(commit_key, n, cache)
+            }
+        }
+    };
+}
+
+impl_boxed_table!(Box<dyn LookUpTable>);
+impl_boxed_table!(Box<dyn LookUpTable + Send + Sync>);
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::lookup::table::four_bits::{Add4Bit, XOR4Bit};
+
+    #[test]
+    fn test_table_methods() {
+        let table = XOR4Bit::new();
+        assert_eq!(table.len(), 256);
+        assert!(!table.is_empty());
+
+        assert!(table.contains(&(Fr::from(3u8), Fr::from(5u8))));
+        assert!(!table.contains(&(Fr::from(16u8), Fr::from(5u8))));
+
+        let rows: Vec<_> = table.iter().collect();
+        assert_eq!(rows.len(), table.len());
+        for (key, value) in rows {
+            assert_eq!(table.read(&key), Some(value));
+        }
+
+        let (t_1, t_2, t_3) = table.to_multiset();
+        assert_eq!(t_1.len(), table.len());
+        assert_eq!(t_2.len(), table.len());
+        assert_eq!(t_3.len(), table.len());
+    }
+
+    #[test]
+    fn test_to_multiset_is_deterministic() {
+        // Insert the same rows in two different orders
+        let rows: Vec<_> = (0..64u64)
+            .map(|i| ((Fr::from(i % 8), Fr::from(i / 8)), Fr::from(i)))
+            .collect();
+        let table_a = Generic::with_hashmap(rows.iter().cloned().collect());
+        let table_b = Generic::with_hashmap(rows.iter().rev().cloned().collect());
+
+        assert_eq!(table_a.to_multiset(), table_b.to_multiset());
+
+        // Rows are ordered by their inputs
+        let (t_1, t_2, _) = table_a.to_multiset();
+        assert_eq!(t_1.0[0], Fr::from(0u8));
+        assert_eq!(t_2.0[0], Fr::from(0u8));
+        assert_eq!(t_1.0[1], Fr::from(0u8));
+        assert_eq!(t_2.0[1], Fr::from(1u8));
+    }
+
+    #[test]
+    fn test_merged_commitment() {
+        // The verifier never sees the merged table, only the commitments to its columns.
+        // Combining the column commitments with `alpha` gives the commitment to the merged table.
+        let (proving_key, _) = kzg10::trusted_setup(2usize.pow(9), b"insecure_seed");
+        let preprocessed_table = XOR4Bit::new().preprocess(&proving_key, 2usize.pow(8));
+        let commitments = preprocessed_table.commitments();
+
+        let alpha = Fr::from(5u8);
+        let domain: EvaluationDomain<Fr> = EvaluationDomain::new(preprocessed_table.n).unwrap();
+        let merged_poly = preprocessed_table.merged(alpha).to_polynomial(&domain);
+
+        let aggregated = kzg10::aggregate_commitments(
+            vec![&commitments.t_1, &commitments.t_2, &commitments.t_3],
+            alpha,
+        );
+        assert_eq!(aggregated, kzg10::commit(&proving_key, &merged_poly));
+
+        // The merged polynomial combined from the columns is the interpolation of the merged table
+        assert_eq!(
+            *preprocessed_table.merged_interpolated(alpha).polynomial(),
+            merged_poly
+        );
+    }
+
+    #[test]
+    fn test_boxed_table() {
+        use crate::lookup::lookup::LookUp;
+
+        let (proving_key, verifier_key) = kzg10::trusted_setup(2usize.pow(10), b"insecure_seed");
+        // The table is chosen at runtime
+        for name in &["xor", "add"] {
+            let table: Box<dyn LookUpTable> = match *name {
+                "xor" => Box::new(XOR4Bit::new()),
+                _ => Box::new(Add4Bit::new()),
+            };
+            assert_eq!(table.input_bits(), Some(4));
+            let preprocessed_table = table.preprocess(&proving_key, 2usize.pow(8));
+
+            let mut lookup = LookUp::new(table);
+            let expected = if *name == "xor" { 3 ^ 5 } else { 3 + 5 };
+            assert_eq!(lookup.read_u8(3, 5), Some(expected));
+
+            let mut prover_transcript = Transcript::new(b"boxed");
+            let proof = lookup.prove(&proving_key, &preprocessed_table, &mut prover_transcript);
+            let mut verifier_transcript = Transcript::new(b"boxed");
+            assert!(proof.verify(&verifier_key, &preprocessed_table, &mut verifier_transcript));
         }
     }
 }
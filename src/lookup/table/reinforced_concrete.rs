@@ -0,0 +1,169 @@
+use super::{Generic, MapTable, TableMap};
+use crate::lookup::lookup::LookUp;
+use algebra::{bls12_381::Fr, PrimeField};
+
+// The Bars layer of Reinforced Concrete decomposes a field element x into digits in a mixed radix (s_1, ..., s_n)
+// x = d_1 * (s_2 * ... * s_n) + d_2 * (s_3 * ... * s_n) + ... + d_n
+// applies a small S-box to every digit, and composes the result using the same radix.
+// The S-box permutes the digits below `v`, and leaves the digits v <= d < s_i unchanged.
+//
+// Both the decomposition and the S-box are checked with a single table, with a row (d, i) -> S(d) for every d < s_i
+// A read therefore also checks that every digit is in range.
+
+/// The parameters for the Bars layer
+pub struct BarsParams {
+    /// The radix of each digit, with the most significant digit first
+    pub decomposition: Vec<u64>,
+    /// The S-box, which must be a permutation of 0..sbox.len()
+    pub sbox: Vec<u64>,
+}
+
+impl BarsParams {
+    /// Applies the S-box to a single digit
+    pub fn apply_sbox(&self, digit: u64) -> u64 {
+        if (digit as usize) < self.sbox.len() {
+            self.sbox[digit as usize]
+        } else {
+            digit
+        }
+    }
+}
+
+/// Constructs a table of (d, i) -> S(d), for every position i and every digit d < s_i
+pub struct Bars {
+    params: BarsParams,
+    table: Generic,
+}
+
+impl Bars {
+    pub fn new(params: BarsParams) -> Self {
+        assert!(params
+            .decomposition
+            .iter()
+            .all(|s_i| *s_i as usize >= params.sbox.len()));
+
+        let mut map = TableMap::default();
+        for (i, s_i) in params.decomposition.iter().enumerate() {
+            let i_fr = Fr::from(i as u64);
+            for digit in 0..*s_i {
+                map.insert(
+                    (Fr::from(digit), i_fr),
+                    Fr::from(params.apply_sbox(digit)),
+                );
+            }
+        }
+
+        Bars {
+            params,
+            table: Generic::with_map(map),
+        }
+    }
+    /// Returns the parameters that the table was constructed with
+    pub fn params(&self) -> &BarsParams {
+        &self.params
+    }
+}
+
+impl MapTable for Bars {
+    fn borrow_map(&self) -> &TableMap {
+        &self.table.borrow_map()
+    }
+}
+
+/// Decomposes `x` into digits using the radix in `decomposition`, with the most significant digit first
+/// Returns None if x is not smaller than the product of the radix
+pub fn decompose(x: &Fr, decomposition: &[u64]) -> Option<Vec<u64>> {
+    let mut limbs = x.into_repr().as_ref().to_vec();
+
+    let mut digits = vec![0u64; decomposition.len()];
+    for (digit, s_i) in digits.iter_mut().zip(decomposition.iter()).rev() {
+        *digit = div_rem(&mut limbs, *s_i);
+    }
+
+    if limbs.iter().any(|limb| *limb != 0) {
+        return None;
+    }
+    Some(digits)
+}
+
+/// Composes digits using the radix in `decomposition`, with the most significant digit first
+pub fn compose(digits: &[u64], decomposition: &[u64]) -> Fr {
+    assert_eq!(digits.len(), decomposition.len());
+
+    digits
+        .iter()
+        .zip(decomposition.iter())
+        .fold(Fr::from(0u8), |acc, (digit, s_i)| {
+            acc * Fr::from(*s_i) + Fr::from(*digit)
+        })
+}
+
+/// Computes Bars(x), reading the S-box of every digit from the table
+/// Returns None if x cannot be decomposed using the radix of the table
+pub fn bars(lookup: &mut LookUp<Bars>, x: &Fr) -> Option<Fr> {
+    let decomposition = lookup.table().params().decomposition.clone();
+    let digits = decompose(x, &decomposition)?;
+
+    // Compose the outputs in the same way as `compose`
+    let mut result = Fr::from(0u8);
+    for (i, (digit, s_i)) in digits.iter().zip(decomposition.iter()).enumerate() {
+        let output = lookup
+            .read(&(Fr::from(*digit), Fr::from(i as u64)))
+            .expect("every digit is in range for its position");
+        result = result * Fr::from(*s_i) + output;
+    }
+    Some(result)
+}
+
+// Divides a little-endian big integer in place, and returns the remainder
+fn div_rem(limbs: &mut [u64], divisor: u64) -> u64 {
+    let divisor = divisor as u128;
+    let mut remainder = 0u128;
+    for limb in limbs.iter_mut().rev() {
+        let current = (remainder << 64) | (*limb as u128);
+        *limb = (current / divisor) as u64;
+        remainder = current % divisor;
+    }
+    remainder as u64
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::lookup::table::LookUpTable;
+
+    fn test_params() -> BarsParams {
+        BarsParams {
+            decomposition: vec![7, 9, 8],
+            sbox: vec![3, 0, 4, 1, 2],
+        }
+    }
+
+    #[test]
+    fn test_decompose() {
+        let decomposition = [7, 9, 8];
+        let x = Fr::from(5u64 * 72 + 4 * 8 + 6);
+        let digits = decompose(&x, &decomposition).unwrap();
+        assert_eq!(digits, vec![5, 4, 6]);
+        assert_eq!(compose(&digits, &decomposition), x);
+
+        assert!(decompose(&Fr::from(7u64 * 72), &decomposition).is_none());
+    }
+
+    #[test]
+    fn test_bars() {
+        let table = Bars::new(test_params());
+        assert_eq!(table.len(), 7 + 9 + 8);
+        // Digits must be in range for their position
+        assert!(table.read(&(Fr::from(8u8), Fr::from(1u8))).is_some());
+        assert!(table.read(&(Fr::from(8u8), Fr::from(0u8))).is_none());
+
+        let mut lookup = LookUp::new(table);
+        // Digits (5, 4, 6) are mapped to (5, 2, 6)
+        let x = Fr::from(5u64 * 72 + 4 * 8 + 6);
+        let expected = Fr::from(5u64 * 72 + 2 * 8 + 6);
+        assert_eq!(bars(&mut lookup, &x), Some(expected));
+
+        assert_eq!(bars(&mut lookup, &Fr::from(7u64 * 72)), None);
+    }
+}
@@ -0,0 +1,120 @@
+use super::{Generic, MapTable, TableMap};
+use crate::encoding::encode_signed;
+pub use crate::encoding::{from_twos_complement, to_twos_complement};
+use algebra::bls12_381::Fr;
+
+// Signed integers are encoded in the field using two's complement in a fixed number of bits, see `encoding`.
+// Moving a value between widths is then a table read:
+// - Sign-extension from n to m bits copies the sign bit into bits n..m
+// - Truncation from m to n bits drops bits n..m
+
+/// Constructs a table of (x, 0) -> x sign-extended to `to_bits` bits, for every `from_bits`-bit value x
+pub struct SignExtend(Generic);
+
+impl SignExtend {
+    pub fn new(from_bits: usize, to_bits: usize) -> Self {
+        assert!(from_bits > 0 && from_bits <= to_bits && to_bits <= 64);
+
+        let func = |x: usize, _: usize| -> Fr {
+            let value = from_twos_complement(x as u64, from_bits);
+            Fr::from(to_twos_complement(value, to_bits))
+        };
+
+        SignExtend(Generic::with_fn_ranges(func, 1 << from_bits, 1))
+    }
+}
+
+impl MapTable for SignExtend {
+    fn borrow_map(&self) -> &TableMap {
+        &self.0.borrow_map()
+    }
+}
+
+/// Constructs a table of (x, 0) -> x mod 2^{to_bits}, for every `from_bits`-bit value x
+pub struct Truncate(Generic);
+
+impl Truncate {
+    pub fn new(from_bits: usize, to_bits: usize) -> Self {
+        assert!(to_bits <= from_bits && from_bits < 64);
+
+        let mask = (1 << to_bits) - 1;
+        let func = |x: usize, _: usize| -> Fr { Fr::from((x & mask) as u64) };
+
+        Truncate(Generic::with_fn_ranges(func, 1 << from_bits, 1))
+    }
+}
+
+impl MapTable for Truncate {
+    fn borrow_map(&self) -> &TableMap {
+        &self.0.borrow_map()
+    }
+}
+
+/// Constructs a table of (a, b) -> f(a, b) for every pair of `bits`-bit signed values a and b,
+/// with the inputs and the output encoded using two's complement in `bits` bits
+/// The output wraps around as in machine arithmetic, so it is reduced modulo 2^bits before it is encoded.
+pub fn signed_table<F: Fn(i64, i64) -> i64>(bits: usize, f: F) -> Generic {
+    assert!(bits > 0 && bits <= 16);
+
+    let mask = (1u64 << bits) - 1;
+    let bound = 1i64 << (bits - 1);
+    let mut map = TableMap::default();
+    for a in -bound..bound {
+        for b in -bound..bound {
+            let output = (f(a, b) as u64) & mask;
+            map.insert(
+                (encode_signed(a, bits), encode_signed(b, bits)),
+                Fr::from(output),
+            );
+        }
+    }
+    Generic::with_map(map)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::lookup::table::LookUpTable;
+
+    #[test]
+    fn test_sign_extend() {
+        let table = SignExtend::new(4, 8);
+        assert_eq!(table.len(), 16);
+        // -3 in 4 bits is extended to -3 in 8 bits
+        assert_eq!(
+            table.read(&(Fr::from(0b1101u8), Fr::from(0u8))),
+            Some(Fr::from(0b1111_1101u8))
+        );
+        // Positive values are unchanged
+        assert_eq!(
+            table.read(&(Fr::from(0b0101u8), Fr::from(0u8))),
+            Some(Fr::from(0b0101u8))
+        );
+    }
+
+    #[test]
+    fn test_truncate() {
+        let table = Truncate::new(8, 4);
+        assert_eq!(table.len(), 256);
+        assert_eq!(
+            table.read(&(Fr::from(0b1111_1101u8), Fr::from(0u8))),
+            Some(Fr::from(0b1101u8))
+        );
+    }
+
+    #[test]
+    fn test_signed_table() {
+        use crate::encoding::decode_signed;
+
+        let table = signed_table(4, |a, b| a * b);
+        assert_eq!(table.len(), 256);
+        let read = |a: i64, b: i64| {
+            let output = table.read(&(encode_signed(a, 4), encode_signed(b, 4)))?;
+            decode_signed(&output, 4)
+        };
+        assert_eq!(read(-3, 2), Some(-6));
+        assert_eq!(read(-8, -1), Some(-8));
+        // 7 * 7 = 49 wraps around to 1
+        assert_eq!(read(7, 7), Some(1));
+    }
+}
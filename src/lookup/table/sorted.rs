@@ -0,0 +1,167 @@
+use crate::{encoding::fr_to_u64, error::Error, lookup::table::LookUpTable, multiset::MultiSet};
+use algebra::bls12_381::Fr;
+
+#[cfg(feature = "parallel")]
+use rayon::prelude::*;
+
+/// A table of integer rows (a, b) -> c, stored as a vector sorted by input
+///
+/// Each row takes 24 bytes, where a `Generic` table stores three field elements and a hash map entry per row,
+/// so tables with millions of rows fit in memory. The rows are only converted into field elements
+/// when they are iterated, and they are already in the order that `to_multiset` puts them in, so preprocessing
+/// does not sort them again. Reads convert the input into integers and binary search the rows.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SortedTable {
+    rows: Vec<(u64, u64, u64)>,
+}
+
+impl SortedTable {
+    // Initialise a table using a bi-variate function, where the left input ranges over 0..n_left
+    // and the right input ranges over 0..n_right
+    // The rows are generated in order, so they are never sorted.
+    // With the `parallel` feature enabled, the rows are computed concurrently
+    pub fn with_fn_ranges<F>(f: F, n_left: u64, n_right: u64) -> Self
+    where
+        F: Fn(u64, u64) -> u64 + Sync,
+    {
+        let f = &f;
+
+        #[cfg(feature = "parallel")]
+        let rows = (0..n_left)
+            .into_par_iter()
+            .flat_map(|i| (0..n_right).into_par_iter().map(move |k| (i, k, f(i, k))))
+            .collect();
+        #[cfg(not(feature = "parallel"))]
+        let rows = (0..n_left)
+            .flat_map(|i| (0..n_right).map(move |k| (i, k, f(i, k))))
+            .collect();
+
+        SortedTable { rows }
+    }
+    // Initialise a table from a list of rows in any order, as `Generic::from_rows` does
+    // Returns the table and the indices of the rows which repeat an earlier row,
+    // or `Error::ConflictingRows` with the indices of the rows whose input has already been given a different output.
+    pub fn from_rows(rows: Vec<(u64, u64, u64)>) -> Result<(Self, Vec<usize>), Error> {
+        let mut indexed: Vec<_> = rows.into_iter().enumerate().collect();
+        // The sort is stable, so the first of the rows with the same input is kept
+        #[cfg(feature = "parallel")]
+        indexed.par_sort_by_key(|(_, (a, b, _))| (*a, *b));
+        #[cfg(not(feature = "parallel"))]
+        indexed.sort_by_key(|(_, (a, b, _))| (*a, *b));
+
+        let mut rows: Vec<(u64, u64, u64)> = Vec::with_capacity(indexed.len());
+        let mut duplicates = Vec::new();
+        let mut conflicts = Vec::new();
+        for (i, row) in indexed {
+            match rows.last() {
+                Some(last) if (last.0, last.1) == (row.0, row.1) => {
+                    if last.2 == row.2 {
+                        duplicates.push(i);
+                    } else {
+                        conflicts.push(i);
+                    }
+                }
+                _ => rows.push(row),
+            }
+        }
+        if !conflicts.is_empty() {
+            conflicts.sort_unstable();
+            return Err(Error::ConflictingRows(conflicts));
+        }
+        duplicates.sort_unstable();
+        rows.shrink_to_fit();
+        Ok((SortedTable { rows }, duplicates))
+    }
+    /// Returns the output of the row with integer inputs (a, b)
+    pub fn get(&self, a: u64, b: u64) -> Option<u64> {
+        self.rows
+            .binary_search_by_key(&(a, b), |(a, b, _)| (*a, *b))
+            .ok()
+            .map(|i| self.rows[i].2)
+    }
+}
+
+impl LookUpTable for SortedTable {
+    fn len(&self) -> usize {
+        self.rows.len()
+    }
+
+    fn read(&self, key: &(Fr, Fr)) -> Option<Fr> {
+        let output = self.get(fr_to_u64(&key.0)?, fr_to_u64(&key.1)?)?;
+        Some(Fr::from(output))
+    }
+
+    fn read_u64(&self, a: u64, b: u64) -> Option<u64> {
+        self.get(a, b)
+    }
+
+    fn iter(&self) -> Box<dyn Iterator<Item = ((Fr, Fr), Fr)> + '_> {
+        Box::new(
+            self.rows
+                .iter()
+                .map(|(a, b, c)| ((Fr::from(*a), Fr::from(*b)), Fr::from(*c))),
+        )
+    }
+
+    /// Converts the rows into field elements column by column, as they are already sorted by their inputs
+    fn to_multiset(&self) -> (MultiSet, MultiSet, MultiSet) {
+        let column = |select: fn(&(u64, u64, u64)) -> u64| -> MultiSet {
+            #[cfg(feature = "parallel")]
+            let values = self
+                .rows
+                .par_iter()
+                .map(|row| Fr::from(select(row)))
+                .collect();
+            #[cfg(not(feature = "parallel"))]
+            let values = self.rows.iter().map(|row| Fr::from(select(row))).collect();
+            MultiSet(values)
+        };
+        (
+            column(|row| row.0),
+            column(|row| row.1),
+            column(|row| row.2),
+        )
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::lookup::table::{four_bits::XOR4Bit, Generic};
+
+    #[test]
+    fn test_sorted_table() {
+        let table = SortedTable::with_fn_ranges(|a, b| a ^ b, 16, 16);
+        assert_eq!(table.len(), 256);
+        assert_eq!(table.get(3, 5), Some(6));
+        assert_eq!(table.get(16, 5), None);
+        assert_eq!(
+            table.read(&(Fr::from(3u8), Fr::from(5u8))),
+            Some(Fr::from(6u8))
+        );
+        assert!(table.read(&(-Fr::from(1u8), Fr::from(5u8))).is_none());
+
+        // The rows are in the order that the default `to_multiset` sorts them into
+        assert_eq!(table.to_multiset(), XOR4Bit::new().to_multiset());
+        assert_eq!(table.id(), XOR4Bit::new().id());
+    }
+
+    #[test]
+    fn test_sorted_from_rows() {
+        let rows = vec![(2, 3, 5), (1, 1, 2), (2, 3, 5), (0, 7, 7)];
+        let (table, duplicates) = SortedTable::from_rows(rows.clone()).unwrap();
+        assert_eq!(duplicates, vec![2]);
+        assert_eq!(table.len(), 3);
+
+        let generic_rows = rows
+            .iter()
+            .map(|(a, b, c)| ((Fr::from(*a), Fr::from(*b)), Fr::from(*c)));
+        let (generic, _) = Generic::from_rows(generic_rows).unwrap();
+        assert_eq!(table.to_multiset(), generic.to_multiset());
+
+        match SortedTable::from_rows(vec![(1, 1, 2), (2, 3, 5), (1, 1, 3)]) {
+            Err(Error::ConflictingRows(rows)) => assert_eq!(rows, vec![2]),
+            _ => panic!("expected the third row to conflict with the first"),
+        }
+    }
+}
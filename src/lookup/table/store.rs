@@ -0,0 +1,351 @@
+use super::{LookUpTable, PreProcessedTable, TableCommitments, TableId};
+use crate::{
+    error::Error,
+    kzg10,
+    multiset::{quotient_poly::QuotientKey, MultiSet},
+};
+use algebra::{
+    bls12_381::{Fr, G1Affine},
+    io::{Read, Write},
+    Bls12_381, CanonicalDeserialize, CanonicalSerialize, SerializationError,
+};
+use ff_fft::DensePolynomial as Polynomial;
+use poly_commit::kzg10::{Commitment, Powers};
+use std::{fs::File, path::Path};
+
+// A stored table is the identifier of the table, followed by `n` and the digest of the SRS, followed by each column as its n padded values,
+// its commitment, and the number of coefficients of its polynomial followed by the coefficients.
+// The polynomial of a column can have fewer than n coefficients, as trailing zero coefficients are trimmed.
+
+impl PreProcessedTable {
+    /// Writes the preprocessed table, keyed by the identifier of the table it was computed from
+    pub fn save<W: Write>(&self, writer: &mut W) -> Result<(), Error> {
+        writer.write_all(&self.id.0)?;
+        (self.n as u64).serialize(writer)?;
+        writer.write_all(&self.srs_digest)?;
+        for (column, commitment, poly) in vec![&self.t_1, &self.t_2, &self.t_3] {
+            for value in column.0.iter() {
+                value.serialize(writer)?;
+            }
+            commitment.0.serialize(writer)?;
+            (poly.coeffs.len() as u64).serialize(writer)?;
+            for coeff in poly.coeffs.iter() {
+                coeff.serialize(writer)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Reads a preprocessed table which was written by `save`
+    /// Returns an error if it was not computed from the table with identifier `id`
+    pub fn load<R: Read>(id: &TableId, reader: &mut R) -> Result<Self, Error> {
+        let mut stored_id = [0u8; 32];
+        reader.read_exact(&mut stored_id)?;
+        if stored_id != id.0 {
+            return Err(Error::TableMismatch);
+        }
+
+        let n = u64::deserialize(reader)? as usize;
+        let mut srs_digest = [0u8; 32];
+        reader.read_exact(&mut srs_digest)?;
+        Ok(PreProcessedTable {
+            id: *id,
+            n,
+            srs_digest,
+            t_1: read_column(reader, n)?,
+            t_2: read_column(reader, n)?,
+            t_3: read_column(reader, n)?,
+            quotient_key: QuotientKey::new(n),
+        })
+    }
+}
+
+fn read_column<R: Read>(
+    reader: &mut R,
+    n: usize,
+) -> Result<(MultiSet, Commitment<Bls12_381>, Polynomial<Fr>), SerializationError> {
+    let mut column = MultiSet::new();
+    for _ in 0..n {
+        column.push(Fr::deserialize(reader)?);
+    }
+    let commitment = Commitment(G1Affine::deserialize(reader)?);
+    let num_coeffs = u64::deserialize(reader)? as usize;
+    if num_coeffs > n {
+        return Err(SerializationError::InvalidData);
+    }
+    let coeffs = (0..num_coeffs)
+        .map(|_| Fr::deserialize(reader))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    Ok((
+        column,
+        commitment,
+        Polynomial::from_coefficients_vec(coeffs),
+    ))
+}
+
+/// Preprocesses `table` to size `n`, or loads it from `dir` if it has already been preprocessed
+/// Stored tables are named after the identifier of the table, the digest of the SRS and `n`.
+pub fn preprocess_cached<T: LookUpTable + ?Sized>(
+    table: &T,
+    commit_key: &Powers<Bls12_381>,
+    n: usize,
+    dir: &Path,
+) -> Result<PreProcessedTable, Error> {
+    let id = table.id();
+    let srs_digest = kzg10::srs_digest(commit_key, n);
+    let path = dir.join(format!(
+        "{}_{}_{}.table",
+        hex::encode(&id.0),
+        hex::encode(&srs_digest),
+        n
+    ));
+
+    if path.exists() {
+        let mut file = File::open(&path)?;
+        return PreProcessedTable::load(&id, &mut file);
+    }
+
+    // The table is written to a temporary file which is renamed into place once it is complete,
+    // so a prover which is stopped while writing never leaves a truncated table at `path`
+    let preprocessed_table = table.preprocess(commit_key, n);
+    let tmp_path = path.with_extension(format!("tmp{}", std::process::id()));
+    let mut file = File::create(&tmp_path)?;
+    preprocessed_table.save(&mut file)?;
+    file.sync_all()?;
+    drop(file);
+    std::fs::rename(&tmp_path, &path)?;
+    Ok(preprocessed_table)
+}
+
+// A table identifier is serialised as its 32 bytes
+impl CanonicalSerialize for TableId {
+    fn serialize<W: Write>(&self, writer: &mut W) -> Result<(), SerializationError> {
+        writer.write_all(&self.0)?;
+        Ok(())
+    }
+    fn serialized_size(&self) -> usize {
+        32
+    }
+}
+
+impl CanonicalDeserialize for TableId {
+    fn deserialize<R: Read>(reader: &mut R) -> Result<Self, SerializationError> {
+        let mut id = [0u8; 32];
+        reader.read_exact(&mut id)?;
+        Ok(TableId(id))
+    }
+}
+
+// Table commitments are serialised as the table identifier, `n` as a u64, the digest of the SRS and the three commitments
+impl CanonicalSerialize for TableCommitments {
+    fn serialize<W: Write>(&self, writer: &mut W) -> Result<(), SerializationError> {
+        self.id.serialize(writer)?;
+        (self.n as u64).serialize(writer)?;
+        writer.write_all(&self.srs_digest)?;
+        for comm in vec![&self.t_1, &self.t_2, &self.t_3] {
+            comm.0.serialize(writer)?;
+        }
+        Ok(())
+    }
+    fn serialized_size(&self) -> usize {
+        32 + 8 + 32 + 3 * self.t_1.0.serialized_size()
+    }
+    fn serialize_uncompressed<W: Write>(&self, writer: &mut W) -> Result<(), SerializationError> {
+        self.id.serialize(writer)?;
+        (self.n as u64).serialize(writer)?;
+        writer.write_all(&self.srs_digest)?;
+        for comm in vec![&self.t_1, &self.t_2, &self.t_3] {
+            comm.0.serialize_uncompressed(writer)?;
+        }
+        Ok(())
+    }
+    fn uncompressed_size(&self) -> usize {
+        32 + 8 + 32 + 3 * self.t_1.0.uncompressed_size()
+    }
+}
+
+impl CanonicalDeserialize for TableCommitments {
+    fn deserialize<R: Read>(reader: &mut R) -> Result<Self, SerializationError> {
+        TableCommitments::read(reader, G1Affine::deserialize)
+    }
+    fn deserialize_uncompressed<R: Read>(reader: &mut R) -> Result<Self, SerializationError> {
+        TableCommitments::read(reader, G1Affine::deserialize_uncompressed)
+    }
+}
+
+impl TableCommitments {
+    fn read<R: Read>(
+        reader: &mut R,
+        read_point: fn(&mut R) -> Result<G1Affine, SerializationError>,
+    ) -> Result<Self, SerializationError> {
+        let id = TableId::deserialize(reader)?;
+        let n = u64::deserialize(reader)? as usize;
+        let mut srs_digest = [0u8; 32];
+        reader.read_exact(&mut srs_digest)?;
+        Ok(TableCommitments {
+            id,
+            n,
+            srs_digest,
+            t_1: Commitment(read_point(reader)?),
+            t_2: Commitment(read_point(reader)?),
+            t_3: Commitment(read_point(reader)?),
+        })
+    }
+}
+
+impl TableCommitments {
+    /// Encodes the compressed form of the table commitments as a hex string
+    pub fn to_hex(&self) -> String {
+        hex::encode(self.to_compressed_bytes())
+    }
+    /// Decodes table commitments from the hex encoding of their compressed form
+    pub fn from_hex(encoded: &str) -> Result<TableCommitments, Error> {
+        let bytes = hex::decode(encoded)?;
+        Ok(TableCommitments::deserialize(&mut &bytes[..])?)
+    }
+    /// Encodes the compressed form of the table commitments as a base64 string
+    pub fn to_base64(&self) -> String {
+        base64::encode(self.to_compressed_bytes())
+    }
+    /// Decodes table commitments from the base64 encoding of their compressed form
+    pub fn from_base64(encoded: &str) -> Result<TableCommitments, Error> {
+        let bytes = base64::decode(encoded)?;
+        Ok(TableCommitments::deserialize(&mut &bytes[..])?)
+    }
+    fn to_compressed_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(self.serialized_size());
+        self.serialize(&mut bytes)
+            .expect("serialising into a vector cannot fail");
+        bytes
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::lookup::table::four_bits::XOR4Bit;
+
+    #[test]
+    fn test_save_and_load() {
+        let (proving_key, _) = kzg10::trusted_setup(2usize.pow(9), b"insecure_seed");
+        let table = XOR4Bit::new();
+        let id = table.id();
+        let preprocessed_table = table.preprocess(&proving_key, 2usize.pow(8));
+
+        let mut bytes = Vec::new();
+        preprocessed_table.save(&mut bytes).unwrap();
+
+        let loaded = PreProcessedTable::load(&id, &mut &bytes[..]).unwrap();
+        assert_eq!(loaded.id, preprocessed_table.id);
+        assert_eq!(loaded.n, preprocessed_table.n);
+        assert_eq!(loaded.srs_digest, preprocessed_table.srs_digest);
+        assert_eq!(loaded.t_1.0, preprocessed_table.t_1.0);
+        assert_eq!(loaded.t_2.1, preprocessed_table.t_2.1);
+        assert_eq!(loaded.t_3.2, preprocessed_table.t_3.2);
+
+        // A different table cannot be loaded from the same bytes
+        let other_id = crate::lookup::table::four_bits::Add4Bit::new().id();
+        assert!(PreProcessedTable::load(&other_id, &mut &bytes[..]).is_err());
+        // Nor can a truncated table
+        assert!(PreProcessedTable::load(&id, &mut &bytes[..bytes.len() - 1]).is_err());
+    }
+
+    #[test]
+    fn test_save_and_load_constant_column() {
+        let (proving_key, _) = kzg10::trusted_setup(2usize.pow(9), b"insecure_seed");
+        let n = 2usize.pow(4);
+        let domain: ff_fft::EvaluationDomain<Fr> = ff_fft::EvaluationDomain::new(n).unwrap();
+        let column = |values: MultiSet| {
+            let poly = values.to_polynomial(&domain);
+            let commitment = kzg10::commit(&proving_key, &poly);
+            (values, commitment, poly)
+        };
+
+        // The polynomial of a constant column has a single coefficient
+        let t_1 = column(MultiSet(vec![Fr::from(7u8); n]));
+        assert_eq!(t_1.2.coeffs.len(), 1);
+        let preprocessed_table = PreProcessedTable {
+            id: TableId([1u8; 32]),
+            n,
+            srs_digest: kzg10::srs_digest(&proving_key, n),
+            t_1,
+            t_2: column((0..n as u64).map(Fr::from).collect()),
+            t_3: column((0..n as u64).map(|i| Fr::from(2 * i)).collect()),
+            quotient_key: QuotientKey::new(n),
+        };
+
+        let mut bytes = Vec::new();
+        preprocessed_table.save(&mut bytes).unwrap();
+        let loaded = PreProcessedTable::load(&preprocessed_table.id, &mut &bytes[..]).unwrap();
+        for (loaded, expected) in vec![
+            (&loaded.t_1, &preprocessed_table.t_1),
+            (&loaded.t_2, &preprocessed_table.t_2),
+            (&loaded.t_3, &preprocessed_table.t_3),
+        ] {
+            assert_eq!(loaded.0, expected.0);
+            assert_eq!(loaded.1, expected.1);
+            assert_eq!(loaded.2, expected.2);
+        }
+    }
+
+    #[test]
+    fn test_table_commitments_serialization() {
+        let (proving_key, _) = kzg10::trusted_setup(2usize.pow(9), b"insecure_seed");
+        let commitments = XOR4Bit::new()
+            .preprocess(&proving_key, 2usize.pow(8))
+            .commitments();
+
+        let mut compressed = Vec::new();
+        commitments.serialize(&mut compressed).unwrap();
+        assert_eq!(compressed.len(), 32 + 8 + 32 + 3 * 48);
+        let mut uncompressed = Vec::new();
+        commitments
+            .serialize_uncompressed(&mut uncompressed)
+            .unwrap();
+        assert_eq!(uncompressed.len(), 32 + 8 + 32 + 3 * 96);
+
+        let decoded = TableCommitments::deserialize(&mut &compressed[..]).unwrap();
+        assert_eq!(decoded, commitments);
+        let decoded = TableCommitments::deserialize_uncompressed(&mut &uncompressed[..]).unwrap();
+        assert_eq!(decoded, commitments);
+    }
+
+    #[test]
+    fn test_table_commitments_text_encodings() {
+        let (proving_key, _) = kzg10::trusted_setup(2usize.pow(9), b"insecure_seed");
+        let commitments = XOR4Bit::new()
+            .preprocess(&proving_key, 2usize.pow(8))
+            .commitments();
+
+        let hex = commitments.to_hex();
+        assert_eq!(hex.len(), 2 * (32 + 8 + 32 + 3 * 48));
+        assert_eq!(TableCommitments::from_hex(&hex).unwrap(), commitments);
+
+        let base64 = commitments.to_base64();
+        assert_eq!(TableCommitments::from_base64(&base64).unwrap(), commitments);
+
+        assert!(TableCommitments::from_hex("not hex").is_err());
+        assert!(TableCommitments::from_base64(&base64[..8]).is_err());
+    }
+
+    #[test]
+    fn test_preprocess_cached() {
+        let (proving_key, _) = kzg10::trusted_setup(2usize.pow(9), b"insecure_seed");
+        let dir = std::env::temp_dir().join("plookup_test_preprocess_cached");
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+
+        let table = XOR4Bit::new();
+        let first = preprocess_cached(&table, &proving_key, 2usize.pow(8), &dir).unwrap();
+        let second = preprocess_cached(&table, &proving_key, 2usize.pow(8), &dir).unwrap();
+        assert_eq!(first.t_1.1, second.t_1.1);
+        assert_eq!(first.t_3.0, second.t_3.0);
+
+        // Only the finished table is left in the directory
+        let files: Vec<_> = std::fs::read_dir(&dir).unwrap().collect();
+        assert_eq!(files.len(), 1);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,190 @@
+use super::LookUpTable;
+use crate::lookup::lookup::LookUp;
+use algebra::bls12_381::Fr;
+use num_traits::identities::Zero;
+use std::collections::HashMap;
+
+// A row which ignores one of its inputs is not expanded into a row for every possible value of that input.
+// Instead it is stored once, with zero in place of the ignored input, and reads which match the row
+// add the row with that zero to the witness. The witness then only ever contains rows of the table,
+// while the value of an ignored input is left unconstrained by the lookup.
+
+/// An input of a table row, which either matches one value or any value
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Pattern {
+    Exact(Fr),
+    Any,
+}
+
+impl Pattern {
+    fn matches(&self, value: &Fr) -> bool {
+        match self {
+            Pattern::Exact(expected) => expected == value,
+            Pattern::Any => true,
+        }
+    }
+    // The value which is stored in the table for this input
+    fn stored(&self) -> Fr {
+        match self {
+            Pattern::Exact(value) => *value,
+            Pattern::Any => Fr::zero(),
+        }
+    }
+}
+
+/// A table whose rows may ignore either of their inputs, such as an instruction decode table
+/// where some opcodes do not use their second operand
+pub struct Wildcard {
+    // The rows keyed by the inputs that are stored in the table
+    rows: HashMap<(Fr, Fr), ((Pattern, Pattern), Fr)>,
+}
+
+impl Wildcard {
+    pub fn new() -> Self {
+        Wildcard {
+            rows: HashMap::new(),
+        }
+    }
+    /// Adds the row (left, right) -> output
+    /// Panics if the row is stored with the same inputs as an existing row,
+    /// for example (a, Any) and (a, 0), as the witness could not tell them apart
+    pub fn insert(&mut self, left: Pattern, right: Pattern, output: Fr) {
+        let stored = (left.stored(), right.stored());
+        assert!(
+            !self.rows.contains_key(&stored),
+            "row is stored with the same inputs as an existing row"
+        );
+        self.rows.insert(stored, ((left, right), output));
+    }
+    /// Returns the inputs of the row that `key` matches, as they are stored in the table
+    /// A row which matches both inputs exactly is preferred, then a row which ignores the right input,
+    /// then one which ignores the left input, and finally one which ignores both.
+    pub fn stored_key(&self, key: &(Fr, Fr)) -> Option<(Fr, Fr)> {
+        let zero = Fr::zero();
+        let candidates = [(key.0, key.1), (key.0, zero), (zero, key.1), (zero, zero)];
+
+        candidates.iter().copied().find(|candidate| {
+            self.rows
+                .get(candidate)
+                .map_or(false, |((left, right), _)| {
+                    left.matches(&key.0) && right.matches(&key.1)
+                })
+        })
+    }
+}
+
+impl LookUpTable for Wildcard {
+    fn len(&self) -> usize {
+        self.rows.len()
+    }
+    /// Only reads rows as they are stored, so that every read is a row of the table
+    /// See `stored_key` and `read_wildcard` for keys which match a row that ignores an input.
+    fn read(&self, key: &(Fr, Fr)) -> Option<Fr> {
+        self.rows.get(key).map(|(_, output)| *output)
+    }
+    fn iter(&self) -> Box<dyn Iterator<Item = ((Fr, Fr), Fr)> + '_> {
+        Box::new(
+            self.rows
+                .iter()
+                .map(|(stored, (_, output))| (*stored, *output)),
+        )
+    }
+}
+
+/// Reads `key` from the table, adding the matching row to the witness with any ignored input replaced by zero
+/// Returns None if no row matches `key`
+pub fn read_wildcard(lookup: &mut LookUp<Wildcard>, key: &(Fr, Fr)) -> Option<Fr> {
+    let stored = lookup.table().stored_key(key)?;
+    lookup.read(&stored)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::kzg10;
+    use merlin::Transcript;
+
+    // Decodes (opcode, operand) into a micro-op, where NOP ignores its operand
+    fn decode_table() -> Wildcard {
+        let mut table = Wildcard::new();
+        table.insert(Pattern::Exact(Fr::from(0u8)), Pattern::Any, Fr::from(100u8));
+        for operand in 0..4u8 {
+            table.insert(
+                Pattern::Exact(Fr::from(1u8)),
+                Pattern::Exact(Fr::from(operand)),
+                Fr::from(110 + operand),
+            );
+        }
+        table
+    }
+
+    #[test]
+    fn test_wildcard_reads() {
+        let table = decode_table();
+        assert_eq!(table.len(), 5);
+        assert_eq!(
+            table.stored_key(&(Fr::from(0u8), Fr::from(77u8))),
+            Some((Fr::from(0u8), Fr::from(0u8)))
+        );
+        assert_eq!(
+            table.read(&(Fr::from(0u8), Fr::from(0u8))),
+            Some(Fr::from(100u8))
+        );
+        assert_eq!(
+            table.read(&(Fr::from(1u8), Fr::from(2u8))),
+            Some(Fr::from(112u8))
+        );
+        assert_eq!(table.stored_key(&(Fr::from(1u8), Fr::from(77u8))), None);
+
+        // A key which only matches a wildcard row is not itself a row of the table
+        assert_eq!(table.read(&(Fr::from(0u8), Fr::from(77u8))), None);
+
+        // The wildcard row is stored once, with zero for the ignored input
+        let (t_1, t_2, _) = table.to_multiset();
+        assert_eq!(t_1.len(), 5);
+        assert_eq!((t_1.0[0], t_2.0[0]), (Fr::from(0u8), Fr::from(0u8)));
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_conflicting_rows() {
+        let mut table = decode_table();
+        table.insert(
+            Pattern::Exact(Fr::from(0u8)),
+            Pattern::Exact(Fr::from(0u8)),
+            Fr::from(1u8),
+        );
+    }
+
+    #[test]
+    fn test_wildcard_lookup() {
+        let (proving_key, verifier_key) = kzg10::trusted_setup(2usize.pow(6), b"insecure_seed");
+        let preprocessed_table = decode_table().preprocess(&proving_key, 2usize.pow(4));
+
+        let mut lookup = LookUp::new(decode_table());
+        assert_eq!(
+            read_wildcard(&mut lookup, &(Fr::from(0u8), Fr::from(77u8))),
+            Some(Fr::from(100u8))
+        );
+        assert_eq!(
+            read_wildcard(&mut lookup, &(Fr::from(1u8), Fr::from(3u8))),
+            Some(Fr::from(113u8))
+        );
+        assert_eq!(
+            read_wildcard(&mut lookup, &(Fr::from(2u8), Fr::from(0u8))),
+            None
+        );
+
+        // A plain read of a key which only matches a wildcard row leaves the witness unchanged
+        assert_eq!(lookup.read(&(Fr::from(0u8), Fr::from(77u8))), None);
+
+        // The ignored operand is not added to the witness
+        assert_eq!(lookup.right_wires().0, vec![Fr::from(0u8), Fr::from(3u8)]);
+        assert!(lookup.validate().is_ok());
+
+        let mut prover_transcript = Transcript::new(b"wildcard");
+        let proof = lookup.prove(&proving_key, &preprocessed_table, &mut prover_transcript);
+        let mut verifier_transcript = Transcript::new(b"wildcard");
+        assert!(proof.verify(&verifier_key, &preprocessed_table, &mut verifier_transcript));
+    }
+}
@@ -0,0 +1,86 @@
+// Proving without blocking an async runtime, enabled with the `tokio` feature
+//
+// A proof can take seconds to minutes, which would stall every other task on an async runtime.
+// `spawn_prove` moves the prover onto tokio's blocking thread pool. The keys and the table are
+// shared through `Arc`s, so that a service can prove many witnesses against them concurrently.
+use super::{proof::LookUpProof, table::PreProcessedTable};
+use crate::{multiset::MultiSet, transcript::TranscriptProtocol};
+use algebra::Bls12_381;
+use poly_commit::kzg10::Powers;
+use std::sync::Arc;
+use tokio::task::{self, JoinError};
+
+/// Proves that every row (f_1, f_2, f_3) of the witness is a row of the table, on tokio's blocking thread pool
+/// The transcript is returned with the proof, so the caller can continue to use it.
+/// Returns an error if the prover panicked.
+pub async fn spawn_prove<T>(
+    f_1: MultiSet,
+    f_2: MultiSet,
+    f_3: MultiSet,
+    proving_key: Arc<Powers<'static, Bls12_381>>,
+    preprocessed_table: Arc<PreProcessedTable>,
+    mut transcript: T,
+) -> Result<(LookUpProof, T), JoinError>
+where
+    T: TranscriptProtocol + Send + 'static,
+{
+    task::spawn_blocking(move || {
+        let proof = LookUpProof::prove(
+            &f_1,
+            &f_2,
+            &f_3,
+            &proving_key,
+            &preprocessed_table,
+            &mut transcript,
+        );
+        (proof, transcript)
+    })
+    .await
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::kzg10;
+    use crate::lookup::table::{four_bits::XOR4Bit, LookUpTable};
+    use algebra::bls12_381::Fr;
+    use merlin::Transcript;
+
+    fn assert_send_sync<T: Send + Sync>() {}
+
+    #[test]
+    fn test_prover_types_are_send_and_sync() {
+        assert_send_sync::<PreProcessedTable>();
+        assert_send_sync::<Powers<'static, Bls12_381>>();
+        assert_send_sync::<MultiSet>();
+        assert_send_sync::<LookUpProof>();
+    }
+
+    #[test]
+    fn test_spawn_prove() {
+        let (proving_key, verifier_key) = kzg10::trusted_setup(2usize.pow(10), b"insecure_seed");
+        let preprocessed_table = Arc::new(XOR4Bit::new().preprocess(&proving_key, 2usize.pow(8)));
+
+        let f_1 = MultiSet(vec![Fr::from(1u8)]);
+        let f_2 = MultiSet(vec![Fr::from(2u8)]);
+        let f_3 = MultiSet(vec![Fr::from(3u8)]);
+
+        let mut runtime = tokio::runtime::Builder::new()
+            .basic_scheduler()
+            .build()
+            .unwrap();
+        let (proof, _) = runtime
+            .block_on(spawn_prove(
+                f_1,
+                f_2,
+                f_3,
+                Arc::new(proving_key),
+                preprocessed_table.clone(),
+                Transcript::new(b"lookup"),
+            ))
+            .unwrap();
+
+        let mut verifier_transcript = Transcript::new(b"lookup");
+        assert!(proof.verify(&verifier_key, &preprocessed_table, &mut verifier_transcript));
+    }
+}
@@ -0,0 +1,272 @@
+// A proving key which is read from a memory-mapped file
+//
+// The SRS for polynomials of degree 2^22 holds gigabytes of bases, more than a prover should keep in memory.
+// `write_mapped_powers` writes the bases of a proving key to a file with a fixed size for each base, and
+// `MappedPowers` maps the file and decodes the bases a chunk at a time, as the MSM of each commitment reaches them.
+// The mapped pages are backed by the file, so the operating system can drop them under memory pressure,
+// and the prover only holds one chunk of decoded bases at once.
+//
+// The file is the number of bases as a u64, followed by each base as its x and y coordinates,
+// each the 48 byte little-endian encoding of its canonical integer.
+// Only `powers_of_g` is stored, as the commitments of the prover are not hiding.
+use crate::{
+    error::Error,
+    kzg10::{self, CommitKey, MsmConfig},
+    threads,
+};
+use algebra::bls12_381::{Fq, Fr, G1Affine, G1Projective};
+use algebra::{
+    io::Write, Bls12_381, CanonicalDeserialize, CanonicalSerialize, PrimeField, SerializationError,
+};
+use algebra_core::msm::VariableBaseMSM;
+use ff_fft::DensePolynomial as Polynomial;
+use memmap::Mmap;
+use num_traits::identities::Zero;
+use poly_commit::kzg10::{Commitment, Powers};
+use std::{cmp::min, fs::File, path::Path};
+
+#[cfg(feature = "parallel")]
+use rayon::prelude::*;
+
+const HEADER_BYTES: usize = 8;
+const BASE_BYTES: usize = 96;
+
+/// The number of bases which are decoded at once, unless `MsmConfig::chunk_size` is set
+pub const DEFAULT_CHUNK_SIZE: usize = 1 << 16;
+
+/// Writes the bases of `powers` in the format that `MappedPowers` reads
+pub fn write_mapped_powers<W: Write>(
+    powers: &Powers<Bls12_381>,
+    writer: &mut W,
+) -> Result<(), SerializationError> {
+    (powers.powers_of_g.len() as u64).serialize(writer)?;
+    for g in powers.powers_of_g.iter() {
+        // The bases of an SRS are powers of a non-zero secret, so none of them is the identity
+        assert!(!g.infinity);
+        g.x.serialize(writer)?;
+        g.y.serialize(writer)?;
+    }
+    Ok(())
+}
+
+/// A proving key whose bases are read from a memory-mapped file
+/// It commits as `Powers` does, and can be passed to provers which accept any `CommitKey`.
+pub struct MappedPowers {
+    map: Mmap,
+    size: usize,
+}
+
+impl MappedPowers {
+    /// Maps the file at `path`, which must have been written by `write_mapped_powers`
+    /// Only the length of the file is checked. The file must not be modified while it is mapped.
+    pub fn open(path: &Path) -> Result<MappedPowers, Error> {
+        let file = File::open(path)?;
+        // The map is read-only, and the file must not be modified while it is mapped
+        let map = unsafe { Mmap::map(&file)? };
+        if map.len() < HEADER_BYTES {
+            return Err(SerializationError::InvalidData.into());
+        }
+        let size = u64::deserialize(&mut &map[..HEADER_BYTES])? as usize;
+        let expected_len = size
+            .checked_mul(BASE_BYTES)
+            .and_then(|len| len.checked_add(HEADER_BYTES));
+        if expected_len != Some(map.len()) {
+            return Err(SerializationError::InvalidData.into());
+        }
+
+        Ok(MappedPowers { map, size })
+    }
+    /// Decodes the bases with indices in `start..end`
+    pub fn bases(&self, start: usize, end: usize) -> Result<Vec<G1Affine>, Error> {
+        assert!(start <= end && end <= self.size);
+        let bytes = &self.map[HEADER_BYTES + start * BASE_BYTES..HEADER_BYTES + end * BASE_BYTES];
+
+        #[cfg(feature = "parallel")]
+        let bases: Result<Vec<_>, _> = bytes.par_chunks(BASE_BYTES).map(read_base).collect();
+        #[cfg(not(feature = "parallel"))]
+        let bases: Result<Vec<_>, _> = bytes.chunks(BASE_BYTES).map(read_base).collect();
+        Ok(bases?)
+    }
+    /// Checks that every base is a point of G1 in the prime order subgroup, reading the file a chunk at a time
+    /// This costs more than a proof, so it is meant to be run once when the file is obtained
+    /// rather than every time it is opened.
+    pub fn check(&self) -> Result<(), Error> {
+        for start in (0..self.size).step_by(DEFAULT_CHUNK_SIZE) {
+            let end = min(start + DEFAULT_CHUNK_SIZE, self.size);
+            for base in self.bases(start, end)? {
+                if !base.is_on_curve() || !base.is_in_correct_subgroup_assuming_on_curve() {
+                    return Err(SerializationError::InvalidData.into());
+                }
+            }
+        }
+        Ok(())
+    }
+
+    // Computes the commitments to the polynomials without converting them to affine form
+    // Each chunk of bases is decoded once, and used for the coefficients of every polynomial in that chunk.
+    fn commit_projective(
+        &self,
+        polynomials: &[&Polynomial<Fr>],
+        config: &MsmConfig,
+    ) -> Result<Vec<G1Projective>, Error> {
+        for p in polynomials {
+            assert!(
+                p.coeffs.len() <= self.size,
+                "polynomial of degree {} is too large for an SRS of size {}",
+                p.degree(),
+                self.size
+            );
+        }
+        let len = polynomials
+            .iter()
+            .map(|p| p.coeffs.len())
+            .max()
+            .unwrap_or(0);
+        let chunk_size = config.chunk_size.unwrap_or(DEFAULT_CHUNK_SIZE).max(1);
+
+        let mut points = vec![G1Projective::zero(); polynomials.len()];
+        for start in (0..len).step_by(chunk_size) {
+            let end = min(start + chunk_size, len);
+            let bases = self.bases(start, end)?;
+
+            for (point, p) in points.iter_mut().zip(polynomials.iter()) {
+                if start >= p.coeffs.len() {
+                    continue;
+                }
+                let scalars: Vec<_> = p.coeffs[start..min(end, p.coeffs.len())]
+                    .iter()
+                    .map(|coeff| coeff.into_repr())
+                    .collect();
+                let bases = &bases[..scalars.len()];
+                *point += &match config.window_bits {
+                    Some(window_bits) => kzg10::bucket_msm(bases, &scalars, window_bits),
+                    None => VariableBaseMSM::multi_scalar_mul(bases, &scalars),
+                };
+            }
+        }
+        Ok(points)
+    }
+}
+
+impl CommitKey for MappedPowers {
+    fn size(&self) -> usize {
+        self.size
+    }
+    /// Returns an error if a base cannot be decoded, which only happens if the file is corrupt, see `check`,
+    /// `Error::InvalidWindow` if `config.window_bits` is out of range,
+    /// or `Error::ThreadPool` if `config.threads` is set and the thread pool could not be created
+    fn commit_many_with_config(
+        &self,
+        polynomials: Vec<&Polynomial<Fr>>,
+        config: &MsmConfig,
+    ) -> Result<Vec<Commitment<Bls12_381>>, Error> {
+        config.check()?;
+        let commit_all = || self.commit_projective(&polynomials, config);
+        let points = match config.threads {
+            Some(budget) => threads::with_thread_budget(budget, commit_all)??,
+            None => commit_all()?,
+        };
+        Ok(kzg10::batch_normalize(&points))
+    }
+}
+
+// Reads a base as its x and y coordinates
+// The point is not checked to be on the curve, see `MappedPowers::check`
+fn read_base(mut bytes: &[u8]) -> Result<G1Affine, SerializationError> {
+    let x = Fq::deserialize(&mut bytes)?;
+    let y = Fq::deserialize(&mut bytes)?;
+    Ok(G1Affine::new(x, y, false))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::lookup::{
+        lookup::LookUp,
+        proof::ProveOptions,
+        table::{four_bits::XOR4Bit, LookUpTable},
+    };
+    use merlin::Transcript;
+    use rand_chacha::ChaChaRng;
+    use rand_core::SeedableRng;
+
+    #[test]
+    fn test_mapped_powers() {
+        let path = std::env::temp_dir().join("plookup_test_mapped_powers");
+        let (proving_key, verifier_key) = kzg10::trusted_setup(2usize.pow(10), b"insecure_seed");
+
+        let mut bytes = Vec::new();
+        write_mapped_powers(&proving_key, &mut bytes).unwrap();
+        std::fs::write(&path, &bytes).unwrap();
+
+        let mapped = MappedPowers::open(&path).unwrap();
+        assert_eq!(CommitKey::size(&mapped), proving_key.size());
+        assert_eq!(
+            mapped.bases(0, mapped.size).unwrap(),
+            proving_key.powers_of_g.to_vec()
+        );
+        mapped.check().unwrap();
+
+        // Every chunking gives the same commitments as the key in memory
+        let mut rng = ChaChaRng::from_seed([0u8; 32]);
+        let (p, q) = (
+            Polynomial::rand(700, &mut rng),
+            Polynomial::rand(100, &mut rng),
+        );
+        let expected = kzg10::commit_many(&proving_key, vec![&p, &q]);
+        for config in &[
+            MsmConfig::default(),
+            MsmConfig {
+                chunk_size: Some(64),
+                ..MsmConfig::default()
+            },
+            MsmConfig {
+                window_bits: Some(4),
+                chunk_size: Some(100),
+                threads: Some(2),
+            },
+        ] {
+            assert_eq!(
+                mapped
+                    .commit_many_with_config(vec![&p, &q], config)
+                    .unwrap(),
+                expected
+            );
+        }
+        // A window which would divide by zero or overflow the buckets is rejected
+        for &window_bits in &[0, 64] {
+            let config = MsmConfig {
+                window_bits: Some(window_bits),
+                ..MsmConfig::default()
+            };
+            assert!(matches!(
+                mapped.commit_many_with_config(vec![&p], &config),
+                Err(Error::InvalidWindow(bits)) if bits == window_bits
+            ));
+        }
+
+        // A proof made with the mapped key verifies
+        let preprocessed_table = XOR4Bit::new().preprocess(&proving_key, 2usize.pow(8));
+        let mut lookup = LookUp::new(XOR4Bit::new());
+        lookup.read(&(Fr::from(5u8), Fr::from(6u8)));
+        let proof = lookup
+            .prove_with_options(
+                &mapped,
+                &preprocessed_table,
+                ProveOptions::new(),
+                &mut Transcript::new(b"lookup"),
+            )
+            .unwrap();
+        assert!(proof.verify(
+            &verifier_key,
+            &preprocessed_table,
+            &mut Transcript::new(b"lookup")
+        ));
+
+        // Truncated files are rejected
+        std::fs::write(&path, &bytes[..bytes.len() - 1]).unwrap();
+        assert!(MappedPowers::open(&path).is_err());
+
+        std::fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,489 @@
+// A machine-readable description of the verifier
+//
+// Other proving systems which embed plookup verification need the exact order of the transcript, the values the
+// verifier derives from the domain, the identity that the quotient must satisfy and the openings that are batched
+// into the pairing check. `VerifierDescription` lists them as data, and `to_json` writes them out, so that the
+// verifier can be rebuilt from the description rather than from the paper.
+//
+// Expressions are written in JSON as nested arrays: ["add", a, b], ["sub", a, b] and ["mul", a, b] for the field
+// operations, ["var", name] for a named value and ["const", k] for a small constant.
+use super::{
+    proof::PARAMETER_LABELS,
+    quotient_poly::{Constraint, CONSTRAINTS},
+};
+use algebra::bls12_381::Fr;
+use std::{
+    collections::HashMap,
+    fmt::Write,
+    ops::{Add, Mul, Sub},
+};
+
+/// An expression over the values that the verifier knows, in the field of the proof
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Expr {
+    /// A value from the proof, a challenge or a value derived from the domain, by name
+    Var(&'static str),
+    /// A small constant
+    Const(u64),
+    Add(Box<Expr>, Box<Expr>),
+    Sub(Box<Expr>, Box<Expr>),
+    Mul(Box<Expr>, Box<Expr>),
+}
+
+/// A step of the verifier's transcript
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TranscriptStep {
+    /// Absorb the commitment with this label from the proof
+    AbsorbCommitment(&'static str),
+    /// Absorb the scalar with this label, from the proof or a challenge drawn earlier
+    AbsorbScalar(&'static str),
+    /// Absorb the identifier of the table
+    AbsorbTableId(&'static str),
+    /// Absorb the digest of the SRS that the table was committed with, as its 32 bytes
+    AbsorbSrsDigest(&'static str),
+    /// Absorb the context of the proof, its application identifier and session
+    AbsorbContext(&'static str),
+    /// Draw the challenge with this label
+    Challenge(&'static str),
+}
+
+/// A value that the verifier computes from the domain of size `n` and the evaluation challenge
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Derived {
+    pub name: &'static str,
+    pub definition: &'static str,
+}
+
+/// Polynomials which are opened together at one point
+/// The commitments and the evaluations are combined with the powers of `aggregation_challenge`, in order.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Opening {
+    /// The point, as an expression over the challenges
+    pub point: Expr,
+    /// The commitment and the claimed evaluation of each polynomial, by name
+    pub polynomials: Vec<(&'static str, &'static str)>,
+    /// The commitment in the proof to the opening witness
+    pub witness: &'static str,
+}
+
+/// The checks which the verifier makes, in the order it makes them
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct VerifierDescription {
+    pub transcript: Vec<TranscriptStep>,
+    /// Commitments which the verifier computes from other commitments, as a linear combination
+    pub derived_commitments: Vec<(&'static str, Vec<(&'static str, Expr)>)>,
+    pub derived: Vec<Derived>,
+    /// The numerator of each check in the quotient, in the order of `quotient_poly::CONSTRAINTS`
+    pub constraints: Vec<(Constraint, Expr)>,
+    /// The quotient identity, which holds when `q_eval` is the evaluation of the quotient
+    pub quotient_identity: (Expr, Expr),
+    pub openings: Vec<Opening>,
+    /// The challenge that the openings at each point are combined with
+    pub aggregation_challenge: &'static str,
+}
+
+/// Returns the name of a variable as an expression
+pub fn var(name: &'static str) -> Expr {
+    Expr::Var(name)
+}
+
+impl Expr {
+    /// Evaluates the expression, returning None if a variable is missing from `values`
+    pub fn evaluate(&self, values: &HashMap<&'static str, Fr>) -> Option<Fr> {
+        Some(match self {
+            Expr::Var(name) => *values.get(name)?,
+            Expr::Const(k) => Fr::from(*k),
+            Expr::Add(a, b) => a.evaluate(values)? + b.evaluate(values)?,
+            Expr::Sub(a, b) => a.evaluate(values)? - b.evaluate(values)?,
+            Expr::Mul(a, b) => a.evaluate(values)? * b.evaluate(values)?,
+        })
+    }
+    fn write_json(&self, out: &mut String) {
+        match self {
+            Expr::Var(name) => write!(out, "[\"var\", \"{}\"]", name).unwrap(),
+            Expr::Const(k) => write!(out, "[\"const\", {}]", k).unwrap(),
+            Expr::Add(a, b) => write_op(out, "add", a, b),
+            Expr::Sub(a, b) => write_op(out, "sub", a, b),
+            Expr::Mul(a, b) => write_op(out, "mul", a, b),
+        }
+    }
+}
+
+fn write_op(out: &mut String, op: &str, a: &Expr, b: &Expr) {
+    write!(out, "[\"{}\", ", op).unwrap();
+    a.write_json(out);
+    out.push_str(", ");
+    b.write_json(out);
+    out.push(']');
+}
+
+impl Add for Expr {
+    type Output = Expr;
+    fn add(self, other: Expr) -> Expr {
+        Expr::Add(Box::new(self), Box::new(other))
+    }
+}
+
+impl Sub for Expr {
+    type Output = Expr;
+    fn sub(self, other: Expr) -> Expr {
+        Expr::Sub(Box::new(self), Box::new(other))
+    }
+}
+
+impl Mul for Expr {
+    type Output = Expr;
+    fn mul(self, other: Expr) -> Expr {
+        Expr::Mul(Box::new(self), Box::new(other))
+    }
+}
+
+/// Returns the numerator of a check in the quotient, evaluated at the evaluation challenge
+pub fn constraint_expression(constraint: Constraint) -> Expr {
+    let one = || Expr::Const(1);
+    let beta_one = || one() + var("beta");
+    let gamma_beta_one = || var("gamma") * beta_one();
+    let x_minus_g_n = || var("evaluation_challenge") - var("g_n");
+
+    match constraint {
+        Constraint::FirstPoint => (var("z_eval") - one()) * var("l_1"),
+        Constraint::Term => {
+            let f_side = x_minus_g_n()
+                * var("z_eval")
+                * beta_one()
+                * (var("gamma") + var("f_eval"))
+                * (gamma_beta_one() + var("t_eval") + var("beta") * var("t_omega_eval"));
+            let h_side = x_minus_g_n()
+                * var("z_omega_eval")
+                * (gamma_beta_one() + var("h_1_eval") + var("beta") * var("h_1_omega_eval"))
+                * (gamma_beta_one() + var("h_2_eval") + var("beta") * var("h_2_omega_eval"));
+            f_side - h_side
+        }
+        Constraint::Interval => var("l_n") * (var("h_1_eval") - var("h_2_omega_eval")),
+        Constraint::LastPoint => (var("z_eval") - one()) * var("l_n"),
+    }
+}
+
+impl VerifierDescription {
+    /// Describes the verifier of `EqualityProof`, given the commitment to t(X)
+    pub fn equality_proof() -> VerifierDescription {
+        use TranscriptStep::*;
+
+        // The public parameters are absorbed as scalars before any challenge
+        let mut transcript: Vec<_> = PARAMETER_LABELS.iter().cloned().map(AbsorbScalar).collect();
+        transcript.extend(vec![
+            AbsorbCommitment("f"),
+            AbsorbCommitment("h_1_poly"),
+            AbsorbCommitment("h_2_poly"),
+            Challenge("beta"),
+            Challenge("gamma"),
+            AbsorbCommitment("accumulator_poly"),
+            Challenge("separation_challenge"),
+            AbsorbCommitment("quotient_poly"),
+            Challenge("evaluation_challenge"),
+            AbsorbScalar("evaluation_challenge"),
+            AbsorbScalar("f_eval"),
+            AbsorbScalar("t_eval"),
+            AbsorbScalar("h_1_eval"),
+            AbsorbScalar("h_2_eval"),
+            AbsorbScalar("z_eval"),
+            AbsorbScalar("q_eval"),
+            AbsorbScalar("t_omega_eval"),
+            AbsorbScalar("h_1_omega_eval"),
+            AbsorbScalar("h_2_omega_eval"),
+            AbsorbScalar("z_omega_eval"),
+            Challenge("witness_aggregation"),
+        ]);
+
+        let derived = vec![
+            Derived {
+                name: "g_n",
+                definition: "the last element g^(n-1) of the domain of size n",
+            },
+            Derived {
+                name: "l_1",
+                definition: "the first lagrange polynomial of the domain, at evaluation_challenge",
+            },
+            Derived {
+                name: "l_n",
+                definition: "the last lagrange polynomial of the domain, at evaluation_challenge",
+            },
+            Derived {
+                name: "vanishing",
+                definition: "evaluation_challenge^n - 1",
+            },
+        ];
+
+        let constraints: Vec<_> = CONSTRAINTS
+            .iter()
+            .map(|constraint| (*constraint, constraint_expression(*constraint)))
+            .collect();
+
+        // The i'th check is multiplied by separation_challenge^i
+        let numerator = constraints
+            .iter()
+            .rev()
+            .map(|(_, expr)| expr.clone())
+            .fold(None, |acc: Option<Expr>, expr| match acc {
+                None => Some(expr),
+                Some(acc) => Some(acc * var("separation_challenge") + expr),
+            })
+            .unwrap();
+
+        let openings = vec![
+            Opening {
+                point: var("evaluation_challenge"),
+                polynomials: vec![
+                    ("f", "f_eval"),
+                    ("t", "t_eval"),
+                    ("h_1", "h_1_eval"),
+                    ("h_2", "h_2_eval"),
+                    ("z", "z_eval"),
+                    ("q", "q_eval"),
+                ],
+                witness: "aggregate_witness_comm",
+            },
+            Opening {
+                point: var("evaluation_challenge") * var("omega"),
+                polynomials: vec![
+                    ("t", "t_omega_eval"),
+                    ("h_1", "h_1_omega_eval"),
+                    ("h_2", "h_2_omega_eval"),
+                    ("z", "z_omega_eval"),
+                ],
+                witness: "shifted_aggregate_witness_comm",
+            },
+        ];
+
+        VerifierDescription {
+            transcript,
+            derived_commitments: vec![],
+            derived,
+            constraints,
+            quotient_identity: (var("q_eval") * var("vanishing"), numerator),
+            openings,
+            aggregation_challenge: "witness_aggregation",
+        }
+    }
+    /// Describes the verifier of `LookUpProof`, given the commitments t_1, t_2 and t_3 to the columns of the table
+    /// The table is folded with `alpha` before the multiset equality proof is verified,
+    /// and after the context of the proof, the public parameters, the table identifier and the SRS digest are absorbed.
+    pub fn lookup_proof() -> VerifierDescription {
+        let mut description = VerifierDescription::equality_proof();
+
+        let parameters = PARAMETER_LABELS.len();
+        let mut transcript = vec![TranscriptStep::AbsorbContext("context")];
+        transcript.extend_from_slice(&description.transcript[..parameters]);
+        transcript.extend(vec![
+            TranscriptStep::AbsorbTableId("table_id"),
+            TranscriptStep::AbsorbSrsDigest("srs_digest"),
+            TranscriptStep::Challenge("alpha"),
+            TranscriptStep::AbsorbScalar("alpha"),
+        ]);
+        transcript.extend_from_slice(&description.transcript[parameters..]);
+        description.transcript = transcript;
+
+        description.derived_commitments = vec![(
+            "t",
+            vec![
+                ("t_1", Expr::Const(1)),
+                ("t_2", var("alpha")),
+                ("t_3", var("alpha") * var("alpha")),
+            ],
+        )];
+        description
+    }
+    /// Writes the description as JSON
+    pub fn to_json(&self) -> String {
+        let mut out = String::from("{\n  \"transcript\": [\n");
+        let steps: Vec<_> = self
+            .transcript
+            .iter()
+            .map(|step| {
+                let (op, label) = match step {
+                    TranscriptStep::AbsorbCommitment(label) => ("absorb_commitment", label),
+                    TranscriptStep::AbsorbScalar(label) => ("absorb_scalar", label),
+                    TranscriptStep::AbsorbTableId(label) => ("absorb_table_id", label),
+                    TranscriptStep::AbsorbSrsDigest(label) => ("absorb_srs_digest", label),
+                    TranscriptStep::AbsorbContext(label) => ("absorb_context", label),
+                    TranscriptStep::Challenge(label) => ("challenge", label),
+                };
+                format!("    {{\"op\": \"{}\", \"label\": \"{}\"}}", op, label)
+            })
+            .collect();
+        writeln!(out, "{}\n  ],", steps.join(",\n")).unwrap();
+
+        out.push_str("  \"derived_commitments\": [");
+        let commitments: Vec<_> = self
+            .derived_commitments
+            .iter()
+            .map(|(name, terms)| {
+                let terms: Vec<_> = terms
+                    .iter()
+                    .map(|(commitment, coefficient)| {
+                        let mut term =
+                            format!("{{\"commitment\": \"{}\", \"coefficient\": ", commitment);
+                        coefficient.write_json(&mut term);
+                        term.push('}');
+                        term
+                    })
+                    .collect();
+                format!(
+                    "{{\"name\": \"{}\", \"terms\": [{}]}}",
+                    name,
+                    terms.join(", ")
+                )
+            })
+            .collect();
+        writeln!(out, "{}],", commitments.join(", ")).unwrap();
+
+        out.push_str("  \"derived\": [\n");
+        let derived: Vec<_> = self
+            .derived
+            .iter()
+            .map(|d| {
+                format!(
+                    "    {{\"name\": \"{}\", \"definition\": \"{}\"}}",
+                    d.name, d.definition
+                )
+            })
+            .collect();
+        writeln!(out, "{}\n  ],", derived.join(",\n")).unwrap();
+
+        out.push_str("  \"constraints\": [\n");
+        let constraints: Vec<_> = self
+            .constraints
+            .iter()
+            .map(|(constraint, expr)| {
+                let mut entry = format!(
+                    "    {{\"name\": \"{:?}\", \"paper\": \"{}\", \"expression\": ",
+                    constraint,
+                    constraint.paper_label()
+                );
+                expr.write_json(&mut entry);
+                entry.push('}');
+                entry
+            })
+            .collect();
+        writeln!(out, "{}\n  ],", constraints.join(",\n")).unwrap();
+
+        out.push_str("  \"quotient_identity\": {\"lhs\": ");
+        self.quotient_identity.0.write_json(&mut out);
+        out.push_str(", \"rhs\": ");
+        self.quotient_identity.1.write_json(&mut out);
+        out.push_str("},\n");
+
+        out.push_str("  \"openings\": [\n");
+        let openings: Vec<_> = self
+            .openings
+            .iter()
+            .map(|opening| {
+                let mut entry = String::from("    {\"point\": ");
+                opening.point.write_json(&mut entry);
+                let polynomials: Vec<_> = opening
+                    .polynomials
+                    .iter()
+                    .map(|(commitment, evaluation)| {
+                        format!(
+                            "{{\"commitment\": \"{}\", \"evaluation\": \"{}\"}}",
+                            commitment, evaluation
+                        )
+                    })
+                    .collect();
+                write!(
+                    entry,
+                    ", \"polynomials\": [{}], \"witness\": \"{}\"}}",
+                    polynomials.join(", "),
+                    opening.witness
+                )
+                .unwrap();
+                entry
+            })
+            .collect();
+        writeln!(out, "{}\n  ],", openings.join(",\n")).unwrap();
+
+        writeln!(
+            out,
+            "  \"aggregation_challenge\": \"{}\"\n}}",
+            self.aggregation_challenge
+        )
+        .unwrap();
+        out
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::multiset::{multiset_equality::*, quotient_poly, MultiSet};
+    use ff_fft::{DensePolynomial as Polynomial, EvaluationDomain};
+    use num_traits::identities::Zero;
+
+    #[test]
+    fn test_description_matches_quotient() {
+        let f: MultiSet = [2u8, 3, 4].iter().map(|x| Fr::from(*x)).collect();
+        let t: MultiSet = [2u8, 3, 4, 5].iter().map(|x| Fr::from(*x)).collect();
+        let domain: EvaluationDomain<Fr> = EvaluationDomain::new(t.len()).unwrap();
+        let (beta, gamma, separation) = (Fr::from(10u8), Fr::from(11u8), Fr::from(13u8));
+
+        let (h_1, h_2) = compute_h1_h2(&f, &t);
+        let (f_poly, t_poly) = (f.to_polynomial(&domain), t.to_polynomial(&domain));
+        let (h_1_poly, h_2_poly) = (h_1.to_polynomial(&domain), h_2.to_polynomial(&domain));
+        let z_evaluations = compute_accumulator_values(&f, &t, &h_1, &h_2, beta, gamma);
+        let z_poly = Polynomial::from_coefficients_vec(domain.ifft(&z_evaluations));
+        let q_poly = quotient_poly::compute(
+            &domain, &z_poly, &f_poly, &t_poly, &h_1_poly, &h_2_poly, beta, gamma, separation,
+        );
+
+        let point = Fr::from(12345u64);
+        let point_omega = point * domain.group_gen;
+        let lagrange = domain.evaluate_all_lagrange_coefficients(point);
+        let values: HashMap<&'static str, Fr> = vec![
+            ("beta", beta),
+            ("gamma", gamma),
+            ("separation_challenge", separation),
+            ("evaluation_challenge", point),
+            ("g_n", domain.elements().last().unwrap()),
+            ("l_1", lagrange[0]),
+            ("l_n", lagrange[domain.size() - 1]),
+            ("vanishing", domain.evaluate_vanishing_polynomial(point)),
+            ("f_eval", f_poly.evaluate(point)),
+            ("t_eval", t_poly.evaluate(point)),
+            ("t_omega_eval", t_poly.evaluate(point_omega)),
+            ("h_1_eval", h_1_poly.evaluate(point)),
+            ("h_1_omega_eval", h_1_poly.evaluate(point_omega)),
+            ("h_2_eval", h_2_poly.evaluate(point)),
+            ("h_2_omega_eval", h_2_poly.evaluate(point_omega)),
+            ("z_eval", z_poly.evaluate(point)),
+            ("z_omega_eval", z_poly.evaluate(point_omega)),
+            ("q_eval", q_poly.evaluate(point)),
+        ]
+        .into_iter()
+        .collect();
+
+        let description = VerifierDescription::equality_proof();
+        let (lhs, rhs) = &description.quotient_identity;
+        assert!(!lhs.evaluate(&values).unwrap().is_zero());
+        assert_eq!(lhs.evaluate(&values), rhs.evaluate(&values));
+
+        // A wrong quotient evaluation fails the identity
+        let mut wrong = values.clone();
+        wrong.insert("q_eval", values["q_eval"] + Fr::from(1u8));
+        assert_ne!(lhs.evaluate(&wrong), rhs.evaluate(&wrong));
+    }
+
+    #[test]
+    fn test_json() {
+        let json = VerifierDescription::lookup_proof().to_json();
+        assert!(json.starts_with(
+            "{\n  \"transcript\": [\n    {\"op\": \"absorb_context\", \"label\": \"context\"}"
+        ));
+        assert!(json.contains("{\"op\": \"absorb_scalar\", \"label\": \"protocol_version\"}"));
+        assert!(json.contains("{\"op\": \"absorb_table_id\", \"label\": \"table_id\"}"));
+        assert!(json.contains("{\"op\": \"absorb_srs_digest\", \"label\": \"srs_digest\"}"));
+        assert!(json.contains("{\"name\": \"FirstPoint\", \"paper\": \"(a)\", \"expression\": [\"mul\", [\"sub\", [\"var\", \"z_eval\"], [\"const\", 1]], [\"var\", \"l_1\"]]}"));
+        assert!(json.contains("\"aggregation_challenge\": \"witness_aggregation\""));
+        assert_eq!(json.matches('[').count(), json.matches(']').count());
+        assert_eq!(json.matches('{').count(), json.matches('}').count());
+    }
+}
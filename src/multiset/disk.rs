@@ -0,0 +1,134 @@
+use super::MultiSet;
+use crate::error::Error;
+use algebra::{bls12_381::Fr, CanonicalDeserialize, CanonicalSerialize};
+use std::{
+    collections::HashMap,
+    fs::{File, OpenOptions},
+    io::{BufReader, BufWriter, Write},
+    path::{Path, PathBuf},
+};
+
+/// A multiset which is stored in a file rather than in memory
+///
+/// For very large witnesses, the prover cannot hold the several copies of `f` that sorting needs.
+/// Instead, `f` is written to disk as it is produced, and `compute_h1_h2_from_disk`
+/// streams it back to compute h_1 and h_2 using memory proportional to the size of the table.
+pub struct DiskMultiSet {
+    path: PathBuf,
+    writer: BufWriter<File>,
+    len: usize,
+}
+
+impl DiskMultiSet {
+    /// Creates an empty multiset backed by the file at `path`, which is truncated if it exists
+    pub fn create(path: &Path) -> Result<DiskMultiSet, Error> {
+        let file = OpenOptions::new()
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .open(path)?;
+
+        Ok(DiskMultiSet {
+            path: path.to_path_buf(),
+            writer: BufWriter::new(file),
+            len: 0,
+        })
+    }
+    /// Pushes a value onto the end of the set
+    pub fn push(&mut self, value: Fr) -> Result<(), Error> {
+        value.serialize(&mut self.writer)?;
+        self.len += 1;
+        Ok(())
+    }
+    /// Returns the cardinality of the multiset
+    pub fn len(&self) -> usize {
+        self.len
+    }
+    /// Returns true if the multiset has no elements
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+    /// Streams the elements of the multiset from disk, in the order they were pushed
+    pub fn iter(&mut self) -> Result<impl Iterator<Item = Result<Fr, Error>>, Error> {
+        self.writer.flush()?;
+        let mut reader = BufReader::new(File::open(&self.path)?);
+
+        Ok((0..self.len).map(move |_| Fr::deserialize(&mut reader).map_err(Error::from)))
+    }
+    /// Reads the whole multiset into memory
+    pub fn to_multiset(&mut self) -> Result<MultiSet, Error> {
+        let values = self.iter()?.collect::<Result<Vec<_>, _>>()?;
+        Ok(MultiSet(values))
+    }
+}
+
+/// Computes the multisets h_1 and h_2, streaming `f` from disk
+/// The result is the same as `compute_h1_h2`, but `s` is never held in memory.
+/// Panics if an element of `f` is not in `t`
+pub fn compute_h1_h2_from_disk(
+    f: &mut DiskMultiSet,
+    t: &MultiSet,
+) -> Result<(MultiSet, MultiSet), Error> {
+    // Elements of `f` are placed next to the first occurrence of the same element in `t`,
+    // so `s` is determined by how many elements of `f` are placed after each element of `t`
+    let mut first_position: HashMap<Fr, usize> = HashMap::with_capacity(t.len());
+    for (index, element) in t.0.iter().enumerate() {
+        first_position.entry(*element).or_insert(index);
+    }
+
+    let mut counts = vec![0usize; t.len()];
+    for element in f.iter()? {
+        let index = first_position
+            .get(&element?)
+            .expect("element in f is not contained in t");
+        counts[*index] += 1;
+    }
+
+    // Split `s` in the same way as `MultiSet::halve`, where the middle element is in both halves
+    let middle = (f.len() + t.len()) / 2;
+    let mut h_1 = MultiSet::new();
+    let mut h_2 = MultiSet::new();
+    let mut position = 0;
+    for (element, count) in t.0.iter().zip(counts.into_iter()) {
+        for _ in 0..=count {
+            if position <= middle {
+                h_1.push(*element);
+            }
+            if position >= middle {
+                h_2.push(*element);
+            }
+            position += 1;
+        }
+    }
+
+    Ok((h_1, h_2))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::multiset::multiset_equality::compute_h1_h2;
+
+    #[test]
+    fn test_compute_h1_h2_from_disk() {
+        let path = std::env::temp_dir().join("plookup_test_compute_h1_h2_from_disk");
+
+        let t: MultiSet = [2u8, 5, 3, 5, 7, 1, 9].iter().map(|x| Fr::from(*x)).collect();
+        let f_values = [5u8, 1, 1, 9, 5, 3, 5, 2];
+
+        let mut f = DiskMultiSet::create(&path).unwrap();
+        for value in f_values.iter() {
+            f.push(Fr::from(*value)).unwrap();
+        }
+        assert_eq!(f.len(), f_values.len());
+
+        let in_memory_f = f.to_multiset().unwrap();
+        let expected: MultiSet = f_values.iter().map(|x| Fr::from(*x)).collect();
+        assert_eq!(in_memory_f, expected);
+
+        let (h_1, h_2) = compute_h1_h2_from_disk(&mut f, &t).unwrap();
+        assert_eq!((h_1, h_2), compute_h1_h2(&in_memory_f, &t));
+
+        std::fs::remove_file(&path).unwrap();
+    }
+}
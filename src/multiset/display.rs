@@ -0,0 +1,131 @@
+// Compact formatting of field elements, multisets and polynomials
+//
+// Tables and witnesses mostly hold small integers, but the field's own formatting prints every element as its
+// internal limbs. Here an element is printed as an integer if it is less than 2^32, as a negative integer if it is
+// within 2^32 of the modulus, and as its hex encoding otherwise. Runs of one repeated value, such as the padding at
+// the end of a witness or a table, are printed once with the length of the run: `[1, 2, 3 × 254]`.
+use super::MultiSet;
+use crate::encoding::{fr_to_hex, fr_to_u64};
+use algebra::bls12_381::Fr;
+use ff_fft::DensePolynomial as Polynomial;
+use std::fmt;
+
+// Values below this bound, or within it of the modulus, are printed as integers
+const SMALL_BOUND: u64 = 1 << 32;
+// Runs of at least this many equal values are elided
+const MIN_RUN: usize = 4;
+
+/// Formats a field element compactly, see the module documentation
+#[derive(Clone, Copy)]
+pub struct DisplayScalar<'a>(pub &'a Fr);
+
+/// Formats a polynomial as its degree followed by its coefficients, from the constant term
+#[derive(Clone, Copy)]
+pub struct DisplayPolynomial<'a>(pub &'a Polynomial<Fr>);
+
+impl fmt::Display for DisplayScalar<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match fr_to_u64(self.0) {
+            Some(x) if x < SMALL_BOUND => write!(f, "{}", x),
+            _ => match fr_to_u64(&-*self.0) {
+                Some(x) if x < SMALL_BOUND => write!(f, "-{}", x),
+                _ => write!(f, "{}", fr_to_hex(self.0)),
+            },
+        }
+    }
+}
+
+impl fmt::Debug for DisplayScalar<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Display::fmt(self, f)
+    }
+}
+
+impl fmt::Display for DisplayPolynomial<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.0.is_zero() {
+            return write!(f, "zero polynomial");
+        }
+        write!(f, "degree {}: ", self.0.degree())?;
+        fmt_values(&self.0.coeffs, f)
+    }
+}
+
+impl fmt::Debug for DisplayPolynomial<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Display::fmt(self, f)
+    }
+}
+
+impl fmt::Display for MultiSet {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt_values(&self.0, f)
+    }
+}
+
+impl fmt::Debug for MultiSet {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "MultiSet(len {}: ", self.len())?;
+        fmt_values(&self.0, f)?;
+        write!(f, ")")
+    }
+}
+
+// Writes the values as a list, eliding runs of equal values
+fn fmt_values(values: &[Fr], f: &mut fmt::Formatter) -> fmt::Result {
+    write!(f, "[")?;
+    let mut i = 0;
+    while i < values.len() {
+        let run = values[i..]
+            .iter()
+            .take_while(|value| **value == values[i])
+            .count();
+        if i > 0 {
+            write!(f, ", ")?;
+        }
+        if run >= MIN_RUN {
+            write!(f, "{} × {}", DisplayScalar(&values[i]), run)?;
+            i += run;
+        } else {
+            write!(f, "{}", DisplayScalar(&values[i]))?;
+            i += 1;
+        }
+    }
+    write!(f, "]")
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use algebra::UniformRand;
+
+    #[test]
+    fn test_display() {
+        assert_eq!(DisplayScalar(&Fr::from(42u8)).to_string(), "42");
+        assert_eq!(DisplayScalar(&-Fr::from(3u8)).to_string(), "-3");
+        let large = Fr::from(1u64 << 40);
+        assert_eq!(DisplayScalar(&large).to_string(), fr_to_hex(&large));
+
+        let mut set: MultiSet = (1..=3u8).map(Fr::from).collect();
+        set.extend(253, Fr::from(3u8));
+        set.push(Fr::from(0u8));
+        assert_eq!(set.to_string(), "[1, 2, 3 × 254, 0]");
+        assert_eq!(
+            format!("{:?}", set),
+            "MultiSet(len 257: [1, 2, 3 × 254, 0])"
+        );
+
+        // Short runs are printed in full
+        let set: MultiSet = vec![5u8, 5, 5].into_iter().map(Fr::from).collect();
+        assert_eq!(set.to_string(), "[5, 5, 5]");
+
+        let poly = Polynomial::from_coefficients_vec(vec![Fr::from(1u8), -Fr::from(1u8)]);
+        assert_eq!(DisplayPolynomial(&poly).to_string(), "degree 1: [1, -1]");
+        let zero = Polynomial::from_coefficients_vec(vec![]);
+        assert_eq!(DisplayPolynomial(&zero).to_string(), "zero polynomial");
+
+        // A random element is printed as hex
+        let random = Fr::rand(&mut rand::thread_rng());
+        assert!(DisplayScalar(&random).to_string().starts_with("0x"));
+    }
+}
@@ -0,0 +1,101 @@
+use super::MultiSet;
+use algebra::bls12_381::Fr;
+use ff_fft::{DensePolynomial as Polynomial, EvaluationDomain};
+use std::sync::{Arc, Mutex};
+
+/// A multiset together with its polynomial over a domain
+///
+/// The polynomial is interpolated the first time it is needed and kept after that, so a table `t` which
+/// many proofs are made against is only interpolated once. The evaluation form of the polynomial over the
+/// domain is the multiset itself, padded with zeros, so it is not stored separately.
+#[derive(Debug)]
+pub struct Interpolated {
+    set: MultiSet,
+    domain: EvaluationDomain<Fr>,
+    poly: Mutex<Option<Arc<Polynomial<Fr>>>>,
+}
+
+impl Interpolated {
+    /// Wraps `set`, whose polynomial over `domain` is computed on first use
+    /// Panics if the set does not fit in the domain.
+    pub fn new(set: MultiSet, domain: EvaluationDomain<Fr>) -> Interpolated {
+        assert!(set.len() <= domain.size());
+        Interpolated {
+            set,
+            domain,
+            poly: Mutex::new(None),
+        }
+    }
+    /// Wraps `set` with its polynomial over `domain`, when the caller has already computed it
+    /// The polynomial is not checked against the set.
+    pub fn with_polynomial(
+        set: MultiSet,
+        domain: EvaluationDomain<Fr>,
+        poly: Polynomial<Fr>,
+    ) -> Interpolated {
+        assert!(set.len() <= domain.size());
+        Interpolated {
+            set,
+            domain,
+            poly: Mutex::new(Some(Arc::new(poly))),
+        }
+    }
+    /// Returns the multiset
+    pub fn set(&self) -> &MultiSet {
+        &self.set
+    }
+    /// Returns the domain that the multiset is interpolated over
+    pub fn domain(&self) -> &EvaluationDomain<Fr> {
+        &self.domain
+    }
+    /// Returns the polynomial whose evaluations over the domain are the multiset,
+    /// interpolating it if it has not been already
+    pub fn polynomial(&self) -> Arc<Polynomial<Fr>> {
+        let mut poly = self.poly.lock().unwrap();
+        poly.get_or_insert_with(|| Arc::new(self.set.to_polynomial(&self.domain)))
+            .clone()
+    }
+    /// Returns true if the polynomial has been computed
+    pub fn is_interpolated(&self) -> bool {
+        self.poly.lock().unwrap().is_some()
+    }
+    /// Returns the multiset, dropping its polynomial
+    pub fn into_set(self) -> MultiSet {
+        self.set
+    }
+}
+
+impl Clone for Interpolated {
+    fn clone(&self) -> Self {
+        Interpolated {
+            set: self.set.clone(),
+            domain: self.domain,
+            poly: Mutex::new(self.poly.lock().unwrap().clone()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_interpolated() {
+        let set: MultiSet = (0..5u8).map(Fr::from).collect();
+        let domain: EvaluationDomain<Fr> = EvaluationDomain::new(8).unwrap();
+
+        let t = Interpolated::new(set.clone(), domain);
+        assert!(!t.is_interpolated());
+        let poly = t.polynomial();
+        assert!(t.is_interpolated());
+        assert_eq!(*poly, set.to_polynomial(&domain));
+
+        // The polynomial is shared rather than recomputed
+        assert!(Arc::ptr_eq(&poly, &t.polynomial()));
+        assert!(t.clone().is_interpolated());
+
+        let t = Interpolated::with_polynomial(set.clone(), domain, set.to_polynomial(&domain));
+        assert!(t.is_interpolated());
+        assert_eq!(t.into_set(), set);
+    }
+}
@@ -1,7 +1,21 @@
+pub mod disk;
+pub mod description;
+pub mod display;
+pub mod interpolated;
 pub mod multiset;
 pub mod multiset_equality;
 pub mod proof;
 pub mod quotient_poly;
+pub mod scratch;
+pub mod state;
+pub mod wire_format;
 
+pub use interpolated::Interpolated;
 pub use multiset::MultiSet;
-pub use proof::EqualityProof;
+pub use proof::{
+    report_progress, CancellationToken, EqualityProof, ProverConfig, ProverStage, PublicParameters,
+    PARAMETER_LABELS, PROTOCOL_VERSION,
+};
+pub use scratch::{ProverScratch, ScratchBuffers};
+pub use state::ProverState;
+pub use wire_format::WireFormat;
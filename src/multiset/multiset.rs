@@ -1,10 +1,20 @@
-use algebra::bls12_381::Fr;
+use algebra::{
+    bls12_381::Fr,
+    io::{Read, Write},
+    CanonicalDeserialize, CanonicalSerialize, PrimeField, SerializationError,
+};
 use ff_fft::{DensePolynomial as Polynomial, EvaluationDomain};
 use num_traits::identities::{One, Zero};
+use std::collections::HashMap;
 use std::ops::{Add, Mul};
+
+#[cfg(feature = "parallel")]
+use rayon::prelude::*;
+
 /// A MultiSet is a variation of a set, where we allow duplicate members
 /// This can be emulated in Rust by using vectors
-#[derive(Clone, Eq, PartialEq, Debug)]
+/// `Debug` and `Display` print the elements compactly, see `multiset::display`.
+#[derive(Clone, Eq, PartialEq, Hash)]
 pub struct MultiSet(pub Vec<Fr>);
 
 impl MultiSet {
@@ -16,6 +26,14 @@ impl MultiSet {
     pub fn push(&mut self, value: Fr) {
         self.0.push(value)
     }
+    /// Pushes an integer onto the end of the set, as the field element it represents
+    pub fn push_u64(&mut self, value: u64) {
+        self.push(Fr::from(value))
+    }
+    /// Pushes any unsigned integer of at most 64 bits onto the end of the set
+    pub fn push_int<T: Into<u64>>(&mut self, value: T) {
+        self.push_u64(value.into())
+    }
     /// Pushes 'n' elements into the multiset
     pub fn extend(&mut self, n: usize, value: Fr) {
         let elements = vec![value; n];
@@ -26,6 +44,16 @@ impl MultiSet {
     pub fn last(&self) -> Fr {
         *self.0.last().unwrap()
     }
+    /// Creates a multiset from a stream of values, without buffering them elsewhere
+    pub fn from_stream<I: IntoIterator<Item = Fr>>(values: I) -> MultiSet {
+        let mut set = MultiSet::new();
+        set.push_all(values);
+        set
+    }
+    /// Pushes every value of a stream onto the end of the set
+    pub fn push_all<I: IntoIterator<Item = Fr>>(&mut self, values: I) {
+        self.0.extend(values)
+    }
     fn from_slice(slice: &[Fr]) -> MultiSet {
         MultiSet(slice.to_vec())
     }
@@ -43,27 +71,45 @@ impl MultiSet {
         MultiSet(result)
     }
 
-    /// Returns the position of the element in the Multiset
-    /// Panics if element is not in the Multiset
-    fn position(&self, element: &Fr) -> usize {
-        let index = self.0.iter().position(|&x| x == *element).unwrap();
-        index
-    }
-
     /// Performs a element-wise insertion into the second multiset
     /// Example: f {1,2,3,1} t : {3,1,2,3}
     /// We now take each element from f and find the element in `t` then insert the element from `f` into right next to it's duplicate
     /// We are assuming that `f` is contained in `t`
+    /// Panics if an element of `f` is not in `t`
     pub fn concatenate_and_sort(&self, t: &MultiSet) -> MultiSet {
-        assert!(self.is_subset_of(t));
-        let mut result = t.clone();
+        // Rather than inserting one element at a time, we key each element of `t` by its position
+        // and each element of `f` by the position of its first occurrence in `t`.
+        // Elements which share a key are equal, so sorting by the key has the same effect.
+        let mut first_position: HashMap<Fr, usize> = HashMap::with_capacity(t.len());
+        for (index, element) in t.0.iter().enumerate() {
+            first_position.entry(*element).or_insert(index);
+        }
 
+        let mut keyed: Vec<(usize, Fr)> = Vec::with_capacity(self.len() + t.len());
+        keyed.extend(t.0.iter().copied().enumerate());
         for element in self.0.iter() {
-            let index = result.position(element);
-            result.0.insert(index, *element);
+            let index = first_position
+                .get(element)
+                .expect("element in f is not contained in t");
+            keyed.push((*index, *element));
         }
 
-        result
+        sort_unstable_by_key(&mut keyed);
+
+        MultiSet(keyed.into_iter().map(|(_, element)| element).collect())
+    }
+
+    /// Sorts the multiset by the canonical (integer) representation of each element
+    /// With the `parallel` feature enabled, the sort is done across all available threads
+    pub fn sort(&mut self) {
+        #[cfg(feature = "parallel")]
+        let mut keyed: Vec<_> = self.0.par_iter().map(|x| (x.into_repr(), *x)).collect();
+        #[cfg(not(feature = "parallel"))]
+        let mut keyed: Vec<_> = self.0.iter().map(|x| (x.into_repr(), *x)).collect();
+
+        sort_unstable_by_key(&mut keyed);
+
+        self.0 = keyed.into_iter().map(|(_, element)| element).collect();
     }
 
     /// Checks whether self is a subset of other
@@ -82,6 +128,49 @@ impl MultiSet {
     pub fn contains(&self, element: &Fr) -> bool {
         self.0.contains(element)
     }
+    /// Returns a map from each distinct element to the number of times it occurs in the multiset
+    pub fn counts(&self) -> HashMap<Fr, usize> {
+        let mut counts = HashMap::new();
+        for element in self.0.iter() {
+            *counts.entry(*element).or_insert(0) += 1;
+        }
+        counts
+    }
+    /// Returns the elements that are in both multisets
+    /// Each element appears min(count in self, count in other) times
+    /// The order of the elements in `self` is preserved
+    pub fn intersection(&self, other: &MultiSet) -> MultiSet {
+        let mut remaining = other.counts();
+        let mut result = MultiSet::new();
+
+        for element in self.0.iter() {
+            if let Some(count) = remaining.get_mut(element) {
+                if *count > 0 {
+                    *count -= 1;
+                    result.push(*element);
+                }
+            }
+        }
+        result
+    }
+    /// Returns the elements in self that are not accounted for in other
+    /// Each element appears max(count in self - count in other, 0) times
+    /// The order of the elements in `self` is preserved
+    ///
+    /// Example: If `f` is the witness and `t` is the table, then `f.difference(&t)` will contain
+    /// the witness values which are not in the table, when counting multiplicities.
+    pub fn difference(&self, other: &MultiSet) -> MultiSet {
+        let mut remaining = other.counts();
+        let mut result = MultiSet::new();
+
+        for element in self.0.iter() {
+            match remaining.get_mut(element) {
+                Some(count) if *count > 0 => *count -= 1,
+                _ => result.push(*element),
+            }
+        }
+        result
+    }
     /// Splits a multiset into halves as specified by the paper
     /// If s = [1,2,3,4,5,6,7], we can deduce n using |s| = 2 * n + 1 = 7
     /// n is therefore 3
@@ -131,6 +220,55 @@ impl MultiSet {
     }
 }
 
+// Sorts (key, element) pairs by their key
+// Uses a parallel sort when the `parallel` feature is enabled
+fn sort_unstable_by_key<K: Ord + Send, V: Send>(keyed: &mut Vec<(K, V)>) {
+    #[cfg(feature = "parallel")]
+    keyed.par_sort_unstable_by(|a, b| a.0.cmp(&b.0));
+    #[cfg(not(feature = "parallel"))]
+    keyed.sort_unstable_by(|a, b| a.0.cmp(&b.0));
+}
+
+impl std::iter::FromIterator<Fr> for MultiSet {
+    fn from_iter<I: IntoIterator<Item = Fr>>(values: I) -> Self {
+        MultiSet::from_stream(values)
+    }
+}
+
+impl From<&[u64]> for MultiSet {
+    fn from(values: &[u64]) -> Self {
+        values.iter().map(|value| Fr::from(*value)).collect()
+    }
+}
+
+// A multiset is serialised as its length as a u64, followed by its elements
+impl CanonicalSerialize for MultiSet {
+    fn serialize<W: Write>(&self, writer: &mut W) -> Result<(), SerializationError> {
+        (self.len() as u64).serialize(writer)?;
+        for value in self.0.iter() {
+            value.serialize(writer)?;
+        }
+        Ok(())
+    }
+    fn serialized_size(&self) -> usize {
+        8 + self
+            .0
+            .iter()
+            .map(|value| value.serialized_size())
+            .sum::<usize>()
+    }
+}
+
+impl CanonicalDeserialize for MultiSet {
+    fn deserialize<R: Read>(reader: &mut R) -> Result<Self, SerializationError> {
+        let len = u64::deserialize(reader)? as usize;
+        (0..len)
+            .map(|_| Fr::deserialize(reader))
+            .collect::<Result<Vec<_>, _>>()
+            .map(MultiSet)
+    }
+}
+
 impl Add for MultiSet {
     type Output = MultiSet;
     fn add(self, other: MultiSet) -> Self::Output {
@@ -162,6 +300,47 @@ impl Mul<Fr> for &MultiSet {
 mod test {
     use super::*;
     #[test]
+    fn test_serialization() {
+        let set: MultiSet = (0..10u64).map(Fr::from).collect();
+
+        let mut bytes = Vec::new();
+        set.serialize(&mut bytes).unwrap();
+        assert_eq!(bytes.len(), set.serialized_size());
+        assert_eq!(bytes.len(), 8 + 10 * 32);
+
+        assert_eq!(MultiSet::deserialize(&mut &bytes[..]).unwrap(), set);
+        assert!(MultiSet::deserialize(&mut &bytes[..bytes.len() - 1]).is_err());
+    }
+
+    #[test]
+    fn test_from_stream() {
+        let values = (0..10u64).map(Fr::from);
+        let set = MultiSet::from_stream(values);
+        assert_eq!(set.len(), 10);
+        assert_eq!(set.last(), Fr::from(9u8));
+
+        let collected: MultiSet = (0..10u64).map(Fr::from).collect();
+        assert_eq!(collected, set);
+
+        let mut pushed = MultiSet::new();
+        pushed.push_all((0..5u64).map(Fr::from));
+        pushed.push_all((5..10u64).map(Fr::from));
+        assert_eq!(pushed, set);
+    }
+    #[test]
+    fn test_push_int() {
+        let set: MultiSet = (0..4u64).map(Fr::from).collect();
+
+        let mut pushed = MultiSet::new();
+        pushed.push_u64(0);
+        pushed.push_int(1u8);
+        pushed.push_int(2u16);
+        pushed.push_int(3u32);
+        assert_eq!(pushed, set);
+
+        assert_eq!(MultiSet::from(&[0u64, 1, 2, 3][..]), set);
+    }
+    #[test]
     fn test_concatenate() {
         let mut a = MultiSet::new();
         a.push(Fr::from(1u64));
@@ -262,6 +441,52 @@ mod test {
         assert!(!c.is_subset_of(&a));
     }
     #[test]
+    fn test_counts() {
+        let mut a = MultiSet::new();
+        a.push(Fr::from(1u8));
+        a.push(Fr::from(2u8));
+        a.push(Fr::from(1u8));
+        a.push(Fr::from(3u8));
+        a.push(Fr::from(1u8));
+
+        let counts = a.counts();
+        assert_eq!(counts.len(), 3);
+        assert_eq!(counts[&Fr::from(1u8)], 3);
+        assert_eq!(counts[&Fr::from(2u8)], 1);
+        assert_eq!(counts[&Fr::from(3u8)], 1);
+    }
+    #[test]
+    fn test_intersection_and_difference() {
+        let mut a = MultiSet::new();
+        a.push(Fr::from(1u8));
+        a.push(Fr::from(2u8));
+        a.push(Fr::from(1u8));
+        a.push(Fr::from(4u8));
+        a.push(Fr::from(1u8));
+
+        let mut b = MultiSet::new();
+        b.push(Fr::from(1u8));
+        b.push(Fr::from(1u8));
+        b.push(Fr::from(3u8));
+        b.push(Fr::from(4u8));
+
+        let mut expected_intersection = MultiSet::new();
+        expected_intersection.push(Fr::from(1u8));
+        expected_intersection.push(Fr::from(1u8));
+        expected_intersection.push(Fr::from(4u8));
+        assert_eq!(a.intersection(&b), expected_intersection);
+
+        let mut expected_difference = MultiSet::new();
+        expected_difference.push(Fr::from(2u8));
+        expected_difference.push(Fr::from(1u8));
+        assert_eq!(a.difference(&b), expected_difference);
+
+        // The intersection and the difference partition `a`
+        let partition = a.intersection(&b).concatenate(&a.difference(&b));
+        assert_eq!(partition.len(), a.len());
+        assert!(b.difference(&a).contains(&Fr::from(3u8)));
+    }
+    #[test]
     fn test_sort_by() {
         let mut f = MultiSet::new();
         f.push(Fr::from(2u8));
@@ -358,4 +583,24 @@ mod test {
         assert_eq!(sorted_s.len(), expected_sorted_s.len());
         assert_eq!(sorted_s, expected_sorted_s)
     }
+    #[test]
+    fn test_sort() {
+        let mut a = MultiSet::new();
+        a.push(Fr::from(5u8));
+        a.push(Fr::from(1u8));
+        a.push(-Fr::from(1u8));
+        a.push(Fr::from(3u8));
+        a.push(Fr::from(1u8));
+
+        a.sort();
+
+        let mut expected = MultiSet::new();
+        expected.push(Fr::from(1u8));
+        expected.push(Fr::from(1u8));
+        expected.push(Fr::from(3u8));
+        expected.push(Fr::from(5u8));
+        // -1 is the largest element in its canonical representation
+        expected.push(-Fr::from(1u8));
+        assert_eq!(a, expected);
+    }
 }
@@ -2,6 +2,9 @@ use crate::multiset::MultiSet;
 use algebra::bls12_381::Fr;
 use num_traits::identities::One;
 
+#[cfg(feature = "parallel")]
+use rayon::prelude::*;
+
 /// Computes the multisets h_1 and h_2
 pub fn compute_h1_h2(f: &MultiSet, t: &MultiSet) -> (MultiSet, MultiSet) {
     //
@@ -46,50 +49,118 @@ pub fn compute_accumulator_values(
 ) -> Vec<Fr> {
     let n = f.len();
 
-    // F(beta, gamma)
-    let mut numerator: Vec<Fr> = Vec::with_capacity(n + 1);
-    // G(beta, gamma)
-    let mut denominator: Vec<Fr> = Vec::with_capacity(n + 1);
-
-    // Z evaluated at the first root of unity is 1
-    numerator.push(Fr::one());
-    denominator.push(Fr::one());
-
     let beta_one = Fr::one() + beta;
 
-    // Compute values for Z(X)
-    for i in 0..n {
-        let f_i = beta_one * compute_f_i(i, f, t, beta, gamma);
-        let g_i = compute_g_i(i, h_1, h_2, beta, gamma);
-
-        let last_numerator = *numerator.last().unwrap();
-        let last_denominator = *denominator.last().unwrap();
-
-        numerator.push(f_i * last_numerator);
-        denominator.push(g_i * last_denominator);
-    }
+    // Compute the terms of F(beta, gamma) and G(beta, gamma)
+    let f_term = |i: usize| beta_one * compute_f_i(i, f, t, beta, gamma);
+    let g_term = |i: usize| compute_g_i(i, h_1, h_2, beta, gamma);
+    #[cfg(feature = "parallel")]
+    let (mut numerator, mut denominator): (Vec<Fr>, Vec<Fr>) = (
+        (0..n).into_par_iter().map(f_term).collect(),
+        (0..n).into_par_iter().map(g_term).collect(),
+    );
+    #[cfg(not(feature = "parallel"))]
+    let (mut numerator, mut denominator): (Vec<Fr>, Vec<Fr>) =
+        ((0..n).map(f_term).collect(), (0..n).map(g_term).collect());
+
+    // Accumulate the terms
+    prefix_product(&mut numerator);
+    prefix_product(&mut denominator);
 
     // Check that Z(g^{n+1}) = 1
-    let last_numerator = *numerator.last().unwrap();
-    let last_denominator = *denominator.last().unwrap();
+    let last_numerator = numerator.last().copied().unwrap_or_else(Fr::one);
+    let last_denominator = denominator.last().copied().unwrap_or_else(Fr::one);
 
     assert_eq!(last_numerator / last_denominator, Fr::one());
 
     // Combine numerator and denominator
     assert_eq!(numerator.len(), denominator.len());
-    assert_eq!(numerator.len(), n + 1);
-    let mut evaluations = Vec::with_capacity(numerator.len());
-    for (n, d) in numerator.into_iter().zip(denominator) {
-        evaluations.push(n / d)
-    }
+    assert_eq!(numerator.len(), n);
+    #[cfg(feature = "parallel")]
+    let quotients = numerator.par_iter().zip(denominator.par_iter());
+    #[cfg(not(feature = "parallel"))]
+    let quotients = numerator.iter().zip(denominator.iter());
+
+    // Z evaluated at the first root of unity is 1
+    let mut evaluations = Vec::with_capacity(n + 1);
+    evaluations.push(Fr::one());
+    evaluations.extend(quotients.map(|(n, d)| *n / d).collect::<Vec<_>>());
     evaluations
 }
 
+// Replaces each value with the product of all values up to and including it
+// With the `parallel` feature enabled, this is the two pass parallel prefix product:
+// each chunk first computes its own running product, then every chunk is multiplied
+// by the product of all the chunks before it.
+fn prefix_product(values: &mut [Fr]) {
+    #[cfg(feature = "parallel")]
+    {
+        let chunk_size = std::cmp::max(1024, values.len() / rayon::current_num_threads() + 1);
+
+        let chunk_products: Vec<Fr> = values
+            .par_chunks_mut(chunk_size)
+            .map(|chunk| {
+                sequential_prefix_product(chunk);
+                *chunk.last().unwrap()
+            })
+            .collect();
+
+        // The product of all the chunks before each chunk
+        let mut carries = Vec::with_capacity(chunk_products.len());
+        let mut carry = Fr::one();
+        for product in chunk_products {
+            carries.push(carry);
+            carry *= &product;
+        }
+
+        values
+            .par_chunks_mut(chunk_size)
+            .zip(carries.into_par_iter())
+            .skip(1)
+            .for_each(|(chunk, carry)| {
+                for value in chunk.iter_mut() {
+                    *value *= &carry;
+                }
+            });
+    }
+    #[cfg(not(feature = "parallel"))]
+    sequential_prefix_product(values);
+}
+
+fn sequential_prefix_product(values: &mut [Fr]) {
+    let mut running_product = Fr::one();
+    for value in values.iter_mut() {
+        running_product *= &*value;
+        *value = running_product;
+    }
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
     use ff_fft::{DensePolynomial as Polynomial, EvaluationDomain};
 
+    #[test]
+    fn test_prefix_product() {
+        // Large enough to be split into several chunks
+        let values: Vec<Fr> = (1..5000u64).map(Fr::from).collect();
+
+        let mut expected = Vec::with_capacity(values.len());
+        let mut running_product = Fr::one();
+        for value in values.iter() {
+            running_product *= value;
+            expected.push(running_product);
+        }
+
+        let mut result = values.clone();
+        prefix_product(&mut result);
+        assert_eq!(result, expected);
+
+        let mut empty: Vec<Fr> = vec![];
+        prefix_product(&mut empty);
+        assert!(empty.is_empty());
+    }
+
     #[test]
     fn test_manually_compute_z() {
         // This test manually computes the values of the accumulator Z(x)
@@ -1,15 +1,218 @@
 use crate::{
-    kzg10,
-    multiset::{multiset_equality, quotient_poly, MultiSet},
+    error::Error,
+    kzg10::{self, CommitKey, MsmConfig, MsmPool, OpeningBatch},
+    multiset::{
+        multiset_equality,
+        quotient_poly::{self, QuotientKey},
+        scratch::ScratchBuffers,
+        state::{HPolynomials, ProverState},
+        Interpolated, MultiSet,
+    },
     transcript::TranscriptProtocol,
 };
-use algebra::{bls12_381::Fr, Bls12_381};
+use algebra::{
+    bls12_381::{Fr, G1Affine},
+    io::{Read, Write},
+    to_bytes, Bls12_381, CanonicalDeserialize, CanonicalSerialize, SerializationError, ToBytes,
+    UniformRand,
+};
 use ff_fft::{DensePolynomial as Polynomial, EvaluationDomain};
+use num_traits::identities::Zero;
 use poly_commit::kzg10::{Commitment, Powers, VerifierKey};
+use rand_core::RngCore;
+use std::sync::{
+    atomic::{AtomicBool, Ordering},
+    Arc,
+};
+// The number of blinding factors added to a polynomial is one more than the number of points it is opened at.
+// f(X) is opened at `z`, while h_1(X), h_2(X) and Z(X) are also opened at `z * omega`.
+const F_BLINDING_FACTORS: usize = 2;
+const SHIFTED_BLINDING_FACTORS: usize = 3;
+
+/// Configures the prover
+///
+/// A polynomial with `k` blinding factors stays hidden when it is opened at fewer than `k` points outside of the domain.
+/// Protocols which open the polynomials at more points, for example when batching them with other openings,
+/// need more blinding factors. Each extra blinding factor raises the degree of the blinded polynomials by one.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ProverConfig {
+    /// The number of blinding factors added to f(X)
+    pub f_blinding_factors: usize,
+    /// The number of blinding factors added to h_1(X), h_2(X) and Z(X), which are also opened at `z * omega`
+    pub shifted_blinding_factors: usize,
+    /// The number of random points at which the prover checks the plookup identity before it outputs a proof
+    /// A proof which fails the check would be rejected by the verifier, so this only catches bugs in the prover.
+    /// Each point costs one evaluation of every polynomial.
+    pub audit_points: usize,
+    /// The window size, thread count and chunking of the MSMs which compute the commitments
+    pub msm: MsmConfig,
+}
+
+impl Default for ProverConfig {
+    /// The minimum number of blinding factors for the openings made by this protocol, without any audit
+    fn default() -> Self {
+        ProverConfig {
+            f_blinding_factors: F_BLINDING_FACTORS,
+            shifted_blinding_factors: SHIFTED_BLINDING_FACTORS,
+            audit_points: 0,
+            msm: MsmConfig::default(),
+        }
+    }
+}
+
+impl ProverConfig {
+    /// Returns a config for polynomials which are each opened at `extra_openings` more points than this protocol opens them at
+    pub fn with_extra_openings(extra_openings: usize) -> ProverConfig {
+        ProverConfig {
+            f_blinding_factors: F_BLINDING_FACTORS + extra_openings,
+            shifted_blinding_factors: SHIFTED_BLINDING_FACTORS + extra_openings,
+            audit_points: 0,
+            msm: MsmConfig::default(),
+        }
+    }
+}
+
+/// The version of the protocol, which the prover and the verifier absorb before any challenge
+/// It is increased whenever the messages of the prover or the order of the transcript change.
+pub const PROTOCOL_VERSION: u64 = 2;
+
+/// The labels with which the fields of `PublicParameters` are absorbed, in order
+pub const PARAMETER_LABELS: [&str; 5] = [
+    "protocol_version",
+    "domain_size",
+    "f_blinding_factors",
+    "shifted_blinding_factors",
+    "committed_f",
+];
+
+/// The public parameters of a proof, which are absorbed into the transcript before any challenge is drawn
+/// The proof carries the parameters it was made with. The verifier absorbs its own values instead,
+/// and rejects a proof whose parameters differ, so a proof cannot be replayed against another domain,
+/// version of the protocol or configuration of the prover. See `EqualityProof::absorb_parameters`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct PublicParameters {
+    pub version: u64,
+    /// The size of the domain
+    pub n: usize,
+    /// The number of blinding factors the prover added to f(X), or zero if the caller committed to f(X)
+    pub f_blinding_factors: usize,
+    /// The number of blinding factors the prover added to h_1(X), h_2(X) and Z(X)
+    pub shifted_blinding_factors: usize,
+    /// Whether the caller committed to f(X), see `EqualityProof::prove_with_committed_f`
+    pub committed_f: bool,
+}
+
+impl PublicParameters {
+    /// Returns the parameters of a proof over the domain of size `n`, made with `config`
+    pub fn new(n: usize, config: &ProverConfig, committed_f: bool) -> PublicParameters {
+        PublicParameters {
+            version: PROTOCOL_VERSION,
+            n,
+            f_blinding_factors: if committed_f {
+                0
+            } else {
+                config.f_blinding_factors
+            },
+            shifted_blinding_factors: config.shifted_blinding_factors,
+            committed_f,
+        }
+    }
+    /// Absorbs each parameter as a scalar, with the labels in `PARAMETER_LABELS`
+    pub fn absorb<T: TranscriptProtocol + ?Sized>(&self, transcript: &mut T) {
+        let values = [
+            self.version,
+            self.n as u64,
+            self.f_blinding_factors as u64,
+            self.shifted_blinding_factors as u64,
+            self.committed_f as u64,
+        ];
+        for (label, value) in PARAMETER_LABELS.iter().zip(values.iter()) {
+            transcript.append_scalar(label.as_bytes(), &Fr::from(*value));
+        }
+    }
+}
+
+/// The stages of the prover, in the order that they run
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum ProverStage {
+    /// Folding the columns of the witness and the table into one multiset each
+    Aggregation,
+    /// Computing the sorted multisets h_1 and h_2
+    Sorting,
+    /// Interpolating and committing to f(X), h_1(X) and h_2(X)
+    HPolynomials,
+    /// Computing and committing to the accumulator Z(X)
+    Accumulator,
+    /// Computing the quotient polynomial
+    Quotient,
+    /// Committing to the quotient polynomial
+    Commitments,
+    /// Evaluating the polynomials and computing the opening proofs
+    Openings,
+}
+
+impl ProverStage {
+    /// An estimate of the percentage of the prover's work which is done before this stage starts
+    /// Most of the work is in the commitments and the opening proofs, which are multi-scalar multiplications,
+    /// and in the FFTs used to compute the quotient.
+    pub fn percent_complete(&self) -> u8 {
+        match self {
+            ProverStage::Aggregation => 0,
+            ProverStage::Sorting => 2,
+            ProverStage::HPolynomials => 7,
+            ProverStage::Accumulator => 37,
+            ProverStage::Quotient => 50,
+            ProverStage::Commitments => 62,
+            ProverStage::Openings => 80,
+        }
+    }
+}
+
+/// Adapts a progress callback into a prover checkpoint, see `ProveOptions::checkpoint`
+/// The callback is called with each stage as it starts, and the estimated percentage of the proof which is complete.
+/// The proof is complete when the prover returns.
+pub fn report_progress<F: FnMut(ProverStage, u8)>(
+    mut callback: F,
+) -> impl FnMut(ProverStage) -> Result<(), Error> {
+    move |stage| {
+        callback(stage, stage.percent_complete());
+        Ok(())
+    }
+}
+
+/// A flag which aborts a proof at the start of the next prover stage once it is set
+/// Clones share the same flag, so a service can keep one clone and cancel the proof
+/// while the prover checks another.
+#[derive(Clone, Debug, Default)]
+pub struct CancellationToken(Arc<AtomicBool>);
+
+impl CancellationToken {
+    pub fn new() -> CancellationToken {
+        CancellationToken::default()
+    }
+    /// Requests that the proof is aborted
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::Relaxed)
+    }
+    /// Returns true if the proof has been cancelled
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+    /// A prover checkpoint which returns `Error::Cancelled` once the token is cancelled
+    pub fn check(&self, stage: ProverStage) -> Result<(), Error> {
+        if self.is_cancelled() {
+            Err(Error::Cancelled(stage))
+        } else {
+            Ok(())
+        }
+    }
+}
+
 // Evaluations store the evaluations of different polynomial.
 // `t` denotes that the polynomial was evaluated at t(z) for some random evaluation challenge `z`
 // `t_omega` denotes the polynomial was evaluated at t(z * omega) where omega is the group generator
 // In the FFT context, the normal terminology is that t(z*omega) means to evaluate a polynomial at the next root of unity from `z`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
 pub struct Evaluations {
     pub f: Fr,
     pub t: Fr,
@@ -22,6 +225,7 @@ pub struct Evaluations {
     pub z_omega: Fr,
 }
 // Commitments of different polynomials
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
 pub struct Commitments {
     pub f: Commitment<Bls12_381>,
     pub q: Commitment<Bls12_381>,
@@ -43,6 +247,7 @@ pub struct Commitments {
 // q_eval which is the quotient evaluation is usually created from the prover messages
 //
 // Lastly, the Witness commitments can also be batched with the PLONK opening Proof.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
 pub struct EqualityProof {
     pub aggregate_witness_comm: Commitment<Bls12_381>,
     pub shifted_aggregate_witness_comm: Commitment<Bls12_381>,
@@ -50,49 +255,390 @@ pub struct EqualityProof {
     pub evaluations: Evaluations,
 
     pub commitments: Commitments,
+
+    /// The public parameters that the proof was made with
+    pub parameters: PublicParameters,
 }
 
 impl EqualityProof {
-    pub fn prove(
+    /// Proves that `f` is contained in `t`
+    ///
+    /// The transcript can be any implementation of `TranscriptProtocol`, including one whose
+    /// challenges are derived by a surrounding protocol. The prover and the verifier absorb values
+    /// and draw challenges in the following order:
+    /// 1. Absorb the public parameters as the scalars labelled in `PARAMETER_LABELS`, see `PublicParameters`
+    /// 2. Absorb the commitment `f`, so that the witness is fixed before any challenge of the equality proof
+    /// 3. Absorb the commitments `h_1_poly` and `h_2_poly`
+    /// 4. Draw the challenges `beta` and then `gamma`
+    /// 5. Absorb the commitment `accumulator_poly`
+    /// 6. Draw the challenge `separation_challenge`, which separates the checks in the quotient
+    /// 7. Absorb the commitment `quotient_poly`
+    /// 8. Draw the challenge `evaluation_challenge`, then absorb it as a scalar
+    /// 9. Absorb the scalars `f_eval`, `t_eval`, `h_1_eval`, `h_2_eval`, `z_eval`, `q_eval`,
+    ///    `t_omega_eval`, `h_1_omega_eval`, `h_2_omega_eval` and `z_omega_eval`
+    /// 10. Draw the challenge `witness_aggregation`
+    ///
+    /// When the proof is part of another protocol, that protocol absorbs the public parameters
+    /// before its own first challenge, and the equality proof starts at step 2.
+    pub fn prove<T: TranscriptProtocol + ?Sized>(
         f: MultiSet,
         t: MultiSet,
         proving_key: &Powers<Bls12_381>,
-        transcript: &mut dyn TranscriptProtocol,
+        transcript: &mut T,
     ) -> EqualityProof {
-        let domain: EvaluationDomain<Fr> = EvaluationDomain::new(t.len()).unwrap();
-        // Convert witness and table to polynomials
-        let f_poly = f.to_polynomial(&domain);
-        let f_commit = kzg10::commit(proving_key, &f_poly);
-        let t_poly = t.to_polynomial(&domain);
-        // Compute h_1 and h_2
-        let (h_1, h_2) = multiset_equality::compute_h1_h2(&f, &t);
-        // Convert h_1 and h_2 to polynomials
-        let h_1_poly = h_1.to_polynomial(&domain);
-        let h_2_poly = h_2.to_polynomial(&domain);
-        // Commit to h_1(X) and h_2(X)
-        let h_1_commit = kzg10::commit(proving_key, &h_1_poly);
-        let h_2_commit = kzg10::commit(proving_key, &h_2_poly);
+        let quotient_key = QuotientKey::new(t.len());
+        EqualityProof::prove_with_key(f, t, proving_key, &quotient_key, transcript)
+    }
+    /// Proves that `f` is contained in `t`, reusing a quotient key computed for the size of `t`
+    /// The transcript is the same as in `prove`.
+    pub fn prove_with_key<T: TranscriptProtocol + ?Sized>(
+        f: MultiSet,
+        t: MultiSet,
+        proving_key: &Powers<Bls12_381>,
+        quotient_key: &QuotientKey,
+        transcript: &mut T,
+    ) -> EqualityProof {
+        EqualityProof::prove_with_config(
+            f,
+            t,
+            proving_key,
+            quotient_key,
+            &ProverConfig::default(),
+            transcript,
+        )
+        .unwrap_or_else(|err| panic!("prover failed: {}", err))
+    }
+    /// Proves that `f` is contained in `t`, blinding the polynomials as set in `config`
+    /// The proving key must support polynomials of degree `n + config.shifted_blinding_factors`.
+    /// The transcript is the same as in `prove`.
+    /// Returns `Error::SrsTooSmall` if the proving key is too small for `config`, an error if the MSM settings
+    /// in `config` are invalid, or `Error::AuditFailed` if the proof fails the prover's own audit.
+    pub fn prove_with_config<T: TranscriptProtocol + ?Sized>(
+        f: MultiSet,
+        t: MultiSet,
+        proving_key: &Powers<Bls12_381>,
+        quotient_key: &QuotientKey,
+        config: &ProverConfig,
+        transcript: &mut T,
+    ) -> Result<EqualityProof, Error> {
+        EqualityProof::prove_with_checkpoint(
+            f,
+            t,
+            proving_key,
+            quotient_key,
+            config,
+            &mut |_| Ok(()),
+            transcript,
+        )
+    }
+    /// Proves that `f` is contained in `t`, calling `checkpoint` before each stage of the prover
+    /// If `checkpoint` returns an error, the prover stops and returns that error.
+    /// This lets a long-running proof be cancelled, see `CancellationToken`.
+    /// The transcript is the same as in `prove`, up to the point where the prover stops.
+    pub fn prove_with_checkpoint<T: TranscriptProtocol + ?Sized>(
+        f: MultiSet,
+        t: MultiSet,
+        proving_key: &Powers<Bls12_381>,
+        quotient_key: &QuotientKey,
+        config: &ProverConfig,
+        checkpoint: &mut dyn FnMut(ProverStage) -> Result<(), Error>,
+        transcript: &mut T,
+    ) -> Result<EqualityProof, Error> {
+        PublicParameters::new(quotient_key.domain().size(), config, false).absorb(transcript);
+        EqualityProof::prove_inner(
+            f,
+            None,
+            &Interpolated::new(t, *quotient_key.domain()),
+            proving_key,
+            quotient_key,
+            config,
+            checkpoint,
+            &mut ProverState::new(),
+            &mut |_| Ok(()),
+            &mut ScratchBuffers::new(),
+            transcript,
+        )
+    }
+    /// Proves that `f` is contained in `t`, using the polynomial that `t` holds or interpolating it once
+    /// Consecutive proofs against the same `t` reuse t(X) instead of interpolating it for each proof.
+    /// `t` must be interpolated over the domain of `quotient_key`. The transcript is the same as in `prove`.
+    pub fn prove_interpolated<T: TranscriptProtocol + ?Sized>(
+        f: MultiSet,
+        t: &Interpolated,
+        proving_key: &Powers<Bls12_381>,
+        quotient_key: &QuotientKey,
+        config: &ProverConfig,
+        transcript: &mut T,
+    ) -> Result<EqualityProof, Error> {
+        PublicParameters::new(quotient_key.domain().size(), config, false).absorb(transcript);
+        EqualityProof::prove_inner(
+            f,
+            None,
+            t,
+            proving_key,
+            quotient_key,
+            config,
+            &mut |_| Ok(()),
+            &mut ProverState::new(),
+            &mut |_| Ok(()),
+            &mut ScratchBuffers::new(),
+            transcript,
+        )
+    }
+    /// Proves that `f` is contained in `t`, skipping the stages which have already completed in `state`
+    /// `save` is called with the state each time a stage completes, so that it can be written to disk with
+    /// `ProverState::save`. If the prover stops, a new prover given the last saved state, the same inputs and a
+    /// transcript holding the same values resumes from the stage after the last one that completed.
+    /// Returns `Error::StateMismatch` if the state was saved by a proof for a different witness or table.
+    pub fn prove_resumable<T: TranscriptProtocol + ?Sized>(
+        f: MultiSet,
+        t: MultiSet,
+        proving_key: &Powers<Bls12_381>,
+        quotient_key: &QuotientKey,
+        config: &ProverConfig,
+        state: &mut ProverState,
+        save: &mut dyn FnMut(&ProverState) -> Result<(), Error>,
+        transcript: &mut T,
+    ) -> Result<EqualityProof, Error> {
+        PublicParameters::new(quotient_key.domain().size(), config, false).absorb(transcript);
+        EqualityProof::prove_inner(
+            f,
+            None,
+            &Interpolated::new(t, *quotient_key.domain()),
+            proving_key,
+            quotient_key,
+            config,
+            &mut |_| Ok(()),
+            state,
+            save,
+            &mut ScratchBuffers::new(),
+            transcript,
+        )
+    }
+    /// Proves that the witness which the caller has already committed to in `f_commit` is contained in `t`,
+    /// for example when f(X) is also a wire polynomial of a PLONK proof
+    /// `f_poly` is the polynomial that `f_commit` commits to, which the caller has blinded,
+    /// and `f` holds its evaluations over the domain of size `t.len()`.
+    /// The proof carries `f_commit`, which the verifier checks with `verify_with_committed_f`.
+    /// The commitment is absorbed as `f` before any challenge of the equality proof, as the prover's own would be.
+    pub fn prove_with_committed_f<T: TranscriptProtocol + ?Sized>(
+        f: MultiSet,
+        f_poly: Polynomial<Fr>,
+        f_commit: Commitment<Bls12_381>,
+        t: MultiSet,
+        proving_key: &Powers<Bls12_381>,
+        quotient_key: &QuotientKey,
+        config: &ProverConfig,
+        transcript: &mut T,
+    ) -> Result<EqualityProof, Error> {
+        PublicParameters::new(quotient_key.domain().size(), config, true).absorb(transcript);
+        EqualityProof::prove_committed(
+            f,
+            f_poly,
+            f_commit,
+            t,
+            proving_key,
+            quotient_key,
+            config,
+            transcript,
+        )
+    }
+    // Proves as `prove_with_committed_f` does, for a protocol which has already absorbed the public parameters
+    pub(crate) fn prove_committed<T: TranscriptProtocol + ?Sized>(
+        f: MultiSet,
+        f_poly: Polynomial<Fr>,
+        f_commit: Commitment<Bls12_381>,
+        t: MultiSet,
+        proving_key: &Powers<Bls12_381>,
+        quotient_key: &QuotientKey,
+        config: &ProverConfig,
+        transcript: &mut T,
+    ) -> Result<EqualityProof, Error> {
+        let domain = quotient_key.domain();
+        let mut expected = f.0.clone();
+        expected.resize(domain.size(), Fr::zero());
+        if evaluate_over_domain(&f_poly, domain) != expected {
+            return Err(Error::WitnessMismatch);
+        }
+
+        EqualityProof::prove_inner(
+            f,
+            Some((f_poly, f_commit)),
+            &Interpolated::new(t, *quotient_key.domain()),
+            proving_key,
+            quotient_key,
+            config,
+            &mut |_| Ok(()),
+            &mut ProverState::new(),
+            &mut |_| Ok(()),
+            &mut ScratchBuffers::new(),
+            transcript,
+        )
+    }
+    pub(crate) fn prove_inner<K: CommitKey + ?Sized, T: TranscriptProtocol + ?Sized>(
+        f: MultiSet,
+        committed_f: Option<(Polynomial<Fr>, Commitment<Bls12_381>)>,
+        t: &Interpolated,
+        proving_key: &K,
+        quotient_key: &QuotientKey,
+        config: &ProverConfig,
+        checkpoint: &mut dyn FnMut(ProverStage) -> Result<(), Error>,
+        state: &mut ProverState,
+        save: &mut dyn FnMut(&ProverState) -> Result<(), Error>,
+        scratch: &mut ScratchBuffers,
+        transcript: &mut T,
+    ) -> Result<EqualityProof, Error> {
+        // Fewer blinding factors would reveal information about the witness
+        assert!(config.f_blinding_factors >= F_BLINDING_FACTORS);
+        assert!(config.shifted_blinding_factors >= SHIFTED_BLINDING_FACTORS);
+
+        let domain = *quotient_key.domain();
+        let parameters = PublicParameters::new(domain.size(), config, committed_f.is_some());
+        assert_eq!(t.domain().size(), domain.size());
+        // Fail before any work is done, rather than when committing to a polynomial the SRS is too small for
+        kzg10::check_capacity_with_config(proving_key, domain.size(), config)?;
+        // Build the thread pool of the MSMs once, rather than for every commitment
+        let msm = MsmPool::new(&config.msm)?;
+        state.bind(&f, t.set())?;
+        // Derive the blinding randomness from the transcript, the witness and the thread rng
+        let mut rng = transcript.build_prover_rng(&to_bytes![f.0].unwrap());
+        if state.h_polynomials.is_none() {
+            // Compute h_1 and h_2
+            checkpoint(ProverStage::Sorting)?;
+            let (h_1, h_2) = multiset_equality::compute_h1_h2(&f, t.set());
+            // Convert witness and table to polynomials
+            checkpoint(ProverStage::HPolynomials)?;
+            let (f_poly, committed_f_commit) = match committed_f {
+                Some((f_poly, f_commit)) => (f_poly, Some(f_commit)),
+                None => {
+                    let f_poly = blind(
+                        f.to_polynomial(&domain),
+                        config.f_blinding_factors,
+                        &domain,
+                        &mut rng,
+                    );
+                    (f_poly, None)
+                }
+            };
+            // Convert h_1 and h_2 to polynomials
+            let h_1_poly = blind(
+                h_1.to_polynomial(&domain),
+                config.shifted_blinding_factors,
+                &domain,
+                &mut rng,
+            );
+            let h_2_poly = blind(
+                h_2.to_polynomial(&domain),
+                config.shifted_blinding_factors,
+                &domain,
+                &mut rng,
+            );
+            // Commit to h_1(X), h_2(X) and, unless the caller already has, f(X)
+            let mut to_commit = vec![&h_1_poly, &h_2_poly];
+            if committed_f_commit.is_none() {
+                to_commit.push(&f_poly);
+            }
+            let commitments = msm.commit_many(proving_key, to_commit)?;
+            let (h_1_commit, h_2_commit) = (commitments[0], commitments[1]);
+            let f_commit = committed_f_commit.unwrap_or_else(|| commitments[2]);
+
+            state.h_polynomials = Some(HPolynomials {
+                h_1,
+                h_2,
+                f_poly,
+                h_1_poly,
+                h_2_poly,
+                f_commit,
+                h_1_commit,
+                h_2_commit,
+            });
+            save(state)?;
+        }
+        let t_poly = t.polynomial();
+        let t_poly: &Polynomial<Fr> = &t_poly;
         // Add commitments to transcript
-        transcript.append_commitment(b"h_1_poly", &h_1_commit);
-        transcript.append_commitment(b"h_2_poly", &h_2_commit);
+        let h = state.h_polynomials.as_ref().unwrap();
+        transcript.append_commitment(b"f", &h.f_commit);
+        transcript.append_commitment(b"h_1_poly", &h.h_1_commit);
+        transcript.append_commitment(b"h_2_poly", &h.h_2_commit);
         let beta = transcript.challenge_scalar(b"beta");
         let gamma = transcript.challenge_scalar(b"gamma");
-        // Compute Z(X)
-        let z_evaluations =
-            multiset_equality::compute_accumulator_values(&f, &t, &h_1, &h_2, beta, gamma);
-        let z_poly = Polynomial::from_coefficients_vec(domain.ifft(&z_evaluations));
-        // Commit to Z(X)
-        let z_commit = kzg10::commit(proving_key, &z_poly);
-        transcript.append_commitment(b"accumulator_poly", &z_commit);
+        // Compute and commit to Z(X)
+        if state.accumulator.is_none() {
+            checkpoint(ProverStage::Accumulator)?;
+            let z_evaluations = multiset_equality::compute_accumulator_values(
+                &f,
+                t.set(),
+                &h.h_1,
+                &h.h_2,
+                beta,
+                gamma,
+            );
+            let z_poly = blind(
+                Polynomial::from_coefficients_vec(domain.ifft(&z_evaluations)),
+                config.shifted_blinding_factors,
+                &domain,
+                &mut rng,
+            );
+            let z_commit = msm.commit(proving_key, &z_poly)?;
+            state.accumulator = Some((z_poly, z_commit));
+            save(state)?;
+        }
+        let (z_poly, z_commit) = state.accumulator.as_ref().unwrap();
+        transcript.append_commitment(b"accumulator_poly", z_commit);
+        // The checks in the quotient are separated by a challenge drawn after Z(X) is fixed
+        let separation = transcript.challenge_scalar(b"separation_challenge");
         // Compute quotient polynomial
-        let (quotient_poly, _) = quotient_poly::compute(
-            &domain, &z_poly, &f_poly, &t_poly, &h_1_poly, &h_2_poly, beta, gamma,
-        );
-        // Commit to quotient polynomial
-        let q_commit = kzg10::commit(proving_key, &quotient_poly);
+        if state.quotient_poly.is_none() {
+            checkpoint(ProverStage::Quotient)?;
+            let quotient_poly = quotient_poly::compute_with_scratch(
+                quotient_key,
+                z_poly,
+                &h.f_poly,
+                t_poly,
+                &h.h_1_poly,
+                &h.h_2_poly,
+                beta,
+                gamma,
+                separation,
+                scratch,
+            );
+            // Check that the quotient is correct at random points, so that a bug in the prover is caught before the proof is output
+            for _ in 0..config.audit_points {
+                let point = Fr::rand(&mut rand::thread_rng());
+                let holds = quotient_poly::check_identity(
+                    &domain,
+                    z_poly,
+                    &h.f_poly,
+                    t_poly,
+                    &h.h_1_poly,
+                    &h.h_2_poly,
+                    &quotient_poly,
+                    beta,
+                    gamma,
+                    separation,
+                    point,
+                );
+                if !holds {
+                    return Err(Error::AuditFailed);
+                }
+            }
+            state.quotient_poly = Some(quotient_poly);
+            save(state)?;
+        }
+        let quotient_poly = state.quotient_poly.as_ref().unwrap();
+        // Commit to the quotient polynomial
+        if state.quotient_commit.is_none() {
+            checkpoint(ProverStage::Commitments)?;
+            state.quotient_commit = Some(msm.commit(proving_key, quotient_poly)?);
+            save(state)?;
+        }
+        let q_commit = state.quotient_commit.unwrap();
         transcript.append_commitment(b"quotient_poly", &q_commit);
+        let (f_poly, h_1_poly, h_2_poly) = (&h.f_poly, &h.h_1_poly, &h.h_2_poly);
         // Compute the Witness that f was a subset of t
         //
+        checkpoint(ProverStage::Openings)?;
         let evaluation_challenge = transcript.challenge_scalar(b"evaluation_challenge");
         transcript.append_scalar(b"evaluation_challenge", &evaluation_challenge);
         let evaluation_omega = evaluation_challenge * domain.group_gen;
@@ -120,27 +666,24 @@ impl EqualityProof {
         transcript.append_scalar(b"z_omega_eval", &z_omega_eval);
         let aggregation_challenge = transcript.challenge_scalar(b"witness_aggregation");
         // Compute opening proof for f(X) evaluated at `z`
-        let agg_witness = kzg10::compute_aggregate_witness(
-            vec![
-                &f_poly,
-                &t_poly,
-                &h_1_poly,
-                &h_2_poly,
-                &z_poly,
-                &quotient_poly,
-            ],
-            evaluation_challenge,
-            aggregation_challenge,
-        );
-        let agg_witness_comm = kzg10::commit(proving_key, &agg_witness);
+        let agg_witness_comm = msm.commit(
+            proving_key,
+            &kzg10::compute_aggregate_witness(
+                vec![f_poly, t_poly, h_1_poly, h_2_poly, z_poly, quotient_poly],
+                evaluation_challenge,
+                aggregation_challenge,
+            ),
+        )?;
         // Compute opening proofs for f(X) evaluated at `z * omega`
-        let shifted_agg_witness = kzg10::compute_aggregate_witness(
-            vec![&t_poly, &h_1_poly, &h_2_poly, &z_poly],
-            evaluation_omega,
-            aggregation_challenge,
-        );
-        let shifted_agg_witness_comm = kzg10::commit(proving_key, &shifted_agg_witness);
-        EqualityProof {
+        let shifted_agg_witness_comm = msm.commit(
+            proving_key,
+            &kzg10::compute_aggregate_witness(
+                vec![t_poly, h_1_poly, h_2_poly, z_poly],
+                evaluation_omega,
+                aggregation_challenge,
+            ),
+        )?;
+        Ok(EqualityProof {
             evaluations: Evaluations {
                 f: f_eval,
                 t: t_eval,
@@ -153,39 +696,95 @@ impl EqualityProof {
                 z_omega: z_omega_eval,
             },
             commitments: Commitments {
-                f: f_commit,
+                f: h.f_commit,
                 q: q_commit,
-                h_1: h_1_commit,
-                h_2: h_2_commit,
-                z: z_commit,
+                h_1: h.h_1_commit,
+                h_2: h.h_2_commit,
+                z: *z_commit,
             },
             aggregate_witness_comm: agg_witness_comm,
             shifted_aggregate_witness_comm: shifted_agg_witness_comm,
-        }
+            parameters,
+        })
     }
 
-    pub fn verify(
+    /// Verifies the proof against a commitment to `t`, for a proof made with the default `ProverConfig`
+    /// The transcript must absorb the same values in the same order as the prover, see `prove`
+    pub fn verify<T: TranscriptProtocol + ?Sized>(
         &self,
         n: usize,
         verification_key: &VerifierKey<Bls12_381>,
         commitment_to_t: Commitment<Bls12_381>,
-        transcript: &mut dyn TranscriptProtocol,
+        transcript: &mut T,
     ) -> bool {
+        self.verify_with_config(
+            n,
+            verification_key,
+            commitment_to_t,
+            &ProverConfig::default(),
+            transcript,
+        )
+    }
+    /// Verifies a proof made with `prove_with_config`, where `config` is the configuration the prover used
+    /// A proof made with another number of blinding factors is rejected.
+    pub fn verify_with_config<T: TranscriptProtocol + ?Sized>(
+        &self,
+        n: usize,
+        verification_key: &VerifierKey<Bls12_381>,
+        commitment_to_t: Commitment<Bls12_381>,
+        config: &ProverConfig,
+        transcript: &mut T,
+    ) -> bool {
+        self.absorb_parameters(&PublicParameters::new(n, config, false), transcript)
+            && self
+                .openings(n, commitment_to_t, transcript)
+                .check(verification_key)
+    }
+    /// Checks that the proof was made with the public parameters that the verifier expects
+    /// The verifier computes `expected` itself, see `PublicParameters::new`, and absorbs its own values as the prover did.
+    /// Returns false without absorbing anything if the parameters of the proof differ from them.
+    pub fn absorb_parameters<T: TranscriptProtocol + ?Sized>(
+        &self,
+        expected: &PublicParameters,
+        transcript: &mut T,
+    ) -> bool {
+        if self.parameters != *expected {
+            return false;
+        }
+        expected.absorb(transcript);
+        true
+    }
+    /// Absorbs the proof into the transcript as `verify` does, and returns the openings that the proof must satisfy
+    /// The public parameters are not absorbed, see `absorb_parameters`.
+    /// The openings are not checked, so that they can be checked together with the openings of other proofs.
+    pub fn openings<T: TranscriptProtocol + ?Sized>(
+        &self,
+        n: usize,
+        commitment_to_t: Commitment<Bls12_381>,
+        transcript: &mut T,
+    ) -> OpeningBatch {
         let domain: EvaluationDomain<Fr> = EvaluationDomain::new(n).unwrap();
 
+        transcript.append_commitment(b"f", &self.commitments.f);
         transcript.append_commitment(b"h_1_poly", &self.commitments.h_1);
         transcript.append_commitment(b"h_2_poly", &self.commitments.h_2);
         let beta = transcript.challenge_scalar(b"beta");
         let gamma = transcript.challenge_scalar(b"gamma");
         transcript.append_commitment(b"accumulator_poly", &self.commitments.z);
+        let separation = transcript.challenge_scalar(b"separation_challenge");
         transcript.append_commitment(b"quotient_poly", &self.commitments.q);
         let evaluation_challenge = transcript.challenge_scalar(b"evaluation_challenge");
         transcript.append_scalar(b"evaluation_challenge", &evaluation_challenge);
         let evaluation_omega = evaluation_challenge * domain.group_gen;
 
         // Compute quotient evaluation (Q(z)) from the provers messages
-        let q_eval =
-            self.compute_quotient_evaluation(&beta, &gamma, &evaluation_challenge, &domain);
+        let q_eval = self.compute_quotient_evaluation(
+            &beta,
+            &gamma,
+            &separation,
+            &evaluation_challenge,
+            &domain,
+        );
 
         transcript.append_scalar(b"f_eval", &self.evaluations.f);
         transcript.append_scalar(b"t_eval", &self.evaluations.t);
@@ -201,7 +800,7 @@ impl EqualityProof {
         let aggregation_challenge = transcript.challenge_scalar(b"witness_aggregation");
 
         // Create aggregate opening proof for all polynomials evaluated at the evaluation challenge `z`
-        let agg_commitment = kzg10::aggregate_commitments(
+        let agg_commitment = kzg10::aggregate_commitments_projective(
             vec![
                 &self.commitments.f,
                 &commitment_to_t,
@@ -225,7 +824,7 @@ impl EqualityProof {
         );
 
         // Create aggregate opening proof for all polynomials evaluated at the shifted evaluation challenge `z * omega`
-        let shifted_agg_commitment = kzg10::aggregate_commitments(
+        let shifted_agg_commitment = kzg10::aggregate_commitments_projective(
             vec![
                 &commitment_to_t,
                 &self.commitments.h_1,
@@ -244,25 +843,47 @@ impl EqualityProof {
             aggregation_challenge,
         );
 
-        // Batch Verify both opening proofs
-        let ok = kzg10::batch_verify(
-            &verification_key,
-            vec![agg_commitment, shifted_agg_commitment],
-            vec![
-                self.aggregate_witness_comm,
-                self.shifted_aggregate_witness_comm,
-            ],
-            vec![evaluation_challenge, evaluation_omega],
-            vec![agg_value, shifted_agg_value],
-        );
+        // Convert both aggregated commitments to affine form using one inversion
+        let agg_commitments = kzg10::batch_normalize(&[agg_commitment, shifted_agg_commitment]);
 
-        ok
+        let mut openings = OpeningBatch::new();
+        openings.push(
+            agg_commitments[0],
+            self.aggregate_witness_comm,
+            evaluation_challenge,
+            agg_value,
+        );
+        openings.push(
+            agg_commitments[1],
+            self.shifted_aggregate_witness_comm,
+            evaluation_omega,
+            shifted_agg_value,
+        );
+        openings
+    }
+    /// Verifies a proof made with `prove_with_committed_f` against the caller's commitment to f(X)
+    /// `config` is the configuration the prover used.
+    pub fn verify_with_committed_f<T: TranscriptProtocol + ?Sized>(
+        &self,
+        n: usize,
+        verification_key: &VerifierKey<Bls12_381>,
+        commitment_to_f: Commitment<Bls12_381>,
+        commitment_to_t: Commitment<Bls12_381>,
+        config: &ProverConfig,
+        transcript: &mut T,
+    ) -> bool {
+        self.commitments.f == commitment_to_f
+            && self.absorb_parameters(&PublicParameters::new(n, config, true), transcript)
+            && self
+                .openings(n, commitment_to_t, transcript)
+                .check(verification_key)
     }
     /// Computes the quotient evaluation from the prover messages
     fn compute_quotient_evaluation(
         &self,
         beta: &Fr,
         gamma: &Fr,
+        separation: &Fr,
         evaluation_challenge: &Fr,
         domain: &EvaluationDomain<Fr>,
     ) -> Fr {
@@ -308,6 +929,214 @@ impl EqualityProof {
         // L_{n+1}(X)[Z(X) - 1]
         let e = (self.evaluations.z - Fr::from(1u8)) * ln_plus_1_z;
 
-        (a + b - c + d + e) / v_h
+        // The checks are separated by powers of the challenge, in the order of `quotient_poly::CONSTRAINTS`
+        let separation_sq = *separation * separation;
+        let separation_cube = separation_sq * separation;
+        (a + (b - c) * separation + d * separation_sq + e * separation_cube) / v_h
+    }
+}
+
+// Returns the evaluations of `poly` over `domain`, for a polynomial of any degree
+// X^n is one on the domain, so the coefficients are folded modulo X^n - 1 before the FFT
+fn evaluate_over_domain(poly: &Polynomial<Fr>, domain: &EvaluationDomain<Fr>) -> Vec<Fr> {
+    let mut folded = vec![Fr::zero(); domain.size()];
+    for (i, coeff) in poly.coeffs.iter().enumerate() {
+        folded[i % domain.size()] += coeff;
+    }
+    domain.fft(&folded)
+}
+
+// Adds a random multiple of the vanishing polynomial to `poly`
+// This does not change the evaluations of `poly` over the domain, so the prover's identities still hold,
+// but it hides `poly` when it is opened at less than `num_blinding_factors` points outside of the domain
+pub(crate) fn blind<R: RngCore>(
+    poly: Polynomial<Fr>,
+    num_blinding_factors: usize,
+    domain: &EvaluationDomain<Fr>,
+    rng: &mut R,
+) -> Polynomial<Fr> {
+    let blinding_poly = Polynomial::rand(num_blinding_factors - 1, rng);
+    &poly + &blinding_poly.mul_by_vanishing_poly(*domain)
+}
+
+// The proof is serialised as: the aggregate witness commitments, followed by the evaluations,
+// followed by the commitments, followed by the public parameters, each in the order that their fields are declared.
+// G1 elements are 48 bytes when compressed and 96 bytes when uncompressed.
+// The public parameters are four u64s and a byte which is 1 if the caller committed to f(X) and 0 otherwise.
+impl CanonicalSerialize for Evaluations {
+    fn serialize<W: Write>(&self, writer: &mut W) -> Result<(), SerializationError> {
+        for eval in self.as_vec() {
+            eval.serialize(writer)?;
+        }
+        Ok(())
+    }
+    fn serialized_size(&self) -> usize {
+        self.as_vec().iter().map(|eval| eval.serialized_size()).sum()
+    }
+}
+
+impl CanonicalDeserialize for Evaluations {
+    fn deserialize<R: Read>(reader: &mut R) -> Result<Self, SerializationError> {
+        Ok(Evaluations {
+            f: Fr::deserialize(reader)?,
+            t: Fr::deserialize(reader)?,
+            t_omega: Fr::deserialize(reader)?,
+            h_1: Fr::deserialize(reader)?,
+            h_1_omega: Fr::deserialize(reader)?,
+            h_2: Fr::deserialize(reader)?,
+            h_2_omega: Fr::deserialize(reader)?,
+            z: Fr::deserialize(reader)?,
+            z_omega: Fr::deserialize(reader)?,
+        })
+    }
+}
+
+impl Evaluations {
+    fn as_vec(&self) -> Vec<&Fr> {
+        vec![
+            &self.f,
+            &self.t,
+            &self.t_omega,
+            &self.h_1,
+            &self.h_1_omega,
+            &self.h_2,
+            &self.h_2_omega,
+            &self.z,
+            &self.z_omega,
+        ]
+    }
+}
+
+impl CanonicalSerialize for PublicParameters {
+    fn serialize<W: Write>(&self, writer: &mut W) -> Result<(), SerializationError> {
+        self.version.serialize(writer)?;
+        (self.n as u64).serialize(writer)?;
+        (self.f_blinding_factors as u64).serialize(writer)?;
+        (self.shifted_blinding_factors as u64).serialize(writer)?;
+        (self.committed_f as u8).serialize(writer)
+    }
+    fn serialized_size(&self) -> usize {
+        4 * 8 + 1
+    }
+}
+
+impl CanonicalDeserialize for PublicParameters {
+    fn deserialize<R: Read>(reader: &mut R) -> Result<Self, SerializationError> {
+        Ok(PublicParameters {
+            version: u64::deserialize(reader)?,
+            n: u64::deserialize(reader)? as usize,
+            f_blinding_factors: u64::deserialize(reader)? as usize,
+            shifted_blinding_factors: u64::deserialize(reader)? as usize,
+            committed_f: match u8::deserialize(reader)? {
+                0 => false,
+                1 => true,
+                _ => return Err(SerializationError::InvalidData),
+            },
+        })
+    }
+}
+
+impl CanonicalSerialize for Commitments {
+    fn serialize<W: Write>(&self, writer: &mut W) -> Result<(), SerializationError> {
+        for comm in self.as_vec() {
+            comm.0.serialize(writer)?;
+        }
+        Ok(())
+    }
+    fn serialized_size(&self) -> usize {
+        self.as_vec().iter().map(|comm| comm.0.serialized_size()).sum()
+    }
+    fn serialize_uncompressed<W: Write>(&self, writer: &mut W) -> Result<(), SerializationError> {
+        for comm in self.as_vec() {
+            comm.0.serialize_uncompressed(writer)?;
+        }
+        Ok(())
+    }
+    fn uncompressed_size(&self) -> usize {
+        self.as_vec().iter().map(|comm| comm.0.uncompressed_size()).sum()
+    }
+}
+
+impl CanonicalDeserialize for Commitments {
+    fn deserialize<R: Read>(reader: &mut R) -> Result<Self, SerializationError> {
+        Commitments::read(reader, G1Affine::deserialize)
+    }
+    fn deserialize_uncompressed<R: Read>(reader: &mut R) -> Result<Self, SerializationError> {
+        Commitments::read(reader, G1Affine::deserialize_uncompressed)
+    }
+}
+
+impl Commitments {
+    fn as_vec(&self) -> Vec<&Commitment<Bls12_381>> {
+        vec![&self.f, &self.q, &self.h_1, &self.h_2, &self.z]
+    }
+    // Reads the commitments in the same order that `as_vec` returns them
+    fn read<R: Read>(
+        reader: &mut R,
+        read_point: fn(&mut R) -> Result<G1Affine, SerializationError>,
+    ) -> Result<Self, SerializationError> {
+        Ok(Commitments {
+            f: Commitment(read_point(reader)?),
+            q: Commitment(read_point(reader)?),
+            h_1: Commitment(read_point(reader)?),
+            h_2: Commitment(read_point(reader)?),
+            z: Commitment(read_point(reader)?),
+        })
+    }
+}
+
+impl CanonicalSerialize for EqualityProof {
+    fn serialize<W: Write>(&self, writer: &mut W) -> Result<(), SerializationError> {
+        self.aggregate_witness_comm.0.serialize(writer)?;
+        self.shifted_aggregate_witness_comm.0.serialize(writer)?;
+        self.evaluations.serialize(writer)?;
+        self.commitments.serialize(writer)?;
+        self.parameters.serialize(writer)
+    }
+    fn serialized_size(&self) -> usize {
+        self.aggregate_witness_comm.0.serialized_size()
+            + self.shifted_aggregate_witness_comm.0.serialized_size()
+            + self.evaluations.serialized_size()
+            + self.commitments.serialized_size()
+            + self.parameters.serialized_size()
+    }
+    fn serialize_uncompressed<W: Write>(&self, writer: &mut W) -> Result<(), SerializationError> {
+        self.aggregate_witness_comm.0.serialize_uncompressed(writer)?;
+        self.shifted_aggregate_witness_comm
+            .0
+            .serialize_uncompressed(writer)?;
+        self.evaluations.serialize_uncompressed(writer)?;
+        self.commitments.serialize_uncompressed(writer)?;
+        self.parameters.serialize_uncompressed(writer)
+    }
+    fn uncompressed_size(&self) -> usize {
+        self.aggregate_witness_comm.0.uncompressed_size()
+            + self.shifted_aggregate_witness_comm.0.uncompressed_size()
+            + self.evaluations.uncompressed_size()
+            + self.commitments.uncompressed_size()
+            + self.parameters.uncompressed_size()
+    }
+}
+
+impl CanonicalDeserialize for EqualityProof {
+    fn deserialize<R: Read>(reader: &mut R) -> Result<Self, SerializationError> {
+        Ok(EqualityProof {
+            aggregate_witness_comm: Commitment(G1Affine::deserialize(reader)?),
+            shifted_aggregate_witness_comm: Commitment(G1Affine::deserialize(reader)?),
+            evaluations: Evaluations::deserialize(reader)?,
+            commitments: Commitments::deserialize(reader)?,
+            parameters: PublicParameters::deserialize(reader)?,
+        })
+    }
+    fn deserialize_uncompressed<R: Read>(reader: &mut R) -> Result<Self, SerializationError> {
+        Ok(EqualityProof {
+            aggregate_witness_comm: Commitment(G1Affine::deserialize_uncompressed(reader)?),
+            shifted_aggregate_witness_comm: Commitment(G1Affine::deserialize_uncompressed(
+                reader,
+            )?),
+            evaluations: Evaluations::deserialize_uncompressed(reader)?,
+            commitments: Commitments::deserialize_uncompressed(reader)?,
+            parameters: PublicParameters::deserialize_uncompressed(reader)?,
+        })
     }
 }
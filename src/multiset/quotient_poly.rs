@@ -1,4 +1,6 @@
-use algebra::bls12_381::Fr;
+use crate::multiset::ScratchBuffers;
+use algebra::{bls12_381::Fr, Field, PrimeField};
+use algebra_core::fields::batch_inversion;
 use ff_fft::{DensePolynomial as Polynomial, EvaluationDomain};
 use num_traits::identities::{One, Zero};
 // The quotient polynomial will encode the four checks for the multiset equality argument
@@ -12,6 +14,10 @@ use num_traits::identities::{One, Zero};
 // We can denote check 3 as an interval check because it checks whether h_1 and h_2 combined form 's' without any gaps. See paper for more details on 's'
 // We can denote check 2 as the term check
 //
+// As in PLONK, the checks are not simply added together: the i'th check in `CONSTRAINTS` is multiplied by
+// the i'th power of the challenge `separation`, which is drawn after Z(X) is committed to.
+// Otherwise a prover could choose the values of Z(X) so that a failing check cancels out another.
+//
 // Notice that the term check equation will determine the degree of the quotient polynomial
 // We can compute it by adding the degrees of Z(x), f(x) and t(x).
 // deg(Z(x)) = n because it has n + 1 elements
@@ -22,80 +28,137 @@ use num_traits::identities::{One, Zero};
 // So the degree of the quotient polynomial Q(x) is 3n - n = 2n
 // Significance: Adding this protocol into PLONK will not "blow up" the degree of the quotient polynomial
 // Where "blow up" denotes increasing the overall degree past 4n for standard plonk
-pub fn compute(
-    domain: &EvaluationDomain<Fr>,
-    z_poly: &Polynomial<Fr>,
-    f_poly: &Polynomial<Fr>,
-    t_poly: &Polynomial<Fr>,
-    h_1_poly: &Polynomial<Fr>,
-    h_2_poly: &Polynomial<Fr>,
-    beta: Fr,
-    gamma: Fr,
-) -> (Polynomial<Fr>, Polynomial<Fr>) {
-    // 1. Compute Point check polynomial
-    let point_check = compute_point_checks(z_poly, domain);
-    //2. Compute interval check polynomial
-    let interval_check = compute_interval_check(h_1_poly, h_2_poly, domain);
-    //3. Compute term check polynomial
-    let term_check = compute_term_check(
-        domain, z_poly, f_poly, t_poly, h_1_poly, h_2_poly, beta, gamma,
-    );
-    // Compute quotient polynomial
-    let sum = &(&interval_check + &point_check) + &term_check;
 
-    sum.divide_by_vanishing_poly(*domain).unwrap()
+/// A check which the quotient polynomial encodes
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Constraint {
+    /// L_1(X) (Z(X) - 1) = 0
+    FirstPoint,
+    /// (X - g^n) Z(X) (1 + beta) (gamma + f(X)) (gamma(1 + beta) + t(X) + beta t(Xg))
+    ///   = (X - g^n) Z(Xg) (gamma(1 + beta) + h_1(X) + beta h_1(Xg)) (gamma(1 + beta) + h_2(X) + beta h_2(Xg))
+    Term,
+    /// L_n(X) (h_1(X) - h_2(Xg)) = 0
+    Interval,
+    /// L_n(X) (Z(X) - 1) = 0
+    LastPoint,
 }
 
-fn compute_point_checks(z_poly: &Polynomial<Fr>, domain: &EvaluationDomain<Fr>) -> Polynomial<Fr> {
-    // Compute lagrange polynomials
-    let l1_poly = compute_n_lagrange_poly(domain, 0);
-    let ln_poly = compute_n_lagrange_poly(domain, domain.size() - 1);
+/// The checks encoded by the quotient, in the order of the powers of `separation` they are multiplied by
+/// This is the order of the checks (a) to (d) that the verifier makes in the plookup paper.
+pub const CONSTRAINTS: [Constraint; 4] = [
+    Constraint::FirstPoint,
+    Constraint::Term,
+    Constraint::Interval,
+    Constraint::LastPoint,
+];
+
+impl Constraint {
+    /// Returns the label of the check in the plookup paper
+    pub fn paper_label(&self) -> &'static str {
+        match self {
+            Constraint::FirstPoint => "(a)",
+            Constraint::Term => "(b)",
+            Constraint::Interval => "(c)",
+            Constraint::LastPoint => "(d)",
+        }
+    }
+}
 
-    // Compute Z'(X) = Z(x) - 1
-    let z_prime_poly = z_poly - &Polynomial::from_coefficients_vec(vec![Fr::one()]);
+// The value of each check at one point, which is zero at every point of the domain if the check holds
+struct ConstraintValues {
+    first_point: Fr,
+    term: Fr,
+    interval: Fr,
+    last_point: Fr,
+}
 
-    // We can batch the two point checks into one with the following: (Z(X)-1)[L_1(x) + L_n(x)]
-    //
-    let l_poly = &l1_poly + &ln_poly;
-    &z_prime_poly * &l_poly
+impl ConstraintValues {
+    // Combines the checks in the order of `CONSTRAINTS`, using the powers of `separation`
+    fn combine(&self, separation: Fr) -> Fr {
+        let values = [self.first_point, self.term, self.interval, self.last_point];
+        values
+            .iter()
+            .rev()
+            .fold(Fr::zero(), |acc, value| acc * separation + value)
+    }
 }
 
-fn compute_interval_check(
-    h_1_poly: &Polynomial<Fr>,
-    h_2_poly: &Polynomial<Fr>,
-    domain: &EvaluationDomain<Fr>,
-) -> Polynomial<Fr> {
-    // Increase domain size by two
-    let domain_2n: EvaluationDomain<Fr> = EvaluationDomain::new(2 * domain.size()).unwrap();
-
-    // Compute last lagrange polynomial in evaluation form
-    let ln_evals = compute_n_lagrange_evaluations(domain.size(), domain.size() - 1);
-    let ln_2n_evals = domain_2n.fft(&domain.ifft(&ln_evals));
-
-    // Convert h_1 and h_2 to evaluation form
-    let h_1_evals = domain_2n.fft(&h_1_poly);
-    let mut h_2_evals = domain_2n.fft(&h_2_poly);
-    // We need h_2(x * g) so push 2 extra elements into the domain
-    h_2_evals.push(h_2_evals[0]);
-    h_2_evals.push(h_2_evals[1]);
-
-    // Compute [L_n(x)](h_1(x) - h_2(x * g))
-    let i_evals: Vec<_> = (0..domain_2n.size())
-        .into_iter()
-        .map(|i| {
-            let ln_i = ln_2n_evals[i];
-            let h_1_i = h_1_evals[i];
-            let h_2_i_next = h_2_evals[i + 2];
-            ln_i * (h_1_i - h_2_i_next)
-        })
-        .collect();
+/// The domains and evaluations needed to compute the quotient polynomial for a table of size `n`
+///
+/// The quotient is computed over a coset of the extended domain of size 4n, where the vanishing
+/// polynomial is never zero. Z_H(X) = X^n - 1 only takes 4 distinct values over this coset,
+/// so their inverses are computed once here, along with the coset and the first and last
+/// lagrange polynomials, and reused for every proof against the table.
+#[derive(Clone, Debug)]
+pub struct QuotientKey {
+    domain: EvaluationDomain<Fr>,
+    domain_ext: EvaluationDomain<Fr>,
+    // The elements of the coset of the extended domain
+    coset_elements: Vec<Fr>,
+    // The first and last lagrange polynomials over the original domain, evaluated over the coset
+    l1_evals: Vec<Fr>,
+    ln_evals: Vec<Fr>,
+    // The inverse of Z_H(X) at the i'th element of the coset is `vanishing_inverses[i % shift]`
+    vanishing_inverses: Vec<Fr>,
+}
 
-    // Convert the evaluations for our point check to coefficient form
-    let i_poly = Polynomial::from_coefficients_vec(domain_2n.ifft(&i_evals));
-    i_poly
+impl QuotientKey {
+    /// Computes the quotient key for a domain of size `n`, using an extended domain of size 4n
+    pub fn new(n: usize) -> QuotientKey {
+        QuotientKey::with_extension(n, 4 * n)
+    }
+    /// Computes the quotient key for a domain of size `n`, using an extended domain of size `ext_size`
+    pub fn with_extension(n: usize, ext_size: usize) -> QuotientKey {
+        let domain: EvaluationDomain<Fr> = EvaluationDomain::new(n).unwrap();
+        let domain_ext: EvaluationDomain<Fr> = EvaluationDomain::new(ext_size).unwrap();
+        let shift = domain_ext.size() / domain.size();
+
+        let generator = Fr::multiplicative_generator();
+        let coset_elements: Vec<_> = domain_ext.elements().map(|root| generator * root).collect();
+
+        // (g * w^i)^n only depends on i modulo `shift`, as w^shift is a root of unity of order n
+        let vanishing_evals: Vec<_> = coset_elements[0..shift]
+            .iter()
+            .map(|x| x.pow(&[domain.size() as u64]) - Fr::one())
+            .collect();
+        let mut vanishing_inverses = vanishing_evals.clone();
+        batch_inversion(&mut vanishing_inverses);
+
+        // L_1(X) = Z_H(X) / n(X - 1) and L_n(X) = g^n Z_H(X) / n(X - g^n) are evaluated directly over the coset,
+        // which never meets the domain, rather than interpolated and extended with FFTs
+        let g_n = domain.group_gen_inv;
+        let mut l1_evals: Vec<_> = coset_elements.iter().map(|x| *x - Fr::one()).collect();
+        let mut ln_evals: Vec<_> = coset_elements.iter().map(|x| *x - g_n).collect();
+        batch_inversion(&mut l1_evals);
+        batch_inversion(&mut ln_evals);
+        for (i, (l1_eval, ln_eval)) in l1_evals.iter_mut().zip(ln_evals.iter_mut()).enumerate() {
+            let scaled_vanishing_eval = vanishing_evals[i % shift] * domain.size_inv;
+            *l1_eval *= &scaled_vanishing_eval;
+            *ln_eval *= &(g_n * scaled_vanishing_eval);
+        }
+
+        QuotientKey {
+            domain,
+            domain_ext,
+            coset_elements,
+            l1_evals,
+            ln_evals,
+            vanishing_inverses,
+        }
+    }
+    /// Returns the domain that the polynomials are interpolated over
+    pub fn domain(&self) -> &EvaluationDomain<Fr> {
+        &self.domain
+    }
+    /// Returns the size of the extended domain that the quotient is evaluated over
+    pub fn extended_size(&self) -> usize {
+        self.domain_ext.size()
+    }
 }
 
-pub fn compute_term_check(
+/// Computes the quotient polynomial, setting up the domains it is evaluated over
+/// See `compute_with_key` to reuse them across proofs.
+pub fn compute(
     domain: &EvaluationDomain<Fr>,
     z_poly: &Polynomial<Fr>,
     f_poly: &Polynomial<Fr>,
@@ -104,147 +167,226 @@ pub fn compute_term_check(
     h_2_poly: &Polynomial<Fr>,
     beta: Fr,
     gamma: Fr,
+    separation: Fr,
 ) -> Polynomial<Fr> {
-    // The equation for this is quite big. Similar to PLONK, we can split the point check into two.
-    // The first part will compute the grand product Z(X) term
-    // The second part will compute the grand product Z(Xg) term
-
-    // First Part
-    let part_a = compute_term_check_a(domain, z_poly, f_poly, t_poly, beta, gamma);
-    // Second part
-    let part_b = compute_term_check_b(domain, z_poly, h_1_poly, h_2_poly, beta, gamma);
+    compute_with_key(
+        &QuotientKey::new(domain.size()),
+        z_poly,
+        f_poly,
+        t_poly,
+        h_1_poly,
+        h_2_poly,
+        beta,
+        gamma,
+        separation,
+    )
+}
 
-    &part_a - &part_b
+/// Computes the quotient polynomial using the precomputed domains and inverses in `key`
+pub fn compute_with_key(
+    key: &QuotientKey,
+    z_poly: &Polynomial<Fr>,
+    f_poly: &Polynomial<Fr>,
+    t_poly: &Polynomial<Fr>,
+    h_1_poly: &Polynomial<Fr>,
+    h_2_poly: &Polynomial<Fr>,
+    beta: Fr,
+    gamma: Fr,
+    separation: Fr,
+) -> Polynomial<Fr> {
+    compute_with_scratch(
+        key,
+        z_poly,
+        f_poly,
+        t_poly,
+        h_1_poly,
+        h_2_poly,
+        beta,
+        gamma,
+        separation,
+        &mut ScratchBuffers::new(),
+    )
 }
-// This computes the grand product term for Z(X) or F(\beta, \gamma)
-fn compute_term_check_a(
-    domain: &EvaluationDomain<Fr>,
+
+/// Computes the quotient polynomial as `compute_with_key` does,
+/// taking the vectors of evaluations over the extended domain from `scratch` and returning them afterwards
+pub fn compute_with_scratch(
+    key: &QuotientKey,
     z_poly: &Polynomial<Fr>,
     f_poly: &Polynomial<Fr>,
     t_poly: &Polynomial<Fr>,
+    h_1_poly: &Polynomial<Fr>,
+    h_2_poly: &Polynomial<Fr>,
     beta: Fr,
     gamma: Fr,
+    separation: Fr,
+    scratch: &mut ScratchBuffers,
 ) -> Polynomial<Fr> {
-    // Increase the domain size by 4
-    let domain_4n: &EvaluationDomain<Fr> = &EvaluationDomain::new(4 * domain.size()).unwrap();
-
-    // Convert all polynomials into evaluation form
-    let z_evals = domain_4n.fft(&z_poly);
-    let f_evals = domain_4n.fft(f_poly);
-    let mut t_evals = domain_4n.fft(t_poly);
-    // Add four terms to the t(x) evaluations as we need to compute t(Xg)
-    t_evals.push(t_evals[0]);
-    t_evals.push(t_evals[1]);
-    t_evals.push(t_evals[2]);
-    t_evals.push(t_evals[3]);
+    // The term check determines the degree of the numerator. Blinding factors can push it past 3n,
+    // so we compute all checks in evaluation form over a domain large enough to hold it,
+    // and only convert back to coefficient form once.
+    let numerator_degree = 1
+        + z_poly.degree()
+        + std::cmp::max(
+            f_poly.degree() + t_poly.degree(),
+            h_1_poly.degree() + h_2_poly.degree(),
+        );
+    // For very small domains, the extended domain of size 4n may not be large enough
+    if numerator_degree >= key.domain_ext.size() {
+        let key = QuotientKey::with_extension(key.domain.size(), numerator_degree + 1);
+        return compute_with_scratch(
+            &key, z_poly, f_poly, t_poly, h_1_poly, h_2_poly, beta, gamma, separation, scratch,
+        );
+    }
+    let domain_ext = &key.domain_ext;
+    // A shift by the generator of the original domain, is a shift by `shift` positions in the extended domain
+    let shift = domain_ext.size() / key.domain.size();
 
-    let beta_one = Fr::one() + beta;
+    // Convert all polynomials into evaluation form over the coset
+    let z_evals = compute_shifted_evaluations(domain_ext, z_poly, shift, scratch);
+    let f_evals = compute_shifted_evaluations(domain_ext, f_poly, 0, scratch);
+    let t_evals = compute_shifted_evaluations(domain_ext, t_poly, shift, scratch);
+    let h_1_evals = compute_shifted_evaluations(domain_ext, h_1_poly, shift, scratch);
+    let h_2_evals = compute_shifted_evaluations(domain_ext, h_2_poly, shift, scratch);
 
+    let l1_evals = &key.l1_evals;
+    let ln_evals = &key.ln_evals;
+
+    // Compute (1 + beta)
+    let beta_one = Fr::one() + beta;
+    // Compute gamma * (1 + beta)
+    let gamma_beta_one = gamma * beta_one;
     // Compute the last element in the domain
-    let g_n = domain.elements().last().unwrap();
+    let g_n = key.domain.group_gen_inv;
 
-    let i_evals: Vec<_> = (0..domain_4n.size())
-        .into_iter()
-        .zip(domain_4n.elements())
+    let mut evals: Vec<_> = key
+        .coset_elements
+        .iter()
+        .enumerate()
         .map(|(i, root_i)| {
-            let z_i = z_evals[i];
-            let f_i = f_evals[i];
-            let t_i = t_evals[i];
-            let t_i_next = t_evals[i + 4];
-
-            // Compute X - g^n
-            let a = root_i - g_n;
-
-            // Compute Z(X)(1+beta)
-            let b = z_i * beta_one;
-
-            // Compute gamma + f(X)
-            let c = gamma + f_i;
-
-            // Compute gamma(1+beta) +t(x) + beta * t(Xg)
-            let d = (gamma * beta_one) + t_i + (beta * t_i_next);
-
-            a * b * c * d
+            // 1. Compute Point checks (Z(X)-1)L_1(x) and (Z(X)-1)L_n(x)
+            let first_point = (z_evals[i] - Fr::one()) * l1_evals[i];
+            let last_point = (z_evals[i] - Fr::one()) * ln_evals[i];
+
+            // 2. Compute interval check [L_n(x)](h_1(x) - h_2(x * g))
+            let interval = ln_evals[i] * (h_1_evals[i] - h_2_evals[i + shift]);
+
+            // 3. Compute term check
+            // The first part is the grand product term for Z(X) or F(\beta, \gamma)
+            // (X - g^n) Z(X)(1+beta) (gamma + f(X)) (gamma(1+beta) + t(x) + beta * t(Xg))
+            let part_a = (*root_i - g_n)
+                * z_evals[i]
+                * beta_one
+                * (gamma + f_evals[i])
+                * (gamma_beta_one + t_evals[i] + (beta * t_evals[i + shift]));
+            // The second part is the grand product term for Z(Xg) or G(\beta, \gamma)
+            // (X - g^n) Z(Xg) (gamma(1+beta) + h_1(x) + beta * h_1(Xg)) (gamma(1+beta) + h_2(x) + beta * h_2(Xg))
+            let part_b = (*root_i - g_n)
+                * z_evals[i + shift]
+                * (gamma_beta_one + h_1_evals[i] + (beta * h_1_evals[i + shift]))
+                * (gamma_beta_one + h_2_evals[i] + (beta * h_2_evals[i + shift]));
+
+            let values = ConstraintValues {
+                first_point,
+                term: part_a - part_b,
+                interval,
+                last_point,
+            };
+
+            // Divide by the vanishing polynomial
+            values.combine(separation) * key.vanishing_inverses[i % shift]
         })
         .collect();
+    scratch.give(z_evals);
+    scratch.give(f_evals);
+    scratch.give(t_evals);
+    scratch.give(h_1_evals);
+    scratch.give(h_2_evals);
 
-    // Convert the evaluations for our term check to coefficient form
-    let i_poly = Polynomial::from_coefficients_vec(domain_4n.ifft(&i_evals));
-
-    assert_eq!(
-        i_poly.evaluate(domain.elements().last().unwrap()),
-        Fr::zero()
-    );
-
-    i_poly
+    // Compute quotient polynomial
+    // The quotient is returned, so its evaluations are collected into a new vector rather than a buffer of `scratch`
+    domain_ext.coset_ifft_in_place(&mut evals);
+    Polynomial::from_coefficients_vec(evals)
 }
-// This computes the grand product term for Z(Xg) or G(\beta, \gamma)
-fn compute_term_check_b(
+
+/// Checks that the numerator of the quotient equals the quotient times the vanishing polynomial at `point`
+/// This evaluates both sides of the identity directly from the polynomials,
+/// independently of how the quotient was computed.
+pub fn check_identity(
     domain: &EvaluationDomain<Fr>,
     z_poly: &Polynomial<Fr>,
+    f_poly: &Polynomial<Fr>,
+    t_poly: &Polynomial<Fr>,
     h_1_poly: &Polynomial<Fr>,
     h_2_poly: &Polynomial<Fr>,
+    quotient_poly: &Polynomial<Fr>,
     beta: Fr,
     gamma: Fr,
-) -> Polynomial<Fr> {
-    // Increase the domain size by 4
-    let domain_4n: &EvaluationDomain<Fr> = &EvaluationDomain::new(4 * domain.size()).unwrap();
-
-    // Convert all polynomials into evaluation form, then add four terms to each evaluation as we need to compute their evaluations at the next root of unity
-    let mut z_evals = domain_4n.fft(z_poly);
-    z_evals.push(z_evals[0]);
-    z_evals.push(z_evals[1]);
-    z_evals.push(z_evals[2]);
-    z_evals.push(z_evals[3]);
-    let mut h_1_evals = domain_4n.fft(h_1_poly);
-    h_1_evals.push(h_1_evals[0]);
-    h_1_evals.push(h_1_evals[1]);
-    h_1_evals.push(h_1_evals[2]);
-    h_1_evals.push(h_1_evals[3]);
-
-    let mut h_2_evals = domain_4n.fft(h_2_poly);
-    h_2_evals.push(h_2_evals[0]);
-    h_2_evals.push(h_2_evals[1]);
-    h_2_evals.push(h_2_evals[2]);
-    h_2_evals.push(h_2_evals[3]);
+    separation: Fr,
+    point: Fr,
+) -> bool {
+    let point_omega = point * domain.group_gen;
+    let g_n = domain.group_gen_inv;
+
+    // L_1(x) = Z_H(x) / n(x - 1) and L_n(x) = g^n * Z_H(x) / n(x - g^n)
+    let vanishing_eval = domain.evaluate_vanishing_polynomial(point);
+    let l1_eval = match (point - Fr::one()).inverse() {
+        Some(inv) => vanishing_eval * domain.size_inv * inv,
+        None => return false,
+    };
+    let ln_eval = match (point - g_n).inverse() {
+        Some(inv) => g_n * vanishing_eval * domain.size_inv * inv,
+        None => return false,
+    };
+
+    let z_eval = z_poly.evaluate(point);
+    let z_omega_eval = z_poly.evaluate(point_omega);
+    let f_eval = f_poly.evaluate(point);
+    let t_eval = t_poly.evaluate(point);
+    let t_omega_eval = t_poly.evaluate(point_omega);
+    let h_1_eval = h_1_poly.evaluate(point);
+    let h_1_omega_eval = h_1_poly.evaluate(point_omega);
+    let h_2_eval = h_2_poly.evaluate(point);
+    let h_2_omega_eval = h_2_poly.evaluate(point_omega);
 
-    // Compute (1 + beta)
     let beta_one = Fr::one() + beta;
-    // Compute the last element in the domain
-    let g_n = domain.elements().last().unwrap();
-
-    let i_evals: Vec<_> = (0..domain_4n.size())
-        .into_iter()
-        .zip(domain_4n.elements())
-        .map(|(i, root_i)| {
-            let z_i_next = z_evals[i + 4];
-            let h_1_i = h_1_evals[i];
-            let h_1_i_next = h_1_evals[i + 4];
-            let h_2_i = h_2_evals[i];
-            let h_2_i_next = h_2_evals[i + 4];
-
-            // Compute (X - g^n) Z(Xg)
-            let a = (root_i - g_n) * z_i_next;
-
-            // Compute [gamma * (1+beta)] + h_1(x) + beta * h_1(Xg)
-            let b = (gamma * beta_one) + h_1_i + (beta * h_1_i_next);
-
-            // Compute [gamma * (1+beta)] + h_2(x) + beta * h_2(Xg)
-            let c = (gamma * beta_one) + h_2_i + (beta * h_2_i_next);
-
-            a * b * c
-        })
-        .collect();
-
-    // Convert the evaluations for our term check to coefficient form
-    let i_poly = Polynomial::from_coefficients_vec(domain_4n.ifft(&i_evals));
-
-    assert_eq!(
-        i_poly.evaluate(domain.elements().last().unwrap()),
-        Fr::zero()
-    );
+    let gamma_beta_one = gamma * beta_one;
+
+    let part_a = (point - g_n)
+        * z_eval
+        * beta_one
+        * (gamma + f_eval)
+        * (gamma_beta_one + t_eval + (beta * t_omega_eval));
+    let part_b = (point - g_n)
+        * z_omega_eval
+        * (gamma_beta_one + h_1_eval + (beta * h_1_omega_eval))
+        * (gamma_beta_one + h_2_eval + (beta * h_2_omega_eval));
+    let values = ConstraintValues {
+        first_point: (z_eval - Fr::one()) * l1_eval,
+        term: part_a - part_b,
+        interval: ln_eval * (h_1_eval - h_2_omega_eval),
+        last_point: (z_eval - Fr::one()) * ln_eval,
+    };
+    let numerator = values.combine(separation);
+
+    numerator == quotient_poly.evaluate(point) * vanishing_eval
+}
 
-    i_poly
+// Converts a polynomial into evaluation form over the coset of the extended domain
+// The first `shift` evaluations are appended to the end, so that the evaluation of the polynomial
+// at the next root of unity of the original domain is always `shift` positions ahead
+fn compute_shifted_evaluations(
+    domain_ext: &EvaluationDomain<Fr>,
+    poly: &Polynomial<Fr>,
+    shift: usize,
+    scratch: &mut ScratchBuffers,
+) -> Vec<Fr> {
+    let mut evals = scratch.take(domain_ext.size() + shift);
+    evals.extend_from_slice(&poly.coeffs);
+    domain_ext.coset_fft_in_place(&mut evals);
+    let wrap_around = evals[0..shift].to_vec();
+    evals.extend(wrap_around);
+    evals
 }
 
 // Computes the n'th lagrange poly for a particular domain
@@ -285,6 +427,7 @@ mod test {
         let domain: EvaluationDomain<Fr> = EvaluationDomain::new(f.len()).unwrap();
         let beta = Fr::from(10u8);
         let gamma = Fr::from(11u8);
+        let separation = Fr::from(13u8);
 
         // Compute h_1 and h_2
         let (h_1, h_2) = compute_h1_h2(&f, &t);
@@ -304,9 +447,112 @@ mod test {
         let z_evaluations = compute_accumulator_values(&f, &t, &h_1, &h_2, beta, gamma);
         let z_poly = Polynomial::from_coefficients_vec(domain.ifft(&z_evaluations));
 
-        let (_, remainder) = compute(
-            &domain, &z_poly, &f_poly, &t_poly, &h_1_poly, &h_2_poly, beta, gamma,
+        // The numerator is only divisible by the vanishing polynomial if all checks pass,
+        // in which case the quotient has degree at most deg(numerator) - n
+        let numerator_degree = 1 + z_poly.degree() + f_poly.degree() + t_poly.degree();
+        let quotient = compute(
+            &domain, &z_poly, &f_poly, &t_poly, &h_1_poly, &h_2_poly, beta, gamma, separation,
+        );
+        assert!(quotient.degree() <= numerator_degree - domain.size());
+
+        // An accumulator computed with a different challenge fails the term check
+        let z_evaluations = compute_accumulator_values(&f, &t, &h_1, &h_2, beta, gamma + gamma);
+        let bad_z_poly = Polynomial::from_coefficients_vec(domain.ifft(&z_evaluations));
+        let quotient = compute(
+            &domain,
+            &bad_z_poly,
+            &f_poly,
+            &t_poly,
+            &h_1_poly,
+            &h_2_poly,
+            beta,
+            gamma,
+            separation,
+        );
+        assert!(quotient.degree() > numerator_degree - domain.size());
+    }
+
+    #[test]
+    fn test_check_identity() {
+        let f: MultiSet = [2u8, 3, 4].iter().map(|x| Fr::from(*x)).collect();
+        let t: MultiSet = [2u8, 3, 4, 5].iter().map(|x| Fr::from(*x)).collect();
+        let domain: EvaluationDomain<Fr> = EvaluationDomain::new(t.len()).unwrap();
+        let (beta, gamma, separation) = (Fr::from(10u8), Fr::from(11u8), Fr::from(13u8));
+
+        let (h_1, h_2) = compute_h1_h2(&f, &t);
+        let (f_poly, t_poly) = (f.to_polynomial(&domain), t.to_polynomial(&domain));
+        let (h_1_poly, h_2_poly) = (h_1.to_polynomial(&domain), h_2.to_polynomial(&domain));
+        let z_evaluations = compute_accumulator_values(&f, &t, &h_1, &h_2, beta, gamma);
+        let z_poly = Polynomial::from_coefficients_vec(domain.ifft(&z_evaluations));
+
+        let quotient = compute(
+            &domain, &z_poly, &f_poly, &t_poly, &h_1_poly, &h_2_poly, beta, gamma, separation,
+        );
+        let point = Fr::from(12345u64);
+        assert!(check_identity(
+            &domain, &z_poly, &f_poly, &t_poly, &h_1_poly, &h_2_poly, &quotient, beta, gamma,
+            separation, point
+        ));
+
+        // Swapping h_1 and h_2 breaks the identity
+        let swapped = compute(
+            &domain, &z_poly, &f_poly, &t_poly, &h_2_poly, &h_1_poly, beta, gamma, separation,
         );
-        assert!(remainder.is_zero());
+        assert!(!check_identity(
+            &domain, &z_poly, &f_poly, &t_poly, &h_2_poly, &h_1_poly, &swapped, beta, gamma,
+            separation, point
+        ));
+
+        // The quotient is bound to the separation challenge
+        assert!(!check_identity(
+            &domain,
+            &z_poly,
+            &f_poly,
+            &t_poly,
+            &h_1_poly,
+            &h_2_poly,
+            &quotient,
+            beta,
+            gamma,
+            separation + Fr::one(),
+            point
+        ));
+    }
+
+    #[test]
+    fn test_constraints() {
+        let labels: Vec<_> = CONSTRAINTS.iter().map(|c| c.paper_label()).collect();
+        assert_eq!(labels, vec!["(a)", "(b)", "(c)", "(d)"]);
+
+        // The checks are combined as c_0 + c_1 s + c_2 s^2 + c_3 s^3
+        let values = ConstraintValues {
+            first_point: Fr::from(1u8),
+            term: Fr::from(2u8),
+            interval: Fr::from(3u8),
+            last_point: Fr::from(4u8),
+        };
+        assert_eq!(values.combine(Fr::from(10u8)), Fr::from(4321u64));
+    }
+
+    #[test]
+    fn test_quotient_key() {
+        let n = 8;
+        let key = QuotientKey::new(n);
+        assert_eq!(key.domain().size(), n);
+        assert_eq!(key.extended_size(), 4 * n);
+
+        // The cached inverses agree with evaluating the vanishing polynomial over the coset
+        for (i, x) in key.coset_elements.iter().enumerate() {
+            let vanishing_eval = key.domain().evaluate_vanishing_polynomial(*x);
+            assert_eq!(vanishing_eval * key.vanishing_inverses[i % 4], Fr::one());
+        }
+
+        // The lagrange polynomials evaluated in closed form agree with interpolating them
+        let l1_poly = compute_n_lagrange_poly(key.domain(), 0);
+        let ln_poly = compute_n_lagrange_poly(key.domain(), n - 1);
+        for (i, x) in key.coset_elements.iter().enumerate() {
+            assert_eq!(key.l1_evals[i], l1_poly.evaluate(*x));
+            assert_eq!(key.ln_evals[i], ln_poly.evaluate(*x));
+        }
     }
 }
@@ -0,0 +1,92 @@
+// Reusing the buffers of temporary evaluations between the stages of a proof, and between proofs
+//
+// The quotient is computed from the evaluations of five polynomials over the extended domain, each in a vector
+// of 4n field elements which is dropped as soon as the quotient is interpolated. On large domains allocating
+// and zeroing these vectors for every proof puts pressure on the allocator, and the pages are faulted in again.
+// `ScratchBuffers` keeps the vectors once they are returned to it, and hands out their capacity again.
+use algebra::bls12_381::Fr;
+
+/// A pool of vectors of field elements which have been used and returned
+#[derive(Clone, Debug, Default)]
+pub struct ScratchBuffers {
+    free: Vec<Vec<Fr>>,
+    allocations: usize,
+}
+
+impl ScratchBuffers {
+    pub fn new() -> ScratchBuffers {
+        ScratchBuffers::default()
+    }
+    /// Returns an empty vector which can hold at least `capacity` elements without reallocating
+    /// The smallest returned vector which is large enough is reused, and a new one is only allocated if there is none.
+    pub fn take(&mut self, capacity: usize) -> Vec<Fr> {
+        let smallest = (0..self.free.len())
+            .filter(|i| self.free[*i].capacity() >= capacity)
+            .min_by_key(|i| self.free[*i].capacity());
+        match smallest {
+            Some(i) => {
+                let mut buffer = self.free.swap_remove(i);
+                buffer.clear();
+                buffer
+            }
+            None => {
+                self.allocations += 1;
+                Vec::with_capacity(capacity)
+            }
+        }
+    }
+    /// Returns a vector to the pool, so that its capacity can be reused
+    pub fn give(&mut self, buffer: Vec<Fr>) {
+        self.free.push(buffer);
+    }
+    /// Returns the number of vectors the pool has allocated because none of its vectors were large enough
+    pub fn allocations(&self) -> usize {
+        self.allocations
+    }
+}
+
+/// Reusable memory for a prover which makes many proofs
+/// Pass the same scratch to each proof, see `ProveOptions::scratch`.
+#[derive(Clone, Debug, Default)]
+pub struct ProverScratch {
+    pub(crate) scratch: ScratchBuffers,
+}
+
+impl ProverScratch {
+    pub fn new() -> ProverScratch {
+        ProverScratch::default()
+    }
+    /// Returns the buffers of temporary evaluations kept by the scratch
+    pub fn scratch(&self) -> &ScratchBuffers {
+        &self.scratch
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_scratch_buffers() {
+        let mut scratch = ScratchBuffers::new();
+        let mut a = scratch.take(16);
+        a.push(Fr::from(1u8));
+        let b = scratch.take(8);
+        assert_eq!(scratch.allocations(), 2);
+
+        scratch.give(a);
+        scratch.give(b);
+        // The returned vectors are empty and keep their capacity
+        let c = scratch.take(12);
+        assert!(c.is_empty());
+        assert!(c.capacity() >= 16);
+        let d = scratch.take(4);
+        assert!(d.capacity() < 16);
+        assert_eq!(scratch.allocations(), 2);
+
+        scratch.give(c);
+        scratch.give(d);
+        scratch.take(32);
+        assert_eq!(scratch.allocations(), 3);
+    }
+}
@@ -0,0 +1,195 @@
+// Resuming a proof from the last stage of the prover that completed
+//
+// The transcript cannot be written to disk, but it only depends on the values absorbed into it.
+// `ProverState` holds the results of each expensive stage, and a resumed prover uses them instead of
+// recomputing the stage, absorbing the same commitments and so drawing the same challenges.
+// The blinding randomness is only sampled while the polynomials are computed, so stored polynomials keep their blinding.
+//
+// The state is written as the digest of the witness and table, followed by each stage in order.
+// Each stage is a flag byte, which is one if the stage completed, followed by its results if it did.
+use super::{MultiSet, ProverStage};
+use crate::error::Error;
+use algebra::{
+    bls12_381::{Fr, G1Affine},
+    io::{Read, Write},
+    to_bytes, Bls12_381, CanonicalDeserialize, CanonicalSerialize, SerializationError, ToBytes,
+};
+use ff_fft::DensePolynomial as Polynomial;
+use merlin::Transcript;
+use poly_commit::kzg10::Commitment;
+
+/// The results of the stages of a proof which have completed, from which the proof can be resumed
+/// Pass the state to `EqualityProof::prove_resumable`, and write it to disk with `save` as each stage completes.
+#[derive(Clone, Debug, Default)]
+pub struct ProverState {
+    // The digest of the witness and the table that the state was computed for
+    pub(crate) digest: Option<[u8; 32]>,
+    pub(crate) h_polynomials: Option<HPolynomials>,
+    // The blinded Z(X) and its commitment
+    pub(crate) accumulator: Option<(Polynomial<Fr>, Commitment<Bls12_381>)>,
+    pub(crate) quotient_poly: Option<Polynomial<Fr>>,
+    pub(crate) quotient_commit: Option<Commitment<Bls12_381>>,
+}
+
+// The sorted multisets h_1 and h_2, the blinded f(X), h_1(X) and h_2(X), and their commitments
+#[derive(Clone, Debug)]
+pub(crate) struct HPolynomials {
+    pub h_1: MultiSet,
+    pub h_2: MultiSet,
+    pub f_poly: Polynomial<Fr>,
+    pub h_1_poly: Polynomial<Fr>,
+    pub h_2_poly: Polynomial<Fr>,
+    pub f_commit: Commitment<Bls12_381>,
+    pub h_1_commit: Commitment<Bls12_381>,
+    pub h_2_commit: Commitment<Bls12_381>,
+}
+
+impl ProverState {
+    /// The state of a proof which has not started
+    pub fn new() -> ProverState {
+        ProverState::default()
+    }
+    /// Returns the last stage of the prover which has completed, if any have
+    pub fn completed(&self) -> Option<ProverStage> {
+        if self.quotient_commit.is_some() {
+            Some(ProverStage::Commitments)
+        } else if self.quotient_poly.is_some() {
+            Some(ProverStage::Quotient)
+        } else if self.accumulator.is_some() {
+            Some(ProverStage::Accumulator)
+        } else if self.h_polynomials.is_some() {
+            Some(ProverStage::HPolynomials)
+        } else {
+            None
+        }
+    }
+    // Binds the state to the witness and table on the first run,
+    // and checks that a resumed proof is for the same witness and table
+    pub(crate) fn bind(&mut self, f: &MultiSet, t: &MultiSet) -> Result<(), Error> {
+        let digest = witness_digest(f, t);
+        match self.digest {
+            Some(stored) if stored != digest => Err(Error::StateMismatch),
+            Some(_) => Ok(()),
+            None => {
+                self.digest = Some(digest);
+                Ok(())
+            }
+        }
+    }
+    /// Writes the state, so that the proof can be resumed after the process exits
+    pub fn save<W: Write>(&self, writer: &mut W) -> Result<(), Error> {
+        writer.write_all(&self.digest.unwrap_or([0u8; 32]))?;
+
+        write_flag(writer, self.h_polynomials.is_some())?;
+        if let Some(h) = &self.h_polynomials {
+            h.h_1.serialize(writer)?;
+            h.h_2.serialize(writer)?;
+            for poly in vec![&h.f_poly, &h.h_1_poly, &h.h_2_poly] {
+                write_poly(writer, poly)?;
+            }
+            for commitment in vec![&h.f_commit, &h.h_1_commit, &h.h_2_commit] {
+                commitment.0.serialize(writer)?;
+            }
+        }
+        write_flag(writer, self.accumulator.is_some())?;
+        if let Some((z_poly, z_commit)) = &self.accumulator {
+            write_poly(writer, z_poly)?;
+            z_commit.0.serialize(writer)?;
+        }
+        write_flag(writer, self.quotient_poly.is_some())?;
+        if let Some(quotient_poly) = &self.quotient_poly {
+            write_poly(writer, quotient_poly)?;
+        }
+        write_flag(writer, self.quotient_commit.is_some())?;
+        if let Some(q_commit) = &self.quotient_commit {
+            q_commit.0.serialize(writer)?;
+        }
+        Ok(())
+    }
+    /// Reads a state which was written by `save`
+    pub fn load<R: Read>(reader: &mut R) -> Result<ProverState, Error> {
+        let mut digest = [0u8; 32];
+        reader.read_exact(&mut digest)?;
+
+        let h_polynomials = if read_flag(reader)? {
+            Some(HPolynomials {
+                h_1: MultiSet::deserialize(reader)?,
+                h_2: MultiSet::deserialize(reader)?,
+                f_poly: read_poly(reader)?,
+                h_1_poly: read_poly(reader)?,
+                h_2_poly: read_poly(reader)?,
+                f_commit: Commitment(G1Affine::deserialize(reader)?),
+                h_1_commit: Commitment(G1Affine::deserialize(reader)?),
+                h_2_commit: Commitment(G1Affine::deserialize(reader)?),
+            })
+        } else {
+            None
+        };
+        let accumulator = if read_flag(reader)? {
+            Some((
+                read_poly(reader)?,
+                Commitment(G1Affine::deserialize(reader)?),
+            ))
+        } else {
+            None
+        };
+        let quotient_poly = if read_flag(reader)? {
+            Some(read_poly(reader)?)
+        } else {
+            None
+        };
+        let quotient_commit = if read_flag(reader)? {
+            Some(Commitment(G1Affine::deserialize(reader)?))
+        } else {
+            None
+        };
+
+        Ok(ProverState {
+            // A state that was saved before the prover started is not bound to a witness
+            digest: Some(digest).filter(|digest| *digest != [0u8; 32]),
+            h_polynomials,
+            accumulator,
+            quotient_poly,
+            quotient_commit,
+        })
+    }
+}
+
+fn witness_digest(f: &MultiSet, t: &MultiSet) -> [u8; 32] {
+    let mut transcript = Transcript::new(b"plookup_prover_state");
+    transcript.append_message(b"f", &to_bytes![f.0].unwrap());
+    transcript.append_message(b"t", &to_bytes![t.0].unwrap());
+
+    let mut digest = [0u8; 32];
+    transcript.challenge_bytes(b"digest", &mut digest);
+    digest
+}
+
+fn write_flag<W: Write>(writer: &mut W, flag: bool) -> Result<(), SerializationError> {
+    (flag as u8).serialize(writer)
+}
+
+fn read_flag<R: Read>(reader: &mut R) -> Result<bool, SerializationError> {
+    match u8::deserialize(reader)? {
+        0 => Ok(false),
+        1 => Ok(true),
+        _ => Err(SerializationError::InvalidData),
+    }
+}
+
+// A polynomial is written as the number of coefficients as a u64, followed by the coefficients
+fn write_poly<W: Write>(writer: &mut W, poly: &Polynomial<Fr>) -> Result<(), SerializationError> {
+    (poly.coeffs.len() as u64).serialize(writer)?;
+    for coeff in poly.coeffs.iter() {
+        coeff.serialize(writer)?;
+    }
+    Ok(())
+}
+
+fn read_poly<R: Read>(reader: &mut R) -> Result<Polynomial<Fr>, SerializationError> {
+    let len = u64::deserialize(reader)? as usize;
+    let coeffs = (0..len)
+        .map(|_| Fr::deserialize(reader))
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(Polynomial::from_coefficients_vec(coeffs))
+}
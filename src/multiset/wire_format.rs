@@ -0,0 +1,282 @@
+// A machine-readable description of the proof wire format
+//
+// Verifiers in other languages must read proofs byte for byte as `EqualityProof` and `LookUpProof` write them.
+// `WireFormat` lists the fields of a serialised proof in order, with their encodings, and `to_json` writes them out,
+// so that other implementations can be generated from, or checked against, the Rust types.
+//
+// Integers are little-endian. A scalar is the 32 byte little-endian encoding of its canonical integer,
+// see `encoding`. A G1 point is 48 bytes in the compressed form of a proof and 96 bytes in the uncompressed form.
+// The names of the fields are the labels that `VerifierDescription` uses for the same values.
+use super::proof::PARAMETER_LABELS;
+use crate::lookup::context::MAX_CONTEXT_BYTES;
+use std::{collections::HashMap, fmt::Write};
+
+/// The encoding of a field of a serialised proof
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FieldType {
+    /// A byte
+    U8,
+    /// A byte which is 1 for true and 0 for false, any other value is rejected
+    Bool,
+    /// A little-endian u64
+    U64,
+    /// A fixed number of bytes
+    Bytes(usize),
+    /// An element of the scalar field
+    Scalar,
+    /// A point of G1
+    Point,
+    /// A u64 length followed by that many bytes, where the length is at most the given maximum
+    LengthPrefixed(usize),
+}
+
+impl FieldType {
+    /// Returns the size in bytes of the field, or None if its size is given by its length prefix
+    pub fn size(&self, compressed: bool) -> Option<usize> {
+        match self {
+            FieldType::U8 | FieldType::Bool => Some(1),
+            FieldType::U64 => Some(8),
+            FieldType::Bytes(len) => Some(*len),
+            FieldType::Scalar => Some(32),
+            FieldType::Point if compressed => Some(48),
+            FieldType::Point => Some(96),
+            FieldType::LengthPrefixed(_) => None,
+        }
+    }
+    fn write_json(&self, out: &mut String) {
+        match self {
+            FieldType::U8 => out.push_str("{\"type\": \"u8\"}"),
+            FieldType::Bool => out.push_str("{\"type\": \"bool\"}"),
+            FieldType::U64 => out.push_str("{\"type\": \"u64\"}"),
+            FieldType::Bytes(len) => {
+                write!(out, "{{\"type\": \"bytes\", \"len\": {}}}", len).unwrap()
+            }
+            FieldType::Scalar => out.push_str("{\"type\": \"scalar\"}"),
+            FieldType::Point => out.push_str("{\"type\": \"g1\"}"),
+            FieldType::LengthPrefixed(max) => write!(
+                out,
+                "{{\"type\": \"length_prefixed_bytes\", \"max_len\": {}}}",
+                max
+            )
+            .unwrap(),
+        }
+    }
+}
+
+/// A field of a serialised proof
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Field {
+    pub name: &'static str,
+    pub ty: FieldType,
+    /// The field is only present if the earlier field with this name has this value
+    pub present_if: Option<(&'static str, u64)>,
+}
+
+fn field(name: &'static str, ty: FieldType) -> Field {
+    Field {
+        name,
+        ty,
+        present_if: None,
+    }
+}
+
+/// The fields of a serialised proof, in the order that they are written
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct WireFormat {
+    pub fields: Vec<Field>,
+}
+
+impl WireFormat {
+    /// Describes the serialisation of `EqualityProof`
+    pub fn equality_proof() -> WireFormat {
+        let mut fields = vec![
+            field("aggregate_witness_comm", FieldType::Point),
+            field("shifted_aggregate_witness_comm", FieldType::Point),
+        ];
+        fields.extend(
+            [
+                "f_eval",
+                "t_eval",
+                "t_omega_eval",
+                "h_1_eval",
+                "h_1_omega_eval",
+                "h_2_eval",
+                "h_2_omega_eval",
+                "z_eval",
+                "z_omega_eval",
+            ]
+            .iter()
+            .cloned()
+            .map(|name| field(name, FieldType::Scalar)),
+        );
+        fields.extend(
+            [
+                "f_poly",
+                "quotient_poly",
+                "h_1_poly",
+                "h_2_poly",
+                "accumulator_poly",
+            ]
+            .iter()
+            .cloned()
+            .map(|name| field(name, FieldType::Point)),
+        );
+        // All of the public parameters are u64s, apart from the last which says whether f(X) was committed to
+        let (committed_f, integers) = PARAMETER_LABELS.split_last().unwrap();
+        fields.extend(
+            integers
+                .iter()
+                .cloned()
+                .map(|name| field(name, FieldType::U64)),
+        );
+        fields.push(field(committed_f, FieldType::Bool));
+        WireFormat { fields }
+    }
+    /// Describes the serialisation of `LookUpProof`
+    /// The mode is 0 for slim proofs and 1 for self-contained proofs, which also carry the table commitments
+    /// and the digest of the SRS they were made with.
+    pub fn lookup_proof() -> WireFormat {
+        let self_contained = |name, ty| Field {
+            name,
+            ty,
+            present_if: Some(("mode", 1)),
+        };
+        let mut fields = vec![
+            field("mode", FieldType::U8),
+            field("table_id", FieldType::Bytes(32)),
+            self_contained("table_size", FieldType::U64),
+            self_contained("srs_digest", FieldType::Bytes(32)),
+            self_contained("t_1", FieldType::Point),
+            self_contained("t_2", FieldType::Point),
+            self_contained("t_3", FieldType::Point),
+        ];
+        fields.extend(WireFormat::equality_proof().fields);
+        fields.push(field(
+            "app_id",
+            FieldType::LengthPrefixed(MAX_CONTEXT_BYTES),
+        ));
+        fields.push(field(
+            "session",
+            FieldType::LengthPrefixed(MAX_CONTEXT_BYTES),
+        ));
+        WireFormat { fields }
+    }
+    /// Returns the size in bytes of a serialised proof
+    /// `values` holds the value of every field which other fields depend on, and the length of every
+    /// length-prefixed field, by name. Returns None if one of them is missing.
+    pub fn size(&self, compressed: bool, values: &HashMap<&str, u64>) -> Option<usize> {
+        let mut size = 0;
+        for field in &self.fields {
+            if let Some((name, value)) = field.present_if {
+                if *values.get(name)? != value {
+                    continue;
+                }
+            }
+            size += match field.ty.size(compressed) {
+                Some(field_size) => field_size,
+                None => 8 + *values.get(field.name)? as usize,
+            };
+        }
+        Some(size)
+    }
+    /// Writes the description as JSON
+    pub fn to_json(&self) -> String {
+        let mut out = String::from("{\n  \"encoding\": {\"integers\": \"little_endian\", \"scalar_bytes\": 32, \"g1_bytes\": {\"compressed\": 48, \"uncompressed\": 96}},\n");
+        out.push_str("  \"fields\": [\n");
+        let fields: Vec<_> = self
+            .fields
+            .iter()
+            .map(|field| {
+                let mut entry = format!("    {{\"name\": \"{}\", \"encoding\": ", field.name);
+                field.ty.write_json(&mut entry);
+                if let Some((name, value)) = field.present_if {
+                    write!(
+                        entry,
+                        ", \"present_if\": {{\"field\": \"{}\", \"equals\": {}}}",
+                        name, value
+                    )
+                    .unwrap();
+                }
+                entry.push('}');
+                entry
+            })
+            .collect();
+        writeln!(out, "{}\n  ]\n}}", fields.join(",\n")).unwrap();
+        out
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::kzg10;
+    use crate::lookup::{
+        context::ProofContext,
+        lookup::LookUp,
+        proof::{LookUpProof, ProofMode, ProveOptions},
+        table::{four_bits::XOR4Bit, LookUpTable},
+    };
+    use algebra::{bls12_381::Fr, CanonicalSerialize};
+    use merlin::Transcript;
+
+    #[test]
+    fn test_wire_format_matches_proofs() {
+        let (proving_key, _) = kzg10::trusted_setup(2usize.pow(10), b"insecure_seed");
+        let preprocessed_table = XOR4Bit::new().preprocess(&proving_key, 2usize.pow(8));
+        let context = ProofContext::new(b"app", b"session");
+        let format = WireFormat::lookup_proof();
+
+        for &(mode, mode_value) in &[(ProofMode::Slim, 0), (ProofMode::SelfContained, 1)] {
+            let mut lookup = LookUp::new(XOR4Bit::new());
+            lookup.read(&(Fr::from(5u8), Fr::from(6u8)));
+            let proof: LookUpProof = lookup
+                .prove_with_options(
+                    &proving_key,
+                    &preprocessed_table,
+                    ProveOptions::new().mode(mode).context(&context),
+                    &mut Transcript::new(b"lookup"),
+                )
+                .unwrap();
+
+            let mut values = HashMap::new();
+            values.insert("mode", mode_value);
+            values.insert("app_id", context.app_id.len() as u64);
+            values.insert("session", context.session.len() as u64);
+
+            let compressed = proof.to_compressed_bytes();
+            assert_eq!(format.size(true, &values), Some(compressed.len()));
+            assert_eq!(compressed[0] as u64, mode_value);
+            assert_eq!(
+                format.size(false, &values),
+                Some(proof.to_uncompressed_bytes().len())
+            );
+
+            // The equality proof is a prefix of the fields of the lookup proof
+            let equality_proof = &proof.multiset_equality_proof;
+            assert_eq!(
+                WireFormat::equality_proof().size(true, &values),
+                Some(equality_proof.serialized_size())
+            );
+            assert_eq!(
+                WireFormat::equality_proof().size(false, &values),
+                Some(equality_proof.uncompressed_size())
+            );
+        }
+
+        // The size is unknown without the mode
+        assert_eq!(format.size(true, &HashMap::new()), None);
+    }
+
+    #[test]
+    fn test_json() {
+        let json = WireFormat::lookup_proof().to_json();
+        assert!(json.contains(
+            "{\"name\": \"table_id\", \"encoding\": {\"type\": \"bytes\", \"len\": 32}}"
+        ));
+        assert!(json.contains("{\"name\": \"t_1\", \"encoding\": {\"type\": \"g1\"}, \"present_if\": {\"field\": \"mode\", \"equals\": 1}}"));
+        assert!(json.contains("{\"name\": \"committed_f\", \"encoding\": {\"type\": \"bool\"}}"));
+        assert!(json.ends_with(
+            "{\"name\": \"session\", \"encoding\": {\"type\": \"length_prefixed_bytes\", \"max_len\": 256}}\n  ]\n}\n"
+        ));
+    }
+}
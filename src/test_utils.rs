@@ -0,0 +1,321 @@
+// Helpers for testing the prover, enabled with the `test-utils` feature
+//
+// The generators build random tables and witnesses. `Intermediates` computes the values that the prover
+// derives from a witness and a table for fixed challenges, and `Intermediates::dump` writes them in a stable
+// text format: one `name[i] = 0x...` line per value, where each value is the big-endian hex encoding of its
+// canonical integer. The reference plookup scripts can print the same format, so that the first value at which
+// the two implementations disagree can be found by diffing their outputs.
+//
+// The `arb_*` functions are proptest strategies for the same types, which downstream crates can use in
+// their own property tests.
+use crate::{
+    encoding::fr_to_hex,
+    lookup::{
+        proof::merge_witness,
+        table::{Generic, LookUpTable, PreProcessedTable},
+    },
+    multiset::{multiset_equality, MultiSet},
+};
+use algebra::{bls12_381::Fr, UniformRand};
+use proptest::{collection::vec, prelude::*};
+use rand::Rng;
+use rand_chacha::ChaChaRng;
+use rand_core::{RngCore, SeedableRng};
+use std::fmt::Write;
+
+/// Returns a table with `num_rows` rows (a, b) -> c of random field elements
+pub fn random_table<R: RngCore>(rng: &mut R, num_rows: usize) -> Generic {
+    let rows = (0..num_rows)
+        .map(|_| ((Fr::rand(rng), Fr::rand(rng)), Fr::rand(rng)))
+        .collect();
+    Generic::with_hashmap(rows)
+}
+
+/// Returns a witness of `num_queries` rows, each chosen uniformly from the rows of `table`
+pub fn random_witness<R: RngCore, T: LookUpTable + ?Sized>(
+    rng: &mut R,
+    table: &T,
+    num_queries: usize,
+) -> (MultiSet, MultiSet, MultiSet) {
+    let (t_1, t_2, t_3) = table.to_multiset();
+    assert!(!t_1.0.is_empty());
+
+    let mut witness = (MultiSet::new(), MultiSet::new(), MultiSet::new());
+    for _ in 0..num_queries {
+        let i = rng.gen_range(0, t_1.len());
+        witness.0.push(t_1.0[i]);
+        witness.1.push(t_2.0[i]);
+        witness.2.push(t_3.0[i]);
+    }
+    witness
+}
+
+/// A strategy for uniformly random field elements
+pub fn arb_fr() -> impl Strategy<Value = Fr> {
+    any::<[u8; 32]>().prop_map(|seed| Fr::rand(&mut ChaChaRng::from_seed(seed)))
+}
+
+/// A strategy for multisets of at most `max_len` random elements
+pub fn arb_multiset(max_len: usize) -> impl Strategy<Value = MultiSet> {
+    vec(arb_fr(), 0..=max_len).prop_map(MultiSet)
+}
+
+/// A strategy for tables of between one and `max_rows` random rows
+pub fn arb_table(max_rows: usize) -> impl Strategy<Value = Generic> {
+    vec((arb_fr(), arb_fr(), arb_fr()), 1..=max_rows).prop_map(|rows| {
+        let rows = rows.into_iter().map(|(a, b, c)| ((a, b), c)).collect();
+        Generic::with_hashmap(rows)
+    })
+}
+
+/// A strategy for a table together with a witness of between one and `max_queries` rows of that table
+pub fn arb_lookup(
+    max_rows: usize,
+    max_queries: usize,
+) -> impl Strategy<Value = (Generic, (MultiSet, MultiSet, MultiSet))> {
+    (arb_table(max_rows), any::<[u8; 32]>(), 1..=max_queries).prop_map(
+        |(table, seed, num_queries)| {
+            let witness = random_witness(&mut ChaChaRng::from_seed(seed), &table, num_queries);
+            (table, witness)
+        },
+    )
+}
+
+/// A strategy for a table together with a witness of between one and `max_queries` rows,
+/// where one row, chosen at random, is not in the table
+pub fn arb_invalid_lookup(
+    max_rows: usize,
+    max_queries: usize,
+) -> impl Strategy<Value = (Generic, (MultiSet, MultiSet, MultiSet))> {
+    assert!(
+        max_queries > 0,
+        "the witness needs room for the row which is not in the table"
+    );
+    // The rows of the table in the witness, which may be none
+    let valid_rows = (arb_table(max_rows), any::<[u8; 32]>(), 0..max_queries).prop_map(
+        |(table, seed, num_queries)| {
+            let witness = random_witness(&mut ChaChaRng::from_seed(seed), &table, num_queries);
+            (table, witness)
+        },
+    );
+    (valid_rows, (arb_fr(), arb_fr(), arb_fr()), any::<usize>()).prop_filter_map(
+        "the extra row is in the table",
+        |((table, (mut f_1, mut f_2, mut f_3)), (a, b, c), index)| {
+            if table.read(&(a, b)) == Some(c) {
+                return None;
+            }
+            let index = index % (f_1.len() + 1);
+            f_1.0.insert(index, a);
+            f_2.0.insert(index, b);
+            f_3.0.insert(index, c);
+            Some((table, (f_1, f_2, f_3)))
+        },
+    )
+}
+
+/// The values computed by the prover before it interpolates any polynomial
+pub struct Intermediates {
+    pub alpha: Fr,
+    pub beta: Fr,
+    pub gamma: Fr,
+    /// The merged and padded witness
+    pub f: MultiSet,
+    /// The merged table
+    pub t: MultiSet,
+    pub h_1: MultiSet,
+    pub h_2: MultiSet,
+    /// The evaluations of the accumulator Z(X) over the domain
+    pub z: Vec<Fr>,
+}
+
+impl Intermediates {
+    /// Computes the intermediate values for the witness (f_1, f_2, f_3) and the table, using the given challenges
+    pub fn compute(
+        f_1: &MultiSet,
+        f_2: &MultiSet,
+        f_3: &MultiSet,
+        preprocessed_table: &PreProcessedTable,
+        alpha: Fr,
+        beta: Fr,
+        gamma: Fr,
+    ) -> Intermediates {
+        let f = merge_witness(f_1, f_2, f_3, alpha, preprocessed_table.n);
+        let t = preprocessed_table.merged(alpha);
+        let (h_1, h_2) = multiset_equality::compute_h1_h2(&f, &t);
+        let z = multiset_equality::compute_accumulator_values(&f, &t, &h_1, &h_2, beta, gamma);
+
+        Intermediates {
+            alpha,
+            beta,
+            gamma,
+            f,
+            t,
+            h_1,
+            h_2,
+            z,
+        }
+    }
+    /// Writes every intermediate value on its own line, in the order that the prover computes them
+    pub fn dump(&self) -> String {
+        let mut out = String::new();
+        for (name, value) in vec![
+            ("alpha", &self.alpha),
+            ("beta", &self.beta),
+            ("gamma", &self.gamma),
+        ] {
+            writeln!(out, "{} = {}", name, to_hex_string(value)).unwrap();
+        }
+        for (name, values) in vec![
+            ("f", &self.f.0),
+            ("t", &self.t.0),
+            ("h_1", &self.h_1.0),
+            ("h_2", &self.h_2.0),
+            ("z", &self.z),
+        ] {
+            for (i, value) in values.iter().enumerate() {
+                writeln!(out, "{}[{}] = {}", name, i, to_hex_string(value)).unwrap();
+            }
+        }
+        out
+    }
+}
+
+/// Encodes a field element as `0x` followed by the 64 digit big-endian hex encoding of its canonical integer
+pub fn to_hex_string(value: &Fr) -> String {
+    fr_to_hex(value)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        kzg10,
+        lookup::{lookup::LookUp, proof::LookUpProof},
+    };
+    use algebra::{CanonicalDeserialize, CanonicalSerialize};
+    use merlin::Transcript;
+    use num_traits::identities::One;
+    use std::panic::{catch_unwind, AssertUnwindSafe};
+
+    #[test]
+    fn test_random_lookups_verify() {
+        let mut rng = ChaChaRng::from_seed([1u8; 32]);
+        let (proving_key, verifier_key) = kzg10::trusted_setup(2usize.pow(7), b"insecure_seed");
+
+        let table = random_table(&mut rng, 30);
+        let (f_1, f_2, f_3) = random_witness(&mut rng, &table, 20);
+        let preprocessed_table = table.preprocess(&proving_key, 2usize.pow(5));
+
+        let mut prover_transcript = Transcript::new(b"test");
+        let proof = LookUpProof::prove(
+            &f_1,
+            &f_2,
+            &f_3,
+            &proving_key,
+            &preprocessed_table,
+            &mut prover_transcript,
+        );
+        let mut verifier_transcript = Transcript::new(b"test");
+        assert!(proof.verify(&verifier_key, &preprocessed_table, &mut verifier_transcript));
+    }
+
+    #[test]
+    fn test_intermediates() {
+        let mut rng = ChaChaRng::from_seed([2u8; 32]);
+        let (proving_key, _) = kzg10::trusted_setup(2usize.pow(5), b"insecure_seed");
+
+        let table = random_table(&mut rng, 10);
+        let (f_1, f_2, f_3) = random_witness(&mut rng, &table, 5);
+        let preprocessed_table = table.preprocess(&proving_key, 2usize.pow(4));
+
+        let (alpha, beta, gamma) = (Fr::from(2u8), Fr::from(3u8), Fr::from(4u8));
+        let intermediates =
+            Intermediates::compute(&f_1, &f_2, &f_3, &preprocessed_table, alpha, beta, gamma);
+        assert_eq!(intermediates.f.len(), 15);
+        assert_eq!(intermediates.t.len(), 16);
+        assert_eq!(intermediates.z[0], Fr::one());
+        assert_eq!(*intermediates.z.last().unwrap(), Fr::one());
+
+        // The dump is stable, and has one line per value
+        let dump = intermediates.dump();
+        assert_eq!(dump, intermediates.dump());
+        let num_values = 3 + 15 + 16 + 2 * intermediates.h_1.len() + intermediates.z.len();
+        assert_eq!(dump.lines().count(), num_values);
+        assert!(dump.starts_with(&format!("alpha = 0x{:064x}\n", 2)));
+    }
+
+    proptest! {
+        #[test]
+        fn prop_invalid_lookup_with_one_query((table, (f_1, f_2, f_3)) in arb_invalid_lookup(1, 1)) {
+            // The only row of the witness is the one which is not in the table
+            prop_assert_eq!(f_1.len(), 1);
+            prop_assert!(table.read(&(f_1.0[0], f_2.0[0])) != Some(f_3.0[0]));
+        }
+    }
+
+    // Each case runs the prover, so only a few cases are tried
+    proptest! {
+        #![proptest_config(ProptestConfig::with_cases(8))]
+
+        #[test]
+        fn prop_subset_verifies((table, (f_1, f_2, f_3)) in arb_lookup(8, 8)) {
+            let (proving_key, verifier_key) = kzg10::trusted_setup(2usize.pow(6), b"insecure_seed");
+            let preprocessed_table = table.preprocess(&proving_key, 2usize.pow(4));
+
+            let mut prover_transcript = Transcript::new(b"test");
+            let proof = LookUpProof::prove(
+                &f_1,
+                &f_2,
+                &f_3,
+                &proving_key,
+                &preprocessed_table,
+                &mut prover_transcript,
+            );
+
+            let mut verifier_transcript = Transcript::new(b"test");
+            prop_assert!(proof.verify(
+                &verifier_key,
+                &preprocessed_table,
+                &mut verifier_transcript
+            ));
+        }
+
+        #[test]
+        fn prop_non_subset_rejected((table, (f_1, f_2, f_3)) in arb_invalid_lookup(8, 8)) {
+            let (proving_key, verifier_key) = kzg10::trusted_setup(2usize.pow(6), b"insecure_seed");
+            let preprocessed_table = table.preprocess(&proving_key, 2usize.pow(4));
+
+            let lookup = LookUp::from_wires(f_1.clone(), f_2.clone(), f_3.clone(), table);
+            prop_assert!(lookup.validate().is_err());
+
+            // The prover refuses to prove a witness which is not in the table;
+            // any proof it does produce must not verify
+            let proof = catch_unwind(AssertUnwindSafe(|| {
+                let mut prover_transcript = Transcript::new(b"test");
+                LookUpProof::prove(
+                    &f_1,
+                    &f_2,
+                    &f_3,
+                    &proving_key,
+                    &preprocessed_table,
+                    &mut prover_transcript,
+                )
+            }));
+            if let Ok(proof) = proof {
+                let mut verifier_transcript = Transcript::new(b"test");
+                prop_assert!(!proof.verify(
+                    &verifier_key,
+                    &preprocessed_table,
+                    &mut verifier_transcript
+                ));
+            }
+        }
+
+        #[test]
+        fn prop_multiset_serialization(set in arb_multiset(16)) {
+            let mut bytes = Vec::new();
+            set.serialize(&mut bytes).unwrap();
+            prop_assert_eq!(MultiSet::deserialize(&mut &bytes[..]).unwrap(), set);
+        }
+    }
+}
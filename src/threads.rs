@@ -0,0 +1,121 @@
+// Limiting the number of threads the prover uses
+//
+// With the `parallel` feature, the prover and the FFTs and MSMs of the backend all run on rayon's thread pools.
+// Unless told otherwise they use the global pool, which has one thread per core, so a prover in a shared service
+// would take every core while it runs. The whole process can be limited once with `set_global_thread_budget`,
+// and a single prover can be limited with `with_thread_budget`.
+use crate::error::Error;
+
+/// Sets the number of threads of the global thread pool, which provers use outside of `with_thread_budget`
+/// The global pool can only be configured once, before it is first used.
+/// Returns `Error::ThreadPool` if `threads` is zero, or if it has already been configured or used.
+/// Without the `parallel` feature the prover only uses the calling thread, and this only checks `threads`.
+pub fn set_global_thread_budget(threads: usize) -> Result<(), Error> {
+    check_budget(threads)?;
+    #[cfg(feature = "parallel")]
+    rayon::ThreadPoolBuilder::new()
+        .num_threads(threads)
+        .build_global()
+        .map_err(|err| Error::ThreadPool(err.to_string()))?;
+    Ok(())
+}
+
+/// Runs `f` on a thread pool of `threads` threads, which every parallel step inside it uses,
+/// including the FFTs and MSMs of the backend
+/// Returns `Error::ThreadPool` if `threads` is zero or the pool could not be created.
+/// Without the `parallel` feature `f` runs on the calling thread.
+pub fn with_thread_budget<R, F>(threads: usize, f: F) -> Result<R, Error>
+where
+    R: Send,
+    F: FnOnce() -> R + Send,
+{
+    check_budget(threads)?;
+    #[cfg(feature = "parallel")]
+    let result = build_pool(threads)?.install(f);
+    #[cfg(not(feature = "parallel"))]
+    let result = f();
+    Ok(result)
+}
+
+/// Builds a thread pool of `threads` threads, for a caller which runs on it many times
+#[cfg(feature = "parallel")]
+pub(crate) fn build_pool(threads: usize) -> Result<rayon::ThreadPool, Error> {
+    rayon::ThreadPoolBuilder::new()
+        .num_threads(threads)
+        .build()
+        .map_err(|err| Error::ThreadPool(err.to_string()))
+}
+
+// rayon reads a budget of zero as one thread per core, which is not what the caller asked for
+pub(crate) fn check_budget(threads: usize) -> Result<(), Error> {
+    if threads == 0 {
+        return Err(Error::ThreadPool(
+            "the thread budget must be at least one thread".to_string(),
+        ));
+    }
+    Ok(())
+}
+
+/// Returns the number of threads that a prover started here would use
+#[cfg(feature = "parallel")]
+pub fn current_thread_budget() -> usize {
+    rayon::current_num_threads()
+}
+
+/// Returns the number of threads that a prover started here would use
+#[cfg(not(feature = "parallel"))]
+pub fn current_thread_budget() -> usize {
+    1
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        kzg10,
+        lookup::{lookup::LookUp, table::four_bits::XOR4Bit, table::LookUpTable},
+    };
+    use merlin::Transcript;
+
+    #[test]
+    fn test_thread_budget() {
+        let (proving_key, verifier_key) = kzg10::trusted_setup(2usize.pow(10), b"insecure_seed");
+        let preprocessed_table = XOR4Bit::new().preprocess(&proving_key, 2usize.pow(8));
+        let mut lookup = LookUp::new(XOR4Bit::new());
+        lookup.read_u8(3, 5);
+
+        let (threads, proof) = with_thread_budget(2, || {
+            let mut prover_transcript = Transcript::new(b"lookup");
+            let proof = lookup.prove(&proving_key, &preprocessed_table, &mut prover_transcript);
+            (current_thread_budget(), proof)
+        })
+        .unwrap();
+        #[cfg(feature = "parallel")]
+        assert_eq!(threads, 2);
+        #[cfg(not(feature = "parallel"))]
+        assert_eq!(threads, 1);
+
+        let mut verifier_transcript = Transcript::new(b"lookup");
+        assert!(proof.verify(&verifier_key, &preprocessed_table, &mut verifier_transcript));
+    }
+
+    #[test]
+    fn test_empty_thread_budget() {
+        // Zero threads is rejected before the global pool is touched, so this does not configure it
+        assert!(matches!(
+            set_global_thread_budget(0),
+            Err(Error::ThreadPool(_))
+        ));
+        assert!(matches!(
+            with_thread_budget(0, || ()),
+            Err(Error::ThreadPool(_))
+        ));
+        assert!(matches!(
+            crate::kzg10::MsmPool::new(&crate::kzg10::MsmConfig {
+                threads: Some(0),
+                ..Default::default()
+            }),
+            Err(Error::ThreadPool(_))
+        ));
+    }
+}
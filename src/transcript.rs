@@ -1,8 +1,11 @@
+use crate::encoding::fr_to_bytes_le;
+use crate::lookup::{context::ProofContext, table::TableId};
 use algebra::bls12_381::Fr;
 use algebra::Bls12_381;
-use algebra::{to_bytes, ToBytes};
-use merlin::Transcript;
+use algebra::{to_bytes, PrimeField, ToBytes};
+use merlin::{Transcript, TranscriptRng};
 use poly_commit::kzg10::Commitment;
+use std::fmt;
 
 pub trait TranscriptProtocol {
     /// Append a `commitment` with the given `label`.
@@ -11,17 +14,59 @@ pub trait TranscriptProtocol {
     /// Append a `Scalar` with the given `label`.
     fn append_scalar(&mut self, label: &'static [u8], s: &Fr);
 
+    /// Append a `message` of bytes with the given `label`.
+    /// The default appends the length of the message and then each chunk of 16 bytes as a scalar,
+    /// so that transcripts which only absorb scalars can absorb bytes without a collision between messages.
+    fn append_message(&mut self, label: &'static [u8], message: &[u8]) {
+        self.append_scalar(label, &Fr::from(message.len() as u64));
+        for chunk in message.chunks(16) {
+            self.append_scalar(label, &Fr::from_le_bytes_mod_order(chunk));
+        }
+    }
+
+    /// Append a `TableId` with the given `label`.
+    fn append_table_id(&mut self, label: &'static [u8], id: &TableId) {
+        self.append_message(label, &id.0)
+    }
+
+    /// Append a `ProofContext` with the given `label`, as its canonical encoding.
+    fn append_context(&mut self, label: &'static [u8], context: &ProofContext) {
+        self.append_message(label, &context.to_bytes())
+    }
+
     /// Compute a `label`ed challenge variable.
     fn challenge_scalar(&mut self, label: &'static [u8]) -> Fr;
+
+    /// Builds the RNG used by the prover for its blinding factors.
+    /// The RNG is seeded from the transcript state, the `witness` and the thread RNG,
+    /// so the blinding factors are not predictable or repeated unless all three are.
+    /// This does not modify the transcript.
+    ///
+    /// The default cannot see the transcript state, so it only seeds the RNG from the `witness` and the thread RNG.
+    /// Implementors which can reach their state should bind the RNG to it, as the merlin transcript does.
+    fn build_prover_rng(&self, witness: &[u8]) -> TranscriptRng {
+        Transcript::new(b"plookup_prover_rng")
+            .build_rng()
+            .rekey_with_witness_bytes(b"prover_witness", witness)
+            .finalize(&mut rand::thread_rng())
+    }
 }
 
 impl TranscriptProtocol for Transcript {
+    fn append_message(&mut self, label: &'static [u8], message: &[u8]) {
+        Transcript::append_message(self, label, message)
+    }
+
     fn append_commitment(&mut self, label: &'static [u8], comm: &Commitment<Bls12_381>) {
         self.append_message(label, &to_bytes![comm].unwrap());
     }
 
     fn append_scalar(&mut self, label: &'static [u8], s: &Fr) {
-        self.append_message(label, &to_bytes![s].unwrap())
+        self.append_message(label, &fr_to_bytes_le(s))
+    }
+
+    fn append_context(&mut self, label: &'static [u8], context: &ProofContext) {
+        Transcript::append_message(self, label, &context.to_bytes())
     }
 
     fn challenge_scalar(&mut self, label: &'static [u8]) -> Fr {
@@ -35,4 +80,295 @@ impl TranscriptProtocol for Transcript {
         let mut rng = &mut self.build_rng().finalize(&mut ChaChaRng::from_seed(buf));
         Fr::rand(&mut rng)
     }
+
+    fn build_prover_rng(&self, witness: &[u8]) -> TranscriptRng {
+        self.build_rng()
+            .rekey_with_witness_bytes(b"prover_witness", witness)
+            .finalize(&mut rand::thread_rng())
+    }
+}
+
+/// Adapts a Fiat-Shamir oracle which is controlled by a surrounding protocol
+/// Every value is passed to `absorb` with its label and its canonical byte encoding,
+/// and every challenge is requested from `challenge` with its label.
+/// See `EqualityProof::prove` for the order in which this happens.
+pub struct ExternalTranscript<A, C>
+where
+    A: FnMut(&'static [u8], &[u8]),
+    C: FnMut(&'static [u8]) -> Fr,
+{
+    absorb: A,
+    challenge: C,
+    // Mirrors every absorbed value and challenge, so that the prover's RNG
+    // can be bound to the transcript state, as it is for a merlin transcript.
+    rng_transcript: Transcript,
+}
+
+impl<A, C> ExternalTranscript<A, C>
+where
+    A: FnMut(&'static [u8], &[u8]),
+    C: FnMut(&'static [u8]) -> Fr,
+{
+    pub fn new(absorb: A, challenge: C) -> Self {
+        ExternalTranscript {
+            absorb,
+            challenge,
+            rng_transcript: Transcript::new(b"external_transcript"),
+        }
+    }
+}
+
+impl<A, C> TranscriptProtocol for ExternalTranscript<A, C>
+where
+    A: FnMut(&'static [u8], &[u8]),
+    C: FnMut(&'static [u8]) -> Fr,
+{
+    fn append_commitment(&mut self, label: &'static [u8], comm: &Commitment<Bls12_381>) {
+        let bytes = to_bytes![comm].unwrap();
+        self.rng_transcript.append_message(label, &bytes);
+        (self.absorb)(label, &bytes)
+    }
+
+    fn append_scalar(&mut self, label: &'static [u8], s: &Fr) {
+        let bytes = fr_to_bytes_le(s).to_vec();
+        self.rng_transcript.append_message(label, &bytes);
+        (self.absorb)(label, &bytes)
+    }
+
+    fn append_message(&mut self, label: &'static [u8], message: &[u8]) {
+        self.rng_transcript.append_message(label, message);
+        (self.absorb)(label, message)
+    }
+
+    fn append_table_id(&mut self, label: &'static [u8], id: &TableId) {
+        self.rng_transcript.append_message(label, &id.0);
+        (self.absorb)(label, &id.0)
+    }
+
+    fn append_context(&mut self, label: &'static [u8], context: &ProofContext) {
+        let bytes = context.to_bytes();
+        self.rng_transcript.append_message(label, &bytes);
+        (self.absorb)(label, &bytes)
+    }
+
+    fn challenge_scalar(&mut self, label: &'static [u8]) -> Fr {
+        let challenge = (self.challenge)(label);
+        self.rng_transcript
+            .append_message(label, &fr_to_bytes_le(&challenge));
+        challenge
+    }
+
+    fn build_prover_rng(&self, witness: &[u8]) -> TranscriptRng {
+        self.rng_transcript.build_prover_rng(witness)
+    }
+}
+
+/// An operation on a transcript, as logged by `TranscriptRecorder`
+/// Values and challenges are logged with their label and their canonical byte encoding.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum TranscriptEvent {
+    Append {
+        label: &'static [u8],
+        bytes: Vec<u8>,
+    },
+    Challenge {
+        label: &'static [u8],
+        bytes: Vec<u8>,
+    },
+}
+
+impl fmt::Display for TranscriptEvent {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let (kind, label, bytes) = match self {
+            TranscriptEvent::Append { label, bytes } => ("append", label, bytes),
+            TranscriptEvent::Challenge { label, bytes } => ("challenge", label, bytes),
+        };
+        write!(f, "{} {}: ", kind, String::from_utf8_lossy(label))?;
+        for byte in bytes.iter() {
+            write!(f, "{:02x}", byte)?;
+        }
+        Ok(())
+    }
+}
+
+/// Wraps a transcript and logs every value appended to it and every challenge drawn from it
+///
+/// Recording both the prover's and the verifier's transcripts and comparing the logs with `diff_logs`
+/// shows the first point at which they diverge, when the verifier's challenges do not match the prover's.
+pub struct TranscriptRecorder<T: TranscriptProtocol> {
+    inner: T,
+    log: Vec<TranscriptEvent>,
+}
+
+impl<T: TranscriptProtocol> TranscriptRecorder<T> {
+    pub fn new(inner: T) -> Self {
+        TranscriptRecorder {
+            inner,
+            log: Vec::new(),
+        }
+    }
+    /// Returns the events recorded so far
+    pub fn log(&self) -> &[TranscriptEvent] {
+        &self.log
+    }
+    /// Returns the wrapped transcript and the recorded events
+    pub fn into_parts(self) -> (T, Vec<TranscriptEvent>) {
+        (self.inner, self.log)
+    }
+}
+
+impl<T: TranscriptProtocol> TranscriptProtocol for TranscriptRecorder<T> {
+    fn append_commitment(&mut self, label: &'static [u8], comm: &Commitment<Bls12_381>) {
+        let bytes = to_bytes![comm].unwrap();
+        self.log.push(TranscriptEvent::Append { label, bytes });
+        self.inner.append_commitment(label, comm)
+    }
+
+    fn append_scalar(&mut self, label: &'static [u8], s: &Fr) {
+        let bytes = fr_to_bytes_le(s).to_vec();
+        self.log.push(TranscriptEvent::Append { label, bytes });
+        self.inner.append_scalar(label, s)
+    }
+
+    fn append_message(&mut self, label: &'static [u8], message: &[u8]) {
+        let bytes = message.to_vec();
+        self.log.push(TranscriptEvent::Append { label, bytes });
+        self.inner.append_message(label, message)
+    }
+
+    fn append_table_id(&mut self, label: &'static [u8], id: &TableId) {
+        let bytes = id.0.to_vec();
+        self.log.push(TranscriptEvent::Append { label, bytes });
+        self.inner.append_table_id(label, id)
+    }
+
+    fn append_context(&mut self, label: &'static [u8], context: &ProofContext) {
+        let bytes = context.to_bytes();
+        self.log.push(TranscriptEvent::Append { label, bytes });
+        self.inner.append_context(label, context)
+    }
+
+    fn challenge_scalar(&mut self, label: &'static [u8]) -> Fr {
+        let challenge = self.inner.challenge_scalar(label);
+        let bytes = fr_to_bytes_le(&challenge).to_vec();
+        self.log.push(TranscriptEvent::Challenge { label, bytes });
+        challenge
+    }
+
+    fn build_prover_rng(&self, witness: &[u8]) -> TranscriptRng {
+        self.inner.build_prover_rng(witness)
+    }
+}
+
+/// The first point at which two transcript logs diverge
+/// An event is `None` if its log ended before the point of divergence.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TranscriptDiff {
+    pub index: usize,
+    pub prover: Option<TranscriptEvent>,
+    pub verifier: Option<TranscriptEvent>,
+}
+
+impl fmt::Display for TranscriptDiff {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "transcripts diverge at event {}", self.index)?;
+        match &self.prover {
+            Some(event) => writeln!(f, "  prover:   {}", event)?,
+            None => writeln!(f, "  prover:   <end of log>")?,
+        }
+        match &self.verifier {
+            Some(event) => write!(f, "  verifier: {}", event),
+            None => write!(f, "  verifier: <end of log>"),
+        }
+    }
+}
+
+/// Compares the logs of the prover's and the verifier's transcripts
+/// Returns the first event at which they differ, or `None` if the logs are the same.
+/// Every challenge after the first difference will also differ, so only the first one is reported.
+pub fn diff_logs(
+    prover: &[TranscriptEvent],
+    verifier: &[TranscriptEvent],
+) -> Option<TranscriptDiff> {
+    let len = std::cmp::max(prover.len(), verifier.len());
+    (0..len)
+        .find(|&i| prover.get(i) != verifier.get(i))
+        .map(|index| TranscriptDiff {
+            index,
+            prover: prover.get(index).cloned(),
+            verifier: verifier.get(index).cloned(),
+        })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    // A transcript which only implements the required methods, and keeps the scalars appended to it
+    struct ScalarTranscript(Vec<Fr>);
+
+    impl TranscriptProtocol for ScalarTranscript {
+        fn append_commitment(&mut self, _: &'static [u8], _: &Commitment<Bls12_381>) {}
+        fn append_scalar(&mut self, _: &'static [u8], s: &Fr) {
+            self.0.push(*s)
+        }
+        fn challenge_scalar(&mut self, _: &'static [u8]) -> Fr {
+            Fr::from(self.0.len() as u64)
+        }
+    }
+
+    #[test]
+    fn test_default_methods() {
+        let mut transcript = ScalarTranscript(Vec::new());
+        let mut id = TableId([0u8; 32]);
+        id.0[16] = 1;
+        transcript.append_table_id(b"table_id", &id);
+        assert_eq!(
+            transcript.0,
+            vec![Fr::from(32u8), Fr::from(0u8), Fr::from(1u8)]
+        );
+
+        // The empty context is the two zero lengths of its fields, which fit in one chunk
+        transcript.0.clear();
+        transcript.append_context(b"context", &ProofContext::default());
+        assert_eq!(transcript.0, vec![Fr::from(16u8), Fr::from(0u8)]);
+
+        // The RNG is not derived from the transcript, but is still random
+        let mut rng_a = transcript.build_prover_rng(b"witness");
+        let mut rng_b = transcript.build_prover_rng(b"witness");
+        assert_ne!(
+            rand_core::RngCore::next_u64(&mut rng_a),
+            rand_core::RngCore::next_u64(&mut rng_b)
+        );
+    }
+
+    #[test]
+    fn test_diff_logs() {
+        let mut prover = TranscriptRecorder::new(Transcript::new(b"test"));
+        let mut verifier = TranscriptRecorder::new(Transcript::new(b"test"));
+
+        for transcript in vec![&mut prover, &mut verifier] {
+            transcript.append_scalar(b"a", &Fr::from(1u8));
+            transcript.challenge_scalar(b"b");
+        }
+        assert_eq!(prover.log().len(), 2);
+        assert_eq!(diff_logs(prover.log(), verifier.log()), None);
+
+        // The verifier appends a different value, so every later challenge differs
+        prover.append_scalar(b"c", &Fr::from(2u8));
+        verifier.append_scalar(b"c", &Fr::from(3u8));
+        prover.challenge_scalar(b"d");
+        verifier.challenge_scalar(b"d");
+
+        let diff = diff_logs(prover.log(), verifier.log()).unwrap();
+        assert_eq!(diff.index, 2);
+        assert_eq!(diff.prover, Some(prover.log()[2].clone()));
+        assert_eq!(diff.verifier, Some(verifier.log()[2].clone()));
+
+        // A log which ends early diverges where it ends
+        let (_, log) = prover.into_parts();
+        let diff = diff_logs(&log, &log[..3]).unwrap();
+        assert_eq!(diff.index, 3);
+        assert_eq!(diff.verifier, None);
+    }
 }
@@ -4,9 +4,13 @@ use algebra::bls12_381::Fr;
 use merlin::Transcript;
 use plookup::kzg10::trusted_setup;
 use plookup::lookup::{
-    lookup::LookUp, table::four_bits::XOR4Bit, table::Generic, table::LookUpTable,
+    lookup::LookUp,
+    proof::{LookUpProof, ProofMode, ProveOptions},
+    table::four_bits::XOR4Bit,
+    table::Generic,
+    table::LookUpTable,
 };
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 
 #[test]
 fn test_xor_four_bit_lookup() {
@@ -102,3 +106,1208 @@ fn test_mul_four_bit_lookup() {
     let ok = proof.verify(&verifier_key, &preprocessed_table, &mut verifier_transcript);
     assert!(ok);
 }
+
+#[test]
+fn test_proof_encodings() {
+    let (prover_key, verifier_key) = trusted_setup(2usize.pow(10), b"insecure_seeding_setup");
+
+    let table = XOR4Bit::new();
+    let preprocessed_table = table.preprocess(&prover_key, 2usize.pow(8));
+
+    let mut lookup = LookUp::new(table);
+    lookup.read(&(Fr::from(1u8), Fr::from(2u8)));
+    lookup.read(&(Fr::from(7u8), Fr::from(9u8)));
+
+    let mut prover_transcript = Transcript::new(b"lookup");
+    let proof = lookup.prove(&prover_key, &preprocessed_table, &mut prover_transcript);
+
+    // The mode, the table identifier, 7 G1 elements, 9 field elements,
+    // the public parameters and the lengths of the empty context
+    let compressed = proof.to_compressed_bytes();
+    assert_eq!(compressed.len(), 1 + 32 + 7 * 48 + 9 * 32 + 33 + 16);
+    let uncompressed = proof.to_uncompressed_bytes();
+    assert_eq!(uncompressed.len(), 1 + 32 + 7 * 96 + 9 * 32 + 33 + 16);
+
+    let from_compressed = LookUpProof::from_compressed_bytes(&compressed).unwrap();
+    let from_uncompressed = LookUpProof::from_uncompressed_bytes(&uncompressed).unwrap();
+
+    // Decoding gives back the same proof
+    assert_eq!(from_compressed, proof);
+    assert_eq!(from_uncompressed, proof);
+
+    for decoded in vec![from_compressed, from_uncompressed] {
+        let mut verifier_transcript = Transcript::new(b"lookup");
+        let ok = decoded.verify(&verifier_key, &preprocessed_table, &mut verifier_transcript);
+        assert!(ok);
+    }
+
+    // A truncated proof is rejected
+    assert!(LookUpProof::from_compressed_bytes(&compressed[..100]).is_err());
+
+    // The compressed form can be encoded as text
+    assert_eq!(LookUpProof::from_hex(&proof.to_hex()).unwrap(), proof);
+    assert_eq!(LookUpProof::from_base64(&proof.to_base64()).unwrap(), proof);
+    assert!(LookUpProof::from_hex("zz").is_err());
+
+    // Proofs can be deduplicated
+    let mut other_transcript = Transcript::new(b"lookup");
+    let other_proof = lookup.prove(&prover_key, &preprocessed_table, &mut other_transcript);
+    let proofs: HashSet<_> = vec![proof.clone(), proof, other_proof]
+        .into_iter()
+        .collect();
+    assert_eq!(proofs.len(), 2);
+}
+
+#[test]
+fn test_external_transcript() {
+    use plookup::transcript::{ExternalTranscript, TranscriptProtocol};
+    use std::cell::RefCell;
+
+    let (prover_key, verifier_key) = trusted_setup(2usize.pow(10), b"insecure_seeding_setup");
+
+    let table = XOR4Bit::new();
+    let preprocessed_table = table.preprocess(&prover_key, 2usize.pow(8));
+
+    let mut lookup = LookUp::new(table);
+    lookup.read(&(Fr::from(3u8), Fr::from(12u8)));
+
+    // The surrounding protocol owns the transcript and has already absorbed its own messages
+    let outer_transcript = || {
+        let mut transcript = Transcript::new(b"outer_protocol");
+        transcript.append_message(b"other_sub_proof", b"previous prover messages");
+        RefCell::new(transcript)
+    };
+
+    let prover_oracle = outer_transcript();
+    let mut prover_transcript = ExternalTranscript::new(
+        |label, bytes| prover_oracle.borrow_mut().append_message(label, bytes),
+        |label| prover_oracle.borrow_mut().challenge_scalar(label),
+    );
+    let proof = lookup.prove(&prover_key, &preprocessed_table, &mut prover_transcript);
+
+    let verifier_oracle = outer_transcript();
+    let mut verifier_transcript = ExternalTranscript::new(
+        |label, bytes| verifier_oracle.borrow_mut().append_message(label, bytes),
+        |label| verifier_oracle.borrow_mut().challenge_scalar(label),
+    );
+    let ok = proof.verify(&verifier_key, &preprocessed_table, &mut verifier_transcript);
+    assert!(ok);
+
+    // An oracle which has absorbed different messages yields different challenges
+    let mut other_transcript = Transcript::new(b"outer_protocol");
+    let ok = proof.verify(&verifier_key, &preprocessed_table, &mut other_transcript);
+    assert!(!ok);
+}
+
+#[test]
+fn test_prover_checkpoints() {
+    use plookup::error::Error;
+    use plookup::multiset::{report_progress, CancellationToken, ProverStage};
+
+    let (prover_key, verifier_key) = trusted_setup(2usize.pow(10), b"insecure_seeding_setup");
+
+    let table = XOR4Bit::new();
+    let preprocessed_table = table.preprocess(&prover_key, 2usize.pow(8));
+
+    let mut lookup = LookUp::new(table);
+    lookup.read(&(Fr::from(5u8), Fr::from(6u8)));
+
+    // The checkpoint is called before every stage, in order
+    let mut stages = Vec::new();
+    let proof = lookup
+        .prove_with_options(
+            &prover_key,
+            &preprocessed_table,
+            ProveOptions::new().checkpoint(&mut |stage| {
+                stages.push(stage);
+                Ok(())
+            }),
+            &mut Transcript::new(b"lookup"),
+        )
+        .unwrap();
+    assert_eq!(
+        stages,
+        vec![
+            ProverStage::Aggregation,
+            ProverStage::Sorting,
+            ProverStage::HPolynomials,
+            ProverStage::Accumulator,
+            ProverStage::Quotient,
+            ProverStage::Commitments,
+            ProverStage::Openings,
+        ]
+    );
+    let mut verifier_transcript = Transcript::new(b"lookup");
+    assert!(proof.verify(&verifier_key, &preprocessed_table, &mut verifier_transcript));
+
+    // Progress is reported as an increasing percentage
+    let mut percentages = Vec::new();
+    lookup
+        .prove_with_options(
+            &prover_key,
+            &preprocessed_table,
+            ProveOptions::new()
+                .checkpoint(&mut report_progress(|_, percent| percentages.push(percent))),
+            &mut Transcript::new(b"lookup"),
+        )
+        .unwrap();
+    assert_eq!(percentages.len(), 7);
+    assert_eq!(percentages[0], 0);
+    assert!(percentages.windows(2).all(|pair| pair[0] < pair[1]));
+    assert!(percentages[6] < 100);
+
+    // A token which is already cancelled stops the prover before it starts
+    let token = CancellationToken::new();
+    token.cancel();
+    let result = lookup.prove_with_options(
+        &prover_key,
+        &preprocessed_table,
+        ProveOptions::new().checkpoint(&mut |stage| token.check(stage)),
+        &mut Transcript::new(b"lookup"),
+    );
+    match result {
+        Err(Error::Cancelled(ProverStage::Aggregation)) => {}
+        _ => panic!("expected the proof to be cancelled"),
+    }
+
+    // A token cancelled while proving stops the prover at the next stage
+    let token = CancellationToken::new();
+    let result = lookup.prove_with_options(
+        &prover_key,
+        &preprocessed_table,
+        ProveOptions::new().checkpoint(&mut |stage| {
+            if stage == ProverStage::Accumulator {
+                token.cancel();
+            }
+            token.check(stage)
+        }),
+        &mut Transcript::new(b"lookup"),
+    );
+    match result {
+        Err(Error::Cancelled(ProverStage::Accumulator)) => {}
+        _ => panic!("expected the proof to be cancelled"),
+    }
+}
+
+#[test]
+fn test_transcript_recorder() {
+    use plookup::lookup::table::four_bits::Add4Bit;
+    use plookup::transcript::{diff_logs, TranscriptEvent, TranscriptRecorder};
+
+    let (prover_key, verifier_key) = trusted_setup(2usize.pow(10), b"insecure_seeding_setup");
+
+    let xor_table = XOR4Bit::new();
+    let preprocessed_xor = xor_table.preprocess(&prover_key, 2usize.pow(8));
+    let preprocessed_add = Add4Bit::new().preprocess(&prover_key, 2usize.pow(8));
+
+    let mut lookup = LookUp::new(xor_table);
+    lookup.read(&(Fr::from(1u8), Fr::from(2u8)));
+
+    let mut prover_transcript = TranscriptRecorder::new(Transcript::new(b"lookup"));
+    let proof = lookup.prove(&prover_key, &preprocessed_xor, &mut prover_transcript);
+
+    // The verifier's transcript matches the prover's
+    let mut verifier_transcript = TranscriptRecorder::new(Transcript::new(b"lookup"));
+    assert!(proof.verify(&verifier_key, &preprocessed_xor, &mut verifier_transcript));
+    assert_eq!(
+        diff_logs(prover_transcript.log(), verifier_transcript.log()),
+        None
+    );
+
+    // A proof made against another table is rejected before the verifier appends anything
+    let mut verifier_transcript = TranscriptRecorder::new(Transcript::new(b"lookup"));
+    assert!(!proof.verify(&verifier_key, &preprocessed_add, &mut verifier_transcript));
+    let diff = diff_logs(prover_transcript.log(), verifier_transcript.log()).unwrap();
+    assert_eq!(diff.index, 0);
+    assert_eq!(diff.verifier, None);
+
+    let mut verifier_transcript = TranscriptRecorder::new(Transcript::new(b"other"));
+    assert!(!proof.verify(&verifier_key, &preprocessed_xor, &mut verifier_transcript));
+    let diff = diff_logs(prover_transcript.log(), verifier_transcript.log()).unwrap();
+    // The first challenge differs, as the transcripts were initialised with different labels
+    // It is drawn after the context, the public parameters, the table identifier and the SRS digest are absorbed
+    assert_eq!(diff.index, 8);
+    match diff.verifier {
+        Some(TranscriptEvent::Challenge { label, .. }) => assert_eq!(label, b"alpha"),
+        _ => panic!("expected the challenge alpha to differ"),
+    }
+}
+
+#[test]
+fn test_proofs_are_blinded() {
+    let (prover_key, verifier_key) = trusted_setup(2usize.pow(10), b"insecure_seeding_setup");
+
+    let table = XOR4Bit::new();
+    let preprocessed_table = table.preprocess(&prover_key, 2usize.pow(8));
+
+    let mut lookup = LookUp::new(table);
+    lookup.read(&(Fr::from(5u8), Fr::from(6u8)));
+
+    // Proving the same witness twice, with the same transcript, gives different commitments
+    let mut proofs = Vec::new();
+    for _ in 0..2 {
+        let mut prover_transcript = Transcript::new(b"lookup");
+        let proof = lookup.prove(&prover_key, &preprocessed_table, &mut prover_transcript);
+
+        let mut verifier_transcript = Transcript::new(b"lookup");
+        assert!(proof.verify(&verifier_key, &preprocessed_table, &mut verifier_transcript));
+        proofs.push(proof);
+    }
+
+    let first = &proofs[0].multiset_equality_proof.commitments;
+    let second = &proofs[1].multiset_equality_proof.commitments;
+    assert_ne!(first.f, second.f);
+    assert_ne!(first.h_1, second.h_1);
+    assert_ne!(first.h_2, second.h_2);
+    assert_ne!(first.z, second.z);
+}
+
+#[test]
+fn test_configurable_blinding() {
+    use plookup::lookup::context::ProofContext;
+    use plookup::multiset::ProverConfig;
+
+    let (prover_key, verifier_key) = trusted_setup(2usize.pow(10), b"insecure_seeding_setup");
+
+    let table = XOR4Bit::new();
+    let preprocessed_table = table.preprocess(&prover_key, 2usize.pow(8));
+
+    let mut lookup = LookUp::new(table);
+    lookup.read(&(Fr::from(5u8), Fr::from(6u8)));
+    lookup.read(&(Fr::from(7u8), Fr::from(8u8)));
+
+    // Blinding for more openings gives a proof which verifies against the same config
+    let config = ProverConfig::with_extra_openings(4);
+    assert_eq!(config.f_blinding_factors, 6);
+    assert_eq!(config.shifted_blinding_factors, 7);
+
+    let mut prover_transcript = Transcript::new(b"lookup");
+    let proof = lookup
+        .prove_with_options(
+            &prover_key,
+            &preprocessed_table,
+            ProveOptions::new().config(&config),
+            &mut prover_transcript,
+        )
+        .unwrap();
+
+    let mut verifier_transcript = Transcript::new(b"lookup");
+    assert!(proof.verify_with_config(
+        &verifier_key,
+        &preprocessed_table,
+        &ProofContext::default(),
+        &config,
+        &mut verifier_transcript
+    ));
+}
+
+#[test]
+fn test_tuned_msm() {
+    use plookup::{error::Error, kzg10::MsmConfig, multiset::ProverConfig};
+
+    let (prover_key, verifier_key) = trusted_setup(2usize.pow(10), b"insecure_seeding_setup");
+
+    let table = XOR4Bit::new();
+    let preprocessed_table = table.preprocess(&prover_key, 2usize.pow(8));
+
+    let mut lookup = LookUp::new(table);
+    lookup.read(&(Fr::from(9u8), Fr::from(3u8)));
+    lookup.read(&(Fr::from(15u8), Fr::from(0u8)));
+
+    // The commitments are the same for any tuning, so the proof verifies as usual
+    let config = ProverConfig {
+        msm: MsmConfig {
+            window_bits: Some(8),
+            threads: Some(2),
+            chunk_size: Some(64),
+        },
+        ..ProverConfig::default()
+    };
+    let mut prover_transcript = Transcript::new(b"lookup");
+    let proof = lookup
+        .prove_with_options(
+            &prover_key,
+            &preprocessed_table,
+            ProveOptions::new().config(&config),
+            &mut prover_transcript,
+        )
+        .unwrap();
+
+    let mut verifier_transcript = Transcript::new(b"lookup");
+    assert!(proof.verify(&verifier_key, &preprocessed_table, &mut verifier_transcript));
+
+    // Settings which cannot be used are returned as errors rather than panicking
+    let mut prove_with_msm = |msm| {
+        let config = ProverConfig {
+            msm,
+            ..ProverConfig::default()
+        };
+        let mut prover_transcript = Transcript::new(b"lookup");
+        lookup.prove_with_options(
+            &prover_key,
+            &preprocessed_table,
+            ProveOptions::new().config(&config),
+            &mut prover_transcript,
+        )
+    };
+    assert!(matches!(
+        prove_with_msm(MsmConfig {
+            threads: Some(0),
+            ..MsmConfig::default()
+        }),
+        Err(Error::ThreadPool(_))
+    ));
+    assert!(matches!(
+        prove_with_msm(MsmConfig {
+            window_bits: Some(0),
+            ..MsmConfig::default()
+        }),
+        Err(Error::InvalidWindow(0))
+    ));
+}
+
+#[test]
+fn test_audit_mode() {
+    use plookup::multiset::ProverConfig;
+
+    let (prover_key, verifier_key) = trusted_setup(2usize.pow(10), b"insecure_seeding_setup");
+
+    let table = XOR4Bit::new();
+    let preprocessed_table = table.preprocess(&prover_key, 2usize.pow(8));
+
+    let mut lookup = LookUp::new(table);
+    lookup.read(&(Fr::from(9u8), Fr::from(3u8)));
+    lookup.read(&(Fr::from(15u8), Fr::from(0u8)));
+
+    // The identity holds at the audited points, so the proof is output as usual
+    let config = ProverConfig {
+        audit_points: 3,
+        ..ProverConfig::default()
+    };
+    let mut prover_transcript = Transcript::new(b"lookup");
+    let proof = lookup
+        .prove_with_options(
+            &prover_key,
+            &preprocessed_table,
+            ProveOptions::new().config(&config),
+            &mut prover_transcript,
+        )
+        .unwrap();
+
+    let mut verifier_transcript = Transcript::new(b"lookup");
+    assert!(proof.verify(&verifier_key, &preprocessed_table, &mut verifier_transcript));
+}
+
+#[test]
+fn test_proof_is_bound_to_parameters() {
+    use plookup::lookup::context::ProofContext;
+    use plookup::multiset::{ProverConfig, PARAMETER_LABELS, PROTOCOL_VERSION};
+    use plookup::transcript::{TranscriptEvent, TranscriptRecorder};
+
+    let (prover_key, verifier_key) = trusted_setup(2usize.pow(10), b"insecure_seeding_setup");
+
+    let table = XOR4Bit::new();
+    let preprocessed_table = table.preprocess(&prover_key, 2usize.pow(8));
+
+    let mut lookup = LookUp::new(table);
+    lookup.read(&(Fr::from(5u8), Fr::from(6u8)));
+
+    let mut prover_transcript = TranscriptRecorder::new(Transcript::new(b"lookup"));
+    let proof = lookup
+        .prove_with_options(
+            &prover_key,
+            &preprocessed_table,
+            ProveOptions::new().config(&ProverConfig::with_extra_openings(1)),
+            &mut prover_transcript,
+        )
+        .unwrap();
+    let parameters = proof.multiset_equality_proof.parameters;
+    assert_eq!(parameters.version, PROTOCOL_VERSION);
+    assert_eq!(parameters.n, 2usize.pow(8));
+    assert_eq!(parameters.shifted_blinding_factors, 4);
+    assert!(!parameters.committed_f);
+
+    // The parameters are absorbed before anything else, apart from the context
+    let log = &prover_transcript.log()[1..];
+    for (event, label) in log.iter().zip(PARAMETER_LABELS.iter()) {
+        match event {
+            TranscriptEvent::Append {
+                label: absorbed, ..
+            } => {
+                assert_eq!(*absorbed, label.as_bytes())
+            }
+            _ => panic!("expected the parameters to be absorbed before any challenge"),
+        }
+    }
+
+    let config = ProverConfig::with_extra_openings(1);
+    let verify = |proof: &LookUpProof, config: &ProverConfig| {
+        let mut verifier_transcript = Transcript::new(b"lookup");
+        proof.verify_with_config(
+            &verifier_key,
+            &preprocessed_table,
+            &ProofContext::default(),
+            config,
+            &mut verifier_transcript,
+        )
+    };
+    assert!(verify(&proof, &config));
+
+    // The verifier compares the parameters with its own, so a proof made with another config is rejected
+    assert!(!verify(&proof, &ProverConfig::default()));
+
+    // A proof for another version of the protocol is rejected
+    let mut other_version = proof.clone();
+    other_version.multiset_equality_proof.parameters.version += 1;
+    assert!(!verify(&other_version, &config));
+
+    // As is a proof which claims more blinding factors than the verifier expects
+    let mut other_blinding = proof.clone();
+    other_blinding
+        .multiset_equality_proof
+        .parameters
+        .shifted_blinding_factors += 1;
+    assert!(!verify(&other_blinding, &config));
+
+    // Or fewer than the prover adds
+    let mut unblinded = proof.clone();
+    unblinded
+        .multiset_equality_proof
+        .parameters
+        .f_blinding_factors = 2;
+    assert!(!verify(&unblinded, &config));
+
+    // The parameters are serialised with the proof
+    let decoded = LookUpProof::from_compressed_bytes(&proof.to_compressed_bytes()).unwrap();
+    assert_eq!(decoded, proof);
+}
+
+#[test]
+fn test_proof_is_bound_to_srs() {
+    use plookup::multiset::PARAMETER_LABELS;
+    use plookup::transcript::{TranscriptEvent, TranscriptRecorder};
+
+    let (prover_key, verifier_key) = trusted_setup(2usize.pow(10), b"insecure_seeding_setup");
+
+    let table = XOR4Bit::new();
+    let preprocessed_table = table.preprocess(&prover_key, 2usize.pow(8));
+
+    let mut lookup = LookUp::new(table);
+    lookup.read(&(Fr::from(5u8), Fr::from(6u8)));
+
+    let mut prover_transcript = TranscriptRecorder::new(Transcript::new(b"lookup"));
+    let proof = lookup.prove(&prover_key, &preprocessed_table, &mut prover_transcript);
+
+    // The digest of the SRS is absorbed right after the table identifier
+    let position = 1 + PARAMETER_LABELS.len() + 1;
+    match &prover_transcript.log()[position] {
+        TranscriptEvent::Append { label, bytes } => {
+            assert_eq!(*label, b"srs_digest");
+            assert_eq!(&bytes[..], &preprocessed_table.srs_digest[..]);
+        }
+        _ => panic!("expected the digest of the SRS to be absorbed"),
+    }
+
+    let mut verifier_transcript = Transcript::new(b"lookup");
+    assert!(proof.verify(&verifier_key, &preprocessed_table, &mut verifier_transcript));
+
+    // The same table committed with another SRS has another digest, and the proof is rejected against it
+    let (other_prover_key, other_verifier_key) =
+        trusted_setup(2usize.pow(10), b"other_seeding_setup");
+    let other_table = XOR4Bit::new().preprocess(&other_prover_key, 2usize.pow(8));
+    assert_eq!(other_table.id, preprocessed_table.id);
+    assert_ne!(other_table.srs_digest, preprocessed_table.srs_digest);
+    let mut verifier_transcript = Transcript::new(b"lookup");
+    assert!(!proof.verify(&other_verifier_key, &other_table, &mut verifier_transcript));
+
+    // Changing only the digest changes every challenge
+    let mut relabelled = preprocessed_table.clone();
+    relabelled.srs_digest = other_table.srs_digest;
+    let mut verifier_transcript = Transcript::new(b"lookup");
+    assert!(!proof.verify(&verifier_key, &relabelled, &mut verifier_transcript));
+}
+
+#[test]
+fn test_proof_context() {
+    use plookup::lookup::context::ProofContext;
+
+    let (prover_key, verifier_key) = trusted_setup(2usize.pow(10), b"insecure_seeding_setup");
+
+    let table = XOR4Bit::new();
+    let preprocessed_table = table.preprocess(&prover_key, 2usize.pow(8));
+
+    let mut lookup = LookUp::new(table);
+    lookup.read(&(Fr::from(5u8), Fr::from(6u8)));
+
+    let context = ProofContext::new(b"app", b"session 1");
+    let mut prover_transcript = Transcript::new(b"lookup");
+    let proof = lookup
+        .prove_with_options(
+            &prover_key,
+            &preprocessed_table,
+            ProveOptions::new().context(&context),
+            &mut prover_transcript,
+        )
+        .unwrap();
+    assert_eq!(proof.context, context);
+
+    let mut verifier_transcript = Transcript::new(b"lookup");
+    assert!(proof.verify_in_context(
+        &verifier_key,
+        &preprocessed_table,
+        &context,
+        &mut verifier_transcript
+    ));
+
+    // The proof is rejected in another session, in another application and without a context
+    for other in &[
+        ProofContext::new(b"app", b"session 2"),
+        ProofContext::new(b"other app", b"session 1"),
+        ProofContext::default(),
+    ] {
+        let mut verifier_transcript = Transcript::new(b"lookup");
+        assert!(!proof.verify_in_context(
+            &verifier_key,
+            &preprocessed_table,
+            other,
+            &mut verifier_transcript
+        ));
+    }
+    let mut verifier_transcript = Transcript::new(b"lookup");
+    assert!(!proof.verify(&verifier_key, &preprocessed_table, &mut verifier_transcript));
+
+    // Relabelling the proof with another context does not make it verify there
+    let mut relabelled = proof.clone();
+    relabelled.context = ProofContext::new(b"app", b"session 2");
+    let mut verifier_transcript = Transcript::new(b"lookup");
+    assert!(!relabelled.verify_in_context(
+        &verifier_key,
+        &preprocessed_table,
+        &relabelled.context,
+        &mut verifier_transcript
+    ));
+
+    // The context is serialised with the proof
+    let decoded = LookUpProof::from_compressed_bytes(&proof.to_compressed_bytes()).unwrap();
+    assert_eq!(decoded, proof);
+
+    // A self-contained proof is verified against the allowed tables in its context, and rejected without one
+    let mut prover_transcript = Transcript::new(b"lookup");
+    let proof = lookup
+        .prove_with_options(
+            &prover_key,
+            &preprocessed_table,
+            ProveOptions::new()
+                .mode(ProofMode::SelfContained)
+                .context(&context),
+            &mut prover_transcript,
+        )
+        .unwrap();
+    let allowed_tables = vec![preprocessed_table.commitments()];
+    let mut verifier_transcript = Transcript::new(b"lookup");
+    assert!(proof.verify_self_contained_in_context(
+        &verifier_key,
+        &allowed_tables,
+        &context,
+        &mut verifier_transcript
+    ));
+    let mut verifier_transcript = Transcript::new(b"lookup");
+    assert!(!proof.verify_self_contained(&verifier_key, &allowed_tables, &mut verifier_transcript));
+}
+
+#[test]
+fn test_proof_is_bound_to_witness_commitment() {
+    use plookup::transcript::{TranscriptEvent, TranscriptRecorder};
+
+    let (prover_key, verifier_key) = trusted_setup(2usize.pow(10), b"insecure_seeding_setup");
+    let preprocessed_table = XOR4Bit::new().preprocess(&prover_key, 2usize.pow(8));
+
+    let mut lookup = LookUp::new(XOR4Bit::new());
+    lookup.read(&(Fr::from(5u8), Fr::from(6u8)));
+    let mut prover_transcript = TranscriptRecorder::new(Transcript::new(b"lookup"));
+    let proof = lookup.prove(&prover_key, &preprocessed_table, &mut prover_transcript);
+
+    // The commitment to the folded witness is absorbed before the challenges of the equality proof
+    let position = |wanted: &[u8]| {
+        prover_transcript
+            .log()
+            .iter()
+            .position(|event| match event {
+                TranscriptEvent::Append { label, .. }
+                | TranscriptEvent::Challenge { label, .. } => *label == wanted,
+            })
+            .unwrap()
+    };
+    assert!(position(b"alpha") < position(b"f"));
+    assert!(position(b"f") < position(b"beta"));
+
+    // A proof whose witness commitment is replaced with that of another witness is rejected
+    let mut other_lookup = LookUp::new(XOR4Bit::new());
+    other_lookup.read(&(Fr::from(1u8), Fr::from(2u8)));
+    let mut prover_transcript = Transcript::new(b"lookup");
+    let other_proof = other_lookup.prove(&prover_key, &preprocessed_table, &mut prover_transcript);
+
+    let mut swapped = proof.clone();
+    swapped.multiset_equality_proof.commitments.f =
+        other_proof.multiset_equality_proof.commitments.f;
+    let mut verifier_transcript = Transcript::new(b"lookup");
+    assert!(!swapped.verify(&verifier_key, &preprocessed_table, &mut verifier_transcript));
+
+    let mut verifier_transcript = Transcript::new(b"lookup");
+    assert!(proof.verify(&verifier_key, &preprocessed_table, &mut verifier_transcript));
+}
+
+#[test]
+fn test_proof_is_bound_to_table() {
+    use plookup::lookup::table::four_bits::Add4Bit;
+
+    let (prover_key, verifier_key) = trusted_setup(2usize.pow(10), b"insecure_seeding_setup");
+
+    let table = XOR4Bit::new();
+    let preprocessed_table = table.preprocess(&prover_key, 2usize.pow(8));
+    let other_preprocessed_table = Add4Bit::new().preprocess(&prover_key, 2usize.pow(8));
+    assert_ne!(preprocessed_table.id, other_preprocessed_table.id);
+
+    let mut lookup = LookUp::new(table);
+    lookup.read(&(Fr::from(0u8), Fr::from(0u8)));
+
+    let mut prover_transcript = Transcript::new(b"lookup");
+    let proof = lookup.prove(&prover_key, &preprocessed_table, &mut prover_transcript);
+    assert_eq!(proof.table_id, preprocessed_table.id);
+
+    // Verifying against another version of the table fails, even though the row (0, 0, 0) is in both tables
+    let mut verifier_transcript = Transcript::new(b"lookup");
+    let ok = proof.verify(
+        &verifier_key,
+        &other_preprocessed_table,
+        &mut verifier_transcript,
+    );
+    assert!(!ok);
+}
+
+#[test]
+fn test_self_contained_proofs() {
+    use plookup::lookup::table::four_bits::Add4Bit;
+
+    let (prover_key, verifier_key) = trusted_setup(2usize.pow(10), b"insecure_seeding_setup");
+
+    let table = XOR4Bit::new();
+    let preprocessed_table = table.preprocess(&prover_key, 2usize.pow(8));
+    let other_preprocessed_table = Add4Bit::new().preprocess(&prover_key, 2usize.pow(8));
+
+    let mut lookup = LookUp::new(table);
+    lookup.read(&(Fr::from(4u8), Fr::from(9u8)));
+
+    let mut prover_transcript = Transcript::new(b"lookup");
+    let proof = lookup.prove_with_mode(
+        &prover_key,
+        &preprocessed_table,
+        ProofMode::SelfContained,
+        &mut prover_transcript,
+    );
+    assert_eq!(proof.mode(), ProofMode::SelfContained);
+
+    // The proof additionally carries the size of the table, the digest of the SRS and its 3 commitments,
+    // then the public parameters and the lengths of its empty context
+    let compressed = proof.to_compressed_bytes();
+    assert_eq!(
+        compressed.len(),
+        1 + 32 + 8 + 32 + 10 * 48 + 9 * 32 + 33 + 16
+    );
+    let decoded = LookUpProof::from_compressed_bytes(&compressed).unwrap();
+    assert_eq!(decoded.mode(), ProofMode::SelfContained);
+
+    // The verifier only holds a list of the tables it accepts
+    let allowed_tables = vec![
+        other_preprocessed_table.commitments(),
+        preprocessed_table.commitments(),
+    ];
+    let mut verifier_transcript = Transcript::new(b"lookup");
+    assert!(decoded.verify_self_contained(
+        &verifier_key,
+        &allowed_tables,
+        &mut verifier_transcript
+    ));
+
+    // A table which is not allowed is rejected
+    let mut verifier_transcript = Transcript::new(b"lookup");
+    assert!(!decoded.verify_self_contained(
+        &verifier_key,
+        &allowed_tables[..1],
+        &mut verifier_transcript
+    ));
+
+    // A slim proof cannot be verified without the table
+    let mut prover_transcript = Transcript::new(b"lookup");
+    let slim_proof = lookup.prove(&prover_key, &preprocessed_table, &mut prover_transcript);
+    assert_eq!(slim_proof.mode(), ProofMode::Slim);
+    let mut verifier_transcript = Transcript::new(b"lookup");
+    assert!(!slim_proof.verify_self_contained(
+        &verifier_key,
+        &allowed_tables,
+        &mut verifier_transcript
+    ));
+}
+
+#[test]
+fn test_externally_committed_witness() {
+    use ff_fft::DensePolynomial as Polynomial;
+    use plookup::kzg10;
+    use plookup::multiset::{EqualityProof, ProverConfig};
+    use plookup::transcript::TranscriptProtocol;
+
+    let (prover_key, verifier_key) = trusted_setup(2usize.pow(10), b"insecure_seeding_setup");
+
+    let table = XOR4Bit::new();
+    let preprocessed_table = table.preprocess(&prover_key, 2usize.pow(8));
+    let domain = *preprocessed_table.quotient_key.domain();
+
+    let mut lookup = LookUp::new(table);
+    lookup.read(&(Fr::from(2u8), Fr::from(5u8)));
+    lookup.read(&(Fr::from(11u8), Fr::from(6u8)));
+
+    // The surrounding protocol chooses alpha, then blinds and commits to the merged witness itself
+    let alpha = Fr::from(7u8);
+    let f = lookup.merged_witness(alpha, preprocessed_table.n);
+    let t = preprocessed_table.merged(alpha);
+    let blinding = Polynomial::from_coefficients_vec(vec![Fr::from(3u8), Fr::from(5u8)]);
+    let f_poly = &f.to_polynomial(&domain) + &blinding.mul_by_vanishing_poly(domain);
+    let f_commit = kzg10::commit(&prover_key, &f_poly);
+
+    let mut prover_transcript = Transcript::new(b"plonk");
+    prover_transcript.append_commitment(b"f", &f_commit);
+    let proof = EqualityProof::prove_with_committed_f(
+        f.clone(),
+        f_poly,
+        f_commit,
+        t,
+        &prover_key,
+        &preprocessed_table.quotient_key,
+        &ProverConfig::default(),
+        &mut prover_transcript,
+    )
+    .unwrap();
+    assert_eq!(proof.commitments.f, f_commit);
+
+    let commitments = preprocessed_table.commitments();
+    let t_commit = kzg10::aggregate_commitments(
+        vec![&commitments.t_1, &commitments.t_2, &commitments.t_3],
+        alpha,
+    );
+    let mut verifier_transcript = Transcript::new(b"plonk");
+    verifier_transcript.append_commitment(b"f", &f_commit);
+    assert!(proof.verify_with_committed_f(
+        preprocessed_table.n,
+        &verifier_key,
+        f_commit,
+        t_commit,
+        &ProverConfig::default(),
+        &mut verifier_transcript
+    ));
+
+    // The proof is not accepted for a commitment to another witness
+    let other_commit = kzg10::commit(&prover_key, &blinding);
+    let mut verifier_transcript = Transcript::new(b"plonk");
+    verifier_transcript.append_commitment(b"f", &f_commit);
+    assert!(!proof.verify_with_committed_f(
+        preprocessed_table.n,
+        &verifier_key,
+        other_commit,
+        t_commit,
+        &ProverConfig::default(),
+        &mut verifier_transcript
+    ));
+
+    // A polynomial which does not match the witness is rejected by the prover
+    let mut prover_transcript = Transcript::new(b"plonk");
+    let result = EqualityProof::prove_with_committed_f(
+        f,
+        blinding.clone(),
+        other_commit,
+        preprocessed_table.merged(alpha),
+        &prover_key,
+        &preprocessed_table.quotient_key,
+        &ProverConfig::default(),
+        &mut prover_transcript,
+    );
+    assert!(matches!(
+        result,
+        Err(plookup::error::Error::WitnessMismatch)
+    ));
+}
+
+#[test]
+fn test_resume_proof() {
+    use plookup::error::Error;
+    use plookup::multiset::{ProverStage, ProverState};
+
+    let (prover_key, verifier_key) = trusted_setup(2usize.pow(10), b"insecure_seeding_setup");
+    let table = XOR4Bit::new();
+    let preprocessed_table = table.preprocess(&prover_key, 2usize.pow(8));
+
+    let mut lookup = LookUp::new(table);
+    lookup.read((1u8, 2u8));
+    lookup.read((7u8, 9u8));
+    let (f_1, f_2, f_3) = lookup.wires();
+
+    // The prover crashes after the accumulator is saved
+    let mut saved = Vec::new();
+    let mut prover_transcript = Transcript::new(b"lookup");
+    let result = LookUpProof::prove_with_options(
+        f_1,
+        f_2,
+        f_3,
+        &prover_key,
+        &preprocessed_table,
+        ProveOptions::new().resume(&mut ProverState::new(), &mut |state| {
+            saved.clear();
+            state.save(&mut saved)?;
+            match state.completed() {
+                Some(ProverStage::Accumulator) => Err(Error::Cancelled(ProverStage::Quotient)),
+                _ => Ok(()),
+            }
+        }),
+        &mut prover_transcript,
+    );
+    assert!(result.is_err());
+
+    // A new prover resumes from the saved state
+    let mut state = ProverState::load(&mut &saved[..]).unwrap();
+    assert_eq!(state.completed(), Some(ProverStage::Accumulator));
+    let mut prover_transcript = Transcript::new(b"lookup");
+    let proof = LookUpProof::prove_with_options(
+        f_1,
+        f_2,
+        f_3,
+        &prover_key,
+        &preprocessed_table,
+        ProveOptions::new().resume(&mut state, &mut |_| Ok(())),
+        &mut prover_transcript,
+    )
+    .unwrap();
+    assert_eq!(state.completed(), Some(ProverStage::Commitments));
+
+    let mut verifier_transcript = Transcript::new(b"lookup");
+    assert!(proof.verify(&verifier_key, &preprocessed_table, &mut verifier_transcript));
+
+    // The state cannot be used to resume a proof for another witness
+    let mut state = ProverState::load(&mut &saved[..]).unwrap();
+    let mut prover_transcript = Transcript::new(b"lookup");
+    let result = LookUpProof::prove_with_options(
+        f_2,
+        f_1,
+        f_3,
+        &prover_key,
+        &preprocessed_table,
+        ProveOptions::new().resume(&mut state, &mut |_| Ok(())),
+        &mut prover_transcript,
+    );
+    assert!(matches!(result, Err(Error::StateMismatch)));
+}
+
+#[test]
+fn test_prove_against_interpolated_table() {
+    use plookup::kzg10;
+    use plookup::multiset::{EqualityProof, ProverConfig};
+
+    let (prover_key, verifier_key) = trusted_setup(2usize.pow(10), b"insecure_seeding_setup");
+    let table = XOR4Bit::new();
+    let preprocessed_table = table.preprocess(&prover_key, 2usize.pow(8));
+
+    // The table is folded and interpolated once, then reused for every proof
+    let alpha = Fr::from(7u8);
+    let t = preprocessed_table.merged_interpolated(alpha);
+    let commitments = preprocessed_table.commitments();
+    let t_commit = kzg10::aggregate_commitments(
+        vec![&commitments.t_1, &commitments.t_2, &commitments.t_3],
+        alpha,
+    );
+
+    for rows in vec![vec![(1u8, 2u8), (7, 9)], vec![(15, 15), (0, 3), (4, 4)]] {
+        let mut lookup = LookUp::new(XOR4Bit::new());
+        for (a, b) in rows {
+            lookup.read(&(Fr::from(a), Fr::from(b)));
+        }
+        let f = lookup.merged_witness(alpha, preprocessed_table.n);
+
+        let mut prover_transcript = Transcript::new(b"interpolated");
+        let proof = EqualityProof::prove_interpolated(
+            f,
+            &t,
+            &prover_key,
+            &preprocessed_table.quotient_key,
+            &ProverConfig::default(),
+            &mut prover_transcript,
+        )
+        .unwrap();
+
+        let mut verifier_transcript = Transcript::new(b"interpolated");
+        assert!(proof.verify(
+            preprocessed_table.n,
+            &verifier_key,
+            t_commit,
+            &mut verifier_transcript
+        ));
+    }
+}
+
+#[test]
+fn test_srs_too_small() {
+    use plookup::error::Error;
+    use plookup::lookup::cost;
+
+    // The SRS can commit to the padded table, but not to the quotient
+    let (prover_key, _) = trusted_setup(2usize.pow(8), b"insecure_seeding_setup");
+    let table = XOR4Bit::new();
+    let preprocessed_table = table.preprocess(&prover_key, 2usize.pow(8));
+
+    let mut lookup = LookUp::new(table);
+    lookup.read((1u8, 2u8));
+
+    let (f_1, f_2, f_3) = lookup.wires();
+    let mut prover_transcript = Transcript::new(b"lookup");
+    let result = LookUpProof::prove_with_options(
+        f_1,
+        f_2,
+        f_3,
+        &prover_key,
+        &preprocessed_table,
+        ProveOptions::new(),
+        &mut prover_transcript,
+    );
+    match result {
+        Err(Error::SrsTooSmall {
+            required,
+            available,
+        }) => {
+            assert_eq!(required, cost::srs_size(2usize.pow(8)) - 1);
+            assert_eq!(available, 2usize.pow(8));
+        }
+        _ => panic!("expected the SRS to be too small"),
+    }
+
+    // An SRS of the recommended degree is large enough
+    let degree = cost::required_srs_degree(XOR4Bit::new().len(), 1);
+    let (prover_key, verifier_key) = trusted_setup(degree, b"insecure_seeding_setup");
+    let preprocessed_table = XOR4Bit::new().preprocess(&prover_key, 2usize.pow(8));
+    let mut prover_transcript = Transcript::new(b"lookup");
+    let proof = lookup.prove(&prover_key, &preprocessed_table, &mut prover_transcript);
+    let mut verifier_transcript = Transcript::new(b"lookup");
+    assert!(proof.verify(&verifier_key, &preprocessed_table, &mut verifier_transcript));
+}
+
+#[test]
+fn test_transcript_factory() {
+    use plookup::lookup::context::ProofContext;
+
+    let (prover_key, verifier_key) = trusted_setup(2usize.pow(10), b"insecure_seeding_setup");
+
+    let table = XOR4Bit::new();
+    let preprocessed_table = table.preprocess(&prover_key, 2usize.pow(8));
+
+    let mut lookup = LookUp::new(table);
+    lookup.read(&(Fr::from(5u8), Fr::from(6u8)));
+
+    // The host protocol prepends the hash of the block that the proof is included in
+    let new_transcript = |block_hash: &'static [u8]| {
+        move || {
+            let mut transcript = Transcript::new(b"lookup");
+            transcript.append_message(b"block_hash", block_hash);
+            transcript
+        }
+    };
+
+    let context = ProofContext::default();
+    let proof = lookup
+        .prove_with_options(
+            &prover_key,
+            &preprocessed_table,
+            ProveOptions::new(),
+            &mut new_transcript(b"block 1")(),
+        )
+        .unwrap();
+
+    assert!(proof.verify_with_transcript(
+        &verifier_key,
+        &preprocessed_table,
+        &context,
+        new_transcript(b"block 1")
+    ));
+    assert!(!proof.verify_with_transcript(
+        &verifier_key,
+        &preprocessed_table,
+        &context,
+        new_transcript(b"block 2")
+    ));
+    let mut verifier_transcript = Transcript::new(b"lookup");
+    assert!(!proof.verify(&verifier_key, &preprocessed_table, &mut verifier_transcript));
+}
+
+#[test]
+fn test_prove_from_multisets() {
+    use ff_fft::EvaluationDomain;
+    use plookup::error::Error;
+    use plookup::kzg10;
+    use plookup::lookup::proof::{prove_from_multisets, verify_from_multisets};
+    use plookup::multiset::{quotient_poly::QuotientKey, EqualityProof, MultiSet, ProverConfig};
+
+    let (prover_key, verifier_key) = trusted_setup(2usize.pow(10), b"insecure_seeding_setup");
+    let n = 2usize.pow(8);
+    let quotient_key = QuotientKey::new(n);
+    let domain: EvaluationDomain<Fr> = EvaluationDomain::new(n).unwrap();
+
+    // The caller has already folded the columns of the witness and the table
+    let t: MultiSet = (0..n as u64).map(Fr::from).collect();
+    let t_commit = kzg10::commit(&prover_key, &t.to_polynomial(&domain));
+    let f: MultiSet = [3u64, 5, 5, 200].iter().cloned().map(Fr::from).collect();
+
+    let config = ProverConfig::default();
+    let prove = |f: MultiSet| {
+        prove_from_multisets(
+            f,
+            t.clone(),
+            &prover_key,
+            &quotient_key,
+            &config,
+            &mut Transcript::new(b"lookup"),
+        )
+        .unwrap()
+    };
+    let verify = |proof: &EqualityProof, f_commit| {
+        verify_from_multisets(
+            proof,
+            n,
+            &verifier_key,
+            f_commit,
+            t_commit,
+            &config,
+            &mut Transcript::new(b"lookup"),
+        )
+    };
+
+    let proofs: Vec<_> = vec![f, MultiSet::new()].into_iter().map(prove).collect();
+    for proof in proofs.iter() {
+        assert!(verify(proof, proof.commitments.f));
+    }
+
+    // A proof for another witness in the table is rejected against the verifier's commitment to the witness
+    let f_commit = proofs[0].commitments.f;
+    assert!(!verify(&proofs[1], f_commit));
+    // Even if it is relabelled with that commitment, as the commitment is absorbed before beta
+    let mut relabelled = proofs[1].clone();
+    relabelled.commitments.f = f_commit;
+    assert!(!verify(&relabelled, f_commit));
+
+    // Values which are not in the table are reported before any work is done
+    let f: MultiSet = [3u64, 300, 5].iter().cloned().map(Fr::from).collect();
+    match prove_from_multisets(
+        f,
+        t.clone(),
+        &prover_key,
+        &quotient_key,
+        &ProverConfig::default(),
+        &mut Transcript::new(b"lookup"),
+    ) {
+        Err(Error::MissingRows(missing)) => assert_eq!(missing, vec![1]),
+        _ => panic!("expected the missing value to be reported"),
+    }
+
+    // The witness must be shorter than the table
+    match prove_from_multisets(
+        t.clone(),
+        t,
+        &prover_key,
+        &quotient_key,
+        &ProverConfig::default(),
+        &mut Transcript::new(b"lookup"),
+    ) {
+        Err(Error::CapacityExceeded { rows, capacity }) => assert_eq!((rows, capacity), (n, n - 1)),
+        _ => panic!("expected the witness to be too large"),
+    }
+
+    // The table must have as many rows as the domain
+    let short_table: MultiSet = (0..n as u64 - 1).map(Fr::from).collect();
+    assert!(matches!(
+        prove_from_multisets(
+            MultiSet::new(),
+            short_table,
+            &prover_key,
+            &quotient_key,
+            &ProverConfig::default(),
+            &mut Transcript::new(b"lookup"),
+        ),
+        Err(Error::TableMismatch)
+    ));
+}
+
+#[test]
+fn test_prepared_verifier() {
+    use plookup::kzg10::{self, PreparedVerifierKey};
+    use plookup::lookup::context::ProofContext;
+    use plookup::lookup::table::four_bits::Add4Bit;
+
+    let (prover_key, verifier_key) = trusted_setup(2usize.pow(10), b"insecure_seeding_setup");
+
+    let table = XOR4Bit::new();
+    let preprocessed_table = table.preprocess(&prover_key, 2usize.pow(8));
+
+    // The verifier prepares the key and the table once, and reuses them for every proof
+    let prepared_key = PreparedVerifierKey::new(verifier_key.clone());
+    let table_commitments = preprocessed_table.commitments();
+    let prepared_table = table_commitments.prepare();
+
+    let alpha = Fr::from(7u8);
+    let expected = kzg10::aggregate_commitments(
+        vec![
+            &table_commitments.t_1,
+            &table_commitments.t_2,
+            &table_commitments.t_3,
+        ],
+        alpha,
+    );
+    assert_eq!(prepared_table.merged(alpha), expected);
+
+    let context = ProofContext::default();
+    for &(a, b) in &[(1u8, 2u8), (3, 5), (15, 15)] {
+        let mut lookup = LookUp::new(XOR4Bit::new());
+        lookup.read(&(Fr::from(a), Fr::from(b)));
+        let mut prover_transcript = Transcript::new(b"lookup");
+        let proof = lookup.prove(&prover_key, &preprocessed_table, &mut prover_transcript);
+
+        let mut verifier_transcript = Transcript::new(b"lookup");
+        assert!(proof.verify_prepared(
+            &prepared_key,
+            &prepared_table,
+            &context,
+            &mut verifier_transcript
+        ));
+
+        // The proof is rejected for another context
+        let other_context = ProofContext::new(b"app", b"session");
+        let mut verifier_transcript = Transcript::new(b"lookup");
+        assert!(!proof.verify_prepared(
+            &prepared_key,
+            &prepared_table,
+            &other_context,
+            &mut verifier_transcript
+        ));
+    }
+
+    // A proof against another table is rejected
+    let other_preprocessed_table = Add4Bit::new().preprocess(&prover_key, 2usize.pow(8));
+    let mut lookup = LookUp::new(Add4Bit::new());
+    lookup.read(&(Fr::from(1u8), Fr::from(2u8)));
+    let mut prover_transcript = Transcript::new(b"lookup");
+    let proof = lookup.prove(
+        &prover_key,
+        &other_preprocessed_table,
+        &mut prover_transcript,
+    );
+    let mut verifier_transcript = Transcript::new(b"lookup");
+    assert!(!proof.verify_prepared(
+        &prepared_key,
+        &prepared_table,
+        &context,
+        &mut verifier_transcript
+    ));
+}